@@ -0,0 +1,581 @@
+//! Deserialize Rust types from Bencode with Serde.
+//!
+//! [Deserializer] is backed by the [parser](crate::parser) so malformed input
+//! is reported with the parser's [BertErrorTrace] and the offset where parsing
+//! failed. Errors raised while deserializing a dictionary value are prefixed
+//! with the value's key.
+//!
+//! Integers deserialize from [i64] or, if they're too large, [u64]. Byte
+//! strings are borrowed from the input and `bool`s are read from `i0e` and
+//! `i1e`. Absent fields are `None`; present fields are always `Some`.
+use crate::parser::{bytes, integer, skip, BertErrorKind, BertErrorTrace};
+use nom::{
+    character::complete::char,
+    error::{context, ContextError, ErrorKind, ParseError},
+    IResult,
+};
+use serde::de::{
+    self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, Unexpected, VariantAccess, Visitor,
+};
+use std::fmt::Display;
+use thiserror::Error;
+
+// Lists and dictionaries nested deeper than this are rejected rather than
+// overflowing the stack.
+const RECURSION_LIMIT: usize = 256;
+
+/// Errors while deserializing Bencode.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Malformed Bencode at `offset` bytes into the input.
+    #[error("invalid Bencode at byte {offset}: {trace}")]
+    Parse {
+        offset: usize,
+        trace: BertErrorTrace<Vec<u8>>,
+    },
+    /// Lists or dictionaries are nested deeper than the recursion limit.
+    #[error("recursion limit exceeded at byte {0}")]
+    RecursionLimit(usize),
+    /// Input that follows the deserialized value.
+    #[error("trailing bytes at byte {0}")]
+    TrailingBytes(usize),
+    /// Error in the value of dictionary key `key`.
+    #[error("`{key}`: {source}")]
+    Key { key: String, source: Box<Error> },
+    /// Error from a [de::Deserialize] implementation.
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl de::Error for Error {
+    #[inline]
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Deserialize `T` from exactly one Bencoded value.
+///
+/// # Examples
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize)]
+/// struct Info<'a> {
+///     length: u64,
+///     name: &'a str,
+/// }
+///
+/// let info: Info = star_bert::de::from_bytes(b"d6:lengthi42e4:name4:catse")?;
+/// assert_eq!(info.length, 42);
+/// assert_eq!(info.name, "cats");
+///
+/// let err = star_bert::de::from_bytes::<Info>(b"d6:lengthi042e4:name4:catse").unwrap_err();
+/// assert!(err
+///     .to_string()
+///     .starts_with("`length`: invalid Bencode at byte 9"));
+/// # Ok::<(), star_bert::de::Error>(())
+/// ```
+pub fn from_bytes<'de, T>(input: &'de [u8]) -> Result<T, Error>
+where
+    T: de::Deserialize<'de>,
+{
+    let mut deserializer = Deserializer::from_bytes(input);
+    let value = T::deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+/// Serde deserializer over Bencoded bytes.
+#[derive(Debug)]
+pub struct Deserializer<'de> {
+    original: &'de [u8],
+    input: &'de [u8],
+    depth: usize,
+}
+
+impl<'de> Deserializer<'de> {
+    #[inline]
+    pub fn from_bytes(input: &'de [u8]) -> Self {
+        Self {
+            original: input,
+            input,
+            depth: 0,
+        }
+    }
+
+    /// Check that the input was consumed.
+    #[inline]
+    pub fn end(&self) -> Result<(), Error> {
+        if self.input.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TrailingBytes(self.offset()))
+        }
+    }
+
+    /// Bytes consumed so far.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.original.len() - self.input.len()
+    }
+
+    // Run `parser` on the remaining input and consume what it parsed.
+    fn parse<O, F>(&mut self, parser: F) -> Result<O, Error>
+    where
+        F: FnOnce(&'de [u8]) -> IResult<&'de [u8], O, BertErrorTrace<&'de [u8]>>,
+    {
+        match parser(self.input) {
+            Ok((rest, parsed)) => {
+                self.input = rest;
+                Ok(parsed)
+            }
+            Err(nom::Err::Error(trace) | nom::Err::Failure(trace)) => Err(self.error(trace)),
+            // The parsers are complete so they never ask for more input.
+            Err(nom::Err::Incomplete(_)) => {
+                Err(self.error(BertErrorTrace::from_error_kind(self.input, ErrorKind::Eof)))
+            }
+        }
+    }
+
+    #[inline]
+    fn error(&self, trace: BertErrorTrace<&[u8]>) -> Error {
+        Error::Parse {
+            offset: self.offset(),
+            trace: trace.into(),
+        }
+    }
+
+    fn peek(&self) -> Result<u8, Error> {
+        self.input.first().copied().ok_or_else(|| {
+            self.error(BertErrorTrace::add_context(
+                self.input,
+                "[Expected] {deserialize} Value",
+                BertErrorTrace::from_error_kind(self.input, ErrorKind::Eof),
+            ))
+        })
+    }
+
+    // Integers that don't fit in an `i64` may still fit in a `u64`.
+    fn deserialize_integer<V>(&mut self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match integer::<i64>(self.input) {
+            Ok((rest, integer)) => {
+                self.input = rest;
+                visitor.visit_i64(integer)
+            }
+            Err(_) => visitor.visit_u64(self.parse(integer::<u64>)?),
+        }
+    }
+
+    // Open a list or dictionary, visit it, then check its closing delimiter.
+    fn deserialize_container<V, F>(&mut self, visit: F) -> Result<V, Error>
+    where
+        F: FnOnce(&mut Self) -> Result<V, Error>,
+    {
+        self.depth += 1;
+        if self.depth > RECURSION_LIMIT {
+            return Err(Error::RecursionLimit(self.offset()));
+        }
+        self.input = &self.input[1..];
+
+        let value = visit(self)?;
+        self.parse(context(
+            "[Expected] {deserialize} Closing delimiter `e`",
+            char('e'),
+        ))?;
+        self.depth -= 1;
+        Ok(value)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            b'i' => self.deserialize_integer(visitor),
+            b'0'..=b'9' => visitor.visit_borrowed_bytes(self.parse(bytes)?),
+            b'l' => self.deserialize_container(|de| visitor.visit_seq(Access::new(de))),
+            b'd' => self.deserialize_container(|de| visitor.visit_map(Access::new(de))),
+            _ => Err(self.error(BertErrorTrace::add_context(
+                self.input,
+                "[Expected] {deserialize} Integer, bytes, list, or dictionary",
+                BertErrorTrace::from_error_kind(self.input, ErrorKind::Alt),
+            ))),
+        }
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.peek()? != b'i' {
+            return self.deserialize_any(visitor);
+        }
+
+        match self.parse(integer::<i64>)? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            nonbool => Err(de::Error::invalid_value(
+                Unexpected::Signed(nonbool),
+                &"zero or one",
+            )),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.peek()?.is_ascii_digit() {
+            return self.deserialize_any(visitor);
+        }
+
+        let bytes = self.parse(bytes)?;
+        match std::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(e) => Err(self.error(BertErrorTrace::from_bert_error_kind(
+                bytes,
+                BertErrorKind::from(e),
+            ))),
+        }
+    }
+
+    #[inline]
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    #[inline]
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    // Keys aren't necessarily UTF-8, so they fall back to bytes.
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if !self.peek()?.is_ascii_digit() {
+            return self.deserialize_any(visitor);
+        }
+
+        let bytes = self.parse(bytes)?;
+        match std::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(_) => visitor.visit_borrowed_bytes(bytes),
+        }
+    }
+
+    // Bencode doesn't have null so present values are always `Some`.
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Unit variants are byte strings and other variants are dictionaries with
+    // one key.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.peek()? {
+            b'0'..=b'9' => visitor.visit_enum(Enum {
+                de: self,
+                unit: true,
+            }),
+            b'd' => self.deserialize_container(|de| visitor.visit_enum(Enum { de, unit: false })),
+            _ => Err(de::Error::invalid_type(
+                Unexpected::Other("non-enum"),
+                &visitor,
+            )),
+        }
+    }
+
+    #[inline]
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.parse(skip)?;
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct
+    }
+}
+
+// Elements of lists and entries of dictionaries.
+struct Access<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    key: &'de [u8],
+}
+
+impl<'a, 'de> Access<'a, 'de> {
+    #[inline]
+    fn new(de: &'a mut Deserializer<'de>) -> Self {
+        Self { de, key: &[] }
+    }
+}
+
+impl<'de, 'a> SeqAccess<'de> for Access<'a, 'de> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.de.peek()? == b'e' {
+            Ok(None)
+        } else {
+            seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for Access<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        if self.de.peek()? == b'e' {
+            return Ok(None);
+        }
+
+        let start = self.de.input;
+        self.key = self.de.parse(context(
+            "[Expected] {deserialize} Byte string dictionary key",
+            bytes,
+        ))?;
+        self.de.input = start;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de).map_err(|e| Error::Key {
+            key: String::from_utf8_lossy(self.key).into_owned(),
+            source: Box::new(e),
+        })
+    }
+}
+
+struct Enum<'a, 'de> {
+    de: &'a mut Deserializer<'de>,
+    unit: bool,
+}
+
+impl<'de, 'a> EnumAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    #[inline]
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant), Self::Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a> VariantAccess<'de> for Enum<'a, 'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        if self.unit {
+            Ok(())
+        } else {
+            de::Deserializer::deserialize_ignored_any(self.de, de::IgnoredAny).map(|_| ())
+        }
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.unit {
+            Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"newtype variant",
+            ))
+        } else {
+            seed.deserialize(self.de)
+        }
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.unit {
+            Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"tuple variant",
+            ))
+        } else {
+            de::Deserializer::deserialize_seq(self.de, visitor)
+        }
+    }
+
+    fn struct_variant<V>(
+        self,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if self.unit {
+            Err(de::Error::invalid_type(
+                Unexpected::UnitVariant,
+                &"struct variant",
+            ))
+        } else {
+            de::Deserializer::deserialize_map(self.de, visitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, Error};
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct File<'a> {
+        length: u64,
+        #[serde(borrow)]
+        path: Vec<&'a str>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Info<'a> {
+        #[serde(borrow)]
+        files: Vec<File<'a>>,
+        name: String,
+        #[serde(with = "serde_bytes")]
+        pieces: &'a [u8],
+        private: Option<bool>,
+        md5sum: Option<String>,
+    }
+
+    #[test]
+    fn nested_structs() -> Result<(), Error> {
+        let info: Info = from_bytes(
+            b"d5:filesld6:lengthi42e4:pathl4:cats8:cats.mkveee4:name4:cats6:pieces4:\xca\xfe\xd0\x0d7:privatei1e8:x_unkownli1eee",
+        )?;
+
+        assert_eq!(
+            info.files,
+            [File {
+                length: 42,
+                path: vec!["cats", "cats.mkv"]
+            }]
+        );
+        assert_eq!(info.name, "cats");
+        assert_eq!(info.pieces, [0xca, 0xfe, 0xd0, 0x0d]);
+        assert_eq!(info.private, Some(true));
+        assert_eq!(info.md5sum, None);
+        Ok(())
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Announce {
+        Stopped,
+        Peers(Vec<u16>),
+        Tracker { interval: u32 },
+    }
+
+    #[test]
+    fn enums_and_maps() -> Result<(), Error> {
+        assert_eq!(from_bytes::<Announce>(b"7:Stopped")?, Announce::Stopped);
+        assert_eq!(
+            from_bytes::<Announce>(b"d5:Peersli6881ei6882eee")?,
+            Announce::Peers(vec![6881, 6882])
+        );
+        assert_eq!(
+            from_bytes::<Announce>(b"d7:Trackerd8:intervali1800eee")?,
+            Announce::Tracker { interval: 1800 }
+        );
+
+        let map: BTreeMap<String, u64> = from_bytes(b"d1:ai1e1:bi18446744073709551615ee")?;
+        assert_eq!(map["b"], u64::MAX);
+        Ok(())
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Nested(#[allow(dead_code)] Vec<Nested>);
+
+    #[test]
+    fn errors() {
+        // Errors in values are prefixed by their key.
+        match from_bytes::<File>(b"d6:lengthi-1e4:pathlee") {
+            Err(Error::Key { key, source }) => {
+                assert_eq!(key, "length");
+                assert!(matches!(*source, Error::Custom(_)));
+            }
+            other => panic!("{other:?}"),
+        }
+
+        assert!(matches!(
+            from_bytes::<u8>(b"i1ei2e"),
+            Err(Error::TrailingBytes(3))
+        ));
+        assert!(matches!(
+            from_bytes::<Vec<u8>>(b"li1e"),
+            Err(Error::Parse { offset: 4, .. })
+        ));
+
+        let deep = [vec![b'l'; 1000], vec![b'e'; 1000]].concat();
+        assert!(matches!(
+            from_bytes::<Nested>(&deep),
+            Err(Error::RecursionLimit(256))
+        ));
+        // Ignored values are skipped without recursion.
+        assert!(from_bytes::<serde::de::IgnoredAny>(&deep).is_ok());
+    }
+}
@@ -0,0 +1,377 @@
+//! Structured differences between two [Value]s.
+//!
+//! Diffs explain where two values diverge, such as a torrent before and after
+//! re-serialization, with the path to each difference. [DiffEntry::summary]
+//! describes the values too, and [differing_chunks] narrows changed buffers,
+//! such as `pieces`, down to the hashes that differ.
+
+use crate::{pretty::Pretty, value::Value};
+use std::fmt::{self, Display, Formatter};
+
+/// Step from a value to one of its children.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathSegment<'input> {
+    /// Dictionary key.
+    Key(&'input [u8]),
+    /// List index.
+    Index(usize),
+}
+
+/// Path from the root value to a difference.
+///
+/// Paths are displayed like `info.files[0].length`. The root is displayed as
+/// `.`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DiffPath<'input>(pub Vec<PathSegment<'input>>);
+
+impl Display for DiffPath<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return f.write_str(".");
+        }
+
+        for (i, segment) in self.0.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) if i == 0 => write!(f, "{}", String::from_utf8_lossy(key))?,
+                PathSegment::Key(key) => write!(f, ".{}", String::from_utf8_lossy(key))?,
+                PathSegment::Index(index) => write!(f, "[{index}]")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How a value differs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffKind<'a, 'input> {
+    /// Key or list element that's only in the new value.
+    Added(&'a Value<'input>),
+    /// Key or list element that's only in the old value.
+    Removed(&'a Value<'input>),
+    /// Value that was replaced.
+    Changed {
+        old: &'a Value<'input>,
+        new: &'a Value<'input>,
+    },
+    /// Dictionary with the same keys in a different order. Changes to the
+    /// values are separate entries.
+    Reordered,
+}
+
+/// One difference between two values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffEntry<'a, 'input> {
+    pub path: DiffPath<'input>,
+    pub kind: DiffKind<'a, 'input>,
+}
+
+impl Display for DiffEntry<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            DiffKind::Added(_) => "added",
+            DiffKind::Removed(_) => "removed",
+            DiffKind::Changed { .. } => "changed",
+            DiffKind::Reordered => "reordered",
+        };
+        write!(f, "{}: {kind}", self.path)
+    }
+}
+
+impl<'a, 'input> DiffEntry<'a, 'input> {
+    /// Display the entry along with the values that differ.
+    ///
+    /// Strings are quoted and binary strings are summarized by their length
+    /// and first few bytes. Lists and dictionaries are summarized by their
+    /// length. Changed byte strings note the offset of the first byte that
+    /// differs unless one is a prefix of the other.
+    ///
+    /// # Examples
+    /// ```
+    /// use nom::Finish;
+    /// use star_bert::{diff::diff, parser::value};
+    /// # use star_bert::parser::BertErrorTrace;
+    ///
+    /// let (_, old) = value(b"d7:comment4:cats6:pieces4:\x00\x01\x02\x03e").finish()?;
+    /// let (_, new) = value(b"d6:pieces4:\x00\x01\xff\x035:filesle7:comment4:mewse").finish()?;
+    ///
+    /// let changes: Vec<_> = diff(&old, &new)
+    ///     .iter()
+    ///     .map(|entry| entry.summary().to_string())
+    ///     .collect();
+    /// assert_eq!(
+    ///     changes,
+    ///     [
+    ///         r#"comment: changed "cats" -> "mews", first at byte 0"#,
+    ///         "pieces: changed <4 bytes: 00010203> -> <4 bytes: 0001ff03>, first at byte 2",
+    ///         "files: added <list of 0 values>",
+    ///     ]
+    /// );
+    /// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+    /// ```
+    #[inline]
+    pub fn summary(&self) -> DiffSummary<'_, 'a, 'input> {
+        DiffSummary(self)
+    }
+}
+
+/// [DiffEntry] displayed with its values; created by [DiffEntry::summary].
+#[derive(Debug, Clone, Copy)]
+pub struct DiffSummary<'entry, 'a, 'input>(&'entry DiffEntry<'a, 'input>);
+
+impl Display for DiffSummary<'_, '_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)?;
+        match self.0.kind {
+            DiffKind::Added(value) | DiffKind::Removed(value) => {
+                f.write_str(" ")?;
+                write_brief(f, value)
+            }
+            DiffKind::Changed { old, new } => {
+                f.write_str(" ")?;
+                write_brief(f, old)?;
+                f.write_str(" -> ")?;
+                write_brief(f, new)?;
+                if let (Value::Bytes(old), Value::Bytes(new)) = (old, new) {
+                    if let Some(first) = old.iter().zip(*new).position(|(old, new)| old != new) {
+                        write!(f, ", first at byte {first}")?;
+                    }
+                }
+                Ok(())
+            }
+            DiffKind::Reordered => Ok(()),
+        }
+    }
+}
+
+// Bytes of binary strings that summaries print.
+const SUMMARY_MAX_BYTES: usize = 8;
+
+// Write a value on one line.
+fn write_brief(f: &mut Formatter<'_>, value: &Value<'_>) -> fmt::Result {
+    match value {
+        Value::List(list) => write!(f, "<list of {} values>", list.len()),
+        Value::Dict(dict) => write!(f, "<dict of {} keys>", dict.len()),
+        scalar => write!(f, "{}", Pretty::new(scalar).max_bytes(SUMMARY_MAX_BYTES)),
+    }
+}
+
+/// Indices of the `chunk_length` byte chunks that differ between `old` and
+/// `new`.
+///
+/// Concatenated hashes, such as the 20 byte SHA-1 hashes of `pieces` or the
+/// 32 byte SHA-256 hashes of `piece layers`, are compared hash by hash. Chunks
+/// that are only in the longer buffer differ too.
+///
+/// # Examples
+/// ```
+/// use star_bert::diff::differing_chunks;
+///
+/// let old = [[0; 20], [1; 20], [2; 20]].concat();
+/// let new = [[0; 20], [9; 20], [2; 20], [3; 20]].concat();
+/// assert_eq!(differing_chunks(&old, &new, 20), [1, 3]);
+/// ```
+///
+/// # Panics
+/// Panics if `chunk_length` is zero.
+pub fn differing_chunks(old: &[u8], new: &[u8], chunk_length: usize) -> Vec<usize> {
+    let mut old = old.chunks(chunk_length);
+    let mut new = new.chunks(chunk_length);
+    let mut differing = Vec::new();
+    for index in 0.. {
+        match (old.next(), new.next()) {
+            (None, None) => break,
+            (old, new) if old != new => differing.push(index),
+            _ => {}
+        }
+    }
+    differing
+}
+
+/// Differences from `old` to `new`.
+///
+/// Dictionaries are compared by key and lists by index. Values of different
+/// types are [changed](DiffKind::Changed) rather than compared further. Only
+/// the first value of a duplicated key is compared.
+///
+/// # Examples
+/// ```
+/// use nom::Finish;
+/// use star_bert::{diff::diff, parser::value};
+/// # use star_bert::parser::BertErrorTrace;
+///
+/// let (_, old) = value(b"d4:infod5:filesld6:lengthi1eeee4:name4:catse").finish()?;
+/// let (_, new) = value(b"d4:infod5:filesld6:lengthi2eeee7:comment4:mewse").finish()?;
+///
+/// let changes: Vec<_> = diff(&old, &new).iter().map(ToString::to_string).collect();
+/// assert_eq!(
+///     changes,
+///     [
+///         "info.files[0].length: changed",
+///         "name: removed",
+///         "comment: added"
+///     ]
+/// );
+/// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+/// ```
+pub fn diff<'a, 'input>(
+    old: &'a Value<'input>,
+    new: &'a Value<'input>,
+) -> Vec<DiffEntry<'a, 'input>> {
+    let mut entries = Vec::new();
+    diff_into(old, new, &mut Vec::new(), &mut entries);
+    entries
+}
+
+fn diff_into<'a, 'input>(
+    old: &'a Value<'input>,
+    new: &'a Value<'input>,
+    path: &mut Vec<PathSegment<'input>>,
+    entries: &mut Vec<DiffEntry<'a, 'input>>,
+) {
+    match (old, new) {
+        (Value::Dict(old_dict), Value::Dict(new_dict)) => {
+            let mut same_keys = true;
+
+            for (key, old_value) in old_dict {
+                path.push(PathSegment::Key(key));
+                match new.get(key) {
+                    Some(new_value) => diff_into(old_value, new_value, path, entries),
+                    None => {
+                        same_keys = false;
+                        entries.push(DiffEntry {
+                            path: DiffPath(path.clone()),
+                            kind: DiffKind::Removed(old_value),
+                        })
+                    }
+                }
+                path.pop();
+            }
+
+            for (key, new_value) in new_dict {
+                if old.get(key).is_none() {
+                    same_keys = false;
+                    path.push(PathSegment::Key(key));
+                    entries.push(DiffEntry {
+                        path: DiffPath(path.clone()),
+                        kind: DiffKind::Added(new_value),
+                    });
+                    path.pop();
+                }
+            }
+
+            // Same keys but serialized in a different order.
+            let old_keys = old_dict.iter().map(|(key, _)| key);
+            if same_keys && !old_keys.eq(new_dict.iter().map(|(key, _)| key)) {
+                entries.push(DiffEntry {
+                    path: DiffPath(path.clone()),
+                    kind: DiffKind::Reordered,
+                });
+            }
+        }
+        (Value::List(old_list), Value::List(new_list)) => {
+            for (index, (old_value, new_value)) in old_list.iter().zip(new_list).enumerate() {
+                path.push(PathSegment::Index(index));
+                diff_into(old_value, new_value, path, entries);
+                path.pop();
+            }
+
+            for (index, old_value) in old_list.iter().enumerate().skip(new_list.len()) {
+                path.push(PathSegment::Index(index));
+                entries.push(DiffEntry {
+                    path: DiffPath(path.clone()),
+                    kind: DiffKind::Removed(old_value),
+                });
+                path.pop();
+            }
+
+            for (index, new_value) in new_list.iter().enumerate().skip(old_list.len()) {
+                path.push(PathSegment::Index(index));
+                entries.push(DiffEntry {
+                    path: DiffPath(path.clone()),
+                    kind: DiffKind::Added(new_value),
+                });
+                path.pop();
+            }
+        }
+        (old, new) if old != new => entries.push(DiffEntry {
+            path: DiffPath(path.clone()),
+            kind: DiffKind::Changed { old, new },
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, differing_chunks, DiffKind, PathSegment};
+    use crate::{parser::value, value::Value};
+    use nom::Finish;
+
+    fn parse(input: &[u8]) -> Value<'_> {
+        value(input).finish().unwrap().1
+    }
+
+    #[test]
+    fn identical_values() {
+        let torrent = parse(b"d8:announce15:udp://cats.test4:infod4:name4:catsee");
+        assert!(diff(&torrent, &torrent).is_empty());
+    }
+
+    #[test]
+    fn list_lengths_and_types() {
+        let old = parse(b"li1e4:catsli2eee");
+        let new = parse(b"li1ei3eli2ei4eee");
+        let entries = diff(&old, &new);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path.to_string(), "[1]");
+        assert_eq!(
+            entries[0].kind,
+            DiffKind::Changed {
+                old: &Value::Bytes(b"cats"),
+                new: &Value::Integer(3)
+            }
+        );
+        assert_eq!(
+            entries[1].path.0,
+            [PathSegment::Index(2), PathSegment::Index(1)]
+        );
+        assert_eq!(entries[1].kind, DiffKind::Added(&Value::Integer(4)));
+    }
+
+    #[test]
+    fn reordered_keys() {
+        let sorted = parse(b"d1:ai1e1:bd1:ci1e1:di2eee");
+        let unsorted = parse(b"d1:bd1:di2e1:ci1ee1:ai1ee");
+        let changes: Vec<_> = diff(&sorted, &unsorted)
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(changes, ["b: reordered", ".: reordered"]);
+    }
+
+    #[test]
+    fn summaries_and_chunks() {
+        let old = parse(b"d4:infod6:lengthi1e6:pieces3:abcee");
+        let new = parse(b"d4:infod6:pieces3:abd6:lengthi2eee");
+        let summaries: Vec<_> = diff(&old, &new)
+            .iter()
+            .map(|entry| entry.summary().to_string())
+            .collect();
+
+        assert_eq!(
+            summaries,
+            [
+                "info.length: changed 1 -> 2",
+                r#"info.pieces: changed "abc" -> "abd", first at byte 2"#,
+                "info: reordered"
+            ]
+        );
+
+        assert_eq!(differing_chunks(b"abcdef", b"abcdef", 2), []);
+        assert_eq!(differing_chunks(b"abcdef", b"abXdef", 2), [1]);
+        assert_eq!(differing_chunks(b"abcdef", b"ab", 2), [1, 2]);
+        assert_eq!(differing_chunks(b"", b"abc", 2), [0, 1]);
+    }
+}
@@ -0,0 +1,212 @@
+//! Share dictionary keys between owned values.
+//!
+//! Torrents repeat the same handful of keys (`length`, `path`, `pieces root`)
+//! thousands of times. [Value](crate::value::Value)s borrow their keys from the
+//! input, but values that outlive their input need owned keys. [KeyInterner]
+//! returns keys from a static table of keys defined by BEPs or from a pool of
+//! keys it has already seen so that each key is only allocated once.
+
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fmt::{self, Debug, Formatter},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::Arc,
+};
+
+// Keys defined by BEPs sorted by their bytes.
+const KNOWN_KEYS: &[&[u8]] = &[
+    b"announce",
+    b"announce-list",
+    b"attr",
+    b"collections",
+    b"comment",
+    b"complete",
+    b"created by",
+    b"creation date",
+    b"encoding",
+    b"file tree",
+    b"files",
+    b"httpseeds",
+    b"incomplete",
+    b"info",
+    b"interval",
+    b"length",
+    b"md5sum",
+    b"meta version",
+    b"min interval",
+    b"name",
+    b"nodes",
+    b"path",
+    b"peers",
+    b"piece layers",
+    b"piece length",
+    b"pieces",
+    b"pieces root",
+    b"private",
+    b"sha1",
+    b"signatures",
+    b"similar",
+    b"source",
+    b"symlink path",
+    b"url-list",
+];
+
+/// Dictionary key returned by [KeyInterner].
+///
+/// Keys compare and hash by their bytes regardless of where they're stored.
+#[derive(Clone)]
+pub enum InternedKey {
+    /// Key from the table of keys defined by BEPs.
+    Static(&'static [u8]),
+    /// Key from the interner's pool.
+    Pooled(Arc<[u8]>),
+}
+
+impl Deref for InternedKey {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            InternedKey::Static(key) => key,
+            InternedKey::Pooled(key) => key,
+        }
+    }
+}
+
+impl AsRef<[u8]> for InternedKey {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl Borrow<[u8]> for InternedKey {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        self
+    }
+}
+
+impl PartialEq for InternedKey {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for InternedKey {}
+
+impl PartialEq<[u8]> for InternedKey {
+    #[inline]
+    fn eq(&self, other: &[u8]) -> bool {
+        **self == *other
+    }
+}
+
+impl Hash for InternedKey {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl Debug for InternedKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", String::from_utf8_lossy(self))
+    }
+}
+
+/// Pool of dictionary keys.
+///
+/// # Examples
+/// ```
+/// use star_bert::intern::{InternedKey, KeyInterner};
+///
+/// let mut interner = KeyInterner::new();
+/// assert!(matches!(interner.intern(b"length"), InternedKey::Static(_)));
+///
+/// let first = interner.intern(b"x_cats");
+/// let second = interner.intern(b"x_cats");
+/// assert_eq!(first, second);
+/// assert_eq!(interner.len(), 1);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct KeyInterner {
+    pool: HashSet<Arc<[u8]>>,
+}
+
+impl KeyInterner {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Shared copy of `key`.
+    ///
+    /// Keys defined by BEPs are never allocated. Other keys are allocated the
+    /// first time they're seen and shared afterwards.
+    pub fn intern(&mut self, key: &[u8]) -> InternedKey {
+        if let Ok(index) = KNOWN_KEYS.binary_search(&key) {
+            return InternedKey::Static(KNOWN_KEYS[index]);
+        }
+
+        match self.pool.get(key) {
+            Some(pooled) => InternedKey::Pooled(Arc::clone(pooled)),
+            None => {
+                let pooled: Arc<[u8]> = key.into();
+                self.pool.insert(Arc::clone(&pooled));
+                InternedKey::Pooled(pooled)
+            }
+        }
+    }
+
+    /// Number of keys in the pool excluding the static table.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty()
+    }
+
+    /// Remove every pooled key.
+    ///
+    /// Keys that were already returned stay valid.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.pool.clear()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{InternedKey, KeyInterner, KNOWN_KEYS};
+    use std::sync::Arc;
+
+    // Binary search requires the table to be sorted.
+    #[test]
+    fn known_keys_sorted() {
+        assert!(KNOWN_KEYS.windows(2).all(|keys| keys[0] < keys[1]));
+    }
+
+    #[test]
+    fn pool_shares_keys() {
+        let mut interner = KeyInterner::new();
+
+        let InternedKey::Pooled(first) = interner.intern(b"title") else {
+            panic!("title isn't a known key");
+        };
+        let InternedKey::Pooled(second) = interner.intern(b"title") else {
+            panic!("title isn't a known key");
+        };
+        assert!(Arc::ptr_eq(&first, &second));
+
+        assert_eq!(&*interner.intern(b"pieces root"), b"pieces root");
+        assert_eq!(interner.len(), 1);
+    }
+}
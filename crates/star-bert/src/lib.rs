@@ -5,4 +5,14 @@
 //! - `I`: Input, such as `&`[u8]
 //! - `N`: Numbers that implement [num_integer::Integer]
 
+#[cfg(feature = "serde")]
+pub mod de;
+pub mod diff;
+pub mod intern;
 pub mod parser;
+pub mod pretty;
+#[cfg(feature = "serde")]
+pub mod ser;
+#[cfg(feature = "serde")]
+pub mod serde_adapters;
+pub mod value;
@@ -1,7 +1,13 @@
 mod integer;
 mod bytes;
 pub mod parser_error;
+mod skip;
+mod value;
+mod values;
 
 pub use integer::integer;
 pub use bytes::{bytes, bytes_str};
 pub use parser_error::{BertErrorTrace, BertError, BertErrorKind};
+pub use skip::{raw_dict_value, skip};
+pub use value::{dict, list, value};
+pub use values::{values, Values};
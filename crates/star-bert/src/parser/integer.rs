@@ -7,7 +7,7 @@ use nom::{
     branch::alt,
     bytes::complete::tag,
     character::complete::{char, digit1},
-    combinator::{map_res, opt, peek, recognize, verify},
+    combinator::{map, map_res, opt, peek, recognize, verify},
     error::context,
     sequence::{delimited, pair, tuple},
     IResult,
@@ -56,6 +56,8 @@ use std::{fmt::Debug, str::FromStr};
 /// feature which is disabled by default.
 ///
 /// ```
+/// # #[cfg(feature = "bigint")]
+/// # {
 /// use nom::Finish;
 /// use num_bigint::{BigInt, ToBigInt};
 /// use star_bert::parser::integer;
@@ -63,7 +65,8 @@ use std::{fmt::Debug, str::FromStr};
 ///
 /// let big_num = format!("i{}e", u128::MAX.to_bigint().unwrap() + 1);
 /// let mun_gib = integer::<BigInt>(big_num.as_bytes()).finish()?;
-/// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+/// # }
+/// # Ok::<(), star_bert::parser::BertErrorTrace<Vec<u8>>>(())
 /// ```
 // #[inline]
 pub fn integer<N>(input: &[u8]) -> IResult<&[u8], N, BertErrorTrace<&[u8]>>
@@ -73,47 +76,56 @@ where
 {
     context(
         "[Parse] {integer} Arbitrary precision integer",
-        map_res(
-            delimited(
-                // Opening delimiter
-                tag("i"),
-                // Only parse the digits if the input is not -0\d{0,} or 0\d{1,}
-                // NOTE: I used `tuple` instead of `permutation` due to this line in the
-                // documentation: "The parsers are applied greedily: if there are
-                // multiple unapplied parsers that could parse the next slice of input, the first
-                // one is used."
-                // Permuting causes the checks to fail after the integer is parsed because the
-                // input has already been consumed.
-                tuple((
-                    // -0 is invalid. It doesn't matter what follows -0 as long as -0 matches.
-                    // In other words:
-                    // -0 is invalid thus if the input is only -0 then the parser should reject it
-                    // -01428 is invalid because of the leading 0 so the parser should reject the
-                    // input as well.
-                    context(
-                        "[Check] {integer} BEP-0003 forbids `i-0e` or `-0`",
-                        verify(opt(peek(tag("-0"))), Option::is_none),
-                    ),
-                    // This case handles a preceding 0. I call digit1 because digit0 would reject
-                    // `i0e` which is incorrect.
-                    context(
-                        "[Check] {integer} BEP-0003 forbids leading zeroes",
-                        verify(
-                            opt(peek(pair(char::<&[u8], _>('0'), digit1))),
-                            Option::is_none,
-                        ),
+        map_res(integer_digits, bytes_to_str_to_int),
+    )(input)
+}
+
+/// Recognize a Bencoded integer without converting it.
+///
+/// Returns the digits (with the sign) between 'i' and 'e' after checking them
+/// like [integer] so that integers of any size may be skipped or converted
+/// later.
+pub(super) fn integer_digits(input: &[u8]) -> IResult<&[u8], &[u8], BertErrorTrace<&[u8]>> {
+    map(
+        delimited(
+            // Opening delimiter
+            tag("i"),
+            // Only parse the digits if the input is not -0\d{0,} or 0\d{1,}
+            // NOTE: I used `tuple` instead of `permutation` due to this line in the
+            // documentation: "The parsers are applied greedily: if there are
+            // multiple unapplied parsers that could parse the next slice of input, the first
+            // one is used."
+            // Permuting causes the checks to fail after the integer is parsed because the
+            // input has already been consumed.
+            tuple((
+                // -0 is invalid. It doesn't matter what follows -0 as long as -0 matches.
+                // In other words:
+                // -0 is invalid thus if the input is only -0 then the parser should reject it
+                // -01428 is invalid because of the leading 0 so the parser should reject the
+                // input as well.
+                context(
+                    "[Check] {integer} BEP-0003 forbids `i-0e` or `-0`",
+                    verify(opt(peek(tag("-0"))), Option::is_none),
+                ),
+                // This case handles a preceding 0. I call digit1 because digit0 would reject
+                // `i0e` which is incorrect.
+                context(
+                    "[Check] {integer} BEP-0003 forbids leading zeroes",
+                    verify(
+                        opt(peek(pair(char::<&[u8], _>('0'), digit1))),
+                        Option::is_none,
                     ),
-                    // If the condition holds, match either a positive integer (digit1) or a
-                    // negative (the second parser) `recognize` returns the
-                    // consumed input as the result rather than tuples of `pair`
-                    alt((digit1, recognize(pair(char('-'), digit1)))),
-                )),
-                // Closing delimiter
-                tag("e"),
-            ),
-            // Map the result to N, the integer output
-            |(_, _, maybe_num)| bytes_to_str_to_int(maybe_num),
+                ),
+                // If the condition holds, match either a positive integer (digit1) or a
+                // negative (the second parser) `recognize` returns the
+                // consumed input as the result rather than tuples of `pair`
+                alt((digit1, recognize(pair(char('-'), digit1)))),
+            )),
+            // Closing delimiter
+            tag("e"),
         ),
+        // Only keep the digits
+        |(_, _, digits)| digits,
     )(input)
 }
 
@@ -1,16 +1,34 @@
 use nom::error::{ContextError, ErrorKind, FromExternalError, ParseError};
-use std::{num::ParseIntError, str::Utf8Error};
+use std::{
+    fmt::{self, Debug, Display, Formatter},
+    num::ParseIntError,
+    str::Utf8Error,
+};
 use thiserror::Error;
 
 #[cfg(feature = "bigint")]
 use num_bigint::ParseBigIntError;
 
-#[derive(Debug, Error)]
-#[error("{sources}")]
+#[derive(Debug)]
 pub struct BertErrorTrace<I> {
     sources: Vec<BertError<I>>,
 }
 
+// Contexts are pushed as the error bubbles up so the outermost is displayed first.
+impl<I> Display for BertErrorTrace<I> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, error) in self.sources.iter().rev().enumerate() {
+            if i > 0 {
+                f.write_str(" -> ")?;
+            }
+            write!(f, "{}", error.source)?;
+        }
+        Ok(())
+    }
+}
+
+impl<I> std::error::Error for BertErrorTrace<I> where I: Debug {}
+
 // Error traits from nom
 // These are implemented similar to [nom::error::VerboseError]
 impl<I> ContextError<I> for BertErrorTrace<I> {
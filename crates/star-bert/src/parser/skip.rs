@@ -0,0 +1,176 @@
+//! Skip over Bencoded values without materializing them.
+//!
+//! Skipping checks that a value is well formed and returns its raw bytes, but
+//! it doesn't allocate values or convert integers. Nesting is tracked with a
+//! stack rather than recursion so deeply nested input can't overflow the call
+//! stack.
+use crate::parser::{bytes, integer::integer_digits, BertErrorTrace};
+use nom::{
+    character::complete::char,
+    error::{context, ContextError, ErrorKind, ParseError},
+    IResult,
+};
+
+// Container that the next item belongs to.
+enum Container {
+    List,
+    // Dictionaries alternate between keys and values.
+    Dict { key_next: bool },
+}
+
+/// Skip one complete Bencoded value.
+///
+/// Returns the raw bytes of the value, which may be parsed later with
+/// [value](crate::parser::value) or a Serde deserializer.
+///
+/// # Examples
+/// ```
+/// use nom::Finish;
+/// use star_bert::parser::skip;
+/// # use star_bert::parser::BertErrorTrace;
+///
+/// let (remaining, skipped) =
+///     skip(b"d4:listli1ei2ee3:big40:1234567890123456789012345678901234567890ei42e").finish()?;
+/// assert_eq!(remaining, b"i42e");
+/// assert!(skipped.starts_with(b"d4:list"));
+/// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+/// ```
+pub fn skip(input: &[u8]) -> IResult<&[u8], &[u8], BertErrorTrace<&[u8]>> {
+    context("[Parse] {skip} Skip any value", skip_value)(input)
+}
+
+fn skip_value(input: &[u8]) -> IResult<&[u8], &[u8], BertErrorTrace<&[u8]>> {
+    let mut stack = Vec::new();
+    let mut rest = input;
+
+    loop {
+        let key_next = matches!(stack.last(), Some(Container::Dict { key_next: true }));
+
+        // Whether a whole item was skipped rather than a container opened.
+        let completed;
+        (rest, completed) = match rest.first() {
+            Some(b'e') if key_next || matches!(stack.last(), Some(Container::List)) => {
+                stack.pop();
+                (&rest[1..], true)
+            }
+            Some(b'0'..=b'9') => (bytes(rest)?.0, true),
+            _ if key_next => {
+                return Err(nom::Err::Error(BertErrorTrace::add_context(
+                    rest,
+                    "[Expected] {skip} Byte string dictionary key",
+                    BertErrorTrace::from_error_kind(rest, ErrorKind::Digit),
+                )))
+            }
+            Some(b'i') => (integer_digits(rest)?.0, true),
+            Some(b'l') => {
+                stack.push(Container::List);
+                (&rest[1..], false)
+            }
+            Some(b'd') => {
+                stack.push(Container::Dict { key_next: true });
+                (&rest[1..], false)
+            }
+            _ => {
+                return Err(nom::Err::Error(BertErrorTrace::from_error_kind(
+                    rest,
+                    ErrorKind::Alt,
+                )))
+            }
+        };
+
+        if completed {
+            match stack.last_mut() {
+                None => return Ok((rest, &input[..input.len() - rest.len()])),
+                Some(Container::Dict { key_next }) => *key_next = !*key_next,
+                Some(Container::List) => {}
+            }
+        }
+    }
+}
+
+/// Find `key` in a Bencoded dictionary and return the raw bytes of its value.
+///
+/// Every other value is skipped rather than parsed. The first value is
+/// returned if the key is duplicated.
+///
+/// # Examples
+/// ```
+/// use nom::Finish;
+/// use star_bert::parser::{raw_dict_value, value};
+/// # use star_bert::parser::BertErrorTrace;
+///
+/// let torrent = b"d8:announce15:udp://cats.test4:infod6:lengthi42e4:name4:catsee";
+/// let (_, info) = raw_dict_value(b"info")(torrent).finish()?;
+/// assert_eq!(info, Some(&b"d6:lengthi42e4:name4:catse"[..]));
+///
+/// let (_, info) = value(info.unwrap()).finish()?;
+/// assert_eq!(
+///     info.get(b"name").and_then(|name| name.as_str()),
+///     Some("cats")
+/// );
+/// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn raw_dict_value(
+    key: &[u8],
+) -> impl FnMut(&[u8]) -> IResult<&[u8], Option<&[u8]>, BertErrorTrace<&[u8]>> + '_ {
+    move |input| {
+        let (mut rest, _) = context("[Parse] {raw_dict_value} Dictionary", char('d'))(input)?;
+        let mut found = None;
+
+        loop {
+            if let Some(remaining) = rest.strip_prefix(b"e") {
+                return Ok((remaining, found));
+            }
+
+            let (remaining, k) = bytes(rest)?;
+            let (remaining, value) = skip(remaining)?;
+            if k == key && found.is_none() {
+                found = Some(value);
+            }
+            rest = remaining;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{raw_dict_value, skip, BertErrorTrace};
+    use nom::Finish;
+
+    #[test]
+    fn skip_nested() -> Result<(), BertErrorTrace<Vec<u8>>> {
+        let nested = b"d1:ald1:bleee1:ci-3ee4:cats";
+        let (remaining, skipped) = skip(nested).finish()?;
+        assert_eq!(skipped, &nested[..nested.len() - 6]);
+        assert_eq!(remaining, b"4:cats");
+
+        // Integers aren't converted so they may be larger than any integer type.
+        let big = format!("i{}e", u128::MAX);
+        assert_eq!(skip(big.as_bytes()).finish()?.1, big.as_bytes());
+
+        Ok(())
+    }
+
+    #[test]
+    fn skip_deep_nesting() {
+        let deep = [vec![b'l'; 100_000], vec![b'e'; 100_000]].concat();
+        assert_eq!(skip(&deep).unwrap().1.len(), deep.len());
+    }
+
+    #[test]
+    fn skip_rejects_malformed() {
+        for malformed in [&b"li1e"[..], b"di1ei2ee", b"d1:ae", b"i-0e", b"e", b"x"] {
+            assert!(skip(malformed).is_err(), "{malformed:?}");
+        }
+    }
+
+    #[test]
+    fn missing_key() -> Result<(), BertErrorTrace<Vec<u8>>> {
+        let (remaining, value) = raw_dict_value(b"info")(b"d4:name4:catsei1e").finish()?;
+        assert_eq!(value, None);
+        assert_eq!(remaining, b"i1e");
+        assert!(raw_dict_value(b"info")(b"l4:infoe").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Parse any Bencoded value including lists and dictionaries.
+//!
+//! [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html)
+use crate::{
+    parser::{bytes, integer, BertErrorTrace},
+    value::Value,
+};
+use nom::{
+    branch::alt,
+    character::complete::char,
+    combinator::map,
+    error::context,
+    multi::many0,
+    sequence::{delimited, pair},
+    IResult,
+};
+
+/// Parse any Bencoded value.
+///
+/// Integers are parsed as [i64]. Use [integer] directly for arbitrarily sized
+/// integers.
+///
+/// # Examples
+/// ```
+/// use nom::Finish;
+/// use star_bert::{parser::value, value::Value};
+/// # use star_bert::parser::BertErrorTrace;
+///
+/// let (remaining, cats) = value(b"l4:catsi3ee").finish()?;
+/// assert_eq!(
+///     cats,
+///     Value::List(vec![Value::Bytes(b"cats"), Value::Integer(3)])
+/// );
+/// # assert_eq!(remaining.len(), 0);
+/// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+/// ```
+pub fn value(input: &[u8]) -> IResult<&[u8], Value<'_>, BertErrorTrace<&[u8]>> {
+    context(
+        "[Parse] {value} Integer, bytes, list, or dictionary",
+        alt((
+            map(integer::<i64>, Value::Integer),
+            map(bytes, Value::Bytes),
+            map(list, Value::List),
+            map(dict, Value::Dict),
+        )),
+    )(input)
+}
+
+/// Parse a Bencoded list.
+///
+/// Lists are delimited by 'l' and 'e' and may contain any value, such as
+/// `l4:spami42ee`.
+pub fn list(input: &[u8]) -> IResult<&[u8], Vec<Value<'_>>, BertErrorTrace<&[u8]>> {
+    context(
+        "[Parse] {list} List of values",
+        delimited(
+            char('l'),
+            many0(value),
+            context("[Expected] {list} Closing delimiter `e`", char('e')),
+        ),
+    )(input)
+}
+
+/// Parse a Bencoded dictionary.
+///
+/// Dictionaries are delimited by 'd' and 'e' and contain byte string keys
+/// each followed by a value, such as `d4:spami42ee`. Keys are returned in the
+/// order they were parsed. BEP-0003 requires sorted keys, but that isn't
+/// checked here.
+#[allow(clippy::type_complexity)]
+pub fn dict(input: &[u8]) -> IResult<&[u8], Vec<(&[u8], Value<'_>)>, BertErrorTrace<&[u8]>> {
+    context(
+        "[Parse] {dict} Dictionary of byte string keys and values",
+        delimited(
+            char('d'),
+            many0(pair(bytes, value)),
+            context("[Expected] {dict} Closing delimiter `e`", char('e')),
+        ),
+    )(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        parser::{dict, list, value, BertErrorTrace},
+        value::Value,
+    };
+    use nom::Finish;
+
+    #[test]
+    fn nested_values() -> Result<(), BertErrorTrace<Vec<u8>>> {
+        let (remaining, torrent) =
+            value(b"d8:announce15:udp://cats.test4:infod6:lengthi42e4:name4:catsee").finish()?;
+        assert_eq!(remaining.len(), 0);
+        assert_eq!(
+            torrent.get(b"announce").and_then(Value::as_str),
+            Some("udp://cats.test")
+        );
+
+        let info = torrent.get(b"info").unwrap();
+        assert_eq!(info.get(b"length").and_then(Value::as_integer), Some(42));
+        assert_eq!(info.as_dict().map(<[_]>::len), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_containers() -> Result<(), BertErrorTrace<Vec<u8>>> {
+        assert_eq!(list(b"le").finish()?.1, vec![]);
+        assert_eq!(dict(b"de").finish()?.1, vec![]);
+        Ok(())
+    }
+
+    // Dictionaries keep their keys as they were written.
+    #[test]
+    fn unsorted_dict_keys() -> Result<(), BertErrorTrace<Vec<u8>>> {
+        let (_, meows) = dict(b"d1:bi1e1:ai2e1:bi3ee").finish()?;
+        let keys: Vec<_> = meows.iter().map(|(key, _)| *key).collect();
+        assert_eq!(keys, [&b"b"[..], b"a", b"b"]);
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "")]
+    fn unterminated_list() {
+        list(b"li1ei2e").unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "")]
+    fn integer_dict_key() {
+        dict(b"di1ei2ee").unwrap();
+    }
+}
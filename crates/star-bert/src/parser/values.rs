@@ -0,0 +1,115 @@
+//! Iterate over Bencoded values that are concatenated back to back.
+//!
+//! Session files and network streams may contain several top level values
+//! rather than one, such as `d...ed...e`.
+use crate::{
+    parser::{value, BertErrorTrace},
+    value::Value,
+};
+use std::{iter::FusedIterator, ops::Range};
+
+/// Iterate over the top level values in `input`.
+///
+/// Each value is yielded with its span in `input`. Iteration stops once the
+/// input is exhausted or after the first error.
+///
+/// # Examples
+/// ```
+/// use star_bert::{parser::values, value::Value};
+/// # use star_bert::parser::BertErrorTrace;
+///
+/// let input = b"i42e4:catsli1ee";
+/// let parsed = values(input).collect::<Result<Vec<_>, _>>()?;
+///
+/// assert_eq!(parsed[0], (Value::Integer(42), 0..4));
+/// assert_eq!(parsed[1], (Value::Bytes(b"cats"), 4..10));
+/// assert_eq!(&input[parsed[2].1.clone()], b"li1ee");
+/// # Ok::<(), BertErrorTrace<&[u8]>>(())
+/// ```
+#[inline]
+pub fn values(input: &[u8]) -> Values<'_> {
+    Values {
+        input,
+        offset: 0,
+        failed: false,
+    }
+}
+
+/// Iterator over concatenated Bencoded values created by [values].
+#[derive(Debug, Clone)]
+pub struct Values<'input> {
+    input: &'input [u8],
+    offset: usize,
+    failed: bool,
+}
+
+impl<'input> Values<'input> {
+    /// Input that hasn't been parsed yet.
+    #[inline]
+    pub fn remaining(&self) -> &'input [u8] {
+        &self.input[self.offset..]
+    }
+}
+
+impl<'input> Iterator for Values<'input> {
+    type Item = Result<(Value<'input>, Range<usize>), BertErrorTrace<&'input [u8]>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let remaining = self.remaining();
+        if self.failed || remaining.is_empty() {
+            return None;
+        }
+
+        match value(remaining) {
+            Ok((rest, parsed)) => {
+                let start = self.offset;
+                self.offset = self.input.len() - rest.len();
+                Some(Ok((parsed, start..self.offset)))
+            }
+            Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+                self.failed = true;
+                Some(Err(e))
+            }
+            // The parsers are complete so they never ask for more input.
+            Err(nom::Err::Incomplete(_)) => {
+                self.failed = true;
+                None
+            }
+        }
+    }
+}
+
+impl FusedIterator for Values<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parser::values, value::Value};
+
+    #[test]
+    fn concatenated_dicts() {
+        let input = b"d4:name4:catsed4:name4:mewse";
+        let names: Vec<_> = values(input)
+            .map(|parsed| {
+                let (dict, span) = parsed.unwrap();
+                (dict.get(b"name").and_then(Value::as_str).unwrap(), span)
+            })
+            .collect();
+
+        assert_eq!(names, [("cats", 0..14), ("mews", 14..28)]);
+    }
+
+    #[test]
+    fn stop_after_error() {
+        let mut parsed = values(b"i1ex:bad4:cats");
+
+        assert_eq!(parsed.next().unwrap().unwrap().1, 0..3);
+        assert!(parsed.next().unwrap().is_err());
+        assert!(parsed.next().is_none());
+        assert_eq!(parsed.remaining(), b"x:bad4:cats");
+    }
+
+    #[test]
+    fn empty_input() {
+        assert!(values(b"").next().is_none());
+    }
+}
@@ -0,0 +1,154 @@
+//! Indented, human readable dumps of [Value]s.
+//!
+//! Dumps are meant for debugging torrents that don't parse into a strongly
+//! typed structure. Byte strings that are printable UTF-8 are quoted. Binary
+//! strings, such as `pieces`, are printed as hexadecimal and truncated so that
+//! they don't drown out the structure.
+
+use crate::value::Value;
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Bytes of a binary string that are printed by default.
+pub const DEFAULT_MAX_BYTES: usize = 32;
+
+/// Pretty printer for a [Value].
+///
+/// Dictionary keys are printed in the order they were parsed so that unsorted
+/// keys stand out.
+///
+/// # Examples
+/// ```
+/// use nom::Finish;
+/// use star_bert::{parser::value, pretty::Pretty};
+/// # use star_bert::parser::BertErrorTrace;
+///
+/// let (_, cats) = value(b"d4:infod4:name4:cats6:pieces4:\x00\x01\x02\x03e4:tagsli3e0:ee").finish()?;
+/// assert_eq!(
+///     Pretty::new(&cats).max_bytes(2).to_string(),
+///     "{\n  \"info\": {\n    \"name\": \"cats\",\n    \"pieces\": <4 bytes: 0001...>\n  },\n  \"tags\": [\n    3,\n    \"\"\n  ]\n}"
+/// );
+/// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Pretty<'a, 'input> {
+    value: &'a Value<'input>,
+    indent: usize,
+    max_bytes: usize,
+}
+
+impl<'a, 'input> Pretty<'a, 'input> {
+    /// Print `value` indented by two spaces per level.
+    #[inline]
+    pub fn new(value: &'a Value<'input>) -> Self {
+        Self {
+            value,
+            indent: 2,
+            max_bytes: DEFAULT_MAX_BYTES,
+        }
+    }
+
+    /// Spaces per level of nesting.
+    #[inline]
+    pub fn indent(self, indent: usize) -> Self {
+        Self { indent, ..self }
+    }
+
+    /// Bytes of each binary string to print before truncating it.
+    ///
+    /// Use [usize::MAX] to print every byte.
+    #[inline]
+    pub fn max_bytes(self, max_bytes: usize) -> Self {
+        Self { max_bytes, ..self }
+    }
+
+    fn write_value(&self, f: &mut Formatter<'_>, value: &Value<'_>, depth: usize) -> fmt::Result {
+        match value {
+            Value::Integer(integer) => write!(f, "{integer}"),
+            Value::Bytes(bytes) => self.write_bytes(f, bytes),
+            Value::List(list) if list.is_empty() => f.write_str("[]"),
+            Value::List(list) => {
+                f.write_char('[')?;
+                for (i, element) in list.iter().enumerate() {
+                    f.write_str(if i == 0 { "\n" } else { ",\n" })?;
+                    self.write_indent(f, depth + 1)?;
+                    self.write_value(f, element, depth + 1)?;
+                }
+                f.write_char('\n')?;
+                self.write_indent(f, depth)?;
+                f.write_char(']')
+            }
+            Value::Dict(dict) if dict.is_empty() => f.write_str("{}"),
+            Value::Dict(dict) => {
+                f.write_char('{')?;
+                for (i, (key, element)) in dict.iter().enumerate() {
+                    f.write_str(if i == 0 { "\n" } else { ",\n" })?;
+                    self.write_indent(f, depth + 1)?;
+                    self.write_bytes(f, key)?;
+                    f.write_str(": ")?;
+                    self.write_value(f, element, depth + 1)?;
+                }
+                f.write_char('\n')?;
+                self.write_indent(f, depth)?;
+                f.write_char('}')
+            }
+        }
+    }
+
+    fn write_bytes(&self, f: &mut Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+        match std::str::from_utf8(bytes) {
+            Ok(string) if !string.chars().any(char::is_control) => write!(f, "{string:?}"),
+            _ => {
+                write!(f, "<{} bytes: ", bytes.len())?;
+                for byte in bytes.iter().take(self.max_bytes) {
+                    write!(f, "{byte:02x}")?;
+                }
+                if bytes.len() > self.max_bytes {
+                    f.write_str("...")?;
+                }
+                f.write_char('>')
+            }
+        }
+    }
+
+    #[inline]
+    fn write_indent(&self, f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:width$}", "", width = depth * self.indent)
+    }
+}
+
+impl Display for Pretty<'_, '_> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write_value(f, self.value, 0)
+    }
+}
+
+impl<'input> Value<'input> {
+    /// Pretty print the value with the default options of [Pretty].
+    #[inline]
+    pub fn pretty(&self) -> Pretty<'_, 'input> {
+        Pretty::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pretty;
+    use crate::parser::value;
+    use nom::Finish;
+
+    #[test]
+    fn binary_strings_truncated() {
+        let (_, value) = value(b"l3:\xff\x00\x014:\x01\x02\x03\x04de4:\tcate")
+            .finish()
+            .unwrap();
+        assert_eq!(
+            Pretty::new(&value).max_bytes(3).indent(1).to_string(),
+            "[\n <3 bytes: ff0001>,\n <4 bytes: 010203...>,\n {},\n <4 bytes: 096361...>\n]"
+        );
+        assert_eq!(
+            value.pretty().to_string().lines().nth(2),
+            Some("  <4 bytes: 01020304>,")
+        );
+    }
+}
@@ -0,0 +1,618 @@
+//! Serialize Rust types to Bencode with Serde.
+//!
+//! Dictionary keys are sorted by their raw bytes as BEP-0003 requires, and
+//! entries whose value is `None` are left out because Bencode doesn't have
+//! null. `bool`s are written as `i0e` and `i1e`. Floats and units can't be
+//! represented and are rejected.
+//!
+//! Enums are externally tagged: unit variants are byte strings and other
+//! variants are dictionaries with the variant as their only key.
+use crate::parser::bytes;
+use serde::ser::{self, Serialize};
+use std::fmt::Display;
+use thiserror::Error;
+
+/// Errors while serializing Bencode.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum Error {
+    /// Type that Bencode can't represent.
+    #[error("Bencode can't represent {0}")]
+    Unsupported(&'static str),
+    /// Dictionary key that isn't serialized as a byte string.
+    #[error("dictionary keys must be byte strings")]
+    KeyNotBytes,
+    /// Error from a [ser::Serialize] implementation.
+    #[error("{0}")]
+    Custom(String),
+}
+
+impl ser::Error for Error {
+    #[inline]
+    fn custom<T>(msg: T) -> Self
+    where
+        T: Display,
+    {
+        Error::Custom(msg.to_string())
+    }
+}
+
+/// Serialize `value` to Bencode.
+///
+/// # Examples
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Info<'a> {
+///     name: &'a str,
+///     length: u64,
+///     md5sum: Option<&'a str>,
+/// }
+///
+/// let info = Info {
+///     name: "cats",
+///     length: 42,
+///     md5sum: None,
+/// };
+/// assert_eq!(
+///     star_bert::ser::to_bytes(&info)?,
+///     b"d6:lengthi42e4:name4:catse"
+/// );
+/// # Ok::<(), star_bert::ser::Error>(())
+/// ```
+pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::new();
+    value.serialize(&mut serializer)?;
+    Ok(serializer.into_inner())
+}
+
+/// Serde serializer that writes Bencode into a buffer.
+#[derive(Debug, Default)]
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl Serializer {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Bencode written so far.
+    #[inline]
+    pub fn into_inner(self) -> Vec<u8> {
+        self.output
+    }
+
+    fn write_integer<N>(&mut self, integer: N)
+    where
+        N: Display,
+    {
+        self.output.push(b'i');
+        self.output
+            .extend_from_slice(integer.to_string().as_bytes());
+        self.output.push(b'e');
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.output
+            .extend_from_slice(bytes.len().to_string().as_bytes());
+        self.output.push(b':');
+        self.output.extend_from_slice(bytes);
+    }
+}
+
+impl<'a> ser::Serializer for &'a mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = List<'a>;
+    type SerializeTuple = List<'a>;
+    type SerializeTupleStruct = List<'a>;
+    type SerializeTupleVariant = List<'a>;
+    type SerializeMap = Dict<'a>;
+    type SerializeStruct = Dict<'a>;
+    type SerializeStructVariant = Dict<'a>;
+
+    #[inline]
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        self.write_integer(u8::from(v));
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    #[inline]
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    #[inline]
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    #[inline]
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        self.write_integer(v);
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        self.write_integer(v);
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    #[inline]
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    #[inline]
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    #[inline]
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        self.write_integer(v);
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        self.write_integer(v);
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, Self::Error> {
+        Err(Error::Unsupported("floats"))
+    }
+
+    #[inline]
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, Self::Error> {
+        Err(Error::Unsupported("floats"))
+    }
+
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    #[inline]
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        self.write_bytes(v);
+        Ok(())
+    }
+
+    // Nothing is written so that dictionaries can leave the entry out.
+    #[inline]
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Err(Error::Unsupported("unit"))
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Err(Error::Unsupported("unit structs"))
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, Self::Error> {
+        self.serialize_str(variant)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.output.push(b'd');
+        self.write_bytes(variant.as_bytes());
+        value.serialize(&mut *self)?;
+        self.output.push(b'e');
+        Ok(())
+    }
+
+    #[inline]
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        self.output.push(b'l');
+        Ok(List {
+            ser: self,
+            variant: false,
+        })
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        self.output.push(b'd');
+        self.write_bytes(variant.as_bytes());
+        self.output.push(b'l');
+        Ok(List {
+            ser: self,
+            variant: true,
+        })
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(Dict::new(self, len.unwrap_or_default(), None))
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(Dict::new(self, len, None))
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(Dict::new(self, len, Some(variant)))
+    }
+}
+
+/// Serializes lists, tuples, and tuple variants.
+#[derive(Debug)]
+pub struct List<'a> {
+    ser: &'a mut Serializer,
+    // Tuple variants are wrapped in a dictionary.
+    variant: bool,
+}
+
+impl<'a> List<'a> {
+    fn end(self) -> Result<(), Error> {
+        self.ser.output.push(b'e');
+        if self.variant {
+            self.ser.output.push(b'e');
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeSeq for List<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        List::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTuple for List<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        List::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for List<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        List::end(self)
+    }
+}
+
+impl<'a> ser::SerializeTupleVariant for List<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut *self.ser)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        List::end(self)
+    }
+}
+
+/// Serializes maps, structs, and struct variants.
+///
+/// Entries are buffered so that they can be sorted by key before they're
+/// written.
+#[derive(Debug)]
+pub struct Dict<'a> {
+    ser: &'a mut Serializer,
+    // Raw keys and their serialized values.
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+    key: Option<Vec<u8>>,
+    // Struct variants are wrapped in a dictionary.
+    variant: Option<&'static str>,
+}
+
+impl<'a> Dict<'a> {
+    #[inline]
+    fn new(ser: &'a mut Serializer, len: usize, variant: Option<&'static str>) -> Self {
+        Self {
+            ser,
+            entries: Vec::with_capacity(len),
+            key: None,
+            variant,
+        }
+    }
+
+    fn entry<T>(&mut self, key: Vec<u8>, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = to_bytes(value)?;
+        // `None` doesn't write anything.
+        if !value.is_empty() {
+            self.entries.push((key, value));
+        }
+        Ok(())
+    }
+
+    fn end(mut self) -> Result<(), Error> {
+        if let Some(variant) = self.variant {
+            self.ser.output.push(b'd');
+            self.ser.write_bytes(variant.as_bytes());
+        }
+
+        self.entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.ser.output.push(b'd');
+        for (key, value) in self.entries {
+            self.ser.write_bytes(&key);
+            self.ser.output.extend_from_slice(&value);
+        }
+        self.ser.output.push(b'e');
+
+        if self.variant.is_some() {
+            self.ser.output.push(b'e');
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for Dict<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = to_bytes(key)?;
+        match bytes(&key) {
+            Ok((&[], key)) => {
+                self.key = Some(key.to_vec());
+                Ok(())
+            }
+            _ => Err(Error::KeyNotBytes),
+        }
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| <Error as ser::Error>::custom("value serialized before its key"))?;
+        self.entry(key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Dict::end(self)
+    }
+}
+
+impl<'a> ser::SerializeStruct for Dict<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entry(key.as_bytes().to_vec(), value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Dict::end(self)
+    }
+}
+
+impl<'a> ser::SerializeStructVariant for Dict<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.entry(key.as_bytes().to_vec(), value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        Dict::end(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_bytes, Error};
+    use crate::de::from_bytes;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Torrent {
+        announce: String,
+        #[serde(rename = "creation date")]
+        creation_date: Option<u64>,
+        comment: Option<String>,
+        private: bool,
+        sources: Vec<Source>,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    enum Source {
+        Dht,
+        Tracker(String),
+        Peer { port: u16 },
+    }
+
+    #[test]
+    fn roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let torrent = Torrent {
+            announce: "udp://cats.test".to_owned(),
+            creation_date: Some(42),
+            comment: None,
+            private: false,
+            sources: vec![
+                Source::Dht,
+                Source::Tracker("udp://cats.test".to_owned()),
+                Source::Peer { port: 6881 },
+            ],
+        };
+
+        let bencoded = to_bytes(&torrent)?;
+        assert_eq!(
+            bencoded,
+            b"d8:announce15:udp://cats.test13:creation datei42e7:privatei0e7:sourcesl3:Dhtd7:Tracker15:udp://cats.tested4:Peerd4:porti6881eeeee"
+        );
+        assert_eq!(from_bytes::<Torrent>(&bencoded)?, torrent);
+        Ok(())
+    }
+
+    // Keys are sorted by bytes rather than by insertion or field order.
+    #[test]
+    fn sorted_keys() -> Result<(), Error> {
+        let map = HashMap::from([("b", 2), ("a", 1), ("B", 3)]);
+        assert_eq!(to_bytes(&map)?, b"d1:Bi3e1:ai1e1:bi2ee");
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(matches!(to_bytes(&1.5), Err(Error::Unsupported(_))));
+        assert!(matches!(to_bytes(&()), Err(Error::Unsupported(_))));
+        assert!(matches!(
+            to_bytes(&HashMap::from([(1, 2)])),
+            Err(Error::KeyNotBytes)
+        ));
+    }
+}
@@ -0,0 +1,275 @@
+//! Serde adapters for common Bencode quirks.
+//!
+//! Bencode lacks booleans and null, and torrents in the wild disagree on
+//! whether a field is a single value or a list. These modules are used with
+//! `#[serde(with = "...")]` so that crates don't need their own copies.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Deserialize, Serialize)]
+//! struct Info {
+//!     #[serde(default, with = "star_bert::serde_adapters::option_int_bool")]
+//!     private: Option<bool>,
+//!     #[serde(
+//!         default,
+//!         rename = "url-list",
+//!         with = "star_bert::serde_adapters::string_or_list"
+//!     )]
+//!     url_list: Vec<String>,
+//! }
+//! ```
+
+/// `bool` as the integers 0 and 1.
+pub mod int_bool {
+    use serde::{
+        de::{Error as DeErrorTrait, Unexpected},
+        Deserialize, Deserializer, Serializer,
+    };
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<bool, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match u8::deserialize(deserializer)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            nonbool => Err(D::Error::invalid_value(
+                Unexpected::Unsigned(nonbool.into()),
+                &"zero or one",
+            )),
+        }
+    }
+
+    #[inline]
+    pub fn serialize<S>(value: &bool, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(u8::from(*value))
+    }
+}
+
+/// `Option<bool>` as the integers 0 and 1.
+///
+/// Use `#[serde(default)]` so that absent fields are `None` and skip `None`
+/// while serializing so that they stay absent.
+pub mod option_int_bool {
+    use serde::{Deserializer, Serializer};
+
+    #[inline]
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        super::int_bool::deserialize(deserializer).map(Some)
+    }
+
+    #[inline]
+    pub fn serialize<S>(value: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match value {
+            Some(value) => super::int_bool::serialize(value, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+/// `Vec<T>` from either a list or a single value.
+///
+/// Values are always serialized as a list.
+pub mod string_or_list {
+    use serde::{
+        de::{
+            value::{BorrowedBytesDeserializer, BorrowedStrDeserializer, BytesDeserializer},
+            Error as DeErrorTrait, IntoDeserializer, SeqAccess, Visitor,
+        },
+        Deserialize, Deserializer, Serialize, Serializer,
+    };
+    use std::{
+        fmt::{self, Formatter},
+        marker::PhantomData,
+    };
+
+    struct StringOrListVisitor<T>(PhantomData<T>);
+
+    impl<'de, T> Visitor<'de> for StringOrListVisitor<T>
+    where
+        T: Deserialize<'de>,
+    {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+            formatter.write_str("a value or a list of values")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut values = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(value) = seq.next_element()? {
+                values.push(value);
+            }
+            Ok(values)
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            T::deserialize(v.into_deserializer()).map(|value| vec![value])
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            T::deserialize(v.into_deserializer()).map(|value| vec![value])
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            T::deserialize(v.into_deserializer()).map(|value| vec![value])
+        }
+
+        fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            T::deserialize(BorrowedStrDeserializer::new(v)).map(|value| vec![value])
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            T::deserialize(BytesDeserializer::new(v)).map(|value| vec![value])
+        }
+
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            T::deserialize(BorrowedBytesDeserializer::new(v)).map(|value| vec![value])
+        }
+    }
+
+    #[inline]
+    pub fn deserialize<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        deserializer.deserialize_any(StringOrListVisitor(PhantomData))
+    }
+
+    #[inline]
+    pub fn serialize<S, T>(values: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        serializer.collect_seq(values)
+    }
+}
+
+/// `Option<N>` where zero is the same as an absent field.
+///
+/// Some clients write `i0e` rather than omitting a field such as
+/// `creation date`. Zero (the integer's default) deserializes to `None`, and
+/// `None` should be skipped while serializing so that it stays absent.
+pub mod zero_as_none {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[inline]
+    pub fn deserialize<'de, D, N>(deserializer: D) -> Result<Option<N>, D::Error>
+    where
+        D: Deserializer<'de>,
+        N: Deserialize<'de> + Default + PartialEq,
+    {
+        N::deserialize(deserializer).map(|value| (value != N::default()).then_some(value))
+    }
+
+    #[inline]
+    pub fn serialize<S, N>(value: &Option<N>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        N: Serialize,
+    {
+        match value {
+            Some(value) => value.serialize(serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Token};
+
+    #[derive(Debug, PartialEq, Deserialize, Serialize)]
+    struct Quirks {
+        #[serde(with = "super::int_bool")]
+        private: bool,
+        #[serde(with = "super::string_or_list")]
+        urls: Vec<String>,
+        #[serde(with = "super::zero_as_none")]
+        date: Option<u64>,
+    }
+
+    fn tokens(private: u8, urls: &[Token], date: u64) -> Vec<Token> {
+        [
+            &[
+                Token::Struct {
+                    name: "Quirks",
+                    len: 3,
+                },
+                Token::Str("private"),
+                Token::U8(private),
+                Token::Str("urls"),
+            ][..],
+            urls,
+            &[Token::Str("date"), Token::U64(date), Token::StructEnd],
+        ]
+        .concat()
+    }
+
+    #[test]
+    fn list_roundtrip() {
+        let quirks = Quirks {
+            private: true,
+            urls: vec!["udp://cats.test".to_owned()],
+            date: Some(42),
+        };
+        let list = [
+            Token::Seq { len: Some(1) },
+            Token::Str("udp://cats.test"),
+            Token::SeqEnd,
+        ];
+        assert_tokens(&quirks, &tokens(1, &list, 42));
+    }
+
+    #[test]
+    fn single_value_and_zero() {
+        let quirks = Quirks {
+            private: false,
+            urls: vec!["udp://cats.test".to_owned()],
+            date: None,
+        };
+        assert_de_tokens(&quirks, &tokens(0, &[Token::Bytes(b"udp://cats.test")], 0));
+    }
+
+    #[test]
+    fn reject_nonbool() {
+        assert_de_tokens_error::<Quirks>(
+            &tokens(2, &[], 0)[..3],
+            "invalid value: integer `2`, expected zero or one",
+        );
+    }
+}
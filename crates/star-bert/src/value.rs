@@ -0,0 +1,163 @@
+//! Bencoded values borrowed from their input.
+//!
+//! [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html)
+
+use crate::intern::{InternedKey, KeyInterner};
+
+/// Any Bencoded value.
+///
+/// Byte strings and dictionary keys borrow from the input. Dictionaries keep
+/// their keys in the order they were parsed so that unsorted or duplicate keys
+/// may be inspected rather than silently fixed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value<'input> {
+    Integer(i64),
+    Bytes(&'input [u8]),
+    List(Vec<Value<'input>>),
+    Dict(Vec<(&'input [u8], Value<'input>)>),
+}
+
+impl<'input> Value<'input> {
+    #[inline]
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Value::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> Option<&'input [u8]> {
+        match self {
+            Value::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Byte string as UTF-8 if it's valid.
+    #[inline]
+    pub fn as_str(&self) -> Option<&'input str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    #[inline]
+    pub fn as_list(&self) -> Option<&[Value<'input>]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_dict(&self) -> Option<&[(&'input [u8], Value<'input>)]> {
+        match self {
+            Value::Dict(dict) => Some(dict),
+            _ => None,
+        }
+    }
+
+    /// First value of `key` if this is a dictionary.
+    ///
+    /// ```
+    /// use nom::Finish;
+    /// use star_bert::parser::value;
+    /// # use star_bert::parser::BertErrorTrace;
+    ///
+    /// let (_, dict) = value(b"d4:name4:cats5:piecei3ee").finish()?;
+    /// assert_eq!(
+    ///     dict.get(b"name").and_then(|name| name.as_str()),
+    ///     Some("cats")
+    /// );
+    /// assert_eq!(dict.get(b"length"), None);
+    /// # Ok::<(), BertErrorTrace<Vec<u8>>>(())
+    /// ```
+    #[inline]
+    pub fn get(&self, key: &[u8]) -> Option<&Value<'input>> {
+        self.as_dict()?
+            .iter()
+            .find_map(|(k, value)| (*k == key).then_some(value))
+    }
+
+    /// Copy the value so that it doesn't borrow the input.
+    ///
+    /// Dictionary keys are shared through `interner`.
+    ///
+    /// ```
+    /// use nom::Finish;
+    /// use star_bert::{intern::KeyInterner, parser::values};
+    /// # use star_bert::parser::BertErrorTrace;
+    ///
+    /// let mut interner = KeyInterner::new();
+    /// let owned = values(b"d6:lengthi1e5:x_cati1eed6:lengthi2e5:x_cati2ee")
+    ///     .map(|parsed| parsed.map(|(value, _)| value.to_owned_value(&mut interner)))
+    ///     .collect::<Result<Vec<_>, _>>()?;
+    ///
+    /// assert_eq!(
+    ///     owned[1]
+    ///         .get(b"length")
+    ///         .and_then(|length| length.as_integer()),
+    ///     Some(2)
+    /// );
+    /// // `length` is a known key so only `x_cat` was allocated.
+    /// assert_eq!(interner.len(), 1);
+    /// # Ok::<(), BertErrorTrace<&[u8]>>(())
+    /// ```
+    pub fn to_owned_value(&self, interner: &mut KeyInterner) -> OwnedValue {
+        match self {
+            Value::Integer(integer) => OwnedValue::Integer(*integer),
+            Value::Bytes(bytes) => OwnedValue::Bytes(bytes.to_vec()),
+            Value::List(list) => OwnedValue::List(
+                list.iter()
+                    .map(|value| value.to_owned_value(interner))
+                    .collect(),
+            ),
+            Value::Dict(dict) => OwnedValue::Dict(
+                dict.iter()
+                    .map(|(key, value)| (interner.intern(key), value.to_owned_value(interner)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// [Value] that owns its data.
+///
+/// Dictionary keys are [InternedKey]s so that values parsed with the same
+/// [KeyInterner] share them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OwnedValue {
+    Integer(i64),
+    Bytes(Vec<u8>),
+    List(Vec<OwnedValue>),
+    Dict(Vec<(InternedKey, OwnedValue)>),
+}
+
+impl OwnedValue {
+    #[inline]
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            OwnedValue::Integer(integer) => Some(*integer),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            OwnedValue::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// First value of `key` if this is a dictionary.
+    #[inline]
+    pub fn get(&self, key: &[u8]) -> Option<&OwnedValue> {
+        match self {
+            OwnedValue::Dict(dict) => dict
+                .iter()
+                .find_map(|(k, value)| (**k == *key).then_some(value)),
+            _ => None,
+        }
+    }
+}
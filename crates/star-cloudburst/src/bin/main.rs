@@ -1,14 +1,37 @@
-#![feature(once_cell)]
+mod report;
+mod trackers;
 
 use color_eyre::owo_colors::{OwoColorize, Style};
-use color_eyre::{eyre::Context, Report, Result};
-use star_cloudburst::Torrent;
+use color_eyre::{
+    eyre::{eyre, Context},
+    Report, Result,
+};
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use report::LibraryReport;
+use star_bert::{
+    diff::{diff, differing_chunks, DiffKind, DiffPath, PathSegment},
+    parser::values,
+    pretty::DEFAULT_MAX_BYTES,
+    value::Value,
+};
+use star_cloudburst::{
+    create::TorrentBuilder,
+    metainfo::{infohash::InfoHashVersioned, TorrentVersion},
+    parseoptions::ParseOptions,
+    summary::Verbosity,
+    torrent::parse_many,
+    uri::uriwrapper::UriWrapper,
+    verify::VerifyStatus,
+    PieceLength, Torrent,
+};
 use std::{
     //cell::OnceCell,
-    fs::File,
-    io::{BufReader, Read},
+    fs::{self, File, OpenOptions},
+    io::{BufReader, Read, Write},
     path::{Path, PathBuf},
+    time::{Duration, SystemTime},
 };
+use trackers::Health;
 
 /*const OK: OnceCell<color_eyre::owo_colors::Styled<&str>> =
     Style::new().bright_green().style("Ok").into();
@@ -17,12 +40,204 @@ use std::{
 /// Deserialize torrent files.
 #[derive(argh::FromArgs)]
 struct Args {
-    /// parse torrents as a map for debugging purposes
+    #[argh(subcommand)]
+    command: Option<Command>,
+    /// dump torrents as generic Bencode for debugging torrents that fail to parse
     #[argh(switch, short = 'm')]
     map: bool,
     /// verbose print torrents
     #[argh(switch, short = 'v')]
     verbose: bool,
+    /// search directories for torrents recursively
+    #[argh(switch, short = 'r')]
+    recursive: bool,
+    /// glob of files in directories to read as torrents; repeat for more
+    /// globs (default: *.torrent)
+    #[argh(option)]
+    include: Vec<String>,
+    /// glob of files or directories to skip; repeat for more globs
+    #[argh(option)]
+    exclude: Vec<String>,
+    /// paths to torrent files and/or directories of torrent files
+    #[argh(positional)]
+    torrents: Vec<PathBuf>,
+}
+
+#[derive(argh::FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Create(CreateArgs),
+    CrossSeed(CrossSeedArgs),
+    Diff(DiffArgs),
+    Files(FilesArgs),
+    Hash(HashArgs),
+    Inspect(InspectArgs),
+    Report(ReportArgs),
+    Trackers(TrackersArgs),
+    Verify(VerifyArgs),
+}
+
+/// Create a torrent of a file or directory.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "create")]
+struct CreateArgs {
+    /// file or directory to share
+    #[argh(positional)]
+    path: PathBuf,
+    /// announce URL; separate backup trackers of the same tier with commas and
+    /// repeat for more tiers
+    #[argh(option, short = 'a')]
+    announce: Vec<String>,
+    /// piece length in bytes; a power of two of at least 16 KiB (default: based
+    /// on the total size)
+    #[argh(option, short = 'l', from_str_fn(parse_piece_length))]
+    piece_length: Option<PieceLength>,
+    /// meta version: 1, 2, or hybrid (default: hybrid)
+    #[argh(option, default = "TorrentVersion::Hybrid", from_str_fn(parse_version))]
+    meta_version: TorrentVersion,
+    /// web seed URL; repeat for more web seeds
+    #[argh(option, short = 'w')]
+    web_seed: Vec<UriWrapper>,
+    /// set the private flag
+    #[argh(switch, short = 'p')]
+    private: bool,
+    /// comment
+    #[argh(option, short = 'c')]
+    comment: Option<String>,
+    /// info dict `source` tag
+    #[argh(option, short = 's')]
+    source: Option<String>,
+    /// include the current time as the creation date
+    #[argh(switch, short = 'd')]
+    date: bool,
+    /// path of the new torrent (default: <name>.torrent)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// overwrite the output if it exists
+    #[argh(switch, short = 'f')]
+    force: bool,
+}
+
+fn parse_piece_length(value: &str) -> Result<PieceLength, String> {
+    value
+        .parse()
+        .ok()
+        .and_then(PieceLength::new)
+        .ok_or_else(|| "piece length should be a power of two of at least 16384 bytes".to_owned())
+}
+
+fn parse_version(value: &str) -> Result<TorrentVersion, String> {
+    match value {
+        "1" | "v1" => Ok(TorrentVersion::V1),
+        "2" | "v2" => Ok(TorrentVersion::V2),
+        "hybrid" => Ok(TorrentVersion::Hybrid),
+        _ => Err("meta version should be 1, 2, or hybrid".to_owned()),
+    }
+}
+
+/// Copy a torrent for another tracker with a new source and trackers.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "cross-seed")]
+struct CrossSeedArgs {
+    /// torrent to copy
+    #[argh(positional)]
+    torrent: PathBuf,
+    /// info dict `source` tag of the new tracker
+    #[argh(option)]
+    source: String,
+    /// announce URL of the new tracker; repeat for backup trackers
+    #[argh(option)]
+    announce: Vec<UriWrapper>,
+    /// path of the new torrent (default: <name>.<source>.torrent)
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+/// Compare two torrents as generic Bencode, such as to find out why an info hash changed.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "diff")]
+struct DiffArgs {
+    /// original torrent
+    #[argh(positional)]
+    old: PathBuf,
+    /// torrent to compare to the original
+    #[argh(positional)]
+    new: PathBuf,
+}
+
+/// List the paths and sizes of the files of torrents.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "files")]
+struct FilesArgs {
+    /// list padding files too
+    #[argh(switch, short = 'p')]
+    padding: bool,
+    /// paths to torrent files and/or directories of torrent files
+    #[argh(positional)]
+    torrents: Vec<PathBuf>,
+}
+
+/// Print the v1 and/or v2 info hashes of torrents.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "hash")]
+struct HashArgs {
+    /// paths to torrent files and/or directories of torrent files
+    #[argh(positional)]
+    torrents: Vec<PathBuf>,
+}
+
+/// Summarize torrents.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "inspect")]
+struct InspectArgs {
+    /// print every file, the meta version, and the piece length
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// paths to torrent files and/or directories of torrent files
+    #[argh(positional)]
+    torrents: Vec<PathBuf>,
+}
+
+/// Check downloaded data against the piece hashes of a torrent.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "verify")]
+struct VerifyArgs {
+    /// torrent to check
+    #[argh(positional)]
+    torrent: PathBuf,
+    /// directory the torrent was downloaded to
+    #[argh(positional)]
+    data_dir: PathBuf,
+}
+
+/// Summarize a library of torrents.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "report")]
+struct ReportArgs {
+    /// print the report as JSON
+    #[argh(switch)]
+    json: bool,
+    /// number of tracker hosts to list (default: 10)
+    #[argh(option, default = "10")]
+    top: usize,
+    /// paths to torrent files and/or directories of torrent files
+    #[argh(positional)]
+    torrents: Vec<PathBuf>,
+}
+
+/// List the announce tiers of torrents and optionally check whether the trackers respond.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "trackers")]
+struct TrackersArgs {
+    /// probe each unique tracker with an announce or UDP connect; HTTPS trackers are only connected to and reported as reachable
+    #[argh(switch, short = 'c')]
+    check: bool,
+    /// seconds to wait for each tracker (default: 10)
+    #[argh(option, default = "10")]
+    timeout: u64,
+    /// number of trackers to probe at once (default: 8)
+    #[argh(option, short = 'j', default = "8")]
+    jobs: usize,
     /// paths to torrent files and/or directories of torrent files
     #[argh(positional)]
     torrents: Vec<PathBuf>,
@@ -41,30 +256,75 @@ fn torrent_from_file(path: &Path) -> Result<Vec<u8>, Report> {
     Ok(buffer)
 }
 
-// Filters for .torrent files.
-fn torrent_directory(path: &Path) -> Result<Vec<PathBuf>, Report> {
-    path.read_dir()
-        .wrap_err_with(|| (format!("Failed to read directory: {}", path.display().blue())))?
-        .filter_map(|maybe_entry| {
-            maybe_entry
-                .wrap_err_with(|| {
-                    format!(
-                        "Failed to read directory entry at: {}",
-                        path.display().blue()
-                    )
-                })
-                // Filter to remove any non-torrent entries and return the deserialization result.
-                .map(|entry| {
-                    let path = entry.path();
-                    if path.is_file()
-                        && path.extension().and_then(|ext| ext.to_str()) == Some("torrent")
-                    {
-                        Some(path)
-                    } else {
-                        None
-                    }
-                })
-                .transpose()
+// Files of directories that are read as torrents.
+struct Scan {
+    recursive: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+}
+
+// Walk a directory for torrents. Symlinks are followed, but loops are reported and skipped.
+fn torrent_directory(path: &Path, scan: &Scan) -> Result<Vec<PathBuf>, Report> {
+    let err = Style::new().red().style("Err");
+    let error = Style::new().bright_red();
+
+    let mut overrides = OverrideBuilder::new(path);
+    if scan.include.is_empty() {
+        overrides.add("*.torrent")?;
+    }
+    for glob in &scan.include {
+        overrides.add(glob)?;
+    }
+    for glob in &scan.exclude {
+        // Override globs are includes unless negated.
+        overrides.add(&format!("!{glob}"))?;
+    }
+
+    let walker = WalkBuilder::new(path)
+        .standard_filters(false)
+        .follow_links(true)
+        .max_depth((!scan.recursive).then_some(1))
+        .overrides(overrides.build()?)
+        .sort_by_file_name(Ord::cmp)
+        .build();
+
+    let mut paths = Vec::new();
+    for entry in walker {
+        match entry.wrap_err_with(|| format!("Failed to read directory: {}", path.display().blue()))
+        {
+            Ok(entry)
+                if entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file()) =>
+            {
+                paths.push(entry.into_path())
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("[{err}] => {:#}", error.style(e)),
+        }
+    }
+    Ok(paths)
+}
+
+// Flatten directories and single paths into a vector of paths.
+fn torrent_paths(paths: Vec<PathBuf>, scan: &Scan) -> Vec<PathBuf> {
+    let err = Style::new().red().style("Err");
+    let error = Style::new().bright_red();
+
+    paths
+        .into_iter()
+        .map(|path| {
+            // Files named on the command line are read regardless of the globs.
+            if path.is_file() {
+                Ok(vec![path])
+            } else {
+                torrent_directory(&path, scan)
+            }
+        })
+        .flat_map(|paths| {
+            paths
+                .map_err(|e| eprintln!("[{err}] => {:#}", error.style(e)))
+                .unwrap_or_default()
         })
         .collect()
 }
@@ -74,20 +334,15 @@ fn print_torrents(torrent_paths: &[PathBuf], verbose: bool) {
     let err = Style::new().red().style("Err");
     let error = Style::new().bright_red();
 
-    for path in torrent_paths {
-        match torrent_from_file(path) {
-            Ok(buffer) => {
-                match serde_bencode::from_bytes::<Torrent>(&buffer).wrap_err_with(|| {
-                    format!("Torrent failed to deserialize: {}", path.display().blue())
-                }) {
-                    Ok(torrent) => {
-                        if !verbose {
-                            println!("[{ok}] => {}", torrent.name())
-                        } else {
-                            println!("[{ok}] => {torrent:#?}")
-                        }
-                    }
-                    Err(e) => eprintln!("[{err}] => {}", error.style(e)),
+    for (path, result) in parse_many(torrent_paths.iter().cloned(), ParseOptions::default()) {
+        match result
+            .wrap_err_with(|| format!("Torrent failed to deserialize: {}", path.display().blue()))
+        {
+            Ok(torrent) => {
+                if !verbose {
+                    println!("[{ok}] => {}", torrent.name())
+                } else {
+                    println!("[{ok}] => {torrent:#?}")
                 }
             }
             Err(e) => eprintln!("[{err}] => {:#}", error.style(e)),
@@ -95,12 +350,393 @@ fn print_torrents(torrent_paths: &[PathBuf], verbose: bool) {
     }
 }
 
-fn deserialize_as_map(torrents: &[PathBuf]) {
+// Parse torrents and report failures, sorted by path.
+fn parse_sorted(paths: Vec<PathBuf>, scan: &Scan) -> Vec<(PathBuf, Torrent)> {
+    let err = Style::new().red().style("Err");
+    let error = Style::new().bright_red();
+
+    let mut torrents = Vec::new();
+    for (path, result) in parse_many(torrent_paths(paths, scan), ParseOptions::default()) {
+        match result
+            .wrap_err_with(|| format!("Torrent failed to deserialize: {}", path.display().blue()))
+        {
+            Ok(torrent) => torrents.push((path, torrent)),
+            Err(e) => eprintln!("[{err}] => {:#}", error.style(e)),
+        }
+    }
+    torrents.sort_by(|(left, _), (right, _)| left.cmp(right));
+    torrents
+}
+
+fn print_info_hash(torrent: &Torrent) -> Result<()> {
+    match torrent.info_hash()? {
+        InfoHashVersioned::V1(sha1) => println!("v1 info hash: {sha1}"),
+        InfoHashVersioned::V2(sha2) => println!("v2 info hash: {sha2}"),
+        InfoHashVersioned::Hybrid { sha1, sha2 } => {
+            println!("v1 info hash: {sha1}");
+            println!("v2 info hash: {sha2}");
+        }
+    }
+    Ok(())
+}
+
+fn deserialize_as_map(torrents: &[PathBuf], verbose: bool) {
+    let err = Style::new().red().style("Err");
+    let error = Style::new().bright_red();
+    // Verbose dumps print binary strings such as `pieces` in full.
+    let max_bytes = if verbose {
+        usize::MAX
+    } else {
+        DEFAULT_MAX_BYTES
+    };
+
     for path in torrents {
-        match torrent_from_file(&path) {
-            Ok(buffer) => {}
-            Err(e) => {}
+        let buffer = match torrent_from_file(path) {
+            Ok(buffer) => buffer,
+            Err(e) => {
+                eprintln!("[{err}] => {:#}", error.style(e));
+                continue;
+            }
+        };
+
+        println!("{}", path.display().blue());
+        let mut parsed = values(&buffer);
+        while let Some(result) = parsed.next() {
+            match result {
+                Ok((value, _)) => println!("{}", value.pretty().max_bytes(max_bytes)),
+                Err(e) => {
+                    let offset = buffer.len() - parsed.remaining().len();
+                    eprintln!(
+                        "[{err}] => {}",
+                        error.style(format!("Invalid Bencode value at byte {offset}: {e}"))
+                    )
+                }
+            }
+        }
+    }
+}
+
+fn create(args: CreateArgs) -> Result<()> {
+    let mut builder = TorrentBuilder::new(&args.path)
+        .version(args.meta_version)
+        .private(args.private)
+        .created_by(concat!("cloudburst ", env!("CARGO_PKG_VERSION")));
+
+    for tier in &args.announce {
+        let trackers = tier
+            .split(',')
+            .map(|tracker| {
+                tracker
+                    .trim()
+                    .parse()
+                    .map_err(|e| eyre!("Invalid announce URL {tracker}: {e}"))
+            })
+            .collect::<Result<_>>()?;
+        builder = builder.tier(trackers);
+    }
+    for web_seed in args.web_seed {
+        builder = builder.web_seed(web_seed);
+    }
+    if let Some(piece_length) = args.piece_length {
+        builder = builder.piece_length(piece_length);
+    }
+    if let Some(comment) = args.comment {
+        builder = builder.comment(comment);
+    }
+    if let Some(source) = args.source {
+        builder = builder.source(source);
+    }
+    if args.date {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+        builder = builder.creation_date(now.as_secs());
+    }
+
+    let torrent = builder
+        .build()
+        .wrap_err_with(|| format!("Failed to create torrent: {}", args.path.display().blue()))?;
+
+    let output = args
+        .output
+        .unwrap_or_else(|| PathBuf::from(format!("{}.torrent", torrent.name())));
+    let mut options = OpenOptions::new();
+    if args.force {
+        options.write(true).create(true).truncate(true);
+    } else {
+        options.write(true).create_new(true);
+    }
+    let bytes = torrent.to_bytes()?;
+    options
+        .open(&output)
+        .and_then(|mut file| file.write_all(&bytes))
+        .wrap_err_with(|| format!("Failed to write torrent: {}", output.display().blue()))?;
+
+    println!("{} => {}", torrent.name(), output.display().blue());
+    print_info_hash(&torrent)
+}
+
+fn cross_seed(args: CrossSeedArgs) -> Result<()> {
+    let buffer = torrent_from_file(&args.torrent)?;
+    let mut torrent = Torrent::from_bytes(&buffer).wrap_err_with(|| {
+        format!(
+            "Torrent failed to deserialize: {}",
+            args.torrent.display().blue()
+        )
+    })?;
+
+    let mut trackers = args.announce.into_iter();
+    let announce = trackers
+        .next()
+        .ok_or_else(|| eyre!("Cross seeding requires at least one `--announce` URL"))?;
+    let backups: Vec<_> = trackers.collect();
+    torrent.announce_list = (!backups.is_empty())
+        .then(|| vec![std::iter::once(announce.clone()).chain(backups).collect()]);
+    torrent.announce = Some(announce);
+    torrent.set_source(Some(args.source.clone()));
+
+    let output = args.output.unwrap_or_else(|| {
+        let stem = args
+            .torrent
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| torrent.name().to_owned());
+        args.torrent
+            .with_file_name(format!("{stem}.{}.torrent", args.source))
+    });
+    fs::write(&output, torrent.to_bytes()?)
+        .wrap_err_with(|| format!("Failed to write torrent: {}", output.display().blue()))?;
+
+    println!("{} => {}", torrent.name(), output.display().blue());
+    print_info_hash(&torrent)
+}
+
+fn first_value<'input>(buffer: &'input [u8], path: &Path) -> Result<Value<'input>> {
+    match values(buffer).next() {
+        Some(Ok((value, _))) => Ok(value),
+        Some(Err(e)) => Err(eyre!("Invalid Bencode in {}: {e}", path.display().blue())),
+        None => Err(eyre!("Empty torrent: {}", path.display().blue())),
+    }
+}
+
+// Length of the hashes of concatenated piece hashes.
+fn hash_length(path: &DiffPath<'_>) -> Option<usize> {
+    match path.0.as_slice() {
+        [.., PathSegment::Key(b"pieces")] => Some(20),
+        [.., PathSegment::Key(b"piece layers"), PathSegment::Key(_)] => Some(32),
+        _ => None,
+    }
+}
+
+fn diff_torrents(args: DiffArgs) -> Result<()> {
+    // Piece indices listed per changed piece buffer.
+    const MAX_PIECES: usize = 10;
+
+    let old_buffer = torrent_from_file(&args.old)?;
+    let new_buffer = torrent_from_file(&args.new)?;
+    let old = first_value(&old_buffer, &args.old)?;
+    let new = first_value(&new_buffer, &args.new)?;
+
+    // Info hashes are only available for torrents that parse.
+    for (path, buffer) in [(&args.old, &old_buffer), (&args.new, &new_buffer)] {
+        println!("{}", path.display().blue());
+        match Torrent::from_bytes(buffer) {
+            Ok(torrent) => print_info_hash(&torrent)?,
+            Err(e) => println!("Torrent failed to deserialize: {e}"),
+        }
+    }
+    println!();
+
+    let entries = diff(&old, &new);
+    if entries.is_empty() {
+        println!("No differences");
+    }
+    for entry in &entries {
+        println!("{}", entry.summary());
+
+        let Some(length) = hash_length(&entry.path) else {
+            continue;
+        };
+        let DiffKind::Changed {
+            old: Value::Bytes(old),
+            new: Value::Bytes(new),
+        } = entry.kind
+        else {
+            continue;
+        };
+        let pieces = differing_chunks(old, new, length);
+        let listed: Vec<_> = pieces
+            .iter()
+            .take(MAX_PIECES)
+            .map(ToString::to_string)
+            .collect();
+        let more = if pieces.len() > MAX_PIECES {
+            ", ..."
+        } else {
+            ""
+        };
+        println!(
+            "  {} of {} pieces differ: {}{more}",
+            pieces.len(),
+            old.len().max(new.len()).div_ceil(length),
+            listed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn inspect(args: InspectArgs, scan: &Scan) -> Result<()> {
+    let verbosity = if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::TopLevel
+    };
+
+    for (path, torrent) in parse_sorted(args.torrents, scan) {
+        println!("{}", path.display().blue());
+        println!("{}\n", torrent.summary().with_verbosity(verbosity));
+    }
+    Ok(())
+}
+
+fn list_files(args: FilesArgs, scan: &Scan) -> Result<()> {
+    for (path, torrent) in parse_sorted(args.torrents, scan) {
+        println!("{} ({})", torrent.name(), path.display().blue());
+        for file in torrent.info.iter_files() {
+            if file.padding && !args.padding {
+                continue;
+            }
+
+            // Version 2 files are named by their last path component and rooted at `./`.
+            let file_path = file
+                .file_path
+                .iter()
+                .copied()
+                .chain([file.name])
+                .filter(|part| !part.is_empty() && *part != "./")
+                .collect::<Vec<_>>()
+                .join("/");
+            match file.symlink_path {
+                Some(target) => println!("  {file_path} -> {}", target.join("/")),
+                None => println!("  {:>15}  {file_path}", file.length),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn hash(args: HashArgs, scan: &Scan) -> Result<()> {
+    for (path, torrent) in parse_sorted(args.torrents, scan) {
+        println!("{} ({})", torrent.name(), path.display().blue());
+        print_info_hash(&torrent)?;
+    }
+    Ok(())
+}
+
+fn verify(args: VerifyArgs) -> Result<()> {
+    let buffer = torrent_from_file(&args.torrent)?;
+    let torrent = Torrent::from_bytes(&buffer).wrap_err_with(|| {
+        format!(
+            "Torrent failed to deserialize: {}",
+            args.torrent.display().blue()
+        )
+    })?;
+
+    let report = torrent
+        .verify(&args.data_dir)
+        .and_then(|verifier| verifier.report())
+        .wrap_err_with(|| format!("Failed to verify: {}", torrent.name()))?;
+
+    let mut failed = 0;
+    for (file, status) in &report {
+        let path = file.path.display();
+        match status {
+            VerifyStatus::Valid => println!("[{}] {path}", "Valid".bright_green()),
+            VerifyStatus::Missing => println!("[{}] {path}", "Missing".yellow()),
+            VerifyStatus::Corrupted => println!("[{}] {path}", "Corrupted".red()),
         }
+        failed += usize::from(*status != VerifyStatus::Valid);
+    }
+
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "{failed} of {} files failed verification",
+            report.len()
+        ))
+    }
+}
+
+fn report(args: ReportArgs, scan: &Scan) -> Result<()> {
+    let mut torrents = Vec::new();
+    let mut failed = 0;
+    // parse_many logs the torrents that fail to parse.
+    for (_, result) in parse_many(torrent_paths(args.torrents, scan), ParseOptions::default()) {
+        match result {
+            Ok(torrent) => torrents.push(torrent),
+            Err(_) => failed += 1,
+        }
+    }
+
+    let report = LibraryReport::new(&torrents, failed, args.top);
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        print!("{report}");
+    }
+    Ok(())
+}
+
+fn check_trackers(args: TrackersArgs, scan: &Scan) -> Result<()> {
+    let torrents = parse_sorted(args.torrents, scan);
+    let health = if args.check {
+        let mut unique: Vec<_> = torrents
+            .iter()
+            .flat_map(|(_, torrent)| torrent.unique_trackers().cloned())
+            .collect();
+        unique.sort_by_cached_key(ToString::to_string);
+        unique.dedup();
+        trackers::check_all(unique, Duration::from_secs(args.timeout), args.jobs)
+    } else {
+        Default::default()
+    };
+
+    for (path, torrent) in &torrents {
+        println!("{} ({})", torrent.name(), path.display().blue());
+        for (index, tier) in announce_tiers(torrent).into_iter().enumerate() {
+            println!("  Tier {index}");
+            for tracker in tier {
+                match health.get(tracker) {
+                    Some(status @ Health::Alive) => {
+                        println!("    {tracker} => {}", status.bright_green())
+                    }
+                    Some(status @ (Health::Reachable | Health::Redirected(_))) => {
+                        println!("    {tracker} => {}", status.yellow())
+                    }
+                    Some(status @ (Health::Dead(_) | Health::Invalid(_))) => {
+                        println!("    {tracker} => {}", status.red())
+                    }
+                    None => println!("    {tracker}"),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Tiers as clients try them; `announce` is only used without an `announce-list` (BEP-0012).
+fn announce_tiers(torrent: &Torrent) -> Vec<Vec<&UriWrapper>> {
+    match &torrent.announce_list {
+        Some(tiers) if tiers.iter().any(|tier| !tier.is_empty()) => tiers
+            .iter()
+            .filter(|tier| !tier.is_empty())
+            .map(|tier| tier.iter().collect())
+            .collect(),
+        _ => torrent
+            .announce
+            .iter()
+            .map(|announce| vec![announce])
+            .collect(),
     }
 }
 
@@ -109,25 +745,27 @@ fn main() -> Result<()> {
     color_eyre::install()?;
     pretty_env_logger::init();
 
-    // Flatten directories and single paths into a vector of paths.
-    let torrents: Vec<_> = args
-        .torrents
-        .into_iter()
-        .flat_map(|path| {
-            // An iterator that yields one file if it's a file or a stream of files if it's a dir.
-            // The result is flattened into an iterator of paths that's collected into a vector.
-            if path.is_file() {
-                // I'm sorry.
-                Ok(vec![path])
-            } else {
-                torrent_directory(&path)
-            }
-        })
-        .flatten()
-        .collect();
+    let scan = Scan {
+        recursive: args.recursive,
+        include: args.include,
+        exclude: args.exclude,
+    };
+    match args.command {
+        Some(Command::Create(create_args)) => return create(create_args),
+        Some(Command::CrossSeed(cross_seed_args)) => return cross_seed(cross_seed_args),
+        Some(Command::Diff(diff_args)) => return diff_torrents(diff_args),
+        Some(Command::Files(files_args)) => return list_files(files_args, &scan),
+        Some(Command::Hash(hash_args)) => return hash(hash_args, &scan),
+        Some(Command::Inspect(inspect_args)) => return inspect(inspect_args, &scan),
+        Some(Command::Report(report_args)) => return report(report_args, &scan),
+        Some(Command::Trackers(trackers_args)) => return check_trackers(trackers_args, &scan),
+        Some(Command::Verify(verify_args)) => return verify(verify_args),
+        None => {}
+    }
 
+    let torrents = torrent_paths(args.torrents, &scan);
     if args.map {
-        deserialize_as_map(&torrents)
+        deserialize_as_map(&torrents, args.verbose)
     } else {
         print_torrents(&torrents, args.verbose)
     }
@@ -0,0 +1,153 @@
+//! Aggregate statistics of a torrent library.
+
+use http::Uri;
+use serde::Serialize;
+use star_cloudburst::Torrent;
+use std::{
+    borrow::Borrow,
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::{self, Display, Formatter},
+};
+
+/// Number of torrents that list a tracker.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrackerCount {
+    pub host: String,
+    pub torrents: usize,
+}
+
+/// Statistics over every torrent of a library.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct LibraryReport {
+    /// Torrents that parsed.
+    pub torrents: usize,
+    /// Torrent files that failed to parse.
+    pub failed: usize,
+    /// Total length of the shared files in bytes.
+    pub total_size: u64,
+    /// Torrents by [star_cloudburst::metainfo::MetaInfo::meta_version_str].
+    pub meta_versions: BTreeMap<String, usize>,
+    pub private: usize,
+    pub public: usize,
+    /// Tracker hosts listed by the most torrents.
+    pub top_trackers: Vec<TrackerCount>,
+    /// Torrents by piece length in bytes.
+    pub piece_lengths: BTreeMap<u64, usize>,
+}
+
+impl LibraryReport {
+    /// Aggregate `torrents` and keep the `top` most common tracker hosts.
+    pub fn new<'a, I>(torrents: I, failed: usize, top: usize) -> Self
+    where
+        I: IntoIterator<Item = &'a Torrent>,
+    {
+        let mut report = Self {
+            failed,
+            ..Self::default()
+        };
+        let mut hosts: HashMap<String, usize> = HashMap::new();
+
+        for torrent in torrents {
+            report.torrents += 1;
+            report.total_size = report
+                .total_size
+                .saturating_add(torrent.info.total_length());
+            *report
+                .meta_versions
+                .entry(torrent.info.meta_version_str().to_owned())
+                .or_default() += 1;
+            if torrent.info.is_private() {
+                report.private += 1;
+            } else {
+                report.public += 1;
+            }
+            *report
+                .piece_lengths
+                .entry(torrent.info.piece_length().get())
+                .or_default() += 1;
+
+            // Passkeys make every tracker URL unique so trackers are counted by host.
+            let unique: HashSet<_> = torrent
+                .unique_trackers()
+                .filter_map(|tracker| {
+                    let uri: &Uri = tracker.borrow();
+                    uri.host().map(str::to_ascii_lowercase)
+                })
+                .collect();
+            for host in unique {
+                *hosts.entry(host).or_default() += 1;
+            }
+        }
+
+        let mut top_trackers: Vec<_> = hosts
+            .into_iter()
+            .map(|(host, torrents)| TrackerCount { host, torrents })
+            .collect();
+        top_trackers.sort_by(|left, right| {
+            right
+                .torrents
+                .cmp(&left.torrents)
+                .then_with(|| left.host.cmp(&right.host))
+        });
+        top_trackers.truncate(top);
+        report.top_trackers = top_trackers;
+
+        report
+    }
+}
+
+impl Display for LibraryReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Torrents: {} ({} failed to parse)",
+            self.torrents, self.failed
+        )?;
+        writeln!(f, "Total size: {}", human_size(self.total_size))?;
+
+        let private_percent = if self.torrents == 0 {
+            0.0
+        } else {
+            self.private as f64 * 100.0 / self.torrents as f64
+        };
+        writeln!(
+            f,
+            "Private: {} / public: {} ({private_percent:.1}% private)",
+            self.private, self.public
+        )?;
+
+        writeln!(f, "\nMeta versions:")?;
+        for (version, count) in &self.meta_versions {
+            writeln!(f, "  {version:<12} {count}")?;
+        }
+
+        writeln!(f, "\nTop trackers:")?;
+        for tracker in &self.top_trackers {
+            writeln!(f, "  {:<32} {}", tracker.host, tracker.torrents)?;
+        }
+
+        writeln!(f, "\nPiece lengths:")?;
+        for (piece_length, count) in &self.piece_lengths {
+            writeln!(f, "  {:<12} {count}", human_size(*piece_length))?;
+        }
+        Ok(())
+    }
+}
+
+// Bytes with a binary unit such as `1.5 GiB`.
+fn human_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
@@ -0,0 +1,190 @@
+//! Tracker health checks.
+//!
+//! Each unique tracker is probed once no matter how many torrents list it.
+//! HTTP trackers receive an announce for a dummy info hash and UDP trackers
+//! receive a BEP-0015 connect request. HTTPS trackers are only connected to
+//! because the binary doesn't link a TLS library, so they're reported as
+//! reachable rather than alive. UDP trackers have no default port, so a UDP
+//! URI without one is reported as invalid instead of being probed.
+
+use http::Uri;
+use star_cloudburst::uri::uriwrapper::UriWrapper;
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    io::{self, ErrorKind, Read, Write},
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+// BEP-0015 magic constant that starts every connect request.
+const UDP_PROTOCOL_ID: u64 = 0x0417_2710_1980;
+const DUMMY_ANNOUNCE: &str = "info_hash=%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00%00&peer_id=-SC0000-000000000000&port=6881&uploaded=0&downloaded=0&left=0&compact=1";
+
+/// Result of probing one tracker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Health {
+    /// The tracker answered like a tracker.
+    Alive,
+    /// The tracker accepted a connection but its response wasn't checked.
+    Reachable,
+    /// The tracker redirected the announce.
+    Redirected(String),
+    /// The tracker didn't answer or answered with an error.
+    Dead(String),
+    /// The tracker URI can't be probed.
+    Invalid(String),
+}
+
+impl Display for Health {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Health::Alive => f.write_str("alive"),
+            Health::Reachable => f.write_str("reachable"),
+            Health::Redirected(location) => write!(f, "redirected to {location}"),
+            Health::Dead(reason) => write!(f, "dead ({reason})"),
+            Health::Invalid(reason) => write!(f, "invalid ({reason})"),
+        }
+    }
+}
+
+/// Probe `trackers` on at most `jobs` threads.
+pub fn check_all(
+    trackers: Vec<UriWrapper>,
+    timeout: Duration,
+    jobs: usize,
+) -> HashMap<UriWrapper, Health> {
+    let workers = jobs.clamp(1, trackers.len().max(1));
+    let queue = Arc::new(Mutex::new(trackers.into_iter()));
+    let (sender, receiver) = mpsc::channel();
+
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            let Some(tracker) = queue.lock().expect("queue isn't poisoned").next() else {
+                break;
+            };
+            let health = check(&tracker, timeout);
+            if sender.send((tracker, health)).is_err() {
+                break;
+            }
+        });
+    }
+    drop(sender);
+
+    receiver.into_iter().collect()
+}
+
+/// Probe one tracker.
+pub fn check(tracker: &UriWrapper, timeout: Duration) -> Health {
+    let uri: &Uri = tracker.borrow();
+    let result = match uri.scheme_str() {
+        Some("http") => check_http(uri, timeout),
+        Some("https") => connect_tcp(uri, 443, timeout).map(|_| Health::Reachable),
+        Some("udp") => match uri.port_u16() {
+            Some(port) => check_udp(uri, port, timeout),
+            None => Ok(Health::Invalid("no port".to_owned())),
+        },
+        scheme => Ok(Health::Dead(format!(
+            "unsupported scheme {}",
+            scheme.unwrap_or_default()
+        ))),
+    };
+    result.unwrap_or_else(|e| Health::Dead(e.to_string()))
+}
+
+fn resolve(uri: &Uri, port: u16) -> io::Result<SocketAddr> {
+    let host = uri
+        .host()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "no host"))?;
+    (host, port)
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "host has no addresses"))
+}
+
+fn connect_tcp(uri: &Uri, default_port: u16, timeout: Duration) -> io::Result<TcpStream> {
+    let address = resolve(uri, uri.port_u16().unwrap_or(default_port))?;
+    let stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    Ok(stream)
+}
+
+fn check_http(uri: &Uri, timeout: Duration) -> io::Result<Health> {
+    let mut stream = connect_tcp(uri, 80, timeout)?;
+    let path = uri.path();
+    let separator = if uri.query().is_some() { '&' } else { '?' };
+    let query = uri
+        .query()
+        .map(|query| format!("?{query}"))
+        .unwrap_or_default();
+    write!(
+        stream,
+        "GET {path}{query}{separator}{DUMMY_ANNOUNCE} HTTP/1.0\r\nHost: {}\r\nUser-Agent: cloudburst\r\nConnection: close\r\n\r\n",
+        uri.authority().map(|authority| authority.as_str()).unwrap_or_default()
+    )?;
+
+    // The status line and headers are enough to judge the tracker.
+    let mut response = Vec::new();
+    let mut buffer = [0; 4096];
+    while !response.windows(4).any(|window| window == b"\r\n\r\n") && response.len() < 65536 {
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        response.extend_from_slice(&buffer[..read]);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let mut lines = response.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidData, "not an HTTP response"))?;
+
+    Ok(match status {
+        200..=299 => Health::Alive,
+        300..=399 => Health::Redirected(
+            lines
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("location")
+                        .then(|| value.trim().to_owned())
+                })
+                .unwrap_or_default(),
+        ),
+        status => Health::Dead(format!("HTTP {status}")),
+    })
+}
+
+fn check_udp(uri: &Uri, port: u16, timeout: Duration) -> io::Result<Health> {
+    let address = resolve(uri, port)?;
+    let bind: SocketAddr = if address.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind)?;
+    socket.set_read_timeout(Some(timeout))?;
+    socket.connect(address)?;
+
+    let transaction_id = std::process::id() ^ 0x5ca7_c10d;
+    let mut request = [0; 16];
+    request[..8].copy_from_slice(&UDP_PROTOCOL_ID.to_be_bytes());
+    request[12..].copy_from_slice(&transaction_id.to_be_bytes());
+    socket.send(&request)?;
+
+    let mut response = [0; 16];
+    let read = socket.recv(&mut response)?;
+    if read == 16 && response[..4] == [0; 4] && response[4..8] == transaction_id.to_be_bytes() {
+        Ok(Health::Alive)
+    } else {
+        Ok(Health::Dead("invalid connect response".to_owned()))
+    }
+}
@@ -0,0 +1,258 @@
+//! Edit many torrent files as one transaction.
+//!
+//! Bulk edits such as replacing a tracker across a library shouldn't leave
+//! half of the torrents edited if one of them fails. [BatchEdit] stages edits
+//! in memory, checks that every edited torrent serializes and parses again,
+//! and only then replaces the files. Files are replaced by renaming a
+//! temporary file over them, and files that were already replaced are
+//! restored if a later one fails.
+
+use crate::{parseoptions::ParseOptions, Torrent};
+use log::{debug, error};
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+const BATCHEDIT_TARGET: &str = "star_cloudburst::batch::BatchEdit";
+
+/// Staged edits to torrent files.
+///
+/// ```rust
+/// use star_cloudburst::batch::BatchEdit;
+/// use std::fs;
+///
+/// let cats = std::env::temp_dir().join("star_cloudburst_batch_cats.torrent");
+/// fs::write(&cats, b"d8:announce15:udp://cats.test4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee")?;
+///
+/// let mut batch = BatchEdit::new();
+/// batch.stage(&cats, |torrent| torrent.comment = Some("meow".to_owned()))?;
+/// // Nothing is written until the batch is committed.
+/// assert_eq!(fs::read(&cats)?.windows(4).any(|window| window == b"meow"), false);
+///
+/// assert_eq!(batch.commit()?, 1);
+/// assert!(fs::read(&cats)?.windows(4).any(|window| window == b"meow"));
+/// # fs::remove_file(cats)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BatchEdit {
+    staged: BTreeMap<PathBuf, Torrent>,
+    options: ParseOptions,
+}
+
+impl BatchEdit {
+    /// An empty batch whose torrents are parsed with the default
+    /// [ParseOptions].
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty batch whose torrents are parsed and validated with `options`.
+    #[inline]
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            staged: BTreeMap::new(),
+            options,
+        }
+    }
+
+    /// Stage `edit` on the torrent at `path`.
+    ///
+    /// The file is read and parsed the first time it's staged. Later edits to
+    /// the same path apply to the staged torrent. Returns the edited torrent.
+    pub fn stage<P, F>(&mut self, path: P, edit: F) -> io::Result<&Torrent>
+    where
+        P: AsRef<Path>,
+        F: FnOnce(&mut Torrent),
+    {
+        let path = path.as_ref();
+        if !self.staged.contains_key(path) {
+            let bytes = fs::read(path)?;
+            let torrent =
+                Torrent::parse_with(&bytes, self.options).map_err(|e| invalid_data(path, e))?;
+            self.staged.insert(path.to_owned(), torrent);
+        }
+
+        let torrent = self.staged.get_mut(path).expect("path was staged above");
+        edit(torrent);
+        Ok(torrent)
+    }
+
+    /// Drop the staged edits of `path` and return its staged torrent.
+    #[inline]
+    pub fn unstage(&mut self, path: &Path) -> Option<Torrent> {
+        self.staged.remove(path)
+    }
+
+    /// Staged paths in sorted order.
+    #[inline]
+    pub fn paths(&self) -> impl Iterator<Item = &Path> + '_ {
+        self.staged.keys().map(PathBuf::as_path)
+    }
+
+    /// Number of staged files.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.staged.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.staged.is_empty()
+    }
+
+    /// Serialize and parse every staged torrent again without writing them.
+    ///
+    /// Errors name the first torrent that failed.
+    pub fn validate(&self) -> io::Result<()> {
+        self.serialized().map(|_| ())
+    }
+
+    /// Validate every staged torrent and replace the files.
+    ///
+    /// Nothing is written if any torrent fails to validate. If replacing a
+    /// file fails, the files that were already replaced are restored. Returns
+    /// the number of files written.
+    pub fn commit(self) -> io::Result<usize> {
+        let serialized = self.serialized()?;
+
+        // Write every temporary file before touching the originals.
+        let mut written = Vec::with_capacity(serialized.len());
+        for (path, bytes) in serialized {
+            let temp = sibling(path, "tmp");
+            if let Err(e) = fs::write(&temp, bytes) {
+                let _ = fs::remove_file(&temp);
+                remove_all(written.iter().map(|(_, temp)| temp));
+                return Err(with_path(path, e));
+            }
+            written.push((path, temp));
+        }
+
+        // Keep each original as a backup until every file was replaced.
+        let mut replaced: Vec<(&Path, PathBuf)> = Vec::with_capacity(written.len());
+        for (i, (path, temp)) in written.iter().enumerate() {
+            let backup = sibling(path, "bak");
+            let result = fs::rename(path, &backup).and_then(|_| match fs::rename(temp, path) {
+                Ok(()) => Ok(()),
+                Err(e) => {
+                    let _ = fs::rename(&backup, path);
+                    Err(e)
+                }
+            });
+
+            if let Err(e) = result {
+                error!(
+                    target: BATCHEDIT_TARGET,
+                    "Replacing {} failed; restoring {} files",
+                    path.display(),
+                    replaced.len()
+                );
+                for (path, backup) in replaced.iter().rev() {
+                    if let Err(e) = fs::rename(backup, path) {
+                        error!(
+                            target: BATCHEDIT_TARGET,
+                            "Restoring {} from {} failed: {e}",
+                            path.display(),
+                            backup.display()
+                        );
+                    }
+                }
+                remove_all(written[i..].iter().map(|(_, temp)| temp));
+                return Err(with_path(path, e));
+            }
+            replaced.push((path, backup));
+        }
+
+        remove_all(replaced.iter().map(|(_, backup)| backup));
+        debug!(target: BATCHEDIT_TARGET, "Committed {} files", replaced.len());
+        Ok(replaced.len())
+    }
+
+    // Bytes of every staged torrent after checking that they parse.
+    fn serialized(&self) -> io::Result<Vec<(&Path, Vec<u8>)>> {
+        self.staged
+            .iter()
+            .map(|(path, torrent)| {
+                let bytes = torrent.to_bytes().map_err(|e| invalid_data(path, e))?;
+                Torrent::parse_with(&bytes, self.options).map_err(|e| invalid_data(path, e))?;
+                Ok((path.as_path(), bytes))
+            })
+            .collect()
+    }
+}
+
+// `path` with `extension` appended to its file name.
+fn sibling(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".");
+    name.push(extension);
+    path.with_file_name(name)
+}
+
+fn remove_all<'a, I>(paths: I)
+where
+    I: IntoIterator<Item = &'a PathBuf>,
+{
+    for path in paths {
+        let _ = fs::remove_file(path);
+    }
+}
+
+#[inline]
+fn invalid_data(path: &Path, e: serde_bencode::Error) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, format!("{}: {e}", path.display()))
+}
+
+#[inline]
+fn with_path(path: &Path, e: io::Error) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {e}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BatchEdit;
+    use crate::parseoptions::ParseOptions;
+    use serde_bencode::value::Value;
+    use std::fs;
+
+    const CATS: &[u8] = b"d8:announce15:udp://cats.test4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+
+    #[test]
+    fn invalid_edit_writes_nothing() -> Result<(), Box<dyn std::error::Error>> {
+        let dir =
+            std::env::temp_dir().join(format!("star-cloudburst-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let cats = dir.join("cats.torrent");
+        let mews = dir.join("mews.torrent");
+        fs::write(&cats, CATS)?;
+        fs::write(&mews, CATS)?;
+
+        let mut batch = BatchEdit::with_options(ParseOptions::strict());
+        batch.stage(&cats, |torrent| torrent.comment = Some("meow".to_owned()))?;
+        // Strict options reject fields that this crate doesn't know.
+        batch.stage(&mews, |torrent| {
+            torrent
+                .nonstandard
+                .insert("x_cats".to_owned(), Value::Int(1));
+        })?;
+        assert!(batch.validate().is_err());
+
+        assert!(batch.clone().commit().is_err());
+        assert_eq!(fs::read(&cats)?, CATS);
+        assert_eq!(fs::read(&mews)?, CATS);
+        assert_eq!(fs::read_dir(&dir)?.count(), 2);
+
+        batch.unstage(&mews);
+        assert_eq!(batch.commit()?, 1);
+        assert_ne!(fs::read(&cats)?, CATS);
+        assert_eq!(fs::read_dir(&dir)?.count(), 2);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
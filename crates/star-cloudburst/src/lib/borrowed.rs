@@ -0,0 +1,263 @@
+//! Borrowed torrent types that avoid allocating while deserializing.
+//!
+//! [TorrentRef] mirrors [crate::Torrent] but stores strings and byte buffers as
+//! [Cow]s that borrow from the input. Path components and the `pieces` buffer
+//! make up most of a torrent's allocations so services that parse many torrents
+//! may use these types for a cheap first pass.
+//!
+//! Borrowing depends on the deserializer. [TorrentRef::from_bytes] uses
+//! [star_bert::de], which yields borrowed data, so none of the borrowed fields
+//! allocate. Deserializers that can't borrow, such as `serde_bencode`, still
+//! work but the fields are owned instead.
+//!
+//! URIs, hashes, and nodes are not validated or parsed; use [crate::Torrent]
+//! for a fully validated torrent.
+
+use crate::{
+    files::{FileTreeInfo, TorrentFileAttributes},
+    metainfo::{
+        serde_bool_int::{bool_from_int, bool_to_int},
+        MetaVersion,
+    },
+    pieces::PieceLength,
+};
+use either::Either;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::{
+    borrow::{Borrow, Cow},
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+    num::NonZeroU64,
+    ops::Deref,
+};
+
+/// A string that borrows from the input if possible.
+///
+/// Serde only borrows [Cow] fields that are marked with `#[serde(borrow)]`.
+/// [BorrowedStr] wraps a [Cow] so that strings nested in collections, such as
+/// path components, borrow too.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct BorrowedStr<'a>(#[serde(borrow)] pub Cow<'a, str>);
+
+impl BorrowedStr<'_> {
+    /// Whether the string borrows from the input.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        matches!(self.0, Cow::Borrowed(_))
+    }
+}
+
+impl Deref for BorrowedStr<'_> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Borrow<str> for BorrowedStr<'_> {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for BorrowedStr<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        <str as Display>::fmt(&self.0, f)
+    }
+}
+
+/// Borrowed torrent metadata.
+///
+/// Unknown fields are always ignored because [TorrentRef] is meant for fast,
+/// lenient parsing.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TorrentRef<'a> {
+    /// Tracker URL (unvalidated).
+    #[serde(default, borrow)]
+    pub announce: Option<BorrowedStr<'a>>,
+    /// Tiers of announce URLs (unvalidated).
+    #[serde(default, borrow, rename = "announce-list")]
+    pub announce_list: Option<Vec<Vec<BorrowedStr<'a>>>>,
+    /// Torrent creator or original uploader.
+    #[serde(default, borrow, rename = "created by")]
+    pub created_by: Option<BorrowedStr<'a>>,
+    /// Optional comment.
+    #[serde(default, borrow)]
+    pub comment: Option<BorrowedStr<'a>>,
+    /// Torrent creation date as a Unix timestamp.
+    #[serde(default, rename = "creation date")]
+    pub creation_date: Option<u64>,
+    /// Torrent info dictionary.
+    #[serde(borrow)]
+    pub info: InfoRef<'a>,
+}
+
+impl<'a> TorrentRef<'a> {
+    /// Deserialize a torrent that borrows its strings and buffers from `bytes`.
+    ///
+    /// ```rust
+    /// use star_cloudburst::borrowed::TorrentRef;
+    /// use star_bert::de::Error;
+    ///
+    /// let cats = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let torrent = TorrentRef::from_bytes(cats)?;
+    ///
+    /// assert!(torrent.announce.is_some_and(|announce| announce.is_borrowed()));
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[inline]
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, star_bert::de::Error> {
+        star_bert::de::from_bytes(bytes)
+    }
+}
+
+impl TorrentRef<'_> {
+    /// Suggested name of the torrent file or directory.
+    ///
+    /// ```rust
+    /// use star_cloudburst::borrowed::TorrentRef;
+    /// use star_bert::de::Error;
+    ///
+    /// let cats = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let torrent = TorrentRef::from_bytes(cats)?;
+    ///
+    /// assert_eq!("cats.mkv", torrent.name());
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
+}
+
+/// Borrowed info dict for any meta version.
+///
+/// Unlike [crate::metainfo::MetaInfo], [InfoRef] is a single struct where the
+/// fields specific to each meta version are optional.
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct InfoRef<'a> {
+    /// Files shared by version 1 or hybrid torrents.
+    #[serde(default, borrow)]
+    pub files: Option<Vec<FlatFileRef<'a>>>,
+    /// Version 2 or hybrid file tree.
+    #[serde(default, borrow, rename = "file tree")]
+    pub file_tree: Option<FileTreeRef<'a>>,
+    /// Length of the file in bytes for single file version 1 torrents.
+    #[serde(default)]
+    pub length: Option<NonZeroU64>,
+    /// Torrent file meta version.
+    #[serde(default, rename = "meta version")]
+    pub meta_version: Option<MetaVersion>,
+    /// Suggested name of the file or directory.
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
+    /// Concatenated SHA-1 piece hashes (unvalidated).
+    #[serde(default, borrow, with = "serde_bytes")]
+    pub pieces: Option<Cow<'a, [u8]>>,
+    /// Number of bytes per piece.
+    #[serde(rename = "piece length")]
+    pub piece_length: PieceLength,
+    /// Torrent is restricted to private trackers.
+    #[serde(
+        default,
+        deserialize_with = "bool_from_int",
+        serialize_with = "bool_to_int"
+    )]
+    pub private: Option<bool>,
+    /// Tracker specific string that makes the info hash unique per tracker.
+    #[serde(default, borrow)]
+    pub source: Option<Cow<'a, str>>,
+}
+
+/// Borrowed version 1 file entry.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FlatFileRef<'a> {
+    /// File attribute such as whether the file is executable or hidden.
+    #[serde(default)]
+    pub attr: Option<TorrentFileAttributes>,
+    /// Length of the file in bytes.
+    pub length: NonZeroU64,
+    /// Subdirectory names where the last component is the file name.
+    #[serde(borrow)]
+    pub path: Vec<BorrowedStr<'a>>,
+}
+
+/// Borrowed version 2 file tree.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct FileTreeRef<'a> {
+    #[serde(borrow)]
+    pub node: BTreeMap<BorrowedStr<'a>, FileTreeEntryRef<'a>>,
+}
+
+/// A file or a directory in a [FileTreeRef].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct FileTreeEntryRef<'a>(
+    #[serde(borrow, with = "either::serde_untagged")] pub Either<FileTreeInfo, FileTreeRef<'a>>,
+);
+
+#[cfg(test)]
+mod tests {
+    use super::{BorrowedStr, FlatFileRef, TorrentRef};
+    use serde::{
+        de::value::{BorrowedStrDeserializer, Error as DeError},
+        Deserialize,
+    };
+    use serde_test::{assert_de_tokens, Token};
+    use std::borrow::Cow;
+
+    #[test]
+    fn borrowed_str_borrows() {
+        let component = BorrowedStr(Cow::Borrowed("kitties"));
+        assert_de_tokens(&component, &[Token::BorrowedStr("kitties")]);
+
+        let deserializer = BorrowedStrDeserializer::<DeError>::new("kitties");
+        let component = BorrowedStr::deserialize(deserializer).unwrap();
+        assert!(component.is_borrowed());
+    }
+
+    #[test]
+    fn flat_file_ref_bencode() -> Result<(), serde_bencode::Error> {
+        let file_de = "d6:lengthi42e4:pathl7:scripts5:ai.pyee";
+        let file: FlatFileRef<'_> = serde_bencode::from_str(file_de)?;
+
+        assert_eq!(file.length.get(), 42);
+        assert!(file
+            .path
+            .iter()
+            .map(|part| &**part)
+            .eq(["scripts", "ai.py"]));
+        assert_eq!(file_de, serde_bencode::to_string(&file)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn torrent_ref_from_bytes_borrows() -> Result<(), star_bert::de::Error> {
+        let torrent_de = b"d4:infod5:filesld6:lengthi42e4:pathl7:scripts5:ai.pyeee4:name4:cats6:pieces20:\x00\x01\x02\x03\x04\x05\x06\x07\x08\x09\x00\x01\x02\x03\x04\x05\x06\x07\x08\x0912:piece lengthi16384eee";
+        let torrent = TorrentRef::from_bytes(torrent_de)?;
+
+        assert!(matches!(torrent.info.name, Cow::Borrowed("cats")));
+        assert!(matches!(torrent.info.pieces, Some(Cow::Borrowed(pieces)) if pieces.len() == 20));
+
+        let files = torrent.info.files.expect("files were deserialized");
+        assert!(files[0].path.iter().all(BorrowedStr::is_borrowed));
+        assert!(files[0]
+            .path
+            .iter()
+            .map(|part| &**part)
+            .eq(["scripts", "ai.py"]));
+
+        Ok(())
+    }
+}
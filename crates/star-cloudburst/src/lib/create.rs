@@ -0,0 +1,21 @@
+//! Create torrents from files on disk.
+//!
+//! [builder::TorrentBuilder] creates a complete torrent from a file or
+//! directory. The building blocks it uses are public too:
+//! [walk::walk_files] collects the files of a source directory in a stable
+//! order while skipping what doesn't belong in a torrent. [v2::create_v2]
+//! hashes them into a version 2 only torrent, and
+//! [checksums::embed_checksums] adds whole file checksums to version 1
+//! files. [private::make_private] prepares a torrent for private trackers.
+
+pub mod builder;
+pub mod checksums;
+pub mod private;
+pub mod v2;
+pub mod walk;
+
+pub use builder::TorrentBuilder;
+pub use checksums::{embed_checksums, FileChecksums};
+pub use private::make_private;
+pub use v2::create_v2;
+pub use walk::{walk_files, SourceFile, WalkOptions};
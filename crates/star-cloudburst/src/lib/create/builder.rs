@@ -0,0 +1,520 @@
+//! Create torrents from files on disk.
+//!
+//! [TorrentBuilder] ties the other building blocks together: it walks the
+//! source, hashes version 1 pieces and version 2 Merkle trees, and fills in
+//! the torrent's trackers and metadata. The result serializes to a torrent
+//! file with [Torrent::to_bytes].
+
+use crate::{
+    create::{
+        embed_checksums, make_private,
+        v2::{path_components, root_name},
+        walk_files, FileChecksums, SourceFile, WalkOptions,
+    },
+    crypto::{merkle::read_block, piecehasher::PieceHasher},
+    files::{FlatFile, MetaV1FileRepr, PathComponent, TorrentFileAttributes},
+    metainfo::{Hybrid, MetaInfo, MetaV1, MetaVersion, TorrentVersion},
+    pieces::{PieceLength, Pieces},
+    uri::UriWrapper,
+    Torrent,
+};
+use digest::Digest;
+use log::{debug, warn};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::{self, ErrorKind},
+    num::NonZeroU64,
+    path::PathBuf,
+};
+
+const TORRENTBUILDER_TARGET: &str = "star_cloudburst::create::builder::TorrentBuilder";
+
+/// Create a torrent of a file or directory.
+///
+/// Directories are walked with [walk_files] unless the files are listed with
+/// [TorrentBuilder::files]. Files are sorted by path and empty files are
+/// skipped because neither version 1 nor version 2 torrents can describe
+/// them. The piece length defaults to [PieceLength::recommended_for] the
+/// total size.
+///
+/// Torrents are [TorrentVersion::Hybrid] by default so that every client can
+/// download them. Hybrid torrents pad each file to a piece boundary with
+/// [BEP-0047](https://www.bittorrent.org/beps/bep_0047.html) padding files.
+/// Merkle torrents can't be created.
+///
+/// Nothing that changes between runs, such as the creation date, is set unless
+/// asked for so that the same files always yield the same info hash.
+///
+/// ```rust
+/// use star_cloudburst::{
+///     create::TorrentBuilder, metainfo::TorrentVersion, parseoptions::ParseOptions, Torrent,
+/// };
+/// use std::fs;
+///
+/// let root = std::env::temp_dir().join("star_cloudburst_builder_doc");
+/// fs::create_dir_all(root.join("extras"))?;
+/// fs::write(root.join("cats.mkv"), vec![b'c'; 40_000])?;
+/// fs::write(root.join("extras/mews.mkv"), b"mew")?;
+///
+/// let torrent = TorrentBuilder::new(&root)
+///     .tracker("udp://cats.test:1337".parse()?)
+///     .comment("meow")
+///     .build()?;
+/// assert_eq!(torrent.info.version(), TorrentVersion::Hybrid);
+/// assert_eq!(torrent.name(), "star_cloudburst_builder_doc");
+///
+/// let bytes = torrent.to_bytes()?;
+/// assert_eq!(
+///     Torrent::parse_with(&bytes, ParseOptions::strict())?,
+///     torrent
+/// );
+/// # fs::remove_dir_all(root)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TorrentBuilder {
+    root: PathBuf,
+    files: Option<Vec<SourceFile>>,
+    walk: WalkOptions,
+    version: TorrentVersion,
+    piece_length: Option<PieceLength>,
+    tiers: Vec<Vec<UriWrapper>>,
+    web_seeds: Vec<UriWrapper>,
+    comment: Option<String>,
+    created_by: Option<String>,
+    creation_date: Option<u64>,
+    source: Option<String>,
+    private: bool,
+    checksums: FileChecksums,
+}
+
+impl TorrentBuilder {
+    /// A builder for a hybrid torrent of `root`, which may be a file or a
+    /// directory.
+    pub fn new<P>(root: P) -> Self
+    where
+        P: Into<PathBuf>,
+    {
+        Self {
+            root: root.into(),
+            files: None,
+            walk: WalkOptions::default(),
+            version: TorrentVersion::Hybrid,
+            piece_length: None,
+            tiers: Vec::new(),
+            web_seeds: Vec::new(),
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            source: None,
+            private: false,
+            checksums: FileChecksums::None,
+        }
+    }
+
+    /// Share `files` instead of walking the root.
+    ///
+    /// Paths are relative to the root like the files from [walk_files].
+    #[inline]
+    pub fn files(mut self, files: Vec<SourceFile>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    /// Walk the root with `options`.
+    #[inline]
+    pub fn walk_options(mut self, options: WalkOptions) -> Self {
+        self.walk = options;
+        self
+    }
+
+    /// Create a [TorrentVersion::V1], [TorrentVersion::V2], or
+    /// [TorrentVersion::Hybrid] torrent.
+    #[inline]
+    pub fn version(mut self, version: TorrentVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    #[inline]
+    pub fn piece_length(mut self, piece_length: PieceLength) -> Self {
+        self.piece_length = Some(piece_length);
+        self
+    }
+
+    /// Add `tracker` as a tier of its own.
+    #[inline]
+    pub fn tracker(self, tracker: UriWrapper) -> Self {
+        self.tier(vec![tracker])
+    }
+
+    /// Add a tier of trackers that are tried in order.
+    ///
+    /// The first tracker of the first tier is also the `announce` URL.
+    #[inline]
+    pub fn tier(mut self, trackers: Vec<UriWrapper>) -> Self {
+        if !trackers.is_empty() {
+            self.tiers.push(trackers);
+        }
+        self
+    }
+
+    /// Add a [BEP-0019](https://www.bittorrent.org/beps/bep_0019.html) web
+    /// seed.
+    #[inline]
+    pub fn web_seed(mut self, url: UriWrapper) -> Self {
+        self.web_seeds.push(url);
+        self
+    }
+
+    #[inline]
+    pub fn comment<S>(mut self, comment: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.comment = Some(comment.into());
+        self
+    }
+
+    #[inline]
+    pub fn created_by<S>(mut self, created_by: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.created_by = Some(created_by.into());
+        self
+    }
+
+    /// Creation date as a Unix timestamp.
+    #[inline]
+    pub fn creation_date(mut self, timestamp: u64) -> Self {
+        self.creation_date = Some(timestamp);
+        self
+    }
+
+    /// Tracker specific `source` string.
+    #[inline]
+    pub fn source<S>(mut self, source: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.source = Some(source.into());
+        self
+    }
+
+    /// Create a private torrent with [make_private].
+    ///
+    /// Private torrents need a tracker and can't have web seeds.
+    #[inline]
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Whole file checksums to embed with [embed_checksums].
+    #[inline]
+    pub fn checksums(mut self, checksums: FileChecksums) -> Self {
+        self.checksums = checksums;
+        self
+    }
+
+    /// Hash the files and create the torrent.
+    pub fn build(self) -> io::Result<Torrent> {
+        if self.version == TorrentVersion::MerkleV1 {
+            return Err(io::Error::new(
+                ErrorKind::Unsupported,
+                "Merkle torrents can't be created",
+            ));
+        }
+
+        let mut files = match self.files {
+            Some(files) => files,
+            // Globs that don't parse are the only errors that aren't IO errors.
+            None => walk_files(&self.root, &self.walk).map_err(|e| {
+                let kind = e
+                    .io_error()
+                    .map_or(ErrorKind::InvalidInput, io::Error::kind);
+                io::Error::new(kind, e)
+            })?,
+        };
+        files.retain(|file| {
+            let empty = file.length == 0;
+            if empty {
+                warn!(
+                    target: TORRENTBUILDER_TARGET,
+                    "Skipping empty file: {}",
+                    file.path.display()
+                );
+            }
+            !empty
+        });
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        if files.is_empty() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} has no files to share", self.root.display()),
+            ));
+        }
+
+        let total_length = files.iter().map(|file| file.length).sum();
+        let piece_length = self
+            .piece_length
+            .unwrap_or_else(|| PieceLength::recommended_for(total_length));
+        let single_file = self.root.is_file();
+        let paths: Vec<_> = files
+            .iter()
+            .map(|file| {
+                if single_file {
+                    self.root.clone()
+                } else {
+                    self.root.join(&file.path)
+                }
+            })
+            .collect();
+
+        let mut torrent = match self.version {
+            TorrentVersion::V1 => Torrent {
+                announce: None,
+                announce_list: None,
+                created_by: None,
+                comment: None,
+                creation_date: None,
+                encoding: None,
+                httpseeds: None,
+                info: MetaInfo::MetaV1(MetaV1 {
+                    files: if single_file {
+                        MetaV1FileRepr::Single(nonzero_length(total_length))
+                    } else {
+                        MetaV1FileRepr::Multiple(flat_files(&files, None)?)
+                    },
+                    md5sum: None,
+                    name: root_name(&self.root)?,
+                    pieces: PieceHasher::hash(paths, piece_length)?.pieces(),
+                    piece_length,
+                    private: None,
+                    source: None,
+                }),
+                info_hash_internal: Default::default(),
+                info_raw: Default::default(),
+                nodes: None,
+                piece_layers: None,
+                publisher_url: None,
+                signatures: None,
+                url_list: None,
+                nonstandard: BTreeMap::new(),
+            },
+            TorrentVersion::V2 => super::create_v2(&self.root, &files, piece_length)?,
+            TorrentVersion::MerkleV1 => unreachable!("Merkle torrents were rejected above"),
+            TorrentVersion::Hybrid => {
+                let mut torrent = super::create_v2(&self.root, &files, piece_length)?;
+                let pieces = hash_aligned(&paths, piece_length)?;
+                let MetaInfo::MetaV2(v2) = torrent.info else {
+                    unreachable!("create_v2 creates version 2 torrents")
+                };
+                let (flat, length) = if single_file {
+                    (None, Some(nonzero_length(total_length)))
+                } else {
+                    (Some(flat_files(&files, Some(piece_length))?), None)
+                };
+                torrent.info = MetaInfo::Hybrid(Hybrid {
+                    files: flat,
+                    file_tree: Some(v2.file_tree),
+                    length,
+                    meta_version: Some(MetaVersion::V2),
+                    md5sum: None,
+                    name: v2.name,
+                    pieces: Some(pieces),
+                    piece_length,
+                    private: None,
+                    source: None,
+                });
+                torrent
+            }
+        };
+
+        torrent.announce = self.tiers.first().map(|tier| tier[0].clone());
+        if self.tiers.iter().map(Vec::len).sum::<usize>() > 1 {
+            torrent.announce_list = Some(self.tiers);
+        }
+        torrent.url_list = (!self.web_seeds.is_empty())
+            .then(|| self.web_seeds.into_iter().collect::<HashSet<_>>());
+        torrent.comment = self.comment;
+        torrent.created_by = self.created_by;
+        torrent.creation_date = self.creation_date;
+        torrent.info.set_source(self.source);
+
+        embed_checksums(&mut torrent, &self.root, self.checksums)?;
+        if self.private {
+            make_private(&mut torrent)?;
+        }
+
+        debug!(
+            target: TORRENTBUILDER_TARGET,
+            "Created version {} torrent {} with {} files",
+            torrent.info.version(),
+            torrent.name(),
+            files.len()
+        );
+        Ok(torrent)
+    }
+}
+
+// Lengths of files that weren't skipped aren't zero.
+#[inline]
+fn nonzero_length(length: u64) -> NonZeroU64 {
+    NonZeroU64::new(length).expect("empty files are skipped")
+}
+
+// Version 1 files. With a piece length, every file but the last is followed by
+// a padding file up to the next piece boundary.
+fn flat_files(files: &[SourceFile], align: Option<PieceLength>) -> io::Result<Vec<FlatFile>> {
+    let invalid = |e| io::Error::new(ErrorKind::InvalidData, e);
+    let mut flat = Vec::with_capacity(files.len());
+
+    for (i, file) in files.iter().enumerate() {
+        flat.push(FlatFile {
+            attr: None,
+            length: nonzero_length(file.length),
+            path: path_components(&file.path)?
+                .into_iter()
+                .map(PathComponent::try_from)
+                .collect::<Result<_, _>>()
+                .map_err(invalid)?,
+            md5sum: None,
+            sha1: None,
+            symlink_path: None,
+        });
+
+        let padding = align
+            .filter(|_| i + 1 < files.len())
+            .and_then(|piece_length| {
+                let piece_length = piece_length.get();
+                NonZeroU64::new((piece_length - file.length % piece_length) % piece_length)
+            });
+        if let Some(padding) = padding {
+            flat.push(FlatFile {
+                attr: Some(TorrentFileAttributes::try_from("p").map_err(invalid)?),
+                length: padding,
+                path: [".pad".to_owned(), padding.to_string()]
+                    .into_iter()
+                    .map(PathComponent::try_from)
+                    .collect::<Result<_, _>>()
+                    .map_err(invalid)?,
+                md5sum: None,
+                sha1: None,
+                symlink_path: None,
+            });
+        }
+    }
+
+    Ok(flat)
+}
+
+// SHA-1 pieces of `paths` where every file but the last starts on a piece
+// boundary as if it were padded with zeros.
+fn hash_aligned(paths: &[PathBuf], piece_length: PieceLength) -> io::Result<Pieces> {
+    let mut hashes = Vec::new();
+    let mut piece = vec![0; piece_length.get() as usize];
+
+    for (i, path) in paths.iter().enumerate() {
+        let mut file = File::open(path)?;
+        loop {
+            let read = read_block(&mut file, &mut piece)?;
+            if read == 0 {
+                break;
+            }
+
+            let padded = read < piece.len() && i + 1 < paths.len();
+            let length = if padded {
+                piece[read..].fill(0);
+                piece.len()
+            } else {
+                read
+            };
+            hashes.push(sha1::Sha1::digest(&piece[..length]).into());
+
+            if read < piece.len() {
+                break;
+            }
+        }
+    }
+
+    Ok(Pieces::from_hashes(&hashes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TorrentBuilder;
+    use crate::{
+        create::FileChecksums,
+        metainfo::{MetaInfo, TorrentVersion},
+        parseoptions::ParseOptions,
+        PieceLength, Torrent,
+    };
+    use std::{error::Error, fs};
+
+    #[test]
+    fn build_every_version() -> Result<(), Box<dyn Error>> {
+        let root =
+            std::env::temp_dir().join(format!("star-cloudburst-builder-{}", std::process::id()));
+        fs::create_dir_all(root.join("season 1"))?;
+        fs::write(root.join("season 1/cats.mkv"), vec![b'c'; 40_000])?;
+        fs::write(root.join("season 1/empty.nfo"), b"")?;
+        fs::write(root.join("mews.mkv"), b"mew")?;
+        let piece_length = PieceLength::new(16384).unwrap();
+
+        for version in [
+            TorrentVersion::V1,
+            TorrentVersion::V2,
+            TorrentVersion::Hybrid,
+        ] {
+            let torrent = TorrentBuilder::new(&root)
+                .version(version)
+                .piece_length(piece_length)
+                .tier(vec![
+                    "udp://cats.test:1337".parse()?,
+                    "udp://mews.test:1337".parse()?,
+                ])
+                .checksums(FileChecksums::Sha1)
+                .private(true)
+                .build()?;
+            assert_eq!(torrent.info.version(), version);
+            assert!(torrent.info.is_private());
+            assert_eq!(torrent.announce_list.as_ref().map(Vec::len), Some(1));
+
+            let bytes = serde_bencode::to_bytes(&torrent)?;
+            assert_eq!(
+                Torrent::parse_with(&bytes, ParseOptions::strict())?,
+                torrent
+            );
+            // The empty file is skipped.
+            assert_eq!(torrent.info.total_length(), 40_003);
+        }
+
+        // mews.mkv is padded to a piece and cats.mkv takes three more.
+        let hybrid = TorrentBuilder::new(&root)
+            .piece_length(piece_length)
+            .build()?;
+        let MetaInfo::Hybrid(info) = &hybrid.info else {
+            panic!("expected a hybrid torrent")
+        };
+        assert_eq!(info.pieces.as_ref().map(|pieces| pieces.len()), Some(4));
+        assert_eq!(info.files.as_ref().map(Vec::len), Some(3));
+
+        let single = TorrentBuilder::new(root.join("mews.mkv"))
+            .version(TorrentVersion::V1)
+            .build()?;
+        assert_eq!(single.name(), "mews.mkv");
+        assert_eq!(single.info.total_length(), 3);
+
+        assert!(TorrentBuilder::new(&root).private(true).build().is_err());
+        assert!(TorrentBuilder::new(&root)
+            .version(TorrentVersion::MerkleV1)
+            .build()
+            .is_err());
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
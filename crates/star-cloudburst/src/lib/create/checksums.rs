@@ -0,0 +1,182 @@
+//! Embed whole file checksums in version 1 file entries.
+//!
+//! [BEP-0047](https://www.bittorrent.org/beps/bep_0047.html) adds an optional
+//! `sha1` to each file so that files may be deduplicated across torrents
+//! without their pieces, and BEP-0003 has an optional `md5sum`. Both mean
+//! reading every file a second time, so neither is embedded by default.
+
+use crate::{
+    crypto::{md5::Md5, sha1::Sha1},
+    files::{FlatFile, MetaV1FileRepr},
+    metainfo::MetaInfo,
+    Torrent,
+};
+use digest::Digest;
+use log::debug;
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+const EMBEDCHECKSUMS_TARGET: &str = "star_cloudburst::create::checksums::embed_checksums";
+
+/// Which whole file checksums [embed_checksums] computes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileChecksums {
+    /// Don't read the files again.
+    #[default]
+    None,
+    /// `sha1` of each file.
+    Sha1,
+    /// `sha1` and `md5sum` of each file.
+    Sha1AndMd5,
+}
+
+/// Hash each file of `torrent` under `root` and embed the checksums.
+///
+/// `root` is the directory named by the torrent or the file itself for single
+/// file torrents. Single file torrents only have an `md5sum` field in the info
+/// dict, so only [FileChecksums::Sha1AndMd5] changes them. Padding files and
+/// version 2 only torrents, whose files already have a `pieces root`, are left
+/// unchanged. Embedding checksums changes the info hash.
+///
+/// ```rust
+/// use star_cloudburst::{
+///     create::{embed_checksums, FileChecksums},
+///     hexadecimal::HexBytes,
+///     Torrent,
+/// };
+/// use std::fs;
+///
+/// let root = std::env::temp_dir().join("star_cloudburst_embed_checksums_doc");
+/// fs::create_dir_all(&root)?;
+/// fs::write(root.join("cats.mkv"), b"meow")?;
+///
+/// let cats = b"d4:infod5:filesld6:lengthi4e4:pathl8:cats.mkveee4:name35:star_cloudburst_embed_checksums_doc12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+/// let mut torrent = Torrent::from_bytes(cats)?;
+/// embed_checksums(&mut torrent, &root, FileChecksums::Sha1)?;
+///
+/// // 4:sha120: followed by the hash of "meow".
+/// let sha1: HexBytes = "7d5c2a2d6136fbf166211d5183bf66214a247f31".parse()?;
+/// let bytes = torrent.to_bytes()?;
+/// assert!(bytes
+///     .windows(29)
+///     .any(|window| window[..9] == *b"4:sha120:" && window[9..] == *sha1.as_slice()));
+/// assert!(!bytes.windows(8).any(|window| window == b"6:md5sum"));
+/// # fs::remove_dir_all(root)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn embed_checksums(
+    torrent: &mut Torrent,
+    root: &Path,
+    checksums: FileChecksums,
+) -> io::Result<()> {
+    if checksums == FileChecksums::None {
+        return Ok(());
+    }
+    let with_md5 = checksums == FileChecksums::Sha1AndMd5;
+
+    let (files, md5sum) = match &mut torrent.info {
+        MetaInfo::MetaV1(info) => match &mut info.files {
+            MetaV1FileRepr::Multiple(files) => (Some(files), &mut info.md5sum),
+            MetaV1FileRepr::Single(_) => (None, &mut info.md5sum),
+        },
+        MetaInfo::MerkleV1(info) => (info.files.as_mut(), &mut info.md5sum),
+        MetaInfo::Hybrid(info) => (info.files.as_mut(), &mut info.md5sum),
+        MetaInfo::MetaV2(_) => {
+            debug!(target: EMBEDCHECKSUMS_TARGET, "Version 2 only torrents don't have version 1 files");
+            return Ok(());
+        }
+    };
+
+    match files {
+        Some(files) => {
+            for file in files.iter_mut().filter(|file| !file.is_padding()) {
+                embed_file(file, root, with_md5)?;
+            }
+        }
+        None if with_md5 => *md5sum = Some(hash_file(root)?.1),
+        None => {
+            debug!(target: EMBEDCHECKSUMS_TARGET, "Single file torrents don't have a sha1 field")
+        }
+    }
+
+    torrent.info_hash_internal.clear();
+    torrent.info_raw.clear();
+    Ok(())
+}
+
+fn embed_file(file: &mut FlatFile, root: &Path, with_md5: bool) -> io::Result<()> {
+    let path = file
+        .path
+        .iter()
+        .fold(root.to_path_buf(), |path, component| {
+            path.join(&**component)
+        });
+    let (sha1, md5) = hash_file(&path)?;
+
+    debug!(target: EMBEDCHECKSUMS_TARGET, "{}: sha1 {sha1}", path.display());
+    file.sha1 = Some(sha1);
+    if with_md5 {
+        file.md5sum = Some(md5);
+    }
+    Ok(())
+}
+
+// Both hashes are computed while reading the file once.
+fn hash_file(path: &Path) -> io::Result<(Sha1, Md5)> {
+    let mut file = File::open(path)?;
+    let mut sha1 = sha1::Sha1::new();
+    let mut md5 = md5::Md5::new();
+    let mut buffer = vec![0; 1 << 16];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        sha1.update(&buffer[..read]);
+        md5.update(&buffer[..read]);
+    }
+
+    Ok((
+        Sha1::from_bytes(sha1.finalize().into()),
+        Md5::from_bytes(md5.finalize().into()),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{embed_checksums, FileChecksums};
+    use crate::{metainfo::MetaInfo, Torrent};
+    use std::fs;
+
+    #[test]
+    fn single_file_md5sum() -> Result<(), Box<dyn std::error::Error>> {
+        let cats = std::env::temp_dir().join(format!(
+            "star-cloudburst-embed-checksums-{}",
+            std::process::id()
+        ));
+        fs::write(&cats, b"meow")?;
+
+        let bytes = b"d4:infod6:lengthi4e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+        let mut torrent: Torrent = serde_bencode::from_bytes(bytes)?;
+
+        embed_checksums(&mut torrent, &cats, FileChecksums::Sha1)?;
+        assert_eq!(serde_bencode::to_bytes(&torrent)?, bytes);
+
+        embed_checksums(&mut torrent, &cats, FileChecksums::Sha1AndMd5)?;
+        fs::remove_file(&cats)?;
+        let md5sum = match &torrent.info {
+            MetaInfo::MetaV1(info) => &info.md5sum,
+            MetaInfo::Hybrid(info) => &info.md5sum,
+            info => panic!("expected a version 1 torrent: {info:?}"),
+        };
+        assert_eq!(
+            md5sum.as_ref().map(ToString::to_string).as_deref(),
+            Some("4a4be40c96ac6314e91d93f38043a634")
+        );
+        Ok(())
+    }
+}
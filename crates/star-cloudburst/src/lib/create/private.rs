@@ -0,0 +1,105 @@
+//! Enforce what private trackers expect of new torrents.
+//!
+//! Private trackers reject torrents that can find peers without them. A
+//! private torrent sets `private=1` so that clients disable DHT, PEX, and LSD
+//! ([BEP-0027](https://www.bittorrent.org/beps/bep_0027.html)), and it
+//! shouldn't carry DHT nodes or web seeds either.
+
+use crate::Torrent;
+use log::debug;
+use std::io::{self, ErrorKind};
+
+const MAKEPRIVATE_TARGET: &str = "star_cloudburst::create::private::make_private";
+
+// Nonstandard top level keys that list DHT nodes.
+const NONSTANDARD_NODES: &[&str] = &["nodes6"];
+
+/// Mark `torrent` private and strip peer sources other than its trackers.
+///
+/// `nodes`, `nodes6`, `url-list`, and `httpseeds` are removed and `private`
+/// is set to 1, which changes the info hash. Torrents without a tracker are
+/// an error and are left unchanged because no peer could ever find them.
+///
+/// ```rust
+/// use star_cloudburst::{create::make_private, Torrent};
+///
+/// let cats = b"d8:announce15:udp://cats.test4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee8:url-listl16:http://cats.testee";
+/// let mut torrent = Torrent::from_bytes(cats)?;
+///
+/// let public = format!("{:?}", torrent.info_hash()?);
+/// make_private(&mut torrent)?;
+/// assert!(torrent.info.is_private());
+/// assert!(torrent.url_list.is_none());
+/// assert_ne!(format!("{:?}", torrent.info_hash()?), public);
+///
+/// torrent.announce = None;
+/// assert!(make_private(&mut torrent).is_err());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn make_private(torrent: &mut Torrent) -> io::Result<()> {
+    if torrent.trackers().next().is_none() {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            "private torrents need at least one tracker",
+        ));
+    }
+
+    if torrent.nodes.take().is_some() {
+        debug!(target: MAKEPRIVATE_TARGET, "Removed DHT nodes");
+    }
+    for key in NONSTANDARD_NODES {
+        if torrent.nonstandard.remove(*key).is_some() {
+            debug!(target: MAKEPRIVATE_TARGET, "Removed nonstandard {key}");
+        }
+    }
+    let url_list = torrent.url_list.take();
+    let httpseeds = torrent.httpseeds.take();
+    if url_list.is_some() || httpseeds.is_some() {
+        debug!(target: MAKEPRIVATE_TARGET, "Removed web seeds");
+    }
+
+    if torrent.info.set_private(Some(true)) != Some(true) {
+        torrent.info_hash_internal.clear();
+        torrent.info_raw.clear();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::make_private;
+    use crate::{
+        create::{create_v2, walk_files, WalkOptions},
+        PieceLength, Torrent,
+    };
+    use std::fs;
+
+    #[test]
+    fn private_strips_peer_sources() -> Result<(), Box<dyn std::error::Error>> {
+        let root = std::env::temp_dir().join(format!(
+            "star-cloudburst-make-private-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&root)?;
+        fs::write(root.join("cats.mkv"), b"meow")?;
+
+        let files = walk_files(&root, &WalkOptions::default())?;
+        let mut torrent = create_v2(&root, &files, PieceLength::new(16384).unwrap())?;
+        fs::remove_dir_all(&root)?;
+
+        // Freshly created torrents don't have trackers yet.
+        assert!(make_private(&mut torrent).is_err());
+        assert!(!torrent.info.is_private());
+
+        torrent.announce = Some("udp://cats.test".parse()?);
+        torrent.httpseeds = Some(vec!["http://mews.test".parse()?]);
+        torrent.nodes = Some(vec![]);
+        make_private(&mut torrent)?;
+
+        let bytes = serde_bencode::to_bytes(&torrent)?;
+        let private = Torrent::parse_with(&bytes, Default::default())?;
+        assert!(private.info.is_private());
+        assert!(private.httpseeds.is_none() && private.nodes.is_none());
+        Ok(())
+    }
+}
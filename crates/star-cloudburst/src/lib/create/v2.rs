@@ -0,0 +1,282 @@
+//! Create version 2 only torrents.
+//!
+//! [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) torrents without
+//! version 1 pieces are smaller and hash each file separately, but older
+//! clients can't download them.
+
+use crate::{
+    create::SourceFile,
+    crypto::merkle::hash_file_v2,
+    files::{FileTree, FileTreeEntry, FileTreeInfo, PathComponent},
+    hexadecimal::HexBytes,
+    metainfo::{MetaInfo, MetaV2, MetaVersion},
+    pieces::{PieceLength, MIN_PIECE_LENGTH},
+    Torrent,
+};
+use either::Either;
+use log::{debug, warn};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, ErrorKind},
+    path::{Component, Path},
+};
+
+const CREATEV2_TARGET: &str = "star_cloudburst::create::v2::create_v2";
+
+/// Create a version 2 only torrent of `files` under `root`.
+///
+/// `files` are usually from [crate::create::walk_files]. If `root` is a file,
+/// it's the only file of the torrent. The torrent is named after `root` and
+/// has no trackers.
+///
+/// Empty files are skipped because they don't have a `pieces root`. Piece
+/// lengths under 16 KiB are an error.
+///
+/// ```rust
+/// use star_cloudburst::{
+///     create::{create_v2, walk_files, WalkOptions},
+///     metainfo::MetaInfo,
+///     PieceLength,
+/// };
+/// use std::fs;
+///
+/// let root = std::env::temp_dir().join("star_cloudburst_create_v2_doc");
+/// fs::create_dir_all(root.join("extras"))?;
+/// fs::write(root.join("cats.mkv"), vec![b'c'; 40_000])?;
+/// fs::write(root.join("extras/mews.mkv"), b"mew")?;
+///
+/// let piece_length = PieceLength::new(16384).unwrap();
+/// let files = walk_files(&root, &WalkOptions::default())?;
+/// let torrent = create_v2(&root, &files, piece_length)?;
+///
+/// assert!(matches!(torrent.info, MetaInfo::MetaV2(_)));
+/// assert_eq!(torrent.name(), "star_cloudburst_create_v2_doc");
+/// // Only cats.mkv is larger than a piece.
+/// assert_eq!(torrent.piece_layers.map(|layers| layers.len()), Some(1));
+/// # fs::remove_dir_all(root)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn create_v2(
+    root: &Path,
+    files: &[SourceFile],
+    piece_length: PieceLength,
+) -> io::Result<Torrent> {
+    if piece_length.get() < MIN_PIECE_LENGTH {
+        return Err(io::Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "piece length should be at least 16 KiB; got {}",
+                piece_length.get()
+            ),
+        ));
+    }
+
+    let name = root_name(root)?;
+    let single_file = root.is_file();
+
+    let mut file_tree = FileTree {
+        node: BTreeMap::new(),
+    };
+    let mut piece_layers = HashMap::new();
+
+    for file in files {
+        if file.length == 0 {
+            warn!(
+                target: CREATEV2_TARGET,
+                "Skipping empty file: {}",
+                file.path.display()
+            );
+            continue;
+        }
+
+        let path = if single_file {
+            root.to_path_buf()
+        } else {
+            root.join(&file.path)
+        };
+        let reader = BufReader::new(File::open(&path)?);
+        let hashes = hash_file_v2(reader, piece_length)?.ok_or_else(|| {
+            io::Error::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "{} is empty but should be {} bytes",
+                    path.display(),
+                    file.length
+                ),
+            )
+        })?;
+
+        if !hashes.piece_layer.is_empty() {
+            piece_layers.insert(
+                HexBytes::from(*hashes.pieces_root.as_bytes()),
+                HexBytes::from(hashes.piece_layer_bytes()),
+            );
+        }
+
+        let info = FileTreeInfo {
+            attr: None,
+            length: file.length,
+            pieces_root: Some(hashes.pieces_root),
+            symlink_path: None,
+        };
+        let components = if single_file {
+            vec![name.clone()]
+        } else {
+            path_components(&file.path)?
+        };
+        insert_file(&mut file_tree, components, info)?;
+    }
+
+    check_pieces_roots(&file_tree, &piece_layers, piece_length)?;
+    debug!(
+        target: CREATEV2_TARGET,
+        "Created version 2 torrent {name} with {} files",
+        file_tree.file_count()
+    );
+
+    Ok(Torrent {
+        announce: None,
+        announce_list: None,
+        created_by: None,
+        comment: None,
+        creation_date: None,
+        encoding: None,
+        httpseeds: None,
+        info: MetaInfo::MetaV2(MetaV2 {
+            file_tree,
+            name,
+            meta_version: MetaVersion::V2,
+            piece_length,
+            private: None,
+            source: None,
+        }),
+        info_hash_internal: Default::default(),
+        info_raw: Default::default(),
+        nodes: None,
+        piece_layers: (!piece_layers.is_empty()).then_some(piece_layers),
+        publisher_url: None,
+        signatures: None,
+        url_list: None,
+        nonstandard: BTreeMap::new(),
+    })
+}
+
+// Name of the torrent of `root`.
+pub(super) fn root_name(root: &Path) -> io::Result<String> {
+    root.file_name()
+        .and_then(|name| name.to_str())
+        .map(str::to_owned)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "root should have a UTF-8 name"))
+}
+
+// Names of a relative path as UTF-8.
+pub(super) fn path_components(path: &Path) -> io::Result<Vec<String>> {
+    path.components()
+        .map(|component| match component {
+            Component::Normal(name) => name.to_str().map(str::to_owned).ok_or_else(|| {
+                io::Error::new(
+                    ErrorKind::InvalidData,
+                    format!("{} isn't valid UTF-8", path.display()),
+                )
+            }),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("{} should be relative to the root", path.display()),
+            )),
+        })
+        .collect()
+}
+
+// Add a file to the tree, creating its directories.
+fn insert_file(tree: &mut FileTree, components: Vec<String>, info: FileTreeInfo) -> io::Result<()> {
+    let invalid = |e| io::Error::new(ErrorKind::InvalidData, e);
+    let mut dir = tree;
+
+    for component in components {
+        let component = PathComponent::try_from(component).map_err(invalid)?;
+        let entry = dir.node.entry(component).or_insert_with(|| {
+            FileTreeEntry(Either::Right(FileTree {
+                node: BTreeMap::new(),
+            }))
+        });
+
+        dir = match &mut entry.0 {
+            Either::Right(dir) => dir,
+            Either::Left(_) => unreachable!("Files are always nested under their name"),
+        };
+    }
+
+    dir.node.insert(
+        PathComponent::FILE_INFO_KEY,
+        FileTreeEntry(Either::Left(info)),
+    );
+    Ok(())
+}
+
+// Every file has a pieces root and files larger than a piece have a piece
+// layer.
+fn check_pieces_roots(
+    tree: &FileTree,
+    piece_layers: &HashMap<HexBytes, HexBytes>,
+    piece_length: PieceLength,
+) -> io::Result<()> {
+    for file in tree.iter_dfs() {
+        let info = file.file_info;
+        let missing = match &info.pieces_root {
+            None => Some("pieces root"),
+            Some(root) if info.length > piece_length.get() => {
+                let expected = info.length.div_ceil(piece_length.get()) as usize * 32;
+                match piece_layers.get(&root.as_bytes()[..]) {
+                    Some(layer) if layer.len() == expected => None,
+                    _ => Some("piece layer"),
+                }
+            }
+            Some(_) => None,
+        };
+
+        if let Some(missing) = missing {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{} is missing its {missing}", file.name),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::create_v2;
+    use crate::{create::walk_files, parseoptions::ParseOptions, PieceLength, Torrent};
+    use std::{error::Error, fs};
+
+    #[test]
+    fn create_v2_roundtrip() -> Result<(), Box<dyn Error>> {
+        let root = std::env::temp_dir().join(format!("star-cloudburst-v2-{}", std::process::id()));
+        fs::create_dir_all(root.join("season 1"))?;
+        fs::write(root.join("season 1/cats.mkv"), vec![b'c'; 100_000])?;
+        fs::write(root.join("season 1/empty.nfo"), b"")?;
+        fs::write(root.join("mews.mkv"), b"mew")?;
+
+        let piece_length = PieceLength::new(32 * 1024).unwrap();
+        let files = walk_files(&root, &Default::default())?;
+        let torrent = create_v2(&root, &files, piece_length)?;
+        assert_eq!(torrent.info.file_count(), 2);
+
+        let bytes = serde_bencode::to_bytes(&torrent)?;
+        let parsed = Torrent::parse_with(&bytes, ParseOptions::strict())?;
+        assert_eq!(parsed, torrent);
+
+        let single = create_v2(
+            &root.join("mews.mkv"),
+            &walk_files(&root.join("mews.mkv"), &Default::default())?,
+            piece_length,
+        )?;
+        assert_eq!(single.name(), "mews.mkv");
+        assert!(single.piece_layers.is_none());
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,238 @@
+//! Collect the files of a source directory.
+//!
+//! Source directories often contain files that shouldn't be shared, such as
+//! hidden files or the thumbnails caches that file managers drop everywhere.
+//! [WalkOptions] excludes them during the walk so that they're never hashed.
+//!
+//! Exclusions for a tree may be kept in a [IGNORE_FILE] with gitignore syntax
+//! so that they don't have to be repeated every time the torrent is created.
+
+use ignore::{overrides::OverrideBuilder, WalkBuilder};
+use log::{debug, trace};
+use std::path::{Path, PathBuf};
+
+const WALKFILES_TARGET: &str = "star_cloudburst::create::walk::walk_files";
+
+/// Name of the gitignore style files read by [walk_files].
+///
+/// Like `.gitignore`, patterns apply to the directory of the file and its
+/// subdirectories. Ignore files that are read aren't included themselves.
+pub const IGNORE_FILE: &str = ".torrentignore";
+
+/// File names of junk files left by operating systems and file managers.
+///
+/// Compared without regard to ASCII case.
+pub const JUNK_FILES: &[&str] = &[".DS_Store", "desktop.ini", "ehthumbs.db", "Thumbs.db"];
+
+/// Which files [walk_files] skips.
+///
+/// By default only files excluded by an [IGNORE_FILE] are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkOptions {
+    /// Gitignore style globs of files and directories to exclude, such as
+    /// `*.nfo` or `/extras`. Globs are matched relative to the root.
+    pub exclude: Vec<String>,
+    /// Skip files and directories whose names start with a dot.
+    pub skip_hidden: bool,
+    /// Skip files that are empty.
+    pub skip_empty: bool,
+    /// Skip [JUNK_FILES].
+    pub skip_junk: bool,
+    /// Read exclusions from [IGNORE_FILE]s in the walked directories.
+    pub ignore_files: bool,
+}
+
+impl Default for WalkOptions {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            skip_hidden: false,
+            skip_empty: false,
+            skip_junk: false,
+            ignore_files: true,
+        }
+    }
+}
+
+impl WalkOptions {
+    /// Options that skip hidden, empty, and junk files.
+    #[inline]
+    pub fn clean() -> Self {
+        Self {
+            skip_hidden: true,
+            skip_empty: true,
+            skip_junk: true,
+            ..Self::default()
+        }
+    }
+
+    /// Also exclude files and directories that match `glob`.
+    #[inline]
+    pub fn exclude<S>(mut self, glob: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.exclude.push(glob.into());
+        self
+    }
+}
+
+/// A file to include in a torrent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    /// Path relative to the root that was walked.
+    pub path: PathBuf,
+    pub length: u64,
+}
+
+/// Collect the files under `root` that aren't skipped by `options`.
+///
+/// Files are sorted by path so that the same tree always yields the same
+/// torrent. Symbolic links aren't followed or returned. If `root` is a file,
+/// it's returned by itself regardless of `options`.
+///
+/// ```rust
+/// use star_cloudburst::create::{walk_files, WalkOptions};
+/// use std::{fs, path::Path};
+///
+/// let root = std::env::temp_dir().join("star_cloudburst_walk_files_doc");
+/// fs::create_dir_all(root.join("extras"))?;
+/// fs::write(root.join("cats.mkv"), b"meow")?;
+/// fs::write(root.join("cats.nfo"), b"meow")?;
+/// fs::write(root.join("extras/Thumbs.db"), b"")?;
+/// fs::write(root.join("extras/mews.mkv"), b"mew")?;
+///
+/// let files = walk_files(&root, &WalkOptions::clean().exclude("*.nfo"))?;
+/// let paths: Vec<_> = files.iter().map(|file| file.path.as_path()).collect();
+/// assert_eq!(paths, [Path::new("cats.mkv"), Path::new("extras/mews.mkv")]);
+/// # fs::remove_dir_all(root)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn walk_files(root: &Path, options: &WalkOptions) -> Result<Vec<SourceFile>, ignore::Error> {
+    let metadata = root.metadata()?;
+    if metadata.is_file() {
+        let path = root.file_name().map(PathBuf::from).unwrap_or_default();
+        return Ok(vec![SourceFile {
+            path,
+            length: metadata.len(),
+        }]);
+    }
+
+    let mut overrides = OverrideBuilder::new(root);
+    for glob in &options.exclude {
+        // Override globs are includes unless negated.
+        overrides.add(&format!("!{glob}"))?;
+    }
+
+    let mut walker = WalkBuilder::new(root);
+    if options.ignore_files {
+        walker.add_custom_ignore_filename(IGNORE_FILE);
+    }
+
+    let skip_junk = options.skip_junk;
+    let ignore_files = options.ignore_files;
+    let walker = walker
+        .standard_filters(false)
+        .hidden(options.skip_hidden)
+        .overrides(overrides.build()?)
+        .filter_entry(move |entry| {
+            let name = entry.file_name();
+            let junk = skip_junk
+                && name.to_str().is_some_and(|name| {
+                    JUNK_FILES
+                        .iter()
+                        .any(|junk| junk.eq_ignore_ascii_case(name))
+                });
+            let ignore_file = ignore_files && name == IGNORE_FILE;
+            !(junk || ignore_file)
+        })
+        .sort_by_file_name(Ord::cmp)
+        .build();
+
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = entry?;
+        if !entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_file())
+        {
+            continue;
+        }
+
+        let length = entry.metadata()?.len();
+        if options.skip_empty && length == 0 {
+            trace!(
+                target: WALKFILES_TARGET,
+                "Skipping empty file: {}",
+                entry.path().display()
+            );
+            continue;
+        }
+
+        let path = entry
+            .path()
+            .strip_prefix(root)
+            .expect("Walked paths start with the root")
+            .to_path_buf();
+        files.push(SourceFile { path, length });
+    }
+
+    // The walker sorts siblings but directories and files are interleaved.
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    debug!(
+        target: WALKFILES_TARGET,
+        "Found {} files under {}",
+        files.len(),
+        root.display()
+    );
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk_files, WalkOptions};
+    use std::{error::Error, fs, path::Path};
+
+    #[test]
+    fn walk_skips_hidden_empty_and_excluded() -> Result<(), Box<dyn Error>> {
+        let root =
+            std::env::temp_dir().join(format!("star-cloudburst-walk-{}", std::process::id()));
+        fs::create_dir_all(root.join(".git"))?;
+        fs::create_dir_all(root.join("season 1/extras"))?;
+        fs::write(root.join(".git/HEAD"), b"ref")?;
+        fs::write(root.join(".DS_Store"), b"junk")?;
+        fs::write(root.join("empty.txt"), b"")?;
+        fs::write(root.join("season 1/cats.mkv"), b"meow")?;
+        fs::write(root.join("season 1/extras/bloopers.mkv"), b"mew")?;
+
+        let everything = walk_files(&root, &WalkOptions::default())?;
+        assert_eq!(everything.len(), 5);
+
+        // Exclusions kept next to the files.
+        fs::write(root.join("season 1/.torrentignore"), b"*.mkv\n!cats.mkv\n")?;
+        let ignored = walk_files(&root, &WalkOptions::default())?;
+        assert_eq!(ignored.len(), 4);
+        assert!(ignored
+            .iter()
+            .all(|file| !file.path.ends_with("bloopers.mkv")));
+
+        let unignored = WalkOptions {
+            ignore_files: false,
+            ..WalkOptions::default()
+        };
+        assert_eq!(walk_files(&root, &unignored)?.len(), 6);
+
+        let options = WalkOptions::clean().exclude("extras/");
+        let files = walk_files(&root, &options)?;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, Path::new("season 1/cats.mkv"));
+        assert_eq!(files[0].length, 4);
+
+        let single = walk_files(&root.join("empty.txt"), &options)?;
+        assert_eq!(single[0].path, Path::new("empty.txt"));
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
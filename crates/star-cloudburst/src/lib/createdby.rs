@@ -0,0 +1,160 @@
+//! Recognize the client that created a torrent.
+//!
+//! `created by` is a free form string but popular clients write it in a
+//! predictable format such as `qBittorrent v4.5.2` or `Transmission/3.00
+//! (bb6b5a062e)`. [CreatedBy] splits those into a [Client] and a version.
+
+use crate::Torrent;
+use std::fmt::{self, Display, Formatter};
+
+/// Torrent clients and tools recognized by [CreatedBy].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Client {
+    Libtorrent,
+    Mktorrent,
+    QBittorrent,
+    Transmission,
+}
+
+impl Client {
+    /// Name of the client as it's usually written.
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Client::Libtorrent => "libtorrent",
+            Client::Mktorrent => "mktorrent",
+            Client::QBittorrent => "qBittorrent",
+            Client::Transmission => "Transmission",
+        }
+    }
+}
+
+impl Display for Client {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+const CLIENTS: &[Client] = &[
+    Client::Libtorrent,
+    Client::Mktorrent,
+    Client::QBittorrent,
+    Client::Transmission,
+];
+
+/// Structured `created by` string.
+///
+/// ```rust
+/// use star_cloudburst::createdby::{Client, CreatedBy};
+///
+/// let created_by = CreatedBy::parse("Transmission/3.00 (bb6b5a062e)");
+/// assert_eq!(created_by.client, Some(Client::Transmission));
+/// assert_eq!(created_by.version, Some("3.00"));
+///
+/// let created_by = CreatedBy::parse("My Cool Torrent Maker");
+/// assert_eq!(created_by.client, None);
+/// assert_eq!(created_by.name(), "My Cool Torrent Maker");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CreatedBy<'torrent> {
+    /// Recognized client if any.
+    pub client: Option<Client>,
+    /// Client version without a leading `v`.
+    pub version: Option<&'torrent str>,
+    /// The original string.
+    pub raw: &'torrent str,
+}
+
+impl<'torrent> CreatedBy<'torrent> {
+    /// Recognize the client and version of a `created by` string.
+    ///
+    /// Unrecognized strings only have [CreatedBy::raw].
+    pub fn parse(raw: &'torrent str) -> Self {
+        let trimmed = raw.trim();
+
+        for &client in CLIENTS {
+            let name = client.name();
+            let Some(rest) = trimmed
+                .get(..name.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(name))
+                .map(|_| &trimmed[name.len()..])
+            else {
+                continue;
+            };
+
+            // The version follows a space or slash and may start with a `v`.
+            let rest = rest.trim_start_matches([' ', '/']);
+            let rest = rest.strip_prefix(['v', 'V']).unwrap_or(rest);
+            let version = rest
+                .split_whitespace()
+                .next()
+                .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()));
+
+            return Self {
+                client: Some(client),
+                version,
+                raw,
+            };
+        }
+
+        Self {
+            client: None,
+            version: None,
+            raw,
+        }
+    }
+
+    /// Client name or the original string if the client wasn't recognized.
+    #[inline]
+    pub fn name(&self) -> &str {
+        self.client.map(Client::name).unwrap_or(self.raw)
+    }
+}
+
+impl Display for CreatedBy<'_> {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.raw)
+    }
+}
+
+impl Torrent {
+    /// Structured [Torrent::created_by].
+    #[inline]
+    pub fn created_by_client(&self) -> Option<CreatedBy<'_>> {
+        self.created_by.as_deref().map(CreatedBy::parse)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Client, CreatedBy};
+
+    #[test]
+    fn created_by_common_clients() {
+        let cases = [
+            (
+                "qBittorrent v4.5.2",
+                Some(Client::QBittorrent),
+                Some("4.5.2"),
+            ),
+            ("mktorrent 1.1", Some(Client::Mktorrent), Some("1.1")),
+            ("libtorrent", Some(Client::Libtorrent), None),
+            (
+                "Transmission/2.94",
+                Some(Client::Transmission),
+                Some("2.94"),
+            ),
+            ("uTorrent/3.5.5", None, None),
+        ];
+
+        for (raw, client, version) in cases {
+            let created_by = CreatedBy::parse(raw);
+            assert_eq!(created_by.client, client, "{raw}");
+            assert_eq!(created_by.version, version, "{raw}");
+            assert_eq!(created_by.to_string(), raw);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Sanity checks for torrent creation dates.
+//!
+//! `creation date` is supposed to be seconds since the Unix epoch, but torrents
+//! in the wild carry zeroes, milliseconds, and dates far in the future.
+//! [DateClass] classifies a raw timestamp so that callers may reject or clamp
+//! implausible values.
+
+use crate::Torrent;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Earliest plausible creation date: 2001-01-01, the year BitTorrent was
+/// released.
+pub const EARLIEST_PLAUSIBLE: u64 = 978_307_200;
+
+/// Creation dates up to this many seconds in the future are accepted to allow
+/// for clock skew.
+pub const CLOCK_SKEW: u64 = 24 * 60 * 60;
+
+/// A plausible creation date in seconds since the Unix epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ValidatedDate(u64);
+
+impl ValidatedDate {
+    /// Seconds since the Unix epoch.
+    #[inline]
+    pub fn unix_seconds(self) -> u64 {
+        self.0
+    }
+
+    /// The date as a [SystemTime].
+    #[inline]
+    pub fn system_time(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.0)
+    }
+}
+
+/// Classification of a raw `creation date`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateClass {
+    /// A plausible date in seconds.
+    Seconds(ValidatedDate),
+    /// A plausible date in milliseconds which was converted to seconds.
+    Milliseconds(ValidatedDate),
+    /// Zero, which is usually a placeholder.
+    Zero,
+    /// A date before BitTorrent existed.
+    TooOld(u64),
+    /// A date in the future that isn't milliseconds either.
+    FarFuture(u64),
+}
+
+impl DateClass {
+    /// Classify a timestamp relative to `now`.
+    ///
+    /// ```rust
+    /// use star_cloudburst::creationdate::DateClass;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    ///
+    /// assert!(matches!(
+    ///     DateClass::classify(1_600_000_000, now),
+    ///     DateClass::Seconds(_)
+    /// ));
+    /// assert!(matches!(
+    ///     DateClass::classify(1_600_000_000_000, now),
+    ///     DateClass::Milliseconds(_)
+    /// ));
+    /// assert_eq!(DateClass::classify(0, now), DateClass::Zero);
+    /// ```
+    pub fn classify(timestamp: u64, now: SystemTime) -> Self {
+        let latest = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            .saturating_add(CLOCK_SKEW);
+        let plausible = EARLIEST_PLAUSIBLE..=latest;
+
+        if timestamp == 0 {
+            DateClass::Zero
+        } else if plausible.contains(&timestamp) {
+            DateClass::Seconds(ValidatedDate(timestamp))
+        } else if timestamp < EARLIEST_PLAUSIBLE {
+            DateClass::TooOld(timestamp)
+        } else if plausible.contains(&(timestamp / 1000)) {
+            DateClass::Milliseconds(ValidatedDate(timestamp / 1000))
+        } else {
+            DateClass::FarFuture(timestamp)
+        }
+    }
+
+    /// The date if it's plausible, including dates converted from
+    /// milliseconds.
+    #[inline]
+    pub fn validated(self) -> Option<ValidatedDate> {
+        match self {
+            DateClass::Seconds(date) | DateClass::Milliseconds(date) => Some(date),
+            DateClass::Zero | DateClass::TooOld(_) | DateClass::FarFuture(_) => None,
+        }
+    }
+
+    /// The date clamped to the plausible range relative to `now`.
+    ///
+    /// Old dates become [EARLIEST_PLAUSIBLE] and future dates become `now`.
+    /// Zero has no meaningful date and stays `None`.
+    pub fn clamped(self, now: SystemTime) -> Option<ValidatedDate> {
+        match self {
+            DateClass::Zero => None,
+            DateClass::TooOld(_) => Some(ValidatedDate(EARLIEST_PLAUSIBLE)),
+            DateClass::FarFuture(_) => Some(ValidatedDate(
+                now.duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs()
+                    .max(EARLIEST_PLAUSIBLE),
+            )),
+            _ => self.validated(),
+        }
+    }
+}
+
+impl Torrent {
+    /// Classification of the torrent's creation date, if any.
+    #[inline]
+    pub fn creation_date_class(&self) -> Option<DateClass> {
+        self.creation_date
+            .map(|timestamp| DateClass::classify(timestamp, SystemTime::now()))
+    }
+
+    /// The torrent's creation date if it's plausible.
+    ///
+    /// Dates in milliseconds are converted to seconds. Zero, dates before
+    /// BitTorrent existed, and dates in the future are `None`.
+    #[inline]
+    pub fn validated_creation_date(&self) -> Option<ValidatedDate> {
+        self.creation_date_class().and_then(DateClass::validated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DateClass, ValidatedDate, EARLIEST_PLAUSIBLE};
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn classify_and_clamp_implausible_dates() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(DateClass::classify(42, now), DateClass::TooOld(42));
+        assert_eq!(
+            DateClass::classify(1_700_000_000_000, now).validated(),
+            Some(ValidatedDate(1_700_000_000))
+        );
+
+        let future = DateClass::classify(u64::MAX, now);
+        assert_eq!(future, DateClass::FarFuture(u64::MAX));
+        assert_eq!(future.validated(), None);
+        assert_eq!(future.clamped(now), Some(ValidatedDate(1_700_000_000)));
+        assert_eq!(
+            DateClass::TooOld(42).clamped(now),
+            Some(ValidatedDate(EARLIEST_PLAUSIBLE))
+        );
+        assert_eq!(DateClass::Zero.clamped(now), None);
+    }
+}
@@ -0,0 +1,106 @@
+//! Detect torrents that share the same payload.
+//!
+//! Trackers often require fields such as `private` or `source` in the info
+//! dict which changes the info hash even though the files are identical.
+//! Cross-seeding reuses the downloaded files for every such torrent.
+
+use crate::{crypto::sha::Sha2, files::FileTree, metainfo::MetaInfo, pieces::Pieces, Torrent};
+
+/// How two torrents were determined to share a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossSeedMatch {
+    /// Version 1 piece hashes and piece length are identical.
+    Pieces,
+    /// Version 2 pieces roots of every file are identical.
+    PiecesRoots,
+}
+
+impl Torrent {
+    /// Whether `other` describes the same files and data as `self`.
+    ///
+    /// File paths and lengths must be identical. The data is compared by the
+    /// version 2 pieces roots if both torrents have them or else by the
+    /// version 1 piece hashes. The info hashes are not compared.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{crossseed::CrossSeedMatch, Torrent};
+    /// use serde_bencode::Error;
+    ///
+    /// let public = b"d4:infod4:name8:cats.mkv6:lengthi16e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let private = b"d4:infod4:name8:cats.mkv6:lengthi16e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384e7:privatei1eee";
+    /// let public = Torrent::from_bytes(public)?;
+    /// let private = Torrent::from_bytes(private)?;
+    ///
+    /// assert_eq!(public.cross_seed(&private), Some(CrossSeedMatch::Pieces));
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn cross_seed(&self, other: &Torrent) -> Option<CrossSeedMatch> {
+        if self.info.file_list() != other.info.file_list() {
+            return None;
+        }
+
+        if let (Some(roots), Some(other_roots)) =
+            (pieces_roots(&self.info), pieces_roots(&other.info))
+        {
+            return (roots == other_roots).then_some(CrossSeedMatch::PiecesRoots);
+        }
+
+        match (pieces(&self.info), pieces(&other.info)) {
+            (Some(pieces), Some(other_pieces))
+                if self.info.piece_length() == other.info.piece_length()
+                    && pieces == other_pieces =>
+            {
+                Some(CrossSeedMatch::Pieces)
+            }
+            _ => None,
+        }
+    }
+}
+
+fn pieces(info: &MetaInfo) -> Option<&Pieces> {
+    match info {
+        MetaInfo::MetaV1(info) => Some(&info.pieces),
+        MetaInfo::MetaV2(_) | MetaInfo::MerkleV1(_) => None,
+        MetaInfo::Hybrid(info) => info.pieces.as_ref(),
+    }
+}
+
+// Pieces roots in file order. Files without a root (empty files) are skipped.
+fn pieces_roots(info: &MetaInfo) -> Option<Vec<&Sha2>> {
+    let tree: &FileTree = match info {
+        MetaInfo::MetaV1(_) | MetaInfo::MerkleV1(_) => None,
+        MetaInfo::MetaV2(info) => Some(&info.file_tree),
+        MetaInfo::Hybrid(info) => info.file_tree.as_ref(),
+    }?;
+
+    let roots: Vec<_> = tree
+        .iter_dfs()
+        .filter_map(|view| view.file_info.pieces_root.as_ref())
+        .collect();
+    (!roots.is_empty()).then_some(roots)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CrossSeedMatch;
+    use crate::Torrent;
+
+    const CATS: &str = "d4:infod5:filesld6:lengthi16e4:pathl8:cats.mkveee4:name4:cats6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi16384eee";
+    const CATS_SOURCE: &str = "d4:infod5:filesld6:lengthi16e4:pathl8:cats.mkveee4:name4:cats6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi16384e6:source4:meowee";
+    const CATS_RENAMED: &str = "d4:infod5:filesld6:lengthi16e4:pathl8:dogs.mkveee4:name4:cats6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi16384eee";
+    const CATS_CHANGED: &str = "d4:infod5:filesld6:lengthi16e4:pathl8:cats.mkveee4:name4:cats6:pieces20:bbbbbbbbbbbbbbbbbbbb12:piece lengthi16384eee";
+
+    #[test]
+    fn cross_seed_ignores_info_hash() -> Result<(), serde_bencode::Error> {
+        let cats: Torrent = serde_bencode::from_str(CATS)?;
+        let source: Torrent = serde_bencode::from_str(CATS_SOURCE)?;
+        let renamed: Torrent = serde_bencode::from_str(CATS_RENAMED)?;
+        let changed: Torrent = serde_bencode::from_str(CATS_CHANGED)?;
+
+        assert_eq!(cats.cross_seed(&source), Some(CrossSeedMatch::Pieces));
+        assert_eq!(cats.cross_seed(&renamed), None);
+        assert_eq!(cats.cross_seed(&changed), None);
+
+        Ok(())
+    }
+}
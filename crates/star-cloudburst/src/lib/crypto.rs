@@ -1,6 +1,12 @@
 //! Types for cryptography used in torrents.
 pub mod md5;
+#[cfg(feature = "crypto")]
+pub mod merkle;
+pub mod nodeid;
+#[cfg(feature = "crypto")]
+pub mod piecehasher;
 // pub mod rsa;
+#[cfg(feature = "crypto")]
 pub(crate) mod calculateinfohash;
 pub mod sha1;
 pub mod sha2;
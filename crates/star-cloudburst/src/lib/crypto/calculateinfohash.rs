@@ -22,13 +22,13 @@ pub(crate) trait CalculateInfoHash<const HASHSIZE: usize> {
             target: CALCULATEINFOHASH_TARGET,
             "Calculating an info hash; expected length {HASHSIZE}."
         );
-        // Serialize info dict into a String because info hashes are calculated from
-        // Bencoded info dicts.
-        let info_se = serde_bencode::to_string(info).map_err(SerError::custom)?;
+        // Serialize info dict into bytes because info hashes are calculated from
+        // Bencoded info dicts. Pieces are binary so the dict usually isn't valid UTF-8.
+        let info_se = serde_bencode::to_bytes(info).map_err(SerError::custom)?;
 
-        // Hash the info dict String into whatever digest is specified (i.e. SHA-2)
+        // Hash the info dict bytes into whatever digest is specified (i.e. SHA-2)
         let mut hasher: Self::Hasher = Digest::new();
-        hasher.update(info_se.as_bytes());
+        hasher.update(&info_se);
         let result = hasher.finalize();
 
         // Convert the final result into HexBytes.
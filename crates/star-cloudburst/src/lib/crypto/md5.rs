@@ -1,31 +1,150 @@
 //! MD5 hash.
 
-use crate::hexadecimal::HexBytes;
-use log::{error, trace};
-use serde::{de::Error as DeErrorTrait, Deserialize, Deserializer, Serialize};
-use std::fmt::{self, Display, Formatter};
+use crate::{
+    hexadecimal::HexBytes,
+    parseobserver::{self, ParseEvent},
+    parseoptions::{self, Md5Policy},
+};
+use log::{error, trace, warn};
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use std::{
+    fmt::{self, Display, Formatter},
+    hash::{Hash, Hasher},
+};
 
 const MD5HASH_DE_TARGET: &str = "star_cloudburst::crypto::md5::Md5::deserialize";
 const MD5_LEN: usize = 16;
+const MD5_HEX_LEN: usize = MD5_LEN * 2;
 
 /// MD5 hash wrapper.
 ///
 /// This type wraps one MD5 hash: 128 bits (16 bytes)
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-#[serde(transparent)]
-pub struct Md5(HexBytes);
+///
+/// The spec encodes `md5sum` as 32 lowercase hexadecimal characters. Other
+/// encodings accepted by [Md5Policy::Normalize] are kept so that serializing
+/// the hash writes the original bytes and the info hash doesn't change.
+/// Equality only compares the hashes.
+#[derive(Debug, Clone)]
+pub struct Md5 {
+    digest: [u8; MD5_LEN],
+    original: Option<Box<[u8]>>,
+}
+
+impl Md5 {
+    /// Decode a hash according to `policy`.
+    ///
+    /// [Md5Policy::Ignore] decodes like [Md5Policy::Normalize]; ignoring a
+    /// hash is up to the caller.
+    fn decode(bytes: &[u8], policy: Md5Policy) -> Result<Self, String> {
+        let is_hex = bytes.len() == MD5_HEX_LEN && bytes.iter().all(u8::is_ascii_hexdigit);
+        let canonical = is_hex && !bytes.iter().any(u8::is_ascii_uppercase);
+
+        if !canonical && policy == Md5Policy::Strict {
+            return Err(format!(
+                "md5sum should be {MD5_HEX_LEN} lowercase hexadecimal characters"
+            ));
+        }
+
+        let digest = if is_hex {
+            HexBytes::from_hex_str(String::from_utf8_lossy(bytes))
+                .map_err(|e| e.to_string())?
+                .as_slice()
+                .try_into()
+                .expect("32 hexadecimal characters are 16 bytes")
+        } else if let Ok(digest) = bytes.try_into() {
+            digest
+        } else {
+            return Err(format!(
+                "md5sum should be {MD5_HEX_LEN} hexadecimal characters or {MD5_LEN} bytes; got {} bytes",
+                bytes.len()
+            ));
+        };
+
+        Ok(Self {
+            digest,
+            original: (!canonical).then(|| bytes.into()),
+        })
+    }
+
+    /// Wrap a hash.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::md5::Md5;
+    ///
+    /// const ZEROES: Md5 = Md5::from_bytes([0; 16]);
+    /// assert_eq!(ZEROES.to_string(), "0".repeat(32));
+    /// ```
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; MD5_LEN]) -> Self {
+        Self {
+            digest: bytes,
+            original: None,
+        }
+    }
+
+    /// Hash as 16 bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; MD5_LEN] {
+        &self.digest
+    }
+
+    /// Hash as 16 bytes; the original encoding is dropped.
+    #[inline]
+    pub fn into_bytes(self) -> [u8; MD5_LEN] {
+        self.digest
+    }
+
+    /// The original bytes if they weren't 32 lowercase hexadecimal characters.
+    #[inline]
+    pub fn original(&self) -> Option<&[u8]> {
+        self.original.as_deref()
+    }
+}
 
 impl From<[u8; MD5_LEN]> for Md5 {
     #[inline]
     fn from(bytes: [u8; MD5_LEN]) -> Self {
-        Self(bytes.into())
+        Self::from_bytes(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for Md5 {
+    type Error = DeError;
+
+    /// Wrap 16 raw bytes. Hexadecimal is decoded while deserializing instead.
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self::from_bytes)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"16"))
+    }
+}
+
+impl PartialEq for Md5 {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.digest == other.digest
+    }
+}
+
+impl Eq for Md5 {}
+
+impl Hash for Md5 {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.digest.hash(state);
     }
 }
 
 impl Display for Md5 {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        <HexBytes as Display>::fmt(&self.0, f)
+        <HexBytes as Display>::fmt(&self.digest.into(), f)
     }
 }
 
@@ -36,18 +155,92 @@ impl<'de> Deserialize<'de> for Md5 {
     {
         trace!(target: MD5HASH_DE_TARGET, "Deserializing an MD5 hash.");
 
-        let bytes = HexBytes::deserialize(deserializer)?;
-        let len = bytes.len();
+        let bytes = ByteBuf::deserialize(deserializer)?;
+        Md5::decode(&bytes, parseoptions::current().md5sum).map_err(|e| {
+            error!(target: MD5HASH_DE_TARGET, "Invalid MD5 hash: {e}");
+            DeErrorTrait::custom(e)
+        })
+    }
+}
+
+impl Serialize for Md5 {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.original {
+            Some(original) => serializer.serialize_bytes(original),
+            None => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
 
-        if len != MD5_LEN {
-            error!(
-                target: MD5HASH_DE_TARGET,
-                "Invalid MD5 hash length: {len} - but should be {MD5_LEN}."
-            );
+/// Deserialize an optional `md5sum` according to [crate::parseoptions::ParseOptions::md5sum].
+///
+/// Invalid hashes are `None` with [Md5Policy::Ignore].
+pub(crate) fn optional_md5<'de, D>(deserializer: D) -> Result<Option<Md5>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes = ByteBuf::deserialize(deserializer)?;
+    let policy = parseoptions::current().md5sum;
 
-            Err(DeErrorTrait::invalid_length(len, &"16"))
-        } else {
-            Ok(Md5(bytes))
+    match Md5::decode(&bytes, policy) {
+        Ok(md5) => {
+            if md5.original.is_some() {
+                parseobserver::report(|| ParseEvent::LenientFix {
+                    field: "md5sum",
+                    detail: "decoded a nonconforming encoding".to_owned(),
+                });
+            }
+            Ok(Some(md5))
         }
+        Err(e) if policy == Md5Policy::Ignore => {
+            warn!(target: MD5HASH_DE_TARGET, "Ignoring invalid MD5 hash: {e}");
+            parseobserver::report(|| ParseEvent::LenientFix {
+                field: "md5sum",
+                detail: format!("dropped: {e}"),
+            });
+            Ok(None)
+        }
+        Err(e) => {
+            error!(target: MD5HASH_DE_TARGET, "Invalid MD5 hash: {e}");
+            Err(DeErrorTrait::custom(e))
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Md5 {
+    #[inline]
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(u.arbitrary::<[u8; MD5_LEN]>()?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Md5;
+    use crate::parseoptions::Md5Policy;
+
+    const HEX: &[u8] = b"d41d8cd98f00b204e9800998ecf8427e";
+    const UPPER: &[u8] = b"D41D8CD98F00B204E9800998ECF8427E";
+    const RAW: &[u8] = b"\xd4\x1d\x8c\xd9\x8f\x00\xb2\x04\xe9\x80\x09\x98\xec\xf8\x42\x7e";
+
+    #[test]
+    fn md5_policies() {
+        let hex = Md5::decode(HEX, Md5Policy::Strict).unwrap();
+        assert_eq!(hex.original(), None);
+
+        for encoded in [UPPER, RAW] {
+            assert!(Md5::decode(encoded, Md5Policy::Strict).is_err());
+
+            let normalized = Md5::decode(encoded, Md5Policy::Normalize).unwrap();
+            assert_eq!(normalized, hex);
+            assert_eq!(normalized.original(), Some(encoded));
+        }
+
+        assert!(Md5::decode(b"garbage", Md5Policy::Normalize).is_err());
     }
 }
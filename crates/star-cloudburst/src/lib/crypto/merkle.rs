@@ -0,0 +1,392 @@
+//! Merkle trees of version 2 torrents.
+//!
+//! [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) hashes each file
+//! separately. Files are split into 16 KiB blocks whose SHA-256 hashes are the
+//! leaves of a binary tree. Leaves past the end of the file are zero so that
+//! the tree is complete. The root of the tree is the file's `pieces root` and
+//! the layer where each node covers one piece is the file's piece layer.
+//!
+//! Peers that only have a file's `pieces root` can check a piece with a
+//! [MerkleProof] of the hashes along the path from the piece to the root.
+
+use crate::{crypto::sha2::Sha2, pieces::PieceLength};
+use log::trace;
+use sha2::{Digest, Sha256};
+use std::io::{self, Read};
+
+const MERKLE_TARGET: &str = "star_cloudburst::crypto::merkle::hash_file_v2";
+
+/// Size of the blocks that are the leaves of a file's tree.
+pub const BLOCK_SIZE: u64 = 16 * 1024;
+
+/// Hashes of one file of a version 2 torrent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleHashes {
+    /// Root of the file's tree.
+    pub pieces_root: Sha2,
+    /// Hash of each piece of the file.
+    ///
+    /// Empty if the file fits in one piece because the layer would only
+    /// contain the root.
+    pub piece_layer: Vec<[u8; 32]>,
+}
+
+impl MerkleHashes {
+    /// Piece layer as stored in `piece layers`.
+    #[inline]
+    pub fn piece_layer_bytes(&self) -> Vec<u8> {
+        self.piece_layer.concat()
+    }
+
+    /// Proof that the piece at `index` is part of the file's tree.
+    ///
+    /// `piece_length` must be the piece length that the hashes were
+    /// calculated with. Returns `None` if `index` is out of range or the file
+    /// fits in one piece; such files are checked against the `pieces root`
+    /// directly.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{crypto::merkle::hash_file_v2, PieceLength};
+    ///
+    /// let piece_length = PieceLength::new(16 * 1024).unwrap();
+    /// let cats = vec![b'c'; 80 * 1024];
+    /// let hashes = hash_file_v2(cats.as_slice(), piece_length)?.unwrap();
+    ///
+    /// let proof = hashes.proof(3, piece_length).unwrap();
+    /// assert_eq!(proof.uncles.len(), 3);
+    /// assert!(proof.verify(&hashes.piece_layer[3], &hashes.pieces_root));
+    /// assert!(!proof.verify(&[0; 32], &hashes.pieces_root));
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn proof(&self, index: usize, piece_length: PieceLength) -> Option<MerkleProof> {
+        if index >= self.piece_layer.len() {
+            return None;
+        }
+
+        let width = self.piece_layer.len().next_power_of_two();
+        let padding = piece_padding(piece_length);
+        let mut uncles = Vec::new();
+        let mut position = index;
+        for layer in Layers::new(self.piece_layer.clone(), width, padding) {
+            if layer.len() == 1 {
+                break;
+            }
+            uncles.push(layer[position ^ 1]);
+            position /= 2;
+        }
+
+        Some(MerkleProof {
+            piece: index,
+            uncles,
+        })
+    }
+}
+
+/// Hashes along the path from a piece to its file's `pieces root`.
+///
+/// Uncles are ordered from the piece layer up like the hashes of BEP-0052's
+/// hash messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    /// Index of the piece in the file's piece layer.
+    pub piece: usize,
+    /// Sibling of each node on the path to the root.
+    pub uncles: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// Root of the tree that contains `piece_hash` according to this proof.
+    pub fn root(&self, piece_hash: &[u8; 32]) -> [u8; 32] {
+        let (root, _) =
+            self.uncles
+                .iter()
+                .fold((*piece_hash, self.piece), |(node, position), uncle| {
+                    let parent = if position % 2 == 0 {
+                        parent(&node, uncle)
+                    } else {
+                        parent(uncle, &node)
+                    };
+                    (parent, position / 2)
+                });
+        root
+    }
+
+    /// Whether `piece_hash` is the hash of the piece at [MerkleProof::piece]
+    /// of the file with `pieces_root`.
+    ///
+    /// Piece hashes are calculated like the nodes of the piece layer; see
+    /// [hash_file_v2].
+    #[inline]
+    pub fn verify(&self, piece_hash: &[u8; 32], pieces_root: &Sha2) -> bool {
+        &self.root(piece_hash) == pieces_root.as_bytes()
+    }
+}
+
+/// Check a file's entry in `piece layers` against its `pieces root`.
+///
+/// The layer must have one hash per piece of a file that's `length` bytes
+/// long. Files that fit in one piece don't have a piece layer.
+///
+/// ```rust
+/// use star_cloudburst::{
+///     crypto::merkle::{hash_file_v2, verify_piece_layer},
+///     PieceLength,
+/// };
+///
+/// let piece_length = PieceLength::new(32 * 1024).unwrap();
+/// let cats = vec![b'c'; 100 * 1024];
+/// let hashes = hash_file_v2(cats.as_slice(), piece_length)?.unwrap();
+/// let layer = hashes.piece_layer_bytes();
+///
+/// assert!(verify_piece_layer(&layer, &hashes.pieces_root, 100 * 1024, piece_length));
+/// assert!(!verify_piece_layer(&layer[32..], &hashes.pieces_root, 100 * 1024, piece_length));
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn verify_piece_layer(
+    layer: &[u8],
+    pieces_root: &Sha2,
+    length: u64,
+    piece_length: PieceLength,
+) -> bool {
+    let pieces = length.div_ceil(piece_length.get());
+    if pieces < 2 || layer.len() as u64 != pieces * 32 {
+        return false;
+    }
+
+    let layer: Vec<[u8; 32]> = layer
+        .chunks_exact(32)
+        .map(|hash| hash.try_into().expect("Chunks are 32 bytes."))
+        .collect();
+    let width = layer.len().next_power_of_two();
+    &root(layer, width, piece_padding(piece_length)) == pieces_root.as_bytes()
+}
+
+/// Hash a file's contents into its Merkle tree.
+///
+/// Returns `None` if `reader` is empty because empty files don't have a
+/// `pieces root`.
+///
+/// ```rust
+/// use star_cloudburst::{crypto::merkle::hash_file_v2, PieceLength};
+///
+/// let piece_length = PieceLength::new(32 * 1024).unwrap();
+/// let cats = vec![b'c'; 100 * 1024];
+///
+/// let hashes = hash_file_v2(cats.as_slice(), piece_length)?.unwrap();
+/// assert_eq!(hashes.piece_layer.len(), 4);
+/// assert!(hash_file_v2(&[][..], piece_length)?.is_none());
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn hash_file_v2<R>(mut reader: R, piece_length: PieceLength) -> io::Result<Option<MerkleHashes>>
+where
+    R: Read,
+{
+    // Piece lengths of version 2 torrents are powers of two of at least one block.
+    let blocks_per_piece = (piece_length.get() / BLOCK_SIZE).max(1) as usize;
+
+    let mut leaves = Vec::new();
+    let mut block = vec![0; BLOCK_SIZE as usize];
+    loop {
+        let read = read_block(&mut reader, &mut block)?;
+        if read == 0 {
+            break;
+        }
+        leaves.push(Sha256::digest(&block[..read]).into());
+        if read < block.len() {
+            break;
+        }
+    }
+
+    if leaves.is_empty() {
+        return Ok(None);
+    }
+    trace!(target: MERKLE_TARGET, "Hashed {} blocks", leaves.len());
+
+    let hashes = if leaves.len() <= blocks_per_piece {
+        let width = leaves.len().next_power_of_two();
+        MerkleHashes {
+            pieces_root: root(leaves, width, [0; 32]).into(),
+            piece_layer: Vec::new(),
+        }
+    } else {
+        let piece_layer: Vec<_> = leaves
+            .chunks(blocks_per_piece)
+            .map(|piece| root(piece.to_vec(), blocks_per_piece, [0; 32]))
+            .collect();
+        let width = piece_layer.len().next_power_of_two();
+
+        MerkleHashes {
+            pieces_root: root(piece_layer.clone(), width, piece_padding(piece_length)).into(),
+            piece_layer,
+        }
+    };
+
+    Ok(Some(hashes))
+}
+
+/// Hash one piece of a file into its node in the file's piece layer.
+///
+/// Pieces shorter than `piece_length`, such as the last piece of a file, are
+/// padded with zero leaves.
+pub(crate) fn hash_piece_v2(piece: &[u8], piece_length: PieceLength) -> [u8; 32] {
+    let blocks_per_piece = (piece_length.get() / BLOCK_SIZE).max(1) as usize;
+    let leaves = piece
+        .chunks(BLOCK_SIZE as usize)
+        .map(|block| Sha256::digest(block).into())
+        .collect();
+    root(leaves, blocks_per_piece, [0; 32])
+}
+
+// Fill `block` unless the reader ends first.
+pub(crate) fn read_block<R: Read>(reader: &mut R, block: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < block.len() {
+        match reader.read(&mut block[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+// Node of a piece past the end of a file which is made of zero leaves.
+fn piece_padding(piece_length: PieceLength) -> [u8; 32] {
+    let blocks_per_piece = (piece_length.get() / BLOCK_SIZE).max(1) as usize;
+    root(Vec::new(), blocks_per_piece, [0; 32])
+}
+
+fn parent(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+// Root of a tree `width` nodes wide where missing nodes are `padding`.
+fn root(layer: Vec<[u8; 32]>, width: usize, padding: [u8; 32]) -> [u8; 32] {
+    Layers::new(layer, width, padding)
+        .last()
+        .expect("Trees have at least one layer.")[0]
+}
+
+// Layers of a tree from `layer` up to the root.
+struct Layers(Option<Vec<[u8; 32]>>);
+
+impl Layers {
+    // `width` is the number of nodes in `layer` including `padding`.
+    fn new(mut layer: Vec<[u8; 32]>, width: usize, padding: [u8; 32]) -> Self {
+        layer.resize(width.max(1), padding);
+        Self(Some(layer))
+    }
+}
+
+impl Iterator for Layers {
+    type Item = Vec<[u8; 32]>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let layer = self.0.take()?;
+        if layer.len() > 1 {
+            self.0 = Some(
+                layer
+                    .chunks(2)
+                    .map(|pair| parent(&pair[0], &pair[1]))
+                    .collect(),
+            );
+        }
+        Some(layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{hash_file_v2, hash_piece_v2, verify_piece_layer, BLOCK_SIZE};
+    use crate::PieceLength;
+    use sha2::{Digest, Sha256};
+
+    fn pair(left: [u8; 32], right: [u8; 32]) -> [u8; 32] {
+        Sha256::new()
+            .chain_update(left)
+            .chain_update(right)
+            .finalize()
+            .into()
+    }
+
+    #[test]
+    fn merkle_single_block() -> std::io::Result<()> {
+        let piece_length = PieceLength::new(BLOCK_SIZE).unwrap();
+        let hashes = hash_file_v2(&b"cats"[..], piece_length)?.unwrap();
+
+        // A lone block is its own root.
+        assert_eq!(
+            hashes.pieces_root.as_bytes(),
+            &<[u8; 32]>::from(Sha256::digest(b"cats"))
+        );
+        assert!(hashes.piece_layer.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn merkle_pads_pieces() -> std::io::Result<()> {
+        // Three blocks with two blocks per piece.
+        let piece_length = PieceLength::new(2 * BLOCK_SIZE).unwrap();
+        let data = vec![b'c'; 2 * BLOCK_SIZE as usize + 1];
+        let hashes = hash_file_v2(data.as_slice(), piece_length)?.unwrap();
+
+        let full: [u8; 32] = Sha256::digest(&data[..BLOCK_SIZE as usize]).into();
+        let tail: [u8; 32] = Sha256::digest(b"c").into();
+        let first = pair(full, full);
+        let second = pair(tail, [0; 32]);
+
+        assert_eq!(hashes.piece_layer, [first, second]);
+        assert_eq!(
+            hash_piece_v2(&data[2 * BLOCK_SIZE as usize..], piece_length),
+            second
+        );
+        assert_eq!(hashes.pieces_root.as_bytes(), &pair(first, second));
+        Ok(())
+    }
+
+    #[test]
+    fn merkle_proofs() -> std::io::Result<()> {
+        // Five pieces so the tree is padded with three pieces of zero leaves.
+        let piece_length = PieceLength::new(2 * BLOCK_SIZE).unwrap();
+        let data: Vec<u8> = (0..9 * BLOCK_SIZE as usize)
+            .map(|i| (i / 1000) as u8)
+            .collect();
+        let hashes = hash_file_v2(data.as_slice(), piece_length)?.unwrap();
+        assert_eq!(hashes.piece_layer.len(), 5);
+
+        for (index, piece) in data.chunks(piece_length.get() as usize).enumerate() {
+            let proof = hashes.proof(index, piece_length).unwrap();
+            assert_eq!(proof.uncles.len(), 3);
+            assert!(proof.verify(&hash_piece_v2(piece, piece_length), &hashes.pieces_root));
+        }
+        assert!(hashes.proof(5, piece_length).is_none());
+
+        let mut layer = hashes.piece_layer_bytes();
+        let length = data.len() as u64;
+        assert!(verify_piece_layer(
+            &layer,
+            &hashes.pieces_root,
+            length,
+            piece_length
+        ));
+        assert!(!verify_piece_layer(
+            &layer,
+            &hashes.pieces_root,
+            length * 2,
+            piece_length
+        ));
+        layer[0] ^= 1;
+        assert!(!verify_piece_layer(
+            &layer,
+            &hashes.pieces_root,
+            length,
+            piece_length
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,241 @@
+//! DHT node IDs.
+//!
+//! [BEP-0042](https://www.bittorrent.org/beps/bep_0042.html) ties a node's ID to its external IP
+//! address so that attackers can't choose IDs close to a target. The first 21
+//! bits of the ID are derived from a CRC32-C of the masked IP address and three
+//! random bits that are also stored in the last byte of the ID.
+
+use crate::hexadecimal::HexBytes;
+use log::error;
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use std::{
+    fmt::{self, Display, Formatter},
+    net::{IpAddr, Ipv4Addr},
+};
+
+const NODEID_DE_TARGET: &str = "star_cloudburst::crypto::nodeid::NodeId::deserialize";
+const NODE_ID_LEN: usize = 20;
+const IPV4_MASK: [u8; 4] = [0x03, 0x0f, 0x3f, 0xff];
+const IPV6_MASK: [u8; 8] = [0x01, 0x03, 0x07, 0x0f, 0x1f, 0x3f, 0x7f, 0xff];
+
+/// 160 bit ID of a DHT node.
+///
+/// ```rust
+/// use star_cloudburst::crypto::nodeid::NodeId;
+/// use std::net::Ipv4Addr;
+///
+/// let ip = Ipv4Addr::new(124, 31, 75, 21).into();
+/// // Random bytes from the caller's RNG.
+/// let id = NodeId::secure(ip, [0x2a; 20]);
+///
+/// assert!(id.is_secure(ip));
+/// assert!(!id.is_secure(Ipv4Addr::new(21, 75, 31, 124).into()));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct NodeId([u8; NODE_ID_LEN]);
+
+impl NodeId {
+    /// BEP-0042 compliant ID for a node reachable at `ip`.
+    ///
+    /// The first 21 bits of `random` are replaced by the bits derived from
+    /// `ip`. The rest of the ID is `random` unchanged.
+    pub fn secure(ip: IpAddr, random: [u8; NODE_ID_LEN]) -> Self {
+        let mut id = random;
+        let prefix = secure_prefix(ip, id[NODE_ID_LEN - 1]);
+
+        id[0] = prefix[0];
+        id[1] = prefix[1];
+        id[2] = (prefix[2] & 0xf8) | (id[2] & 0x07);
+        Self(id)
+    }
+
+    /// Whether the ID is BEP-0042 compliant for a node reachable at `ip`.
+    ///
+    /// Local addresses are exempt as per the BEP so any ID is compliant for
+    /// them.
+    pub fn is_secure(&self, ip: IpAddr) -> bool {
+        if is_exempt(ip) {
+            return true;
+        }
+
+        let prefix = secure_prefix(ip, self.0[NODE_ID_LEN - 1]);
+        self.0[..2] == prefix[..2] && (self.0[2] & 0xf8) == (prefix[2] & 0xf8)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; NODE_ID_LEN] {
+        &self.0
+    }
+
+    /// XOR distance between two IDs as used by Kademlia.
+    pub fn distance(&self, other: &Self) -> [u8; NODE_ID_LEN] {
+        let mut distance = [0; NODE_ID_LEN];
+        for (distance, (a, b)) in distance.iter_mut().zip(self.0.iter().zip(&other.0)) {
+            *distance = a ^ b;
+        }
+        distance
+    }
+}
+
+// First three bytes of the CRC32-C of the masked IP. Only the upper 21 bits are
+// used.
+fn secure_prefix(ip: IpAddr, rand: u8) -> [u8; 3] {
+    let r = rand & 0x07;
+    let crc = match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            let mut masked = ip.octets();
+            masked
+                .iter_mut()
+                .zip(IPV4_MASK)
+                .for_each(|(octet, mask)| *octet &= mask);
+            masked[0] |= r << 5;
+            crc32c(&masked)
+        }
+        IpAddr::V6(ip) => {
+            let mut masked = [0; 8];
+            masked.copy_from_slice(&ip.octets()[..8]);
+            masked
+                .iter_mut()
+                .zip(IPV6_MASK)
+                .for_each(|(octet, mask)| *octet &= mask);
+            masked[0] |= r << 5;
+            crc32c(&masked)
+        }
+    };
+
+    let [a, b, c, _] = crc.to_be_bytes();
+    [a, b, c]
+}
+
+// Loopback, private, and link local addresses.
+fn is_exempt(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(ip) => {
+            ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip == Ipv4Addr::UNSPECIFIED
+        }
+        IpAddr::V6(ip) => ip.is_loopback() || ip.is_unspecified(),
+    }
+}
+
+// Bitwise CRC32-C (Castagnoli). IPs are at most eight bytes so a table isn't
+// worth it.
+fn crc32c(bytes: &[u8]) -> u32 {
+    const POLYNOMIAL: u32 = 0x82f6_3b78;
+
+    !bytes.iter().fold(!0, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| {
+            (crc >> 1) ^ (POLYNOMIAL & (crc & 1).wrapping_neg())
+        })
+    })
+}
+
+impl From<[u8; NODE_ID_LEN]> for NodeId {
+    #[inline]
+    fn from(bytes: [u8; NODE_ID_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for NodeId {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"20 bytes"))
+    }
+}
+
+impl Display for NodeId {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        <HexBytes as Display>::fmt(&self.0.into(), f)
+    }
+}
+
+impl<'de> Deserialize<'de> for NodeId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = ByteBuf::deserialize(deserializer)?;
+        bytes.as_slice().try_into().map_err(|e| {
+            error!(target: NODEID_DE_TARGET, "Invalid node ID: {e}");
+            D::Error::custom(e)
+        })
+    }
+}
+
+impl Serialize for NodeId {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{crc32c, NodeId};
+    use crate::hexadecimal::HexBytes;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    // Examples from BEP-0042.
+    const VECTORS: [(Ipv4Addr, &str); 5] = [
+        (
+            Ipv4Addr::new(124, 31, 75, 21),
+            "5fbfbff10c5d6a4ec8a88e4c6ab4c28b95eee401",
+        ),
+        (
+            Ipv4Addr::new(21, 75, 31, 124),
+            "5a3ce9c14e7a08645677bbd1cfe7d8f956d53256",
+        ),
+        (
+            Ipv4Addr::new(65, 23, 51, 170),
+            "a5d43220bc8f112a3d426c84764f8c2a1150e616",
+        ),
+        (
+            Ipv4Addr::new(84, 124, 73, 14),
+            "1b0321dd1bb1fe518101ceef99462b947a01ff41",
+        ),
+        (
+            Ipv4Addr::new(43, 213, 53, 83),
+            "e56f6cbf5b7c4be0237986d5243b87aa6d51305a",
+        ),
+    ];
+
+    #[test]
+    fn crc32c_check_value() {
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+    }
+
+    #[test]
+    fn bep_0042_vectors() {
+        for (ip, id) in VECTORS {
+            let id: NodeId = HexBytes::from_hex_str(id)
+                .unwrap()
+                .as_slice()
+                .try_into()
+                .unwrap();
+            let ip = IpAddr::V4(ip);
+
+            assert!(id.is_secure(ip), "{id} for {ip}");
+            assert_eq!(NodeId::secure(ip, *id.as_bytes()), id);
+
+            let mut insecure = *id.as_bytes();
+            insecure[0] ^= 0xff;
+            assert!(!NodeId::from(insecure).is_secure(ip));
+        }
+
+        assert!(NodeId::from([0; 20]).is_secure(Ipv4Addr::new(192, 168, 1, 1).into()));
+    }
+}
@@ -0,0 +1,388 @@
+//! Hash payloads into v1 pieces.
+//!
+//! Creating a torrent for a large payload means hashing every byte. Rebuilding
+//! the torrent after a small change doesn't have to: [PieceHasher::rehash]
+//! reuses the hashes of pieces whose files kept their size, modification time,
+//! and position in the payload.
+
+use crate::{pieces::PieceLength, Pieces};
+use digest::Digest;
+use log::debug;
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, Read, Seek, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+const PIECEHASHER_TARGET: &str = "star_cloudburst::crypto::piecehasher::PieceHasher";
+
+/// A file of the payload as it was when it was hashed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PayloadFile {
+    pub path: PathBuf,
+    pub length: u64,
+    /// `None` if the platform doesn't support modification times.
+    pub modified: Option<SystemTime>,
+}
+
+impl PayloadFile {
+    /// Read the length and modification time of `path`.
+    pub fn stat(path: PathBuf) -> io::Result<Self> {
+        let metadata = fs::metadata(&path)?;
+        Ok(Self {
+            path,
+            length: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    // Files without a modification time are always hashed again.
+    fn unchanged(&self, other: &Self) -> bool {
+        self.modified.is_some()
+            && self.path == other.path
+            && self.length == other.length
+            && self.modified == other.modified
+    }
+}
+
+/// How many pieces [PieceHasher::rehash] reused or hashed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RehashStats {
+    pub reused: usize,
+    pub hashed: usize,
+}
+
+/// SHA-1 piece hashes of a payload and the files they were hashed from.
+///
+/// Files are concatenated in order and split into pieces like v1 torrents.
+///
+/// ```rust
+/// use star_cloudburst::{crypto::piecehasher::PieceHasher, pieces::PieceLength};
+///
+/// let cats = std::env::temp_dir().join("star_cloudburst_piecehasher_cats.mkv");
+/// std::fs::write(&cats, vec![b'c'; 40_000])?;
+///
+/// let piece_length = PieceLength::new(16384).unwrap();
+/// let hasher = PieceHasher::hash(vec![cats.clone()], piece_length)?;
+/// assert_eq!(hasher.pieces().len(), 3);
+///
+/// // Nothing changed so nothing is read.
+/// let (rehashed, stats) = hasher.rehash(vec![cats.clone()])?;
+/// assert_eq!(stats.reused, 3);
+/// assert_eq!(rehashed.pieces(), hasher.pieces());
+/// # std::fs::remove_file(cats)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceHasher {
+    piece_length: PieceLength,
+    files: Vec<PayloadFile>,
+    hashes: Vec<[u8; 20]>,
+}
+
+impl PieceHasher {
+    /// Hash every piece of `paths`.
+    #[inline]
+    pub fn hash(paths: Vec<PathBuf>, piece_length: PieceLength) -> io::Result<Self> {
+        Self::build(paths, piece_length, None).map(|(hasher, _)| hasher)
+    }
+
+    /// Hash `paths` again while reusing pieces that can't have changed.
+    ///
+    /// A piece is reused if every file it overlaps has the same length and
+    /// modification time and starts at the same offset as before. Edits that
+    /// keep both the length and modification time aren't detected.
+    #[inline]
+    pub fn rehash(&self, paths: Vec<PathBuf>) -> io::Result<(Self, RehashStats)> {
+        Self::build(paths, self.piece_length, Some(self))
+    }
+
+    #[inline]
+    pub fn piece_length(&self) -> PieceLength {
+        self.piece_length
+    }
+
+    /// Files in payload order.
+    #[inline]
+    pub fn files(&self) -> &[PayloadFile] {
+        &self.files
+    }
+
+    #[inline]
+    pub fn pieces(&self) -> Pieces {
+        Pieces::from_hashes(&self.hashes)
+    }
+
+    /// Total length of the payload.
+    #[inline]
+    pub fn total_length(&self) -> u64 {
+        self.files.iter().map(|file| file.length).sum()
+    }
+
+    fn build(
+        paths: Vec<PathBuf>,
+        piece_length: PieceLength,
+        previous: Option<&Self>,
+    ) -> io::Result<(Self, RehashStats)> {
+        let files = paths
+            .into_iter()
+            .map(PayloadFile::stat)
+            .collect::<io::Result<Vec<_>>>()?;
+        let offsets = file_offsets(&files);
+        let total = files.iter().map(|file| file.length).sum::<u64>();
+        let piece_length_u64 = piece_length.get();
+
+        // Previous files by their position in the payload.
+        let (previous_files, previous_total) = match previous {
+            Some(previous) => (
+                file_offsets(&previous.files)
+                    .into_iter()
+                    .zip(&previous.files)
+                    .collect::<HashMap<_, _>>(),
+                previous.total_length(),
+            ),
+            None => (HashMap::new(), 0),
+        };
+
+        let mut hashes = Vec::with_capacity(total.div_ceil(piece_length_u64) as usize);
+        let mut stale = Vec::new();
+
+        for (index, start) in (0..total).step_by(piece_length_u64 as usize).enumerate() {
+            let end = total.min(start + piece_length_u64);
+
+            let reusable = previous
+                .and_then(|previous| previous.hashes.get(index))
+                .filter(|_| previous_total.min(start + piece_length_u64) == end)
+                .filter(|_| {
+                    files
+                        .iter()
+                        .zip(&offsets)
+                        .filter(|&(file, &offset)| {
+                            file.length > 0 && offset < end && offset + file.length > start
+                        })
+                        .all(|(file, offset)| {
+                            previous_files
+                                .get(offset)
+                                .is_some_and(|previous| previous.unchanged(file))
+                        })
+                });
+
+            match reusable {
+                Some(&hash) => hashes.push(hash),
+                None => {
+                    hashes.push([0; 20]);
+                    stale.push((index, start..end));
+                }
+            }
+        }
+
+        let stats = RehashStats {
+            reused: hashes.len() - stale.len(),
+            hashed: stale.len(),
+        };
+        #[cfg(feature = "rayon")]
+        let hashed = hash_ranges_parallel(&files, &offsets, &stale)?;
+        #[cfg(not(feature = "rayon"))]
+        let hashed = hash_ranges(&files, &offsets, &stale)?;
+        for ((index, _), hash) in stale.iter().zip(hashed) {
+            hashes[*index] = hash;
+        }
+
+        debug!(
+            target: PIECEHASHER_TARGET,
+            "Hashed {} pieces and reused {}",
+            stats.hashed,
+            stats.reused
+        );
+
+        Ok((
+            Self {
+                piece_length,
+                files,
+                hashes,
+            },
+            stats,
+        ))
+    }
+}
+
+// SHA-1 of each range of the payload in order.
+#[cfg_attr(feature = "rayon", allow(dead_code))]
+fn hash_ranges(
+    files: &[PayloadFile],
+    offsets: &[u64],
+    ranges: &[(usize, Range<u64>)],
+) -> io::Result<Vec<[u8; 20]>> {
+    let mut reader = PayloadReader::new(files, offsets);
+    let mut buffer = Vec::new();
+
+    ranges
+        .iter()
+        .map(|(_, range)| reader.hash_range(range, &mut buffer))
+        .collect()
+}
+
+// [hash_ranges] on rayon's thread pool.
+//
+// Each worker keeps its own reader so that pieces of different files are read
+// and hashed concurrently. Collecting an indexed parallel iterator keeps the
+// order of `ranges`.
+#[cfg(feature = "rayon")]
+fn hash_ranges_parallel(
+    files: &[PayloadFile],
+    offsets: &[u64],
+    ranges: &[(usize, Range<u64>)],
+) -> io::Result<Vec<[u8; 20]>> {
+    use rayon::prelude::*;
+
+    ranges
+        .par_iter()
+        .map_init(
+            || (PayloadReader::new(files, offsets), Vec::new()),
+            |(reader, buffer), (_, range)| reader.hash_range(range, buffer),
+        )
+        .collect()
+}
+
+fn file_offsets(files: &[PayloadFile]) -> Vec<u64> {
+    files
+        .iter()
+        .scan(0, |offset, file| {
+            let start = *offset;
+            *offset += file.length;
+            Some(start)
+        })
+        .collect()
+}
+
+// Reads byte ranges of the concatenated payload while keeping the last file open.
+struct PayloadReader<'files> {
+    files: &'files [PayloadFile],
+    offsets: &'files [u64],
+    open: Option<(usize, File)>,
+}
+
+impl<'files> PayloadReader<'files> {
+    fn new(files: &'files [PayloadFile], offsets: &'files [u64]) -> Self {
+        Self {
+            files,
+            offsets,
+            open: None,
+        }
+    }
+
+    fn read_range(&mut self, start: u64, buffer: &mut [u8]) -> io::Result<()> {
+        let mut position = start;
+        let mut filled = 0;
+
+        while filled < buffer.len() {
+            // Last file that starts at or before `position` and isn't empty.
+            let index = self
+                .offsets
+                .partition_point(|&offset| offset <= position)
+                .saturating_sub(1);
+            let index = (index..self.files.len())
+                .find(|&index| self.files[index].length > 0)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+            let file_offset = position - self.offsets[index];
+            let len = (self.files[index].length - file_offset).min((buffer.len() - filled) as u64);
+            let file = self.open(index)?;
+            file.seek(SeekFrom::Start(file_offset))?;
+            file.read_exact(&mut buffer[filled..filled + len as usize])?;
+
+            filled += len as usize;
+            position += len;
+        }
+
+        Ok(())
+    }
+
+    fn hash_range(&mut self, range: &Range<u64>, buffer: &mut Vec<u8>) -> io::Result<[u8; 20]> {
+        buffer.resize((range.end - range.start) as usize, 0);
+        self.read_range(range.start, buffer)?;
+        Ok(sha1::Sha1::digest(&buffer).into())
+    }
+
+    fn open(&mut self, index: usize) -> io::Result<&mut File> {
+        if self.open.as_ref().map(|(open, _)| *open) != Some(index) {
+            let path: &Path = &self.files[index].path;
+            self.open = Some((index, File::open(path)?));
+        }
+
+        Ok(&mut self.open.as_mut().expect("File was just opened.").1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PieceHasher, RehashStats};
+    use crate::pieces::PieceLength;
+    use std::{fs, io};
+
+    #[test]
+    fn rehash_changed_tail() -> io::Result<()> {
+        let dir = std::env::temp_dir().join("star_cloudburst_rehash_changed_tail");
+        fs::create_dir_all(&dir)?;
+        let cats = dir.join("cats.mkv");
+        let mews = dir.join("mews.txt");
+        let empty = dir.join("empty");
+        fs::write(&cats, vec![b'c'; 40_000])?;
+        fs::write(&empty, b"")?;
+        fs::write(&mews, vec![b'm'; 5_000])?;
+        let paths = vec![cats, empty, mews.clone()];
+
+        let piece_length = PieceLength::new(16384).unwrap();
+        let hasher = PieceHasher::hash(paths.clone(), piece_length)?;
+        assert_eq!(hasher.pieces().len(), 3);
+
+        // Only the last piece overlaps the changed file.
+        fs::write(&mews, vec![b'm'; 6_000])?;
+        let (rehashed, stats) = hasher.rehash(paths.clone())?;
+        assert_eq!(
+            stats,
+            RehashStats {
+                reused: 2,
+                hashed: 1
+            }
+        );
+        assert_eq!(rehashed, PieceHasher::hash(paths, piece_length)?);
+
+        fs::remove_dir_all(dir)
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_piece_order() -> io::Result<()> {
+        use super::{file_offsets, hash_ranges, hash_ranges_parallel, PayloadFile};
+
+        let dir = std::env::temp_dir().join(format!(
+            "star-cloudburst-parallel-piece-order-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir)?;
+        let files = (0..8u8)
+            .map(|index| {
+                let path = dir.join(format!("cats{index}.mkv"));
+                fs::write(&path, vec![index; 10_000 + usize::from(index) * 3_000])?;
+                PayloadFile::stat(path)
+            })
+            .collect::<io::Result<Vec<_>>>()?;
+        let offsets = file_offsets(&files);
+        let total: u64 = files.iter().map(|file| file.length).sum();
+        let ranges: Vec<_> = (0..total)
+            .step_by(4096)
+            .enumerate()
+            .map(|(index, start)| (index, start..total.min(start + 4096)))
+            .collect();
+
+        assert_eq!(
+            hash_ranges_parallel(&files, &offsets, &ranges)?,
+            hash_ranges(&files, &offsets, &ranges)?
+        );
+        fs::remove_dir_all(dir)
+    }
+}
@@ -1,10 +1,17 @@
 //! SHA-1 hash.
 
+#[cfg(feature = "crypto")]
 use super::calculateinfohash::CalculateInfoHash;
-use crate::{hexadecimal::HexBytes, metainfo::MetaInfo};
+use crate::hexadecimal::HexBytes;
+#[cfg(feature = "crypto")]
+use crate::metainfo::MetaInfo;
+#[cfg(feature = "crypto")]
 use digest::core_api::CoreWrapper;
 use log::{debug, error};
-use serde::{de::Error as DeErrorTrait, Deserialize, Deserializer, Serialize};
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::fmt::{self, Display, Formatter};
 
 const SHA1HASH_DE_TARGET: &str = "star_cloudburst::crypto::sha1::Sha1::deserialize";
@@ -13,17 +20,54 @@ const SHA1_LEN: usize = 20;
 /// SHA1 hash wrapper.
 ///
 /// This type wraps one SHA1 hash: 160 bits (20 bytes).
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-#[serde(transparent)]
-pub struct Sha1(HexBytes);
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sha1([u8; SHA1_LEN]);
+
+impl Sha1 {
+    /// Wrap a hash.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::sha1::Sha1;
+    ///
+    /// const ZEROES: Sha1 = Sha1::from_bytes([0; 20]);
+    /// assert_eq!(ZEROES.as_bytes(), &[0; 20]);
+    /// ```
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; SHA1_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; SHA1_LEN] {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> [u8; SHA1_LEN] {
+        self.0
+    }
+}
 
 impl From<[u8; SHA1_LEN]> for Sha1 {
     #[inline]
     fn from(value: [u8; SHA1_LEN]) -> Self {
-        Self(value.into())
+        Self(value)
     }
 }
 
+impl TryFrom<&[u8]> for Sha1 {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"20"))
+    }
+}
+
+#[cfg(feature = "crypto")]
 impl CalculateInfoHash<SHA1_LEN> for Sha1 {
     type Error = serde_bencode::Error;
     type Info = MetaInfo;
@@ -42,7 +86,7 @@ impl Debug for Sha1 {
 impl Display for Sha1 {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        <HexBytes as Display>::fmt(&self.0, f)
+        <HexBytes as Display>::fmt(&self.0.into(), f)
     }
 }
 
@@ -53,17 +97,34 @@ impl<'de> Deserialize<'de> for Sha1 {
     {
         debug!(target: SHA1HASH_DE_TARGET, "Deserializing a SHA1 hash.");
 
-        let bytes = HexBytes::deserialize(deserializer)?;
+        // Raw bytes or hexadecimal.
+        let bytes = HexBytes::deserialize(deserializer)?.decode_hex_of_len(SHA1_LEN);
         let len = bytes.len();
 
-        if len != SHA1_LEN {
+        bytes.as_slice().try_into().map(Sha1).map_err(|_| {
             error!(
                 target: SHA1HASH_DE_TARGET,
                 "Invalid SHA1 hash length: {len} - but should be {SHA1_LEN}."
             );
-            Err(DeErrorTrait::invalid_length(len, &"20"))
-        } else {
-            Ok(Sha1(bytes))
-        }
+            DeErrorTrait::invalid_length(len, &"20")
+        })
+    }
+}
+
+impl Serialize for Sha1 {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Sha1 {
+    #[inline]
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary().map(Self)
     }
 }
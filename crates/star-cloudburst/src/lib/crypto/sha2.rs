@@ -1,31 +1,159 @@
 //! SHA256 hash.
 
+#[cfg(feature = "crypto")]
 use super::calculateinfohash::CalculateInfoHash;
-use crate::{hexadecimal::HexBytes, metainfo::MetaInfo};
+use super::sha1::Sha1;
+use crate::hexadecimal::HexBytes;
+#[cfg(feature = "crypto")]
+use crate::metainfo::MetaInfo;
+#[cfg(feature = "crypto")]
 use digest::{
     consts,
     core_api::{CoreWrapper, CtVariableCoreWrapper},
 };
 use log::{error, trace};
-use serde::{de::Error as DeErrorTrait, Deserialize, Deserializer, Serialize};
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
 use std::fmt::{self, Display, Formatter};
 
 const SHA256_DE_TARGET: &str = "star_cloudburst::crypto::sha256::Sha256::deserialize";
+const FROMMULTIHASH_TARGET: &str = "star_cloudburst::crypto::sha256::Sha256::from_multihash";
 const SHA256_LEN: usize = 32;
+// Multihash function code for SHA-256 followed by the digest length.
+const MULTIHASH_PREFIX: [u8; 2] = [0x12, SHA256_LEN as u8];
 
 /// SHA256 hash wrapper.
 ///
 /// This wraps one SHA256 hash: 256 bits (32 bytes)
-#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
-pub struct Sha2(HexBytes);
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Sha2([u8; SHA256_LEN]);
 
 impl From<[u8; SHA256_LEN]> for Sha2 {
     #[inline]
     fn from(bytes: [u8; SHA256_LEN]) -> Self {
-        Self(bytes.into())
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for Sha2 {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"32"))
+    }
+}
+
+impl Sha2 {
+    /// Wrap a hash.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::sha2::Sha2;
+    ///
+    /// const ZEROES: Sha2 = Sha2::from_bytes([0; 32]);
+    /// assert_eq!(ZEROES.into_bytes(), [0; 32]);
+    /// ```
+    #[inline]
+    pub const fn from_bytes(bytes: [u8; SHA256_LEN]) -> Self {
+        Self(bytes)
+    }
+
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; SHA256_LEN] {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> [u8; SHA256_LEN] {
+        self.0
+    }
+
+    /// First 20 bytes of the hash.
+    ///
+    /// [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) truncates version 2 info hashes
+    /// wherever a 20 byte info hash is expected, such as tracker announces and the DHT.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::{sha1::Sha1, sha2::Sha2};
+    ///
+    /// let mut bytes = [0xca; 32];
+    /// bytes[20..].fill(0);
+    /// assert_eq!(Sha2::from(bytes).truncate(), Sha1::from([0xca; 20]));
+    /// ```
+    #[inline]
+    pub fn truncate(&self) -> Sha1 {
+        let mut truncated = [0; 20];
+        truncated.copy_from_slice(&self.0[..20]);
+        truncated.into()
+    }
+
+    /// Hex encoded [multihash](https://multiformats.io/multihash/) of the hash.
+    ///
+    /// Version 2 magnet links identify torrents by multihash
+    /// (`xt=urn:btmh:1220...`) rather than by the bare hash.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::sha2::Sha2;
+    ///
+    /// let multihash = Sha2::from([0xca; 32]).to_multihash();
+    /// assert_eq!(&multihash[..6], "1220ca");
+    /// assert_eq!(multihash.len(), 68);
+    /// ```
+    pub fn to_multihash(&self) -> String {
+        format!("{}{self}", HexBytes::from(MULTIHASH_PREFIX))
+    }
+
+    /// Decode a hex encoded SHA-256 multihash.
+    ///
+    /// Multihashes of other hash functions or lengths are an error.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::sha2::Sha2;
+    ///
+    /// let hash = Sha2::from([0xca; 32]);
+    /// assert_eq!(Sha2::from_multihash(hash.to_multihash())?, hash);
+    ///
+    /// // SHA-1 multihash.
+    /// assert!(Sha2::from_multihash("1114cafed00dcafed00dcafed00dcafed00dcafed00d").is_err());
+    /// # Ok::<(), serde::de::value::Error>(())
+    /// ```
+    pub fn from_multihash<S>(multihash: S) -> Result<Self, DeError>
+    where
+        S: AsRef<str>,
+    {
+        let bytes = HexBytes::from_hex_str(multihash)?;
+        let (prefix, digest) = bytes.as_slice().split_at(bytes.len().min(2));
+
+        if prefix != MULTIHASH_PREFIX {
+            error!(
+                target: FROMMULTIHASH_TARGET,
+                "Multihash isn't SHA-256: {}",
+                HexBytes::from(prefix)
+            );
+            return Err(DeError::custom(
+                "multihashes of SHA-256 start with 0x12 0x20",
+            ));
+        }
+
+        <[u8; SHA256_LEN]>::try_from(digest)
+            .map(Into::into)
+            .map_err(|_| {
+                error!(
+                    target: FROMMULTIHASH_TARGET,
+                    "Invalid SHA256 hash size: {} - but should be {SHA256_LEN}",
+                    digest.len()
+                );
+                DeError::invalid_length(digest.len(), &"32")
+            })
     }
 }
 
+#[cfg(feature = "crypto")]
 impl CalculateInfoHash<SHA256_LEN> for Sha2 {
     type Error = serde_bencode::Error;
     type Info = MetaInfo;
@@ -35,7 +163,7 @@ impl CalculateInfoHash<SHA256_LEN> for Sha2 {
 impl Display for Sha2 {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        <HexBytes as Display>::fmt(&self.0, f)
+        <HexBytes as Display>::fmt(&self.0.into(), f)
     }
 }
 
@@ -46,18 +174,34 @@ impl<'de> Deserialize<'de> for Sha2 {
     {
         trace!(target: SHA256_DE_TARGET, "Deserializing a SHA256 hash.");
 
-        let bytes = HexBytes::deserialize(deserializer)?;
+        // Raw bytes or hexadecimal.
+        let bytes = HexBytes::deserialize(deserializer)?.decode_hex_of_len(SHA256_LEN);
         let len = bytes.len();
 
-        if len != SHA256_LEN {
+        bytes.as_slice().try_into().map(Sha2).map_err(|_| {
             error!(
                 target: SHA256_DE_TARGET,
                 "Invalid SHA256 hash size: {len} - but should be {SHA256_LEN}"
             );
+            DeErrorTrait::invalid_length(len, &"32")
+        })
+    }
+}
 
-            Err(DeErrorTrait::invalid_length(len, &"32"))
-        } else {
-            Ok(Sha2(bytes))
-        }
+impl Serialize for Sha2 {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Sha2 {
+    #[inline]
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        u.arbitrary().map(Self)
     }
 }
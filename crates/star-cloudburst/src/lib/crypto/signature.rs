@@ -1,26 +1,236 @@
+#[cfg(feature = "crypto-sign")]
+use digest::Digest;
+#[cfg(feature = "crypto-sign")]
+use rsa::{Pkcs1v15Sign, RsaPrivateKey, RsaPublicKey};
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+#[cfg(feature = "x509")]
+use x509_parser::{certificate::X509Certificate, error::X509Error, nom};
 
+#[cfg(feature = "crypto-sign")]
+use super::sha1::Sha1;
 use crate::hexadecimal::HexBytes;
 // use super::rsa::Rsa;
 
+/// Keys of a signature dict.
+pub(crate) const SIGNATURE_FIELDS: &[&str] = &["certificate", "info", "signature"];
+
 /// Additional info for `Signature`; unused.
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
-#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct SignInfo {}
 
 /// Signatures for signed torrents. [BEP-0035](https://www.bittorrent.org/beps/bep_0035.html)
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
-#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Signature {
-    /// X.509 certificate used to sign the torrent. The user should have a certificate elsewhere if this is missing.
-    #[serde(default)]
-    certificate: Option<String>,
+    /// DER encoded X.509 certificate used to sign the torrent. The user should have a certificate elsewhere if this is missing.
+    #[serde(default, with = "serde_bytes")]
+    certificate: Option<Vec<u8>>,
     /// Extension info (currently unspecified)
     #[serde(default)]
     info: Option<SignInfo>,
     /// RSA signature of torrent's `Info` and `Signature`'s `SignInfo` if present.
     signature: HexBytes,
 }
+
+impl Signature {
+    /// DER encoded X.509 certificate of the signer if it was included.
+    #[inline]
+    pub fn certificate_der(&self) -> Option<&[u8]> {
+        self.certificate.as_deref()
+    }
+
+    /// Parsed X.509 certificate of the signer if it was included.
+    ///
+    /// The certificate exposes the subject, validity, and public key needed to
+    /// verify the signature. Bytes after the certificate are ignored.
+    ///
+    /// ```rust
+    /// use star_cloudburst::crypto::signature::Signature;
+    ///
+    /// let der = include_bytes!("../../../resources/certificates/test-signer.der");
+    /// let bencode = [
+    ///     format!("d11:certificate{}:", der.len()).as_bytes(),
+    ///     der,
+    ///     b"9:signature4:\xca\xfe\xd0\x0de",
+    /// ]
+    /// .concat();
+    ///
+    /// let signature: Signature = serde_bencode::from_bytes(&bencode)?;
+    /// let certificate = signature.certificate().expect("included")?;
+    /// assert_eq!(
+    ///     certificate.subject().to_string(),
+    ///     "CN=Star Cloudburst Test Signer"
+    /// );
+    /// assert!(certificate.validity().is_valid());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "x509")]
+    pub fn certificate(&self) -> Option<Result<X509Certificate<'_>, X509Error>> {
+        self.certificate.as_deref().map(|der| {
+            x509_parser::parse_x509_certificate(der)
+                .map(|(_, certificate)| certificate)
+                .map_err(|e| match e {
+                    nom::Err::Error(e) | nom::Err::Failure(e) => e,
+                    nom::Err::Incomplete(_) => X509Error::InvalidCertificate,
+                })
+        })
+    }
+
+    /// Extension info; currently unspecified.
+    #[inline]
+    pub fn info(&self) -> Option<&SignInfo> {
+        self.info.as_ref()
+    }
+
+    /// Signature of the torrent's info dictionary and [SignInfo] if present.
+    #[inline]
+    pub fn signature(&self) -> &[u8] {
+        self.signature.as_slice()
+    }
+
+    /// Sign a torrent's SHA-1 info hash with an RSA private key.
+    ///
+    /// The signature is PKCS #1 v1.5 over the SHA-1 digest of the info hash
+    /// followed by the bencoded `info` if present. `certificate` is the DER
+    /// encoded X.509 certificate to embed so that clients can verify the
+    /// signature without knowing the signer beforehand.
+    ///
+    /// ```rust
+    /// use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey, RsaPublicKey};
+    /// use star_cloudburst::{
+    ///     crypto::signature::Signature, metainfo::infohash::InfoHashVersioned, Torrent,
+    /// };
+    ///
+    /// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    /// let InfoHashVersioned::V1(info_hash) = torrent.info_hash()? else {
+    ///     unreachable!("Version 1 torrent.")
+    /// };
+    ///
+    /// let key = include_bytes!("../../../resources/certificates/test-signer-rsa.key.der");
+    /// let private_key = RsaPrivateKey::from_pkcs8_der(key)?;
+    /// let signature = Signature::sign(info_hash, None, &private_key, None)?;
+    ///
+    /// let public_key = RsaPublicKey::from(&private_key);
+    /// assert!(signature.verify(info_hash, &public_key).is_ok());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "crypto-sign")]
+    pub fn sign(
+        info_hash: &Sha1,
+        info: Option<SignInfo>,
+        private_key: &RsaPrivateKey,
+        certificate: Option<Vec<u8>>,
+    ) -> Result<Self, rsa::Error> {
+        let digest = signed_digest(info_hash, info.as_ref());
+        let signature = private_key.sign(Pkcs1v15Sign::new::<sha1::Sha1>(), &digest)?;
+
+        Ok(Self {
+            certificate,
+            info,
+            signature: signature.into(),
+        })
+    }
+
+    /// Verify the signature of a torrent's SHA-1 info hash with the signer's
+    /// RSA public key.
+    ///
+    /// See [Signature::sign] for what's signed.
+    #[cfg(feature = "crypto-sign")]
+    pub fn verify(&self, info_hash: &Sha1, public_key: &RsaPublicKey) -> Result<(), rsa::Error> {
+        let digest = signed_digest(info_hash, self.info.as_ref());
+        public_key.verify(
+            Pkcs1v15Sign::new::<sha1::Sha1>(),
+            &digest,
+            self.signature.as_slice(),
+        )
+    }
+
+    /// Verify the signature with the public key of an X.509 certificate such
+    /// as [Signature::certificate].
+    ///
+    /// The certificate's validity and issuer aren't checked; whether to trust
+    /// the signer is up to the caller.
+    ///
+    /// ```rust
+    /// use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey};
+    /// use star_cloudburst::crypto::{sha1::Sha1, signature::Signature};
+    ///
+    /// let key = include_bytes!("../../../resources/certificates/test-signer-rsa.key.der");
+    /// let der = include_bytes!("../../../resources/certificates/test-signer-rsa.der");
+    /// let private_key = RsaPrivateKey::from_pkcs8_der(key)?;
+    ///
+    /// let info_hash = Sha1::from([42; 20]);
+    /// let signature = Signature::sign(&info_hash, None, &private_key, Some(der.to_vec()))?;
+    /// let certificate = signature.certificate().expect("included")?;
+    /// assert!(signature.verify_certificate(&info_hash, &certificate).is_ok());
+    /// assert!(signature
+    ///     .verify_certificate(&Sha1::from([0; 20]), &certificate)
+    ///     .is_err());
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[cfg(all(feature = "crypto-sign", feature = "x509"))]
+    pub fn verify_certificate(
+        &self,
+        info_hash: &Sha1,
+        certificate: &X509Certificate<'_>,
+    ) -> Result<(), rsa::Error> {
+        use rsa::pkcs8::DecodePublicKey;
+
+        let public_key = RsaPublicKey::from_public_key_der(certificate.public_key().raw)
+            .map_err(|e| rsa::Error::Pkcs8(e.into()))?;
+        self.verify(info_hash, &public_key)
+    }
+}
+
+// SHA-1 of the info hash and the bencoded sign info.
+#[cfg(feature = "crypto-sign")]
+fn signed_digest(info_hash: &Sha1, info: Option<&SignInfo>) -> [u8; 20] {
+    let mut hasher = sha1::Sha1::new();
+    hasher.update(info_hash.as_bytes());
+    if let Some(info) = info {
+        hasher.update(serde_bencode::to_bytes(info).expect("SignInfo is a plain dict."));
+    }
+    hasher.finalize().into()
+}
+
+#[cfg(all(test, feature = "crypto-sign"))]
+mod tests {
+    use super::{SignInfo, Signature};
+    use crate::crypto::sha1::Sha1;
+    use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey, RsaPublicKey};
+
+    #[test]
+    fn signed_info_roundtrip() -> Result<(), Box<dyn std::error::Error>> {
+        let key = include_bytes!("../../../resources/certificates/test-signer-rsa.key.der");
+        let private_key = RsaPrivateKey::from_pkcs8_der(key)?;
+        let public_key = RsaPublicKey::from(&private_key);
+        let info_hash = Sha1::from([42; 20]);
+
+        let signature = Signature::sign(&info_hash, Some(SignInfo {}), &private_key, None)?;
+        let signature: Signature =
+            serde_bencode::from_bytes(&serde_bencode::to_bytes(&signature)?)?;
+        assert_eq!(signature.info(), Some(&SignInfo {}));
+        signature.verify(&info_hash, &public_key)?;
+
+        // The sign info is part of the signed data.
+        let unsigned_info = Signature {
+            info: None,
+            ..signature.clone()
+        };
+        assert!(unsigned_info.verify(&info_hash, &public_key).is_err());
+
+        let mut tampered = signature.signature().to_vec();
+        tampered[0] ^= 1;
+        let tampered = Signature {
+            signature: tampered.into(),
+            ..signature
+        };
+        assert!(tampered.verify(&info_hash, &public_key).is_err());
+        Ok(())
+    }
+}
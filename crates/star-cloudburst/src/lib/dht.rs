@@ -0,0 +1,13 @@
+//! Types for torrents and data stored in the DHT.
+//!
+//! BEPs:
+//! [BEP-0044](https://www.bittorrent.org/beps/bep_0044.html)
+//! [BEP-0046](https://www.bittorrent.org/beps/bep_0046.html)
+
+pub mod item;
+pub mod mutabletorrent;
+pub mod publickey;
+
+pub use item::{ImmutableItem, ItemSignature, MutableItem};
+pub use mutabletorrent::{MutableTorrent, MutableTorrentValue};
+pub use publickey::PublicKey;
@@ -0,0 +1,264 @@
+//! Arbitrary data stored in the DHT.
+//!
+//! [BEP-0044](https://www.bittorrent.org/beps/bep_0044.html) stores bencoded values of up to
+//! 1000 bytes in the DHT. Immutable items are found by the hash of their value.
+//! Mutable items are found by the hash of the publisher's public key and a
+//! salt, and they're signed so that only the publisher may update them.
+//!
+//! This module doesn't sign or verify items. Sign
+//! [MutableItem::signing_payload] with an Ed25519 library instead.
+
+use super::{mutabletorrent::SALT_MAX_LEN, PublicKey};
+#[cfg(feature = "crypto")]
+use crate::crypto::sha1::Sha1;
+use log::error;
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use serde_with::skip_serializing_none;
+use std::fmt::{self, Display, Formatter};
+
+const ITEMSIGNATURE_DE_TARGET: &str = "star_cloudburst::dht::item::ItemSignature::deserialize";
+const SIGNATURE_LEN: usize = 64;
+/// Maximum length of a bencoded value.
+pub const VALUE_MAX_LEN: usize = 1000;
+
+/// Ed25519 signature of a [MutableItem].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ItemSignature([u8; SIGNATURE_LEN]);
+
+impl ItemSignature {
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; SIGNATURE_LEN] {
+        &self.0
+    }
+}
+
+impl From<[u8; SIGNATURE_LEN]> for ItemSignature {
+    #[inline]
+    fn from(bytes: [u8; SIGNATURE_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for ItemSignature {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"64 bytes"))
+    }
+}
+
+impl Display for ItemSignature {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for ItemSignature {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = ByteBuf::deserialize(deserializer)?;
+        bytes.as_slice().try_into().map_err(|e| {
+            error!(target: ITEMSIGNATURE_DE_TARGET, "Invalid signature: {e}");
+            D::Error::custom(e)
+        })
+    }
+}
+
+impl Serialize for ItemSignature {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+/// Bencode `value` and check that it fits in an item.
+fn encode_value<V: Serialize>(value: &V) -> Result<Vec<u8>, serde_bencode::Error> {
+    let encoded = serde_bencode::to_bytes(value)?;
+    if encoded.len() > VALUE_MAX_LEN {
+        Err(serde_bencode::Error::Custom(format!(
+            "DHT values are at most {VALUE_MAX_LEN} bytes; got {} bytes",
+            encoded.len()
+        )))
+    } else {
+        Ok(encoded)
+    }
+}
+
+/// Item that's found by the hash of its value.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ImmutableItem<V> {
+    #[serde(rename = "v")]
+    pub value: V,
+}
+
+impl<V: Serialize> ImmutableItem<V> {
+    /// Bencoded value.
+    #[inline]
+    pub fn encoded_value(&self) -> Result<Vec<u8>, serde_bencode::Error> {
+        encode_value(&self.value)
+    }
+
+    /// DHT target of the item: the SHA-1 hash of the bencoded value.
+    ///
+    /// ```rust
+    /// use star_cloudburst::dht::ImmutableItem;
+    ///
+    /// let item = ImmutableItem {
+    ///     value: "Hello World!",
+    /// };
+    /// assert_eq!(
+    ///     item.target()?.to_string(),
+    ///     "e5f96f6f38320f0f33959cb4d3d656452117aadb"
+    /// );
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn target(&self) -> Result<Sha1, serde_bencode::Error> {
+        use digest::Digest;
+
+        let encoded = self.encoded_value()?;
+        Ok(<[u8; 20]>::from(sha1::Sha1::digest(encoded)).into())
+    }
+}
+
+/// Item that's found by the publisher's public key and salt.
+///
+/// [BEP-0046](https://www.bittorrent.org/beps/bep_0046.html) torrents are
+/// `MutableItem<MutableTorrentValue>`s.
+///
+/// ```rust
+/// use star_cloudburst::dht::{MutableItem, PublicKey};
+///
+/// let item = MutableItem {
+///     value: "Hello World!",
+///     public_key: PublicKey::from([0; 32]),
+///     salt: Some(b"foobar".to_vec()),
+///     seq: 1,
+///     signature: [0; 64].into(),
+/// };
+/// assert_eq!(
+///     item.signing_payload()?,
+///     b"4:salt6:foobar3:seqi1e1:v12:Hello World!"
+/// );
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MutableItem<V> {
+    #[serde(rename = "v")]
+    pub value: V,
+    #[serde(rename = "k")]
+    pub public_key: PublicKey,
+    /// Distinguishes items published with the same key. At most 64 bytes.
+    #[serde(default, with = "serde_bytes")]
+    pub salt: Option<Vec<u8>>,
+    /// Sequence number that increases with each update.
+    pub seq: i64,
+    #[serde(rename = "sig")]
+    pub signature: ItemSignature,
+}
+
+impl<V: Serialize> MutableItem<V> {
+    /// Bencoded value.
+    #[inline]
+    pub fn encoded_value(&self) -> Result<Vec<u8>, serde_bencode::Error> {
+        encode_value(&self.value)
+    }
+
+    /// Bytes that are signed by the publisher.
+    ///
+    /// The payload is the salt, sequence number, and value as they would be
+    /// bencoded in a dict but without the dict's delimiters. The salt is
+    /// omitted if it's missing or empty.
+    pub fn signing_payload(&self) -> Result<Vec<u8>, serde_bencode::Error> {
+        let value = self.encoded_value()?;
+        let mut payload = Vec::with_capacity(value.len() + SALT_MAX_LEN + 32);
+
+        if let Some(salt) = self.salt.as_deref().filter(|salt| !salt.is_empty()) {
+            if salt.len() > SALT_MAX_LEN {
+                return Err(serde_bencode::Error::Custom(format!(
+                    "salts are at most {SALT_MAX_LEN} bytes; got {} bytes",
+                    salt.len()
+                )));
+            }
+            payload.extend_from_slice(format!("4:salt{}:", salt.len()).as_bytes());
+            payload.extend_from_slice(salt);
+        }
+        payload.extend_from_slice(format!("3:seqi{}e1:v", self.seq).as_bytes());
+        payload.extend_from_slice(&value);
+
+        Ok(payload)
+    }
+
+    /// DHT target of the item: the SHA-1 hash of the public key followed by the
+    /// salt.
+    #[cfg(feature = "crypto")]
+    #[inline]
+    pub fn target(&self) -> Sha1 {
+        super::mutabletorrent::target(&self.public_key, self.salt.as_deref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MutableItem;
+    use crate::dht::PublicKey;
+
+    fn hello(salt: Option<&[u8]>) -> MutableItem<&'static str> {
+        MutableItem {
+            value: "Hello World!",
+            public_key: PublicKey::from([7; 32]),
+            salt: salt.map(<[u8]>::to_vec),
+            seq: 1,
+            signature: [9; 64].into(),
+        }
+    }
+
+    // Payloads from BEP-0044's test vectors.
+    #[test]
+    fn signing_payload() {
+        assert_eq!(
+            hello(None).signing_payload().unwrap(),
+            b"3:seqi1e1:v12:Hello World!"
+        );
+        assert_eq!(
+            hello(Some(b"")).signing_payload().unwrap(),
+            b"3:seqi1e1:v12:Hello World!"
+        );
+        assert!(hello(Some(&[0; 65])).signing_payload().is_err());
+    }
+
+    #[test]
+    fn mutable_item_roundtrip() {
+        let item = hello(Some(b"foobar"));
+        let encoded = serde_bencode::to_bytes(&item).unwrap();
+        let decoded: MutableItem<String> = serde_bencode::from_bytes(&encoded).unwrap();
+
+        assert_eq!(decoded.value, item.value);
+        assert_eq!(decoded.salt, item.salt);
+        assert_eq!(decoded.signature, item.signature);
+        assert_eq!(
+            decoded.signing_payload().unwrap(),
+            item.signing_payload().unwrap()
+        );
+
+        let large = MutableItem {
+            value: "m".repeat(1000),
+            ..decoded
+        };
+        assert!(large.encoded_value().is_err());
+    }
+}
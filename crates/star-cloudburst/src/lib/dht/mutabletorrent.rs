@@ -0,0 +1,220 @@
+//! Updatable torrents.
+//!
+//! [BEP-0046](https://www.bittorrent.org/beps/bep_0046.html) publishes a torrent's info hash as a
+//! mutable DHT item signed by the publisher. Magnet links point to the item
+//! with the publisher's public key and an optional salt rather than an info
+//! hash so the torrent may be replaced later:
+//!
+//! `magnet:?xs=urn:btpk:<public key in hex>&s=<salt in hex>`
+
+use super::PublicKey;
+use crate::{crypto::sha1::Sha1, hexadecimal::HexBytes};
+use log::error;
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Serialize,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+const MUTABLETORRENT_MAGNET_TARGET: &str =
+    "star_cloudburst::dht::mutabletorrent::MutableTorrent::from_magnet";
+const MAGNET_PREFIX: &str = "magnet:?";
+const BTPK_URN: &str = "urn:btpk:";
+/// Maximum length of a salt as per [BEP-0044](https://www.bittorrent.org/beps/bep_0044.html).
+pub(crate) const SALT_MAX_LEN: usize = 64;
+
+/// Where to find an updatable torrent in the DHT.
+///
+/// ```rust
+/// use star_cloudburst::dht::MutableTorrent;
+///
+/// let magnet = "magnet:?xs=urn:btpk:8543d3e6115f0f98c944077a4493dcd543e49c739fd998550a1f614ab36ed63e&s=6361747321";
+/// let cats: MutableTorrent = magnet.parse()?;
+///
+/// assert_eq!(cats.salt(), Some(&b"cats!"[..]));
+/// assert_eq!(cats.to_string(), magnet);
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MutableTorrent {
+    public_key: PublicKey,
+    salt: Option<Vec<u8>>,
+}
+
+impl MutableTorrent {
+    /// Salts may be at most 64 bytes. Empty salts are the same as no salt.
+    pub fn new(public_key: PublicKey, salt: Option<Vec<u8>>) -> Result<Self, DeError> {
+        let salt = salt.filter(|salt| !salt.is_empty());
+        match salt {
+            Some(salt) if salt.len() > SALT_MAX_LEN => Err(DeError::invalid_length(
+                salt.len(),
+                &"a salt of at most 64 bytes",
+            )),
+            salt => Ok(Self { public_key, salt }),
+        }
+    }
+
+    /// Parse the `xs` and `s` parameters of a magnet link.
+    ///
+    /// Other parameters, such as `dn` or `tr`, are ignored.
+    pub fn from_magnet(magnet: &str) -> Result<Self, DeError> {
+        let params = magnet.strip_prefix(MAGNET_PREFIX).ok_or_else(|| {
+            error!(
+                target: MUTABLETORRENT_MAGNET_TARGET,
+                "Not a magnet link: {magnet}"
+            );
+            DeError::custom(format!("magnet links start with {MAGNET_PREFIX}"))
+        })?;
+
+        let mut public_key = None;
+        let mut salt = None;
+        for (key, value) in params.split('&').filter_map(|param| param.split_once('=')) {
+            match key {
+                "xs" => {
+                    if let Some(hex) = value.strip_prefix(BTPK_URN) {
+                        public_key = Some(hex.parse()?);
+                    }
+                }
+                "s" if !value.is_empty() => {
+                    salt = Some(HexBytes::from_hex_str(value)?.as_slice().to_vec());
+                }
+                _ => {}
+            }
+        }
+
+        let public_key = public_key.ok_or_else(|| {
+            error!(
+                target: MUTABLETORRENT_MAGNET_TARGET,
+                "Magnet link doesn't have a public key: {magnet}"
+            );
+            DeError::missing_field("xs")
+        })?;
+        Self::new(public_key, salt)
+    }
+
+    /// Magnet link with the public key and salt.
+    #[inline]
+    pub fn to_magnet(&self) -> String {
+        self.to_string()
+    }
+
+    #[inline]
+    pub fn public_key(&self) -> &PublicKey {
+        &self.public_key
+    }
+
+    #[inline]
+    pub fn salt(&self) -> Option<&[u8]> {
+        self.salt.as_deref()
+    }
+
+    /// DHT target of the item: the SHA-1 hash of the public key followed by the
+    /// salt.
+    #[cfg(feature = "crypto")]
+    #[inline]
+    pub fn target(&self) -> Sha1 {
+        target(&self.public_key, self.salt())
+    }
+}
+
+/// SHA-1 hash of `public_key` followed by `salt`.
+#[cfg(feature = "crypto")]
+pub(crate) fn target(public_key: &PublicKey, salt: Option<&[u8]>) -> Sha1 {
+    use digest::Digest;
+
+    let mut hasher = sha1::Sha1::new();
+    hasher.update(public_key.as_bytes());
+    if let Some(salt) = salt {
+        hasher.update(salt);
+    }
+    <[u8; 20]>::from(hasher.finalize()).into()
+}
+
+impl FromStr for MutableTorrent {
+    type Err = DeError;
+
+    #[inline]
+    fn from_str(magnet: &str) -> Result<Self, Self::Err> {
+        Self::from_magnet(magnet)
+    }
+}
+
+impl Display for MutableTorrent {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{MAGNET_PREFIX}xs={BTPK_URN}{}", self.public_key)?;
+        if let Some(salt) = &self.salt {
+            f.write_str("&s=")?;
+            salt.iter().try_for_each(|byte| write!(f, "{byte:02x}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Value of a BEP-0046 DHT item.
+///
+/// The publisher updates the torrent by storing a new info hash with a higher
+/// sequence number.
+///
+/// ```rust
+/// use star_cloudburst::{crypto::sha1::Sha1, dht::MutableTorrentValue};
+///
+/// let value = MutableTorrentValue {
+///     info_hash: Sha1::from([0xca; 20]),
+/// };
+/// let encoded = serde_bencode::to_bytes(&value)?;
+/// assert_eq!(&encoded[..8], b"d2:ih20:");
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct MutableTorrentValue {
+    /// Version 1 info hash of the current torrent.
+    #[serde(rename = "ih")]
+    pub info_hash: Sha1,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MutableTorrent;
+    use crate::dht::PublicKey;
+
+    const KEY: &str = "8543d3e6115f0f98c944077a4493dcd543e49c739fd998550a1f614ab36ed63e";
+
+    #[test]
+    fn magnet_parameters() {
+        let magnet = format!("magnet:?dn=cats&xs=http://cats.test/cats.torrent&xs=urn:btpk:{KEY}");
+        let cats: MutableTorrent = magnet.parse().unwrap();
+        assert_eq!(cats.public_key(), &KEY.parse::<PublicKey>().unwrap());
+        assert_eq!(cats.salt(), None);
+
+        let long_salt = format!("magnet:?xs=urn:btpk:{KEY}&s={}", "00".repeat(65));
+        assert!(long_salt.parse::<MutableTorrent>().is_err());
+        assert!("magnet:?dn=cats".parse::<MutableTorrent>().is_err());
+        assert!(format!("magnet:?xs=urn:btpk:{}", &KEY[2..])
+            .parse::<MutableTorrent>()
+            .is_err());
+    }
+
+    // Test vectors from BEP-0044.
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn target_includes_salt() {
+        let key: PublicKey = "77ff84905a91936367c01360803104f92432fcd904a43511876df5cdf3e7e548"
+            .parse()
+            .unwrap();
+        let unsalted = MutableTorrent::new(key, None).unwrap();
+        let salted = MutableTorrent::new(key, Some(b"foobar".to_vec())).unwrap();
+
+        assert_eq!(
+            unsalted.target().to_string(),
+            "4a533d47ec9c7d95b1ad75f576cffc641853b750"
+        );
+        assert_eq!(
+            salted.target().to_string(),
+            "411eba73b6f087ca51a3795d9c8c938d365e32c1"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Ed25519 public keys.
+
+use crate::hexadecimal::HexBytes;
+use log::error;
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+
+const PUBLICKEY_DE_TARGET: &str = "star_cloudburst::dht::PublicKey::deserialize";
+pub(crate) const PUBLIC_KEY_LEN: usize = 32;
+
+/// Ed25519 public key that identifies a mutable DHT item.
+///
+/// Keys are bytes in bencode and hexadecimal in magnet links.
+///
+/// ```rust
+/// use star_cloudburst::dht::PublicKey;
+///
+/// let hex = "8543d3e6115f0f98c944077a4493dcd543e49c739fd998550a1f614ab36ed63e";
+/// let key: PublicKey = hex.parse()?;
+/// assert_eq!(key.to_string(), hex);
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct PublicKey([u8; PUBLIC_KEY_LEN]);
+
+impl PublicKey {
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; PUBLIC_KEY_LEN] {
+        &self.0
+    }
+}
+
+impl From<[u8; PUBLIC_KEY_LEN]> for PublicKey {
+    #[inline]
+    fn from(bytes: [u8; PUBLIC_KEY_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PublicKey {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"32 bytes"))
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = DeError;
+
+    #[inline]
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        HexBytes::from_hex_str(hex)?.as_slice().try_into()
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl<'de> Deserialize<'de> for PublicKey {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = ByteBuf::deserialize(deserializer)?;
+        bytes.as_slice().try_into().map_err(|e| {
+            error!(target: PUBLICKEY_DE_TARGET, "Invalid public key: {e}");
+            D::Error::custom(e)
+        })
+    }
+}
+
+impl Serialize for PublicKey {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
@@ -0,0 +1,199 @@
+//! Structured differences between two torrents.
+//!
+//! [TorrentDiff] describes what changed between two versions of a torrent,
+//! such as a torrent before and after editing, so that front ends can render
+//! the changes however they like.
+
+use crate::{pieces::PieceLength, uri::uriwrapper::UriWrapper, Torrent};
+use std::collections::{BTreeMap, HashSet};
+
+/// A value that changed from `old` to `new`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+/// A file shared by a torrent.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DiffFile {
+    /// Path components joined with `/`.
+    pub path: String,
+    /// Length of the file in bytes.
+    pub length: u64,
+}
+
+/// Changes from one torrent to another.
+///
+/// Files are compared by path and sorted by path. Trackers are compared
+/// regardless of tier and keep the order in which they appear.
+///
+/// ```rust
+/// use star_cloudburst::Torrent;
+/// use serde_bencode::Error;
+///
+/// let cats = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+/// let old = Torrent::from_bytes(cats)?;
+/// let mut new = old.clone();
+/// new.comment = Some("Meow".to_owned());
+///
+/// let diff = old.diff(&new);
+/// assert_eq!(diff.comment.and_then(|change| change.new).as_deref(), Some("Meow"));
+/// assert!(diff.files_added.is_empty());
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TorrentDiff {
+    /// Trackers in the new torrent only.
+    pub trackers_added: Vec<UriWrapper>,
+    /// Trackers in the old torrent only.
+    pub trackers_removed: Vec<UriWrapper>,
+    /// Files in the new torrent only.
+    pub files_added: Vec<DiffFile>,
+    /// Files in the old torrent only.
+    pub files_removed: Vec<DiffFile>,
+    /// Files in both torrents with different lengths.
+    pub files_resized: Vec<Change<DiffFile>>,
+    /// Piece length if it changed.
+    pub piece_length: Option<Change<PieceLength>>,
+    /// Comment if it changed.
+    pub comment: Option<Change<Option<String>>>,
+}
+
+impl TorrentDiff {
+    /// Whether the torrents are the same with respect to the compared fields.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl Torrent {
+    /// Compare trackers, files, piece length, and comment with another torrent.
+    ///
+    /// `self` is the old torrent and `other` is the new torrent.
+    pub fn diff(&self, other: &Torrent) -> TorrentDiff {
+        let old_trackers: Vec<_> = self.unique_trackers().collect();
+        let new_trackers: Vec<_> = other.unique_trackers().collect();
+        let old_set: HashSet<_> = old_trackers.iter().copied().collect();
+        let new_set: HashSet<_> = new_trackers.iter().copied().collect();
+
+        let old_files = files(self);
+        let new_files = files(other);
+
+        let mut files_added = Vec::new();
+        let mut files_resized = Vec::new();
+        for (path, &length) in &new_files {
+            let new = DiffFile {
+                path: path.clone(),
+                length,
+            };
+            match old_files.get(path) {
+                None => files_added.push(new),
+                Some(&old_length) if old_length != length => files_resized.push(Change {
+                    old: DiffFile {
+                        path: path.clone(),
+                        length: old_length,
+                    },
+                    new,
+                }),
+                Some(_) => {}
+            }
+        }
+
+        TorrentDiff {
+            trackers_added: new_trackers
+                .iter()
+                .filter(|tracker| !old_set.contains(*tracker))
+                .copied()
+                .cloned()
+                .collect(),
+            trackers_removed: old_trackers
+                .iter()
+                .filter(|tracker| !new_set.contains(*tracker))
+                .copied()
+                .cloned()
+                .collect(),
+            files_added,
+            files_removed: old_files
+                .iter()
+                .filter(|(path, _)| !new_files.contains_key(*path))
+                .map(|(path, &length)| DiffFile {
+                    path: path.clone(),
+                    length,
+                })
+                .collect(),
+            files_resized,
+            piece_length: changed(self.info.piece_length(), other.info.piece_length()),
+            comment: changed(self.comment.clone(), other.comment.clone()),
+        }
+    }
+}
+
+#[inline]
+fn changed<T: PartialEq>(old: T, new: T) -> Option<Change<T>> {
+    (old != new).then_some(Change { old, new })
+}
+
+fn files(torrent: &Torrent) -> BTreeMap<String, u64> {
+    torrent
+        .info
+        .file_list()
+        .into_iter()
+        .map(|(path, length)| (path.join("/"), length))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Change, DiffFile};
+    use crate::Torrent;
+
+    const CATS: &str = "d8:announce18:udp://cats.example4:infod5:filesld6:lengthi16e4:pathl8:cats.mkveed6:lengthi8e4:pathl8:cats.nfoeee4:name4:cats6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi16384eee";
+    const MORE_CATS: &str = "d8:announce18:udp://cats.example13:announce-listll18:udp://more.exampleee7:comment4:meow4:infod5:filesld6:lengthi32e4:pathl8:cats.mkveed6:lengthi4e4:pathl8:cats.txteee4:name4:cats6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi32768eee";
+
+    #[test]
+    fn diff_trackers_files_and_fields() -> Result<(), serde_bencode::Error> {
+        let old: Torrent = serde_bencode::from_str(CATS)?;
+        let new: Torrent = serde_bencode::from_str(MORE_CATS)?;
+        let diff = old.diff(&new);
+
+        assert_eq!(
+            diff.trackers_added,
+            ["udp://more.example".parse().expect("URI is valid.")]
+        );
+        assert!(diff.trackers_removed.is_empty());
+        assert_eq!(
+            diff.files_added,
+            [DiffFile {
+                path: "cats.txt".to_owned(),
+                length: 4
+            }]
+        );
+        assert_eq!(diff.files_removed[0].path, "cats.nfo");
+        assert_eq!(
+            diff.files_resized,
+            [Change {
+                old: DiffFile {
+                    path: "cats.mkv".to_owned(),
+                    length: 16
+                },
+                new: DiffFile {
+                    path: "cats.mkv".to_owned(),
+                    length: 32
+                },
+            }]
+        );
+        assert!(diff.piece_length.is_some());
+        assert_eq!(
+            diff.comment,
+            Some(Change {
+                old: None,
+                new: Some("meow".to_owned())
+            })
+        );
+        assert!(old.diff(&old).is_empty());
+
+        Ok(())
+    }
+}
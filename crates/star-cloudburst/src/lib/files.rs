@@ -9,10 +9,53 @@ pub mod fileattributes;
 pub mod filedisplayinfo;
 pub mod filetree;
 pub mod flatfile;
+pub mod pathcomponent;
 
 pub use fileattributes::{FileAttribute, TorrentFileAttributes};
-pub use filedisplayinfo::FileDisplayInfo;
+pub use filedisplayinfo::{FileDisplayInfo, FileFilters, FilterFiles};
 pub use filetree::{
-    FileTree, FileTreeDepthFirstIter, FileTreeEntry, FileTreeInfo, FileTreePathView,
+    FileTree, FileTreeBreadthFirstIter, FileTreeDepthError, FileTreeDepthFirstIter, FileTreeEntry,
+    FileTreeInfo, FileTreeMaxDepthIter, FileTreePathView,
 };
 pub use flatfile::{FlatFile, MetaV1FileRepr};
+pub use pathcomponent::PathComponent;
+
+/// Whether `name` is a legacy BitComet padding file such as `_____padding_file_0_...`.
+///
+/// BitComet named padding files before [BEP-0047](https://www.bittorrent.org/beps/bep_0047.html)
+/// added the `p` attribute.
+#[inline]
+pub fn is_padding_name(name: &str) -> bool {
+    name.starts_with('_') && name.trim_start_matches('_').starts_with("padding_file")
+}
+
+/// Generate a non-empty file or directory name.
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_path_component(
+    u: &mut arbitrary::Unstructured<'_>,
+) -> arbitrary::Result<String> {
+    const CHARSET: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789_-";
+
+    let len = u.int_in_range(1..=16)?;
+    (0..len)
+        .map(|_| u.choose(CHARSET).map(|&c| char::from(c)))
+        .collect()
+}
+
+/// Generate a file length that fits in a bencode integer.
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_length(
+    u: &mut arbitrary::Unstructured<'_>,
+) -> arbitrary::Result<std::num::NonZeroU64> {
+    u.int_in_range(1..=i64::MAX as u64)
+        .map(|length| length.try_into().expect("Length is at least one."))
+}
+
+/// Generate a non-empty path of non-empty components.
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_path(
+    u: &mut arbitrary::Unstructured<'_>,
+) -> arbitrary::Result<Vec<PathComponent>> {
+    let len = u.int_in_range(1..=4)?;
+    (0..len).map(|_| u.arbitrary()).collect()
+}
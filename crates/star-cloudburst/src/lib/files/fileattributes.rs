@@ -8,7 +8,6 @@
 
 // Using ArrayVec: https://nnethercote.github.io/perf-book/heap-allocations.html
 use arrayvec::ArrayVec;
-use itertools::Itertools;
 use serde::{
     de::{value::Error as DeError, Error as DeErrorTrait},
     Deserialize, Serialize,
@@ -32,6 +31,7 @@ const FILE_ATTRIBUTE_EXPECTED: [&str; 4] = ["x", "h", "p", "l"];
 /// Counter to the spec, conversions from [char] and [str] slices are currently
 /// fallible. However this may change in the future.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum FileAttribute {
     Executable,
     Hidden,
@@ -160,6 +160,13 @@ impl<'de> Deserialize<'de> for FileAttribute {
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TorrentFileAttributes(ArrayVec<FileAttribute, 4>);
 
+impl TorrentFileAttributes {
+    #[inline]
+    pub fn contains(&self, attr: FileAttribute) -> bool {
+        self.0.contains(&attr)
+    }
+}
+
 impl Display for TorrentFileAttributes {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         let attrs: String = self
@@ -178,14 +185,15 @@ impl TryFrom<&str> for TorrentFileAttributes {
     // Convert a &str containing any case insensitive combination of 'x', 'h', 'p',
     // 'l' to a vector of [FileAttribute].
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let attrs_parsed = value
-            .chars()
-            .map(|ch| ch.to_ascii_lowercase())
-            // Sort so that I could potentially intern the Strings produced during deserialization
-            // in the future.
-            .sorted()
-            // Dedup for the same reason as sorting - plus there is no reason for dupes here.
-            .dedup()
+        let mut chars: Vec<_> = value.chars().map(|ch| ch.to_ascii_lowercase()).collect();
+        // Sort so that I could potentially intern the Strings produced during deserialization
+        // in the future.
+        chars.sort_unstable();
+        // Dedup for the same reason as sorting - plus there is no reason for dupes here.
+        chars.dedup();
+
+        let attrs_parsed = chars
+            .into_iter()
             .map(|maybe_attr| maybe_attr.try_into())
             .collect::<Result<ArrayVec<_, 4>, _>>()?;
 
@@ -215,3 +223,21 @@ impl Serialize for TorrentFileAttributes {
         serializer.serialize_str(&self.to_string())
     }
 }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for TorrentFileAttributes {
+    // Any sorted subset of the attributes.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut attrs = String::with_capacity(FILE_ATTRIBUTE_EXPECTED.len());
+        for attr in FILE_ATTRIBUTE_EXPECTED {
+            if u.arbitrary()? {
+                attrs.push_str(attr);
+            }
+        }
+
+        Ok(attrs
+            .as_str()
+            .try_into()
+            .expect("Only valid attributes were chosen."))
+    }
+}
@@ -1,9 +1,11 @@
-use super::{FileTree, FileTreeDepthFirstIter, FlatFile, MetaV1FileRepr};
-use crate::metainfo::MetaV1;
+use super::{
+    FileAttribute, FileTree, FileTreeDepthFirstIter, FlatFile, MetaV1FileRepr, PathComponent,
+    TorrentFileAttributes,
+};
+use crate::metainfo::{Hybrid, MerkleV1, MetaV1};
 use std::{
     iter::{self, FusedIterator, Map, Once},
     marker::PhantomData,
-    num::NonZeroU64,
     slice::Iter,
 };
 
@@ -12,7 +14,37 @@ use std::{
 pub struct FileDisplayInfo<'file> {
     pub file_path: Vec<&'file str>,
     pub name: &'file str,
-    pub length: NonZeroU64,
+    /// Length in bytes which is zero for symlinks.
+    pub length: u64,
+    /// Padding between files rather than shared data.
+    pub padding: bool,
+    /// Target of a symlink.
+    pub symlink_path: Option<&'file [String]>,
+    /// File attributes such as whether the file is executable or hidden.
+    pub attr: Option<&'file TorrentFileAttributes>,
+}
+
+impl FileDisplayInfo<'_> {
+    #[inline]
+    fn has_attr(&self, attr: FileAttribute) -> bool {
+        self.attr.is_some_and(|attrs| attrs.contains(attr))
+    }
+
+    /// Whether this is a file with data rather than padding or a symlink.
+    #[inline]
+    pub fn is_regular(&self) -> bool {
+        !self.padding && self.symlink_path.is_none()
+    }
+
+    #[inline]
+    pub fn is_hidden(&self) -> bool {
+        self.has_attr(FileAttribute::Hidden)
+    }
+
+    #[inline]
+    pub fn is_executable(&self) -> bool {
+        self.has_attr(FileAttribute::Executable)
+    }
 }
 
 /// Iterators that yield [FileDisplayInfo] based on the meta info dictionary version.
@@ -34,30 +66,76 @@ pub(crate) trait AsFileDisplayInfo {
 impl AsFileDisplayInfo for MetaV1 {
     fn as_file_display(&self) -> FileDisplayInfoBranches<'_> {
         match &self.files {
-            &MetaV1FileRepr::Single(length) => {
-                FileDisplayInfoBranches::MetaV1Once(iter::once(FileDisplayInfo {
-                    file_path: vec![],
-                    name: self.name.as_str(),
-                    length,
-                }))
-            }
-            MetaV1FileRepr::Multiple(files) => {
-                FileDisplayInfoBranches::MetaV1Multi(files.iter().map(&|flat_file| {
-                    let mut file_path: Vec<_> = flat_file.path.iter().map(String::as_str).collect();
-                    // The last string is the name of the file.
-                    let name = file_path.remove(file_path.len() - 1);
-
-                    FileDisplayInfo {
-                        file_path,
-                        name,
-                        length: flat_file.length,
-                    }
-                }))
-            }
+            &MetaV1FileRepr::Single(length) => single_file(&self.name, length.get()),
+            MetaV1FileRepr::Multiple(files) => flat_files(files),
+        }
+    }
+}
+
+impl AsFileDisplayInfo for MerkleV1 {
+    fn as_file_display(&self) -> FileDisplayInfoBranches<'_> {
+        match (&self.files, self.length) {
+            (Some(files), _) => flat_files(files),
+            (None, length) => single_file(
+                &self.name,
+                length
+                    .expect("Merkle torrents without `files` have a `length`.")
+                    .get(),
+            ),
         }
     }
 }
 
+impl AsFileDisplayInfo for Hybrid {
+    /// The file tree is preferred. Hybrids without one fall back to the version 1 `files` or
+    /// `length`, and a hybrid without either is a single empty file like in
+    /// [crate::metainfo::MetaInfo::file_count].
+    fn as_file_display(&self) -> FileDisplayInfoBranches<'_> {
+        match (&self.file_tree, &self.files) {
+            (Some(tree), _) => tree.as_file_display(),
+            (None, Some(files)) => flat_files(files),
+            (None, None) => single_file(
+                &self.name,
+                self.length.map(|length| length.get()).unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+// A version 1 torrent's only file which is named after the torrent.
+#[inline]
+fn single_file(name: &str, length: u64) -> FileDisplayInfoBranches<'_> {
+    FileDisplayInfoBranches::MetaV1Once(iter::once(FileDisplayInfo {
+        file_path: vec![],
+        name,
+        length,
+        padding: false,
+        symlink_path: None,
+        attr: None,
+    }))
+}
+
+#[inline]
+fn flat_files(files: &[FlatFile]) -> FileDisplayInfoBranches<'_> {
+    FileDisplayInfoBranches::MetaV1Multi(files.iter().map(&|flat_file| {
+        // The last string is the name of the file. Parsed paths aren't empty.
+        let (name, file_path) = flat_file
+            .path
+            .split_last()
+            .map_or(("", &[][..]), |(name, dirs)| (name.as_str(), dirs));
+        let file_path = file_path.iter().map(PathComponent::as_str).collect();
+
+        FileDisplayInfo {
+            file_path,
+            name,
+            length: flat_file.length.get(),
+            padding: flat_file.is_padding(),
+            symlink_path: flat_file.symlink_path.as_deref(),
+            attr: flat_file.attr.as_ref(),
+        }
+    }))
+}
+
 /// Iterator to map [FileTreePathView] => [FileDisplayInfo].
 ///
 /// The iterator's lifetime is a subset of [FileTree]'s lifetime.
@@ -67,6 +145,8 @@ impl AsFileDisplayInfo for MetaV1 {
 pub(crate) struct PathViewIntoDisplayInfoIter<'iter> {
     phantom: PhantomData<FileTree>,
     iter: FileTreeDepthFirstIter<'iter>,
+    // Counted up front so that the iterator is exact sized.
+    remaining: usize,
 }
 
 impl<'iter> Iterator for PathViewIntoDisplayInfoIter<'iter> {
@@ -75,14 +155,25 @@ impl<'iter> Iterator for PathViewIntoDisplayInfoIter<'iter> {
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         let view = self.iter.next()?;
+        self.remaining -= 1;
         Some(FileDisplayInfo {
             file_path: view.directory.clone(),
             name: view.name,
             length: view.file_info.length,
+            padding: view.file_info.is_padding(),
+            symlink_path: view.file_info.symlink_path.as_deref(),
+            attr: view.file_info.attr.as_ref(),
         })
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
 
+impl ExactSizeIterator for PathViewIntoDisplayInfoIter<'_> {}
+
 impl FusedIterator for PathViewIntoDisplayInfoIter<'_> {}
 
 impl AsFileDisplayInfo for FileTree {
@@ -91,6 +182,7 @@ impl AsFileDisplayInfo for FileTree {
         FileDisplayInfoBranches::MetaV2(PathViewIntoDisplayInfoIter {
             phantom: PhantomData,
             iter: self.iter_dfs(),
+            remaining: self.file_count(),
         })
     }
 }
@@ -110,6 +202,100 @@ impl<'iter> Iterator for FileDisplayInfoIter<'iter> {
             FileDisplayInfoBranches::MetaV2(iter) => iter.next(),
         }
     }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.branches {
+            FileDisplayInfoBranches::MetaV1Once(iter) => iter.size_hint(),
+            FileDisplayInfoBranches::MetaV1Multi(iter) => iter.size_hint(),
+            FileDisplayInfoBranches::MetaV2(iter) => iter.size_hint(),
+        }
+    }
 }
 
+impl ExactSizeIterator for FileDisplayInfoIter<'_> {}
+
 impl FusedIterator for FileDisplayInfoIter<'_> {}
+
+/// Yields the [FileDisplayInfo]s that pass a filter of [FileFilters].
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct FilterFiles<I> {
+    iter: I,
+    keep: for<'info, 'file> fn(&'info FileDisplayInfo<'file>) -> bool,
+}
+
+impl<'file, I> Iterator for FilterFiles<I>
+where
+    I: Iterator<Item = FileDisplayInfo<'file>>,
+{
+    type Item = FileDisplayInfo<'file>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let keep = self.keep;
+        self.iter.find(|file| keep(file))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, self.iter.size_hint().1)
+    }
+}
+
+impl<'file, I> FusedIterator for FilterFiles<I> where I: FusedIterator<Item = FileDisplayInfo<'file>>
+{}
+
+/// Select files by their attributes.
+///
+/// ```rust
+/// use star_cloudburst::{files::FileFilters, Torrent};
+/// use serde_bencode::Error;
+///
+/// let cats = b"d4:infod9:file treed7:meow.shd0:d4:attr1:x6:lengthi16eee5:.catsd0:d4:attr1:h6:lengthi8eee8:cats.mkvd0:d6:lengthi42eeee12:meta versioni2e4:name4:cats12:piece lengthi16384eee";
+/// let torrent = Torrent::from_bytes(cats)?;
+///
+/// assert_eq!(torrent.info.iter_files().files_only().skip_hidden().count(), 2);
+/// let executables: Vec<_> = torrent.info.iter_files().executables().collect();
+/// assert_eq!(executables[0].file_path.last(), Some(&"meow.sh"));
+/// # Ok::<(), Error>(())
+/// ```
+pub trait FileFilters {
+    /// Skip padding files and symlinks.
+    #[inline]
+    fn files_only<'file>(self) -> FilterFiles<Self>
+    where
+        Self: Sized + Iterator<Item = FileDisplayInfo<'file>>,
+    {
+        FilterFiles {
+            iter: self,
+            keep: |file| file.is_regular(),
+        }
+    }
+
+    /// Skip files with the hidden attribute.
+    #[inline]
+    fn skip_hidden<'file>(self) -> FilterFiles<Self>
+    where
+        Self: Sized + Iterator<Item = FileDisplayInfo<'file>>,
+    {
+        FilterFiles {
+            iter: self,
+            keep: |file| !file.is_hidden(),
+        }
+    }
+
+    /// Only yield files with the executable attribute.
+    #[inline]
+    fn executables<'file>(self) -> FilterFiles<Self>
+    where
+        Self: Sized + Iterator<Item = FileDisplayInfo<'file>>,
+    {
+        FilterFiles {
+            iter: self,
+            keep: |file| file.is_executable(),
+        }
+    }
+}
+
+impl<'file, I> FileFilters for I where I: Iterator<Item = FileDisplayInfo<'file>> {}
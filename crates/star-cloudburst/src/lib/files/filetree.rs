@@ -3,39 +3,152 @@
 //! BEPs:
 //! [BEP_0052](https://www.bittorrent.org/beps/bep_0052.html)
 
-use crate::{crypto::sha::Sha2, files::fileattributes::TorrentFileAttributes};
+use crate::{
+    crypto::sha::Sha2,
+    files::{FileAttribute, PathComponent, TorrentFileAttributes},
+};
 use either::Either;
-use serde::{Deserialize, Serialize};
+use log::{debug, error, trace};
+use serde::{de::Error as DeErrorTrait, Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 use std::{
     collections::{btree_map, BTreeMap, VecDeque},
+    fmt::{self, Display, Formatter, Write},
     iter::FusedIterator,
-    marker::PhantomData,
-    num::NonZeroU64,
+    path::PathBuf,
 };
 
-#[cfg(debug_assertions)]
+#[cfg(feature = "arbitrary")]
+const ARBITRARY_TREE_DEPTH: usize = 3;
+
 const FILETREE_DE_TARGET: &str = "star_cloudburst::files::FileTree::deserialize";
-#[cfg(debug_assertions)]
-use log::{debug, error, trace};
+const FILETREEINFO_DE_TARGET: &str = "star_cloudburst::files::FileTreeInfo::deserialize";
+
+/// Keys of a version 2 file dict.
+pub(crate) const FILETREEINFO_FIELDS: &[&str] = &["attr", "length", "pieces root", "symlink path"];
 
 /// File info for version 2.0 torrents.
 ///
 /// V2 torrents use a different encoding scheme for files. Files and directories are stored as a tree where
 /// the leaf nodes describe files.
+///
+/// Symbolic links ([BEP-0047](https://www.bittorrent.org/beps/bep_0047.html)) have the `l`
+/// attribute, a `symlink path`, and a length of zero. Every other file has a nonzero length.
 #[skip_serializing_none]
-#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
-#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct FileTreeInfo {
     /// File attribute such as whether a file is executable or hidden.
-    #[serde(default)]
     pub attr: Option<TorrentFileAttributes>,
     /// Length of the file in bytes.
-    /// This field is only present for files not directories hence why it is not `Option<NonZeroU64>`.
-    pub length: NonZeroU64,
+    /// This field is only present for files not directories hence why it is not `Option<u64>`.
+    pub length: u64,
     /// Merkel tree root as a SHA256 hash.
-    #[serde(default, rename = "pieces root")]
+    #[serde(rename = "pieces root")]
     pub pieces_root: Option<Sha2>,
+    /// Path components of the symlink's target relative to the torrent's root.
+    #[serde(rename = "symlink path")]
+    pub symlink_path: Option<Vec<String>>,
+}
+
+impl FileTreeInfo {
+    /// Whether this file has the padding attribute.
+    #[inline]
+    pub fn is_padding(&self) -> bool {
+        self.attr
+            .as_ref()
+            .is_some_and(|attr| attr.contains(FileAttribute::Padding))
+    }
+
+    /// Whether this file is a symbolic link.
+    #[inline]
+    pub fn is_symlink(&self) -> bool {
+        self.symlink_path.is_some()
+    }
+}
+
+impl<'de> Deserialize<'de> for FileTreeInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        // `FileTreeInfo` without the symlink checks.
+        #[derive(Deserialize)]
+        struct Unchecked {
+            #[serde(default)]
+            attr: Option<TorrentFileAttributes>,
+            length: u64,
+            #[serde(default, rename = "pieces root")]
+            pieces_root: Option<Sha2>,
+            #[serde(default, rename = "symlink path")]
+            symlink_path: Option<Vec<String>>,
+        }
+
+        let unchecked = Unchecked::deserialize(deserializer)?;
+        let symlink_attr = unchecked
+            .attr
+            .as_ref()
+            .is_some_and(|attr| attr.contains(FileAttribute::Symlink));
+
+        let problem = match (&unchecked.symlink_path, symlink_attr) {
+            (Some(_), false) => Some("`symlink path` requires the `l` attribute"),
+            (None, true) => Some("symlinks require a `symlink path`"),
+            (Some(path), true) if path.is_empty() => Some("`symlink path` is empty"),
+            (Some(_), true) if unchecked.length != 0 || unchecked.pieces_root.is_some() => {
+                Some("symlinks should have a length of zero and no `pieces root`")
+            }
+            (None, false) if unchecked.length == 0 => Some("files should have a nonzero length"),
+            _ => None,
+        };
+        if let Some(problem) = problem {
+            error!(target: FILETREEINFO_DE_TARGET, "Invalid file info: {problem}");
+            return Err(DeErrorTrait::custom(problem));
+        }
+
+        Ok(FileTreeInfo {
+            attr: unchecked.attr,
+            length: unchecked.length,
+            pieces_root: unchecked.pieces_root,
+            symlink_path: unchecked.symlink_path,
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for FileTreeInfo {
+    // Either a symlink or a file with a nonzero length.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.ratio(1, 8)? {
+            return Ok(FileTreeInfo {
+                attr: Some("l".try_into().expect("`l` is a valid attribute.")),
+                length: 0,
+                pieces_root: None,
+                symlink_path: Some(
+                    crate::files::arbitrary_path(u)?
+                        .into_iter()
+                        .map(PathComponent::into_string)
+                        .collect(),
+                ),
+            });
+        }
+
+        let mut attr = String::new();
+        for flag in ["h", "p", "x"] {
+            if u.arbitrary()? {
+                attr.push_str(flag);
+            }
+        }
+
+        Ok(FileTreeInfo {
+            attr: u.arbitrary::<bool>()?.then(|| {
+                attr.as_str()
+                    .try_into()
+                    .expect("Only valid attributes were chosen.")
+            }),
+            length: crate::files::arbitrary_length(u)?.get(),
+            pieces_root: u.arbitrary()?,
+            symlink_path: None,
+        })
+    }
 }
 
 /// A file or a directory in version 2 [FileTree]s.
@@ -60,27 +173,53 @@ pub struct FileTreeEntry(
     #[serde(with = "either::serde_untagged")] pub Either<FileTreeInfo, FileTree>,
 );
 
+/// Directory of a version 2 torrent.
+///
+/// Keys are file or directory names. Files are stored under their name as a dict with the file's
+/// info under [PathComponent::FILE_INFO_KEY], which is the only key that may be empty.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-#[cfg_attr(not(debug_assertions), derive(Deserialize))]
 #[serde(transparent)]
 pub struct FileTree {
-    pub node: BTreeMap<String, FileTreeEntry>,
+    pub node: BTreeMap<PathComponent, FileTreeEntry>,
 }
 
 impl<'iter> FileTree {
+    /// Number of files in the tree without building [FileTreePathView]s.
+    pub fn file_count(&self) -> usize {
+        let mut count = 0;
+        let mut dirs = vec![self];
+
+        while let Some(dir) = dirs.pop() {
+            for entry in dir.node.values() {
+                match &entry.0 {
+                    Either::Left(_) => count += 1,
+                    Either::Right(dir) => dirs.push(dir),
+                }
+            }
+        }
+
+        count
+    }
+
+    /// Iterate over the files of the tree depth first.
+    ///
+    /// Files of a directory are yielded in order with each subdirectory's
+    /// files yielded as soon as the subdirectory is reached.
+    #[inline]
     pub fn iter_dfs(&'iter self) -> FileTreeDepthFirstIter<'iter> {
-        //let mut iters = VecDeque::new();
-        //iters.push_front(self.node.iter());
-        let iters: VecDeque<_> = [(vec!["./"], self.node.iter())].into();
+        FileTreeDepthFirstIter(Traversal::new(self, Order::DepthFirst))
+    }
 
-        FileTreeDepthFirstIter {
-            tree: PhantomData,
-            iters,
-        }
+    /// Iterate over the files of the tree breadth first.
+    ///
+    /// Files closer to the root are yielded before files in deeper
+    /// directories.
+    #[inline]
+    pub fn iter_bfs(&'iter self) -> FileTreeBreadthFirstIter<'iter> {
+        FileTreeBreadthFirstIter(Traversal::new(self, Order::BreadthFirst))
     }
 }
 
-#[cfg(debug_assertions)]
 impl<'de> Deserialize<'de> for FileTree {
     // This impl is primarily for better error logs during deserialization.
     // [star_cloudburst::Info] is deserialized by matching till a valid variant is found.
@@ -90,8 +229,8 @@ impl<'de> Deserialize<'de> for FileTree {
         D: serde::Deserializer<'de>,
     {
         trace!(target: FILETREE_DE_TARGET, "Deserializing `FileTree`.");
-        let node = match BTreeMap::<String, FileTreeEntry>::deserialize(deserializer) {
-            Ok(node) => node,
+        let entries = match BTreeMap::<String, FileTreeEntry>::deserialize(deserializer) {
+            Ok(entries) => entries,
             Err(e) => {
                 error!(
                     target: FILETREE_DE_TARGET,
@@ -102,6 +241,22 @@ impl<'de> Deserialize<'de> for FileTree {
             }
         };
 
+        // Names are validated here rather than by the map so that file info may use the empty key.
+        let node = entries
+            .into_iter()
+            .map(|(name, entry)| {
+                let name = match &entry.0 {
+                    Either::Left(_) if name.is_empty() => Ok(PathComponent::FILE_INFO_KEY),
+                    _ => PathComponent::try_from(name),
+                };
+                name.map(|name| (name, entry))
+            })
+            .collect::<Result<BTreeMap<_, _>, _>>()
+            .map_err(|e| {
+                error!(target: FILETREE_DE_TARGET, "Invalid `FileTree` name: {e}");
+                D::Error::custom(e)
+            })?;
+
         debug!(
             target: FILETREE_DE_TARGET,
             "`FileTree` root length: {}",
@@ -111,6 +266,45 @@ impl<'de> Deserialize<'de> for FileTree {
     }
 }
 
+#[cfg(feature = "arbitrary")]
+impl FileTree {
+    // Files are stored as BEP-0052 describes: a dict keyed by the file name
+    // that holds the file info under an empty key.
+    fn arbitrary_with_depth(
+        u: &mut arbitrary::Unstructured<'_>,
+        depth: usize,
+    ) -> arbitrary::Result<Self> {
+        let len = u.int_in_range(1..=4)?;
+        let mut node = BTreeMap::new();
+
+        for _ in 0..len {
+            let name = u.arbitrary()?;
+            let entry = if depth == 0 || u.arbitrary()? {
+                FileTree {
+                    node: [(
+                        PathComponent::FILE_INFO_KEY,
+                        FileTreeEntry(Either::Left(u.arbitrary()?)),
+                    )]
+                    .into(),
+                }
+            } else {
+                Self::arbitrary_with_depth(u, depth - 1)?
+            };
+            node.insert(name, FileTreeEntry(Either::Right(entry)));
+        }
+
+        Ok(FileTree { node })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for FileTree {
+    #[inline]
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Self::arbitrary_with_depth(u, ARBITRARY_TREE_DEPTH)
+    }
+}
+
 /// A view of a file yielded by a tree iterator.
 ///
 /// Paths are represented as invididual components stored in a vector.
@@ -125,8 +319,9 @@ impl<'de> Deserialize<'de> for FileTree {
 ///        name: "dumbbert.mdl",
 ///        file_info: &FileTreeInfo {
 ///            attr: None,
-///            length: 1.try_into().unwrap(),
+///            length: 1,
 ///            pieces_root: None,
+///            symlink_path: None,
 ///        },
 ///    };
 /// ```
@@ -140,96 +335,282 @@ pub struct FileTreePathView<'iter> {
     pub file_info: &'iter FileTreeInfo,
 }
 
-/// Depth first iterator for [FileTree].
-pub struct FileTreeDepthFirstIter<'iter> {
-    // The iterator returns references to strings held by an instance of FileTree, but it doesn't need to own it.
-    tree: PhantomData<&'iter FileTree>,
-    // Holds iterators produced by traversing the FileTree as well as keeps directory state (see implementation).
-    iters: VecDeque<(
+impl FileTreePathView<'_> {
+    // Path components without separators or empty names.
+    fn components(&self) -> impl Iterator<Item = &str> + '_ {
+        self.directory
+            .iter()
+            .copied()
+            .chain([self.name])
+            .map(|component| component.trim_end_matches('/'))
+            .filter(|component| !component.is_empty())
+    }
+
+    /// Directory components and name joined into a relative path.
+    ///
+    /// ```rust
+    /// use star_cloudburst::files::{FileTreeInfo, FileTreePathView};
+    /// use std::path::Path;
+    ///
+    /// let info = FileTreeInfo {
+    ///     attr: None,
+    ///     length: 1,
+    ///     pieces_root: None,
+    ///     symlink_path: None,
+    /// };
+    /// let dumbbert = FileTreePathView {
+    ///     directory: vec!["./", "alienwarpowers", "models"],
+    ///     name: "dumbbert.mdl",
+    ///     file_info: &info,
+    /// };
+    ///
+    /// assert_eq!(dumbbert.to_path_buf(), Path::new("./alienwarpowers/models/dumbbert.mdl"));
+    /// assert_eq!(dumbbert.to_string(), "./alienwarpowers/models/dumbbert.mdl");
+    /// ```
+    #[inline]
+    pub fn to_path_buf(&self) -> PathBuf {
+        self.components().collect()
+    }
+}
+
+impl Display for FileTreePathView<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (i, component) in self.components().enumerate() {
+            if i > 0 {
+                f.write_char('/')?;
+            }
+            f.write_str(component)?;
+        }
+        Ok(())
+    }
+}
+
+/// Error yielded by [FileTreeMaxDepthIter] for directories nested too deeply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTreeDepthError {
+    /// Maximum depth that was exceeded.
+    pub max_depth: usize,
+    /// Path of the first directory past the maximum depth.
+    pub directory: PathBuf,
+}
+
+impl Display for FileTreeDepthError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "file tree is nested deeper than {} directories at {}",
+            self.max_depth,
+            self.directory.display()
+        )
+    }
+}
+
+impl std::error::Error for FileTreeDepthError {}
+
+#[derive(Debug, Clone, Copy)]
+enum Order {
+    DepthFirst,
+    BreadthFirst,
+}
+
+// Iterative traversal shared by the tree iterators.
+//
+// Directories are kept in a work queue with their path and the iterator over
+// their entries rather than on the call stack, so deeply nested trees can't
+// overflow the stack. Depth first traversal works on the front of the queue
+// as a stack; breadth first traversal appends directories to the back.
+#[derive(Debug, Clone)]
+struct Traversal<'iter> {
+    pending: VecDeque<(
         Vec<&'iter str>,
-        btree_map::Iter<'iter, String, FileTreeEntry>,
+        btree_map::Iter<'iter, PathComponent, FileTreeEntry>,
     )>,
+    order: Order,
+    max_depth: Option<usize>,
 }
 
-impl<'iter> Iterator for FileTreeDepthFirstIter<'iter> {
-    type Item = FileTreePathView<'iter>;
+impl<'iter> Traversal<'iter> {
+    #[inline]
+    fn new(tree: &'iter FileTree, order: Order) -> Self {
+        Self {
+            pending: [(vec!["./"], tree.node.iter())].into(),
+            order,
+            max_depth: None,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let (directory, mut cur_iter) = self.iters.pop_front()?;
+    fn next(&mut self) -> Option<Result<FileTreePathView<'iter>, FileTreeDepthError>> {
+        loop {
+            let (directory, entries) = self.pending.front_mut()?;
 
-        match cur_iter.next() {
-            Some((name, entry)) => match &entry.0 {
-                Either::Left(file_info) => {
-                    // I can't return a slice because it's owned by the iterator.
-                    let directory_view = directory.clone();
-                    // The iterator yielded a file therefore it needs to be checked again on the next call to...next().
-                    self.iters.push_front((directory, cur_iter));
+            let Some((name, entry)) = entries.next() else {
+                // Directory is exhausted so continue with the next pending one.
+                self.pending.pop_front();
+                continue;
+            };
 
-                    Some(FileTreePathView {
-                        directory: directory_view,
+            match &entry.0 {
+                Either::Left(file_info) => {
+                    return Some(Ok(FileTreePathView {
+                        directory: directory.clone(),
                         name: name.as_str(),
                         file_info,
-                    })
+                    }))
                 }
                 Either::Right(dir) => {
-                    // The iterator yielded a directory so the NEXT directory is the old directory with the next path name appended.
-                    let mut next_directory = directory.clone();
-                    next_directory.push(name.as_str());
-
-                    // Push the current iterator back onto the stack.
-                    self.iters.push_front((directory, cur_iter));
-
-                    // As this is depth first, the next iterator is the next directory rather than exhausting the current iterator.
-                    // The current iterator is now behind the next directory's iterator.
-                    // The stack looks like this where the first entry is the top and it grows downward.
-                    // ------------------------
-                    // V next directory
-                    // V current_directory
-                    // V current_directory - 1
-                    // V current_directory - 2
-                    // V ...
-                    // V root directory
-                    // ------------------------
-                    self.iters.push_front((next_directory, dir.node.iter()));
-                    // Call next() to yield the next file. This is recursive and can cause a stack overflow with a malicious torrent.
-                    // So uh, fix it later.
-                    self.next()
+                    let mut subdirectory = directory.clone();
+                    subdirectory.push(name.as_str());
+
+                    // The root ("./") isn't counted.
+                    if let Some(max_depth) = self.max_depth {
+                        if subdirectory.len() - 1 > max_depth {
+                            // Nothing else is yielded after the error.
+                            self.pending.clear();
+                            return Some(Err(FileTreeDepthError {
+                                max_depth,
+                                directory: subdirectory
+                                    .iter()
+                                    .map(|component| component.trim_end_matches('/'))
+                                    .collect(),
+                            }));
+                        }
+                    }
+
+                    match self.order {
+                        // The subdirectory is finished before the rest of this directory.
+                        Order::DepthFirst => {
+                            self.pending.push_front((subdirectory, dir.node.iter()))
+                        }
+                        Order::BreadthFirst => {
+                            self.pending.push_back((subdirectory, dir.node.iter()))
+                        }
+                    }
                 }
-            },
-            // Current iterator has been expended; now traverse backward down the tree.
-            None => self.next(),
+            }
         }
     }
+
+    // Traversals without a maximum depth never fail.
+    #[inline]
+    fn next_unlimited(&mut self) -> Option<FileTreePathView<'iter>> {
+        self.next()
+            .map(|view| view.expect("Traversals without a maximum depth don't fail."))
+    }
+}
+
+/// Depth first iterator for [FileTree].
+#[derive(Debug, Clone)]
+pub struct FileTreeDepthFirstIter<'iter>(Traversal<'iter>);
+
+impl<'iter> FileTreeDepthFirstIter<'iter> {
+    /// Yield an error rather than entering directories nested deeper than
+    /// `max_depth` below the root.
+    ///
+    /// ```rust
+    /// use star_cloudburst::files::FileTreeEntry;
+    ///
+    /// let tree = "d4:catsd4:mewsd8:cats.mkvd0:d6:lengthi42eeeee7:mew.txtd0:d6:lengthi1eeee";
+    /// let FileTreeEntry(tree) = serde_bencode::from_str(tree)?;
+    /// let tree = tree.unwrap_right();
+    ///
+    /// // Files are dicts under their name so cats.mkv is three levels deep.
+    /// assert_eq!(tree.iter_dfs().max_depth(3).filter(Result::is_ok).count(), 2);
+    /// let mut shallow = tree.iter_dfs().max_depth(2);
+    /// assert!(shallow.next().unwrap().is_err());
+    /// assert!(shallow.next().is_none());
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    #[inline]
+    pub fn max_depth(mut self, max_depth: usize) -> FileTreeMaxDepthIter<'iter> {
+        self.0.max_depth = Some(max_depth);
+        FileTreeMaxDepthIter(self.0)
+    }
+}
+
+impl<'iter> Iterator for FileTreeDepthFirstIter<'iter> {
+    type Item = FileTreePathView<'iter>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_unlimited()
+    }
 }
 
 impl FusedIterator for FileTreeDepthFirstIter<'_> {}
 
+/// Breadth first iterator for [FileTree].
+#[derive(Debug, Clone)]
+pub struct FileTreeBreadthFirstIter<'iter>(Traversal<'iter>);
+
+impl<'iter> FileTreeBreadthFirstIter<'iter> {
+    /// Yield an error rather than entering directories nested deeper than
+    /// `max_depth` below the root.
+    #[inline]
+    pub fn max_depth(mut self, max_depth: usize) -> FileTreeMaxDepthIter<'iter> {
+        self.0.max_depth = Some(max_depth);
+        FileTreeMaxDepthIter(self.0)
+    }
+}
+
+impl<'iter> Iterator for FileTreeBreadthFirstIter<'iter> {
+    type Item = FileTreePathView<'iter>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_unlimited()
+    }
+}
+
+impl FusedIterator for FileTreeBreadthFirstIter<'_> {}
+
+/// Tree iterator that yields a [FileTreeDepthError] for directories nested too
+/// deeply.
+///
+/// Iteration stops after the error.
+#[derive(Debug, Clone)]
+pub struct FileTreeMaxDepthIter<'iter>(Traversal<'iter>);
+
+impl<'iter> Iterator for FileTreeMaxDepthIter<'iter> {
+    type Item = Result<FileTreePathView<'iter>, FileTreeDepthError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+}
+
+impl FusedIterator for FileTreeMaxDepthIter<'_> {}
+
 #[cfg(test)]
 mod tests {
-    use super::{FileTree, FileTreeEntry, FileTreeInfo, FileTreePathView};
+    use super::{FileTree, FileTreeEntry, FileTreeInfo, FileTreePathView, PathComponent};
     use either::Either;
     use serde::{Deserialize, Serialize};
     use serde_bencode::Deserializer;
 
     // Convenience function to return a new FileTreeEntry that's a file.
-    fn new_file<S>(name: S) -> (String, FileTreeEntry)
+    fn new_file<S>(name: S) -> (PathComponent, FileTreeEntry)
     where
         S: Into<String>,
     {
         (
-            name.into(),
+            name.into().try_into().unwrap(),
             FileTreeEntry(Either::Left(FileTreeInfo {
                 attr: None,
-                length: 1.try_into().unwrap(),
+                length: 1,
                 pieces_root: None,
+                symlink_path: None,
             })),
         )
     }
 
     // Convenience function to return a new FileTreeEntry that's a directory.
-    fn new_dir(name: &str, entries: Vec<(String, FileTreeEntry)>) -> (String, FileTreeEntry) {
+    fn new_dir(
+        name: &str,
+        entries: Vec<(PathComponent, FileTreeEntry)>,
+    ) -> (PathComponent, FileTreeEntry) {
         (
-            name.to_owned(),
+            name.try_into().unwrap(),
             FileTreeEntry(Either::Right(FileTree {
                 node: entries.into_iter().collect(),
             })),
@@ -421,7 +802,7 @@ mod tests {
 
     #[test]
     fn filetree_dirs_o_fun() {
-        let allen_dos = "allen_dos".to_owned();
+        let allen_dos = PathComponent::try_from("allen_dos").unwrap();
 
         // Initial element is a tree consisting of one file.
         let tree = std::iter::once((
@@ -451,7 +832,7 @@ mod tests {
         )
         .expect("Built, nested tree of directories.");
 
-        // The tree; the iterator above reduces to (String, FileTreeEntry)
+        // The tree; the iterator above reduces to (PathComponent, FileTreeEntry)
         let tree = FileTree {
             node: [tree].into(),
         };
@@ -471,4 +852,104 @@ mod tests {
             allen_dos_dir,
         );
     }
+
+    // Directories are visited level by level.
+    #[test]
+    fn filetree_multiple_iter_breadth() {
+        let tree = multiple_files_tree();
+        let names: Vec<_> = tree.iter_bfs().map(|view| view.name).collect();
+
+        assert_eq!(
+            names[..6],
+            [
+                "alienwarpowers",
+                "alienwarpowers.exe",
+                "assets.toml",
+                "aliens.pdf",
+                "manual.pdf",
+                "kebin_mod0.mdl"
+            ]
+        );
+        assert_eq!(names.len(), tree.iter_dfs().count());
+        assert_eq!(names.last(), Some(&"trevor_audio2.wav"));
+    }
+
+    #[test]
+    fn filetree_max_depth() {
+        let mut tree = FileTree {
+            node: [new_file("allen_signature")].into_iter().collect(),
+        };
+        let allen_dos = PathComponent::try_from("allen_dos").unwrap();
+        for _ in 0..10_000 {
+            tree = FileTree {
+                node: [(allen_dos.clone(), FileTreeEntry(Either::Right(tree)))].into(),
+            };
+        }
+
+        // Deep trees don't overflow the stack.
+        assert_eq!(tree.iter_dfs().next().unwrap().directory.len(), 10_001);
+        assert_eq!(tree.iter_bfs().count(), 1);
+
+        let mut limited = tree.iter_dfs().max_depth(3);
+        let error = limited.next().unwrap().unwrap_err();
+        assert_eq!(error.max_depth, 3);
+        assert_eq!(
+            error.directory,
+            std::path::Path::new("./allen_dos/allen_dos/allen_dos/allen_dos")
+        );
+        assert!(limited.next().is_none());
+        assert_eq!(tree.iter_bfs().max_depth(10_000).count(), 1);
+
+        // Dropping the tree is recursive.
+        std::mem::forget(tree);
+    }
+
+    #[test]
+    fn symlink_roundtrip() -> Result<(), serde_bencode::Error> {
+        let tree = "d7:currentd0:d4:attr1:l6:lengthi0e12:symlink pathl8:releases5:1.0.0eee8:releasesd5:1.0.0d0:d6:lengthi42eeeee";
+        let tree_de: FileTree = serde_bencode::from_str(tree)?;
+
+        let symlinks: Vec<_> = tree_de
+            .iter_dfs()
+            .filter(|view| view.file_info.is_symlink())
+            .collect();
+        assert_eq!(symlinks.len(), 1);
+        assert_eq!(symlinks[0].directory, ["./", "current"]);
+        assert_eq!(serde_bencode::to_string(&tree_de)?, tree);
+
+        Ok(())
+    }
+
+    #[test]
+    fn symlink_validation() {
+        let invalid = [
+            // Symlink without a target.
+            "d0:d4:attr1:l6:lengthi0eee",
+            // Target without the attribute.
+            "d0:d6:lengthi0e12:symlink pathl1:aeee",
+            // Symlinks don't have data.
+            "d0:d4:attr1:l6:lengthi42e12:symlink pathl1:aeee",
+            // Regular files aren't empty.
+            "d0:d6:lengthi0eee",
+        ];
+
+        for file in invalid {
+            assert!(
+                serde_bencode::from_str::<FileTreeEntry>(file).is_err(),
+                "{file}"
+            );
+        }
+    }
+
+    #[test]
+    fn path_view_display_skips_empty_name() -> Result<(), serde_bencode::Error> {
+        let tree = "d4:catsd8:cats.mkvd0:d6:lengthi42eeeee";
+        let tree_de: FileTree = serde_bencode::from_str(tree)?;
+        let view = tree_de.iter_dfs().next().expect("Tree has one file.");
+
+        assert_eq!(view.to_string(), "./cats/cats.mkv");
+        assert_eq!(view.to_path_buf(), std::path::Path::new("./cats/cats.mkv"));
+
+        Ok(())
+    }
 }
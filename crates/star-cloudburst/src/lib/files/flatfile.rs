@@ -1,10 +1,20 @@
 use crate::{
-    crypto::{md5::Md5, sha1::Sha1},
-    files::fileattributes::TorrentFileAttributes,
+    crypto::{
+        md5::{optional_md5, Md5},
+        sha1::Sha1,
+    },
+    files::{is_padding_name, FileAttribute, PathComponent, TorrentFileAttributes},
 };
-use serde::{Deserialize, Serialize};
+use log::error;
+use serde::{de::Error as DeErrorTrait, Deserialize, Deserializer, Serialize};
 use serde_with::skip_serializing_none;
-use std::{num::NonZeroU64, path::PathBuf};
+use std::num::NonZeroU64;
+
+const FLATFILE_DE_TARGET: &str = "star_cloudburst::files::FlatFile::deserialize";
+
+/// Keys of a version 1 file dict.
+pub(crate) const FLATFILE_FIELDS: &[&str] =
+    &["attr", "length", "path", "md5sum", "sha1", "symlink path"];
 
 /// Files shared by the torrent if multiple as per meta version 1.
 /// Meta version 1 represents files in a flattened structure where `path` represents the full
@@ -12,42 +22,91 @@ use std::{num::NonZeroU64, path::PathBuf};
 /// strings per file.
 #[skip_serializing_none]
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
-#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct FlatFile {
     /// File attribute such as whether the file is executable or hidden.
     #[serde(default)]
     pub attr: Option<TorrentFileAttributes>,
     /// Length of the file in bytes.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::files::arbitrary_length))]
     pub length: NonZeroU64,
-    /// List of UTF-8 strings consisting of subdirectory names where the last string is the file name.
-    pub path: Vec<String>,
+    /// Subdirectory names where the last component is the file name.
+    ///
+    /// Parsed paths have at least one component.
+    #[serde(deserialize_with = "file_path")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::files::arbitrary_path))]
+    pub path: Vec<PathComponent>,
     /// Checksum for the shared file.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "optional_md5")]
     pub md5sum: Option<Md5>,
     /// SHA1 of file to aid file deduplication.
     #[serde(default)]
     pub sha1: Option<Sha1>,
     /// Paths for symbolic links.
     #[serde(default, rename = "symlink path")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
     pub symlink_path: Option<Vec<String>>,
 }
 
+impl FlatFile {
+    /// Whether this file is padding between files rather than shared data.
+    ///
+    /// Padding files either have the `p` attribute or a legacy BitComet name.
+    #[inline]
+    pub fn is_padding(&self) -> bool {
+        self.attr
+            .as_ref()
+            .is_some_and(|attr| attr.contains(FileAttribute::Padding))
+            || self.path.last().is_some_and(|name| is_padding_name(name))
+    }
+}
+
+// Deserialize `path` which needs at least the file name.
+fn file_path<'de, D>(deserializer: D) -> Result<Vec<PathComponent>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let path = Vec::<PathComponent>::deserialize(deserializer)?;
+    if path.is_empty() {
+        error!(target: FLATFILE_DE_TARGET, "File path is empty");
+        return Err(DeErrorTrait::invalid_length(0, &"a path with a file name"));
+    }
+    Ok(path)
+}
+
 /// Does this torrent share multiple files or a single file?
 ///
 /// Meta version 1 represents multiple files with a list of [FlatFile].
 /// Single file torrents only include a `length` field with `name` indicating the suggested name of the file.
-#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
-#[serde(tag = "untagged")]
+///
+/// The variant is the value of `files` or `length`; [crate::metainfo::MetaV1] maps the keys.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
 pub enum MetaV1FileRepr {
-    #[serde(rename = "files")]
     Multiple(Vec<FlatFile>),
-    #[serde(rename = "length")]
     Single(NonZeroU64),
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for MetaV1FileRepr {
+    // Multiple file torrents share at least one file.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.arbitrary()? {
+            let len = u.int_in_range(1..=8)?;
+            (0..len)
+                .map(|_| u.arbitrary())
+                .collect::<arbitrary::Result<_>>()
+                .map(MetaV1FileRepr::Multiple)
+        } else {
+            crate::files::arbitrary_length(u).map(MetaV1FileRepr::Single)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{FlatFile, MetaV1FileRepr};
+    use crate::Torrent;
     use serde::{Deserialize, Serialize};
     use serde_test::{assert_de_tokens, assert_tokens, Token};
 
@@ -60,8 +119,9 @@ mod tests {
         FlatFile {
             attr: None,
             length: 42.try_into().unwrap(),
-            path: (0..10)
-                .flat_map(|n| ["scripts".into(), "ai".into(), format!("raees_{n}.py")])
+            path: ["scripts", "raees.py"]
+                .into_iter()
+                .map(|name| name.try_into().unwrap())
                 .collect(),
             md5sum: None,
             sha1: None,
@@ -83,11 +143,6 @@ mod tests {
                     len: 1,
                 },
                 Token::Str("files"),
-                Token::Enum {
-                    name: "MetaV1FileRepr",
-                },
-                Token::Str("untagged"),
-                Token::Str("length"),
                 Token::U64(42),
                 Token::StructEnd,
             ],
@@ -97,24 +152,46 @@ mod tests {
     #[test]
     fn yield_multi_metav2_de() {
         let files = LameV1Files {
-            files: MetaV1FileRepr::Multiple((0..10).map(|_| fake_file()).collect()),
+            files: MetaV1FileRepr::Multiple((0..2).map(|_| fake_file()).collect()),
         };
 
-        assert_tokens(
-            &files,
-            &[
-                Token::Struct {
-                    name: "LameV1Files",
-                    len: 1,
-                },
-                Token::Str("files"),
-                Token::Enum {
-                    name: "MetaV1FileRepr"
-                },
-                Token::Str("untagged"),
-                Token::Str("files"),
-                Token::StructEnd,
-            ],
-        );
+        let file = [
+            Token::Struct {
+                name: "FlatFile",
+                len: 2,
+            },
+            Token::Str("length"),
+            Token::U64(42),
+            Token::Str("path"),
+            Token::Seq { len: Some(2) },
+            Token::Str("scripts"),
+            Token::Str("raees.py"),
+            Token::SeqEnd,
+            Token::StructEnd,
+        ];
+        let tokens: Vec<_> = [
+            Token::Struct {
+                name: "LameV1Files",
+                len: 1,
+            },
+            Token::Str("files"),
+            Token::Seq { len: Some(2) },
+        ]
+        .into_iter()
+        .chain(file)
+        .chain(file)
+        .chain([Token::SeqEnd, Token::StructEnd])
+        .collect();
+
+        assert_tokens(&files, &tokens);
+    }
+
+    #[test]
+    fn empty_path_rejected() {
+        assert!(serde_bencode::from_bytes::<FlatFile>(b"d6:lengthi42e4:pathlee").is_err());
+
+        let cats = b"d4:infod5:filesld6:lengthi42e4:pathleee4:name4:cats12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+        assert!(Torrent::from_bytes(cats).is_err());
+        assert!(Torrent::from_bytes_with_stats(cats).is_err());
     }
 }
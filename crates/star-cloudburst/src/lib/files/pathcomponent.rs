@@ -0,0 +1,210 @@
+//! Validated file and directory names.
+//!
+//! Torrents store paths as lists of names rather than strings with separators.
+//! Nothing stops a torrent from including empty names or names with embedded
+//! separators though, which either break path handling or smuggle extra
+//! directories into a name. [PathComponent] rejects those while deserializing.
+
+use log::error;
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Display, Formatter},
+    ops::Deref,
+};
+
+const PATHCOMPONENT_DE_TARGET: &str = "star_cloudburst::files::PathComponent::deserialize";
+
+/// One file or directory name of a torrent's path.
+///
+/// Names are non-empty and don't contain `/` or NUL. The only exception is
+/// [PathComponent::FILE_INFO_KEY], the empty key that
+/// [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) uses for file info in a
+/// [crate::files::FileTree].
+///
+/// ```rust
+/// use star_cloudburst::files::PathComponent;
+///
+/// let name = PathComponent::try_from("cats.mkv")?;
+/// assert_eq!(name, "cats.mkv");
+///
+/// assert!(PathComponent::try_from("").is_err());
+/// assert!(PathComponent::try_from("cats/cats.mkv").is_err());
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PathComponent(String);
+
+impl PathComponent {
+    /// Key of a file's info in a version 2 [crate::files::FileTree].
+    pub const FILE_INFO_KEY: Self = Self(String::new());
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    #[inline]
+    pub fn into_string(self) -> String {
+        self.0
+    }
+
+    fn validate(name: &str) -> Result<(), DeError> {
+        if name.is_empty() {
+            Err(DeError::invalid_length(
+                0,
+                &"a non-empty file or directory name",
+            ))
+        } else if let Some(invalid) = name.chars().find(|&c| c == '/' || c == '\0') {
+            Err(DeError::custom(format!(
+                "file or directory name contains {invalid:?}: {name:?}"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl TryFrom<String> for PathComponent {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Self::validate(&name).map(|_| Self(name))
+    }
+}
+
+impl TryFrom<&str> for PathComponent {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        Self::validate(name).map(|_| Self(name.to_owned()))
+    }
+}
+
+impl From<PathComponent> for String {
+    #[inline]
+    fn from(name: PathComponent) -> Self {
+        name.0
+    }
+}
+
+impl Deref for PathComponent {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for PathComponent {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for PathComponent {
+    #[inline]
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for PathComponent {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for PathComponent {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl Display for PathComponent {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for PathComponent {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+        Self::validate(&name).map_err(|e| {
+            error!(target: PATHCOMPONENT_DE_TARGET, "Invalid path component: {e}");
+            D::Error::custom(e)
+        })?;
+        Ok(Self(name))
+    }
+}
+
+impl Serialize for PathComponent {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PathComponent {
+    #[inline]
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        crate::files::arbitrary_path_component(u).map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathComponent;
+    use crate::files::{FileTree, FlatFile};
+
+    #[test]
+    fn reject_invalid_flat_file_paths() {
+        let valid = "d6:lengthi42e4:pathl7:scripts5:ai.pyee";
+        let file: FlatFile = serde_bencode::from_str(valid).unwrap();
+        assert_eq!(file.path, ["scripts", "ai.py"]);
+
+        for invalid in [
+            "d6:lengthi42e4:pathl0:5:ai.pyee",
+            "d6:lengthi42e4:pathl10:scripts/ai5:ai.pyee",
+            "d6:lengthi42e4:pathl7:scripts6:ai.py\0ee",
+        ] {
+            assert!(
+                serde_bencode::from_str::<FlatFile>(invalid).is_err(),
+                "{invalid:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn file_info_key_is_only_for_files() {
+        let file = "d8:cats.mkvd0:d6:lengthi42eeee";
+        let tree: FileTree = serde_bencode::from_str(file).unwrap();
+        assert_eq!(tree.file_count(), 1);
+        assert_eq!(serde_bencode::to_string(&tree).unwrap(), file);
+
+        let empty_dir = "d0:d8:cats.mkvd0:d6:lengthi42eeeee";
+        assert!(serde_bencode::from_str::<FileTree>(empty_dir).is_err());
+
+        let separator = "d9:cats/catsd0:d6:lengthi42eeee";
+        assert!(serde_bencode::from_str::<FileTree>(separator).is_err());
+
+        assert!(PathComponent::try_from(String::new()).is_err());
+    }
+}
@@ -2,8 +2,16 @@
 
 // mod hexborrow;
 mod hexbytes;
+#[cfg(feature = "iter-extras")]
 mod nibbles;
 
 // pub use hexborrow::HexBorrow;
 pub use hexbytes::HexBytes;
+#[cfg(feature = "iter-extras")]
 pub use nibbles::{Hexadecimal, Nibbles, PackedHex};
+
+// Pack two hex bytes into a single byte.
+#[inline]
+pub(crate) fn pack_bytes(bytes: [u8; 2]) -> u8 {
+    (bytes[0] << 4) | bytes[1]
+}
@@ -1,16 +1,21 @@
-use super::nibbles::pack_bytes;
+use super::pack_bytes;
 use log::error;
 use serde::{
-    de::{value::Error as DeError, Error as DeErrorTrait, Unexpected},
-    Deserialize, Serialize,
+    de::{value::Error as DeError, Error as DeErrorTrait, SeqAccess, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize,
 };
 use serde_bytes::ByteBuf;
-use std::fmt::{self, Binary, Debug, Display, Formatter, LowerHex, UpperHex};
+use std::{
+    borrow::Borrow,
+    fmt::{self, Binary, Debug, Display, Formatter, LowerHex, UpperHex},
+    str::FromStr,
+};
 
 const HEX_LOWER: &str = "0123456789abcdef";
 // const HEX_UPPER: &str = "0123456789ABCDEF";
 const HEX_EXPECTED: &str = "valid hexadecimal characters [0-9, a-f, A-F]";
 const FROMHEXSTR_TARGET: &str = "star_cloudburst::hexadecimal::HexBytes::from_hex_str";
+const HEXBYTES_DE_TARGET: &str = "star_cloudburst::hexadecimal::HexBytes::deserialize";
 
 // Map valid hex character to 0-15
 fn hex_to_byte(ch: char) -> Result<u8, DeError> {
@@ -26,7 +31,12 @@ fn hex_to_byte(ch: char) -> Result<u8, DeError> {
         })
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+/// Bytes that are displayed as hexadecimal.
+///
+/// Binary formats such as Bencode store the raw bytes. Human readable formats
+/// such as JSON may store a hexadecimal string instead, which is decoded while
+/// deserializing. Raw bytes and lists of bytes are accepted from any format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
 #[serde(transparent)]
 pub struct HexBytes {
     bytes: ByteBuf,
@@ -110,13 +120,6 @@ impl HexBytes {
         }
     }
 
-    #[inline]
-    pub(crate) fn new(bytes: ByteBuf) -> Self {
-        // For some reason, probably due to my own idiocy, I can't use Into to construct
-        // HexBytes.
-        Self { bytes }
-    }
-
     /// Returns the amount of bytes stored.
     ///
     /// ```rust
@@ -128,6 +131,21 @@ impl HexBytes {
     ///
     /// # Ok::<(), Error>(())
     /// ```
+    /// Decode bytes that are exactly `len` hexadecimal characters.
+    ///
+    /// Hashes may arrive as raw bytes or as hexadecimal strings. Wrappers that
+    /// know the length of their hash call this so that either is accepted. Any
+    /// other length is returned as is for the wrapper's length check.
+    pub(crate) fn decode_hex_of_len(self, len: usize) -> Self {
+        let bytes = self.as_slice();
+        if bytes.len() == len * 2 && bytes.iter().all(u8::is_ascii_hexdigit) {
+            // Hexadecimal digits are always valid UTF-8 and an even length.
+            Self::from_hex_str(String::from_utf8_lossy(bytes)).unwrap_or(self)
+        } else {
+            self
+        }
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.bytes.len()
@@ -162,15 +180,180 @@ impl HexBytes {
     }
 }
 
-impl<B> From<B> for HexBytes
-where
-    B: Into<Vec<u8>>,
-{
+// No validation for the From impls because these are just bytes.
+// Strings are parsed as hexadecimal with TryFrom or FromStr instead.
+impl From<Vec<u8>> for HexBytes {
     #[inline]
-    fn from(bytes: B) -> Self {
-        // No validation because these are just bytes.
+    fn from(bytes: Vec<u8>) -> Self {
         Self {
-            bytes: ByteBuf::from(bytes.into()),
+            bytes: ByteBuf::from(bytes),
+        }
+    }
+}
+
+impl From<ByteBuf> for HexBytes {
+    #[inline]
+    fn from(bytes: ByteBuf) -> Self {
+        Self { bytes }
+    }
+}
+
+impl From<&[u8]> for HexBytes {
+    #[inline]
+    fn from(bytes: &[u8]) -> Self {
+        bytes.to_vec().into()
+    }
+}
+
+impl<const N: usize> From<[u8; N]> for HexBytes {
+    #[inline]
+    fn from(bytes: [u8; N]) -> Self {
+        bytes.to_vec().into()
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for HexBytes {
+    #[inline]
+    fn from(bytes: &[u8; N]) -> Self {
+        bytes.to_vec().into()
+    }
+}
+
+impl TryFrom<&str> for HexBytes {
+    type Error = DeError;
+
+    /// Parse a hexadecimal string with [HexBytes::from_hex_str].
+    #[inline]
+    fn try_from(maybe_hex: &str) -> Result<Self, Self::Error> {
+        Self::from_hex_str(maybe_hex)
+    }
+}
+
+impl FromStr for HexBytes {
+    type Err = DeError;
+
+    /// Parse a hexadecimal string with [HexBytes::from_hex_str].
+    ///
+    /// ```
+    /// use serde::de::value::Error;
+    /// use star_cloudburst::hexadecimal::HexBytes;
+    ///
+    /// let bytes: HexBytes = "ca75".parse()?;
+    /// assert_eq!(bytes.as_ref(), [0xca, 0x75]);
+    ///
+    /// let doubled: HexBytes = bytes.into_iter().map(|byte| byte.wrapping_mul(2)).collect();
+    /// assert_eq!(doubled.to_string(), "94ea");
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[inline]
+    fn from_str(maybe_hex: &str) -> Result<Self, Self::Err> {
+        Self::from_hex_str(maybe_hex)
+    }
+}
+
+impl AsRef<[u8]> for HexBytes {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl Borrow<[u8]> for HexBytes {
+    #[inline]
+    fn borrow(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl IntoIterator for HexBytes {
+    type Item = u8;
+    type IntoIter = std::vec::IntoIter<u8>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.bytes.into_vec().into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a HexBytes {
+    type Item = &'a u8;
+    type IntoIter = std::slice::Iter<'a, u8>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.bytes.iter()
+    }
+}
+
+impl FromIterator<u8> for HexBytes {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = u8>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<'de> Deserialize<'de> for HexBytes {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct HexBytesVisitor {
+            human_readable: bool,
+        }
+
+        impl<'de> Visitor<'de> for HexBytesVisitor {
+            type Value = HexBytes;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                if self.human_readable {
+                    formatter.write_str("a hexadecimal string or bytes")
+                } else {
+                    formatter.write_str("bytes")
+                }
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                if self.human_readable {
+                    HexBytes::from_hex_str(v).map_err(|e| {
+                        error!(target: HEXBYTES_DE_TARGET, "Invalid hex string: {e}");
+                        E::custom(e)
+                    })
+                } else {
+                    Ok(v.as_bytes().into())
+                }
+            }
+
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                Ok(v.into())
+            }
+
+            fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                Ok(v.into())
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                ByteBuf::deserialize(serde::de::value::SeqAccessDeserializer::new(seq))
+                    .map(|bytes| bytes.into_vec().into())
+            }
+        }
+
+        let human_readable = deserializer.is_human_readable();
+        if human_readable {
+            deserializer.deserialize_any(HexBytesVisitor { human_readable })
+        } else {
+            deserializer.deserialize_byte_buf(HexBytesVisitor { human_readable })
         }
     }
 }
@@ -221,3 +404,69 @@ impl UpperHex for HexBytes {
         Ok(())
     }
 }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for HexBytes {
+    #[inline]
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(u.arbitrary::<Vec<u8>>()?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HexBytes;
+    use crate::crypto::sha1::Sha1;
+    use serde_test::{assert_de_tokens, assert_de_tokens_error, Configure, Readable, Token};
+    use std::collections::HashMap;
+
+    #[test]
+    fn hexbytes_de_hex_or_bytes() {
+        let readable = HexBytes::from(vec![0xca, 0x75]).readable();
+        assert_de_tokens(&readable, &[Token::Str("ca75")]);
+        assert_de_tokens(&readable, &[Token::Bytes(&[0xca, 0x75])]);
+        assert_de_tokens(
+            &readable,
+            &[
+                Token::Seq { len: Some(2) },
+                Token::U8(0xca),
+                Token::U8(0x75),
+                Token::SeqEnd,
+            ],
+        );
+
+        // Compact formats never store hexadecimal.
+        assert_de_tokens(&HexBytes::from(b"ca75").compact(), &[Token::Str("ca75")]);
+        assert_de_tokens_error::<Readable<HexBytes>>(
+            &[Token::Str("cat")],
+            "invalid length 3, expected valid hex string lengths are divisible by two and greater than zero",
+        );
+    }
+
+    #[test]
+    fn hexbytes_std_traits() {
+        let layers: HashMap<HexBytes, u8> = [(HexBytes::from(b"cat"), 1)].into();
+        assert_eq!(layers.get(&b"cat"[..]), Some(&1));
+
+        assert!(HexBytes::try_from("c").is_err());
+        let bytes = HexBytes::try_from("00ff").unwrap();
+        assert_eq!((&bytes).into_iter().copied().collect::<HexBytes>(), bytes);
+    }
+
+    #[test]
+    fn sha1_de_hex_or_bytes() {
+        let hex = "2aae6c35c94fcfb415dbe95f408b9ce91ee846ed";
+        let raw = HexBytes::from_hex_str(hex).unwrap();
+        let expected: Sha1 = serde_bencode::from_bytes(
+            &[format!("{}:", raw.len()).as_bytes(), raw.as_slice()].concat(),
+        )
+        .unwrap();
+
+        let from_hex: Sha1 = serde_bencode::from_str(&format!("40:{hex}")).unwrap();
+        assert_eq!(from_hex, expected);
+        assert_eq!(from_hex.to_string(), hex);
+
+        // Strict length checks still apply.
+        assert!(serde_bencode::from_str::<Sha1>(&format!("38:{}", &hex[2..])).is_err());
+    }
+}
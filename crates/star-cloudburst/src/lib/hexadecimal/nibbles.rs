@@ -1,11 +1,6 @@
+use super::pack_bytes;
 use std::iter::FusedIterator;
 
-// Pack two hex bytes into a single byte.
-#[inline]
-pub(super) fn pack_bytes(bytes: [u8; 2]) -> u8 {
-    (bytes[0] << 4) | bytes[1]
-}
-
 /// Yields nibbles from bytes.
 #[derive(Clone)]
 #[must_use = "iterators are lazy and do nothing unless consumed"]
@@ -0,0 +1,304 @@
+//! Create the files of a torrent on disk.
+//!
+//! Download tools need the torrent's directories and files to exist before
+//! pieces are written. [Torrent::materialize] creates them under a root
+//! directory while skipping padding files and marking executable files.
+
+use crate::{
+    files::{FileAttribute, MetaV1FileRepr, TorrentFileAttributes},
+    metainfo::MetaInfo,
+    parsestats::ParseStats,
+    Torrent,
+};
+use log::debug;
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::{Component, Path, PathBuf},
+};
+
+const MATERIALIZE_TARGET: &str = "star_cloudburst::Torrent::materialize";
+
+/// How [Torrent::materialize] allocates files.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Allocation {
+    /// Set the file length without writing data.
+    ///
+    /// Most file systems create sparse files that only take up space as
+    /// pieces are written.
+    #[default]
+    Sparse,
+    /// Write zeroes so that the space is reserved up front.
+    Full,
+}
+
+/// A file to create relative to the root directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutFile {
+    pub path: PathBuf,
+    pub length: u64,
+    pub executable: bool,
+}
+
+/// Counts of what [Torrent::materialize] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LayoutStats {
+    /// Files that were created.
+    pub created: usize,
+    /// Files that already existed and were resized to the torrent's length.
+    pub resized: usize,
+    /// Files that already existed with the torrent's length.
+    pub unchanged: usize,
+    /// Padding files that weren't created.
+    pub padding_skipped: usize,
+}
+
+impl Torrent {
+    /// Files of the torrent relative to the directory they're saved in.
+    ///
+    /// Multiple file torrents are nested in a directory named after [Torrent::name]. Padding files
+    /// and symlinks are skipped. Paths with components that could escape the directory, such as `..`, are
+    /// errors.
+    pub fn layout(&self) -> io::Result<Vec<LayoutFile>> {
+        let name = safe_component(self.name())?;
+        let mut layout = Vec::new();
+
+        let mut single = |length: u64| {
+            layout.push(LayoutFile {
+                path: name.into(),
+                length,
+                executable: false,
+            })
+        };
+        let flat_files = match &self.info {
+            MetaInfo::MetaV1(info) => match &info.files {
+                MetaV1FileRepr::Single(length) => {
+                    single(length.get());
+                    None
+                }
+                MetaV1FileRepr::Multiple(files) => Some(files),
+            },
+            MetaInfo::MerkleV1(info) => match (&info.files, info.length) {
+                (Some(files), _) => Some(files),
+                (None, length) => {
+                    single(length.map(|length| length.get()).unwrap_or_default());
+                    None
+                }
+            },
+            MetaInfo::Hybrid(info) if info.file_tree.is_none() => match &info.files {
+                Some(files) => Some(files),
+                None => {
+                    single(info.length.map(|length| length.get()).unwrap_or_default());
+                    None
+                }
+            },
+            MetaInfo::MetaV2(_) | MetaInfo::Hybrid(_) => None,
+        };
+
+        if let Some(files) = flat_files {
+            for file in files
+                .iter()
+                .filter(|file| !file.is_padding() && file.symlink_path.is_none())
+            {
+                layout.push(LayoutFile {
+                    path: nested_path(name, &file.path)?,
+                    length: file.length.get(),
+                    executable: is_executable(file.attr.as_ref()),
+                });
+            }
+        }
+
+        let tree = match &self.info {
+            MetaInfo::MetaV2(info) => Some(&info.file_tree),
+            MetaInfo::Hybrid(info) => info.file_tree.as_ref(),
+            _ => None,
+        };
+        if let Some(tree) = tree {
+            let views: Vec<_> = tree
+                .iter_dfs()
+                .filter(|view| !view.file_info.is_padding() && !view.file_info.is_symlink())
+                .collect();
+            // Skip the root ("./"); the last directory is the file's name.
+            let paths: Vec<Vec<&str>> = views
+                .iter()
+                .map(|view| {
+                    view.directory[1..]
+                        .iter()
+                        .copied()
+                        .chain([view.name])
+                        .filter(|part| !part.is_empty())
+                        .collect()
+                })
+                .collect();
+
+            // Version 2 torrents with one file at the root aren't nested.
+            let nested = !matches!(paths.as_slice(), [path] if path.len() == 1);
+            for (view, path) in views.iter().zip(paths) {
+                layout.push(LayoutFile {
+                    path: if nested {
+                        nested_path(name, &path)?
+                    } else {
+                        nested_path("", &path)?
+                    },
+                    length: view.file_info.length,
+                    executable: is_executable(view.file_info.attr.as_ref()),
+                });
+            }
+        }
+
+        Ok(layout)
+    }
+
+    /// Create the directories and files of the torrent under `root`.
+    ///
+    /// Existing files are kept but resized to the torrent's lengths, which truncates longer files.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{layout::Allocation, Torrent};
+    ///
+    /// let cats = b"d4:infod5:filesld6:lengthi16e4:pathl8:cats.mkveed6:lengthi8e4:pathl6:extras8:cats.nfoeee4:name4:cats6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let torrent = Torrent::from_bytes(cats).expect("Torrent is valid.");
+    ///
+    /// let root = std::env::temp_dir().join("star_cloudburst_materialize_doc");
+    /// let stats = torrent.materialize(&root, Allocation::Sparse)?;
+    /// assert_eq!(stats.created, 2);
+    /// assert_eq!(std::fs::metadata(root.join("cats/extras/cats.nfo"))?.len(), 8);
+    ///
+    /// let stats = torrent.materialize(&root, Allocation::Sparse)?;
+    /// assert_eq!(stats.unchanged, 2);
+    /// # std::fs::remove_dir_all(root)?;
+    /// # Ok::<(), std::io::Error>(())
+    /// ```
+    pub fn materialize(&self, root: &Path, allocation: Allocation) -> io::Result<LayoutStats> {
+        let mut file_counts = ParseStats::default();
+        file_counts.count_files(&self.info);
+        let mut stats = LayoutStats {
+            padding_skipped: file_counts.padding_count,
+            ..Default::default()
+        };
+
+        for file in self.layout()? {
+            let path = root.join(&file.path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            let existing = match fs::metadata(&path) {
+                Ok(metadata) => Some(metadata.len()),
+                Err(e) if e.kind() == ErrorKind::NotFound => None,
+                Err(e) => return Err(e),
+            };
+            match existing {
+                Some(length) if length == file.length => stats.unchanged += 1,
+                Some(_) => {
+                    OpenOptions::new()
+                        .write(true)
+                        .open(&path)?
+                        .set_len(file.length)?;
+                    stats.resized += 1;
+                }
+                None => {
+                    allocate(&File::create(&path)?, file.length, allocation)?;
+                    stats.created += 1;
+                }
+            }
+
+            if file.executable {
+                set_executable(&path)?;
+            }
+        }
+
+        debug!(
+            target: MATERIALIZE_TARGET,
+            "Materialized {} under {}: {stats:?}",
+            self.name(),
+            root.display()
+        );
+        Ok(stats)
+    }
+}
+
+#[inline]
+fn is_executable(attr: Option<&TorrentFileAttributes>) -> bool {
+    attr.is_some_and(|attr| attr.contains(FileAttribute::Executable))
+}
+
+// Reject components that would escape the root directory.
+pub(crate) fn safe_component(component: &str) -> io::Result<&str> {
+    let mut components = Path::new(component).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(component),
+        _ => Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("unsafe path component in torrent: {component:?}"),
+        )),
+    }
+}
+
+pub(crate) fn nested_path<S: AsRef<str>>(name: &str, path: &[S]) -> io::Result<PathBuf> {
+    let mut nested = PathBuf::from(name);
+    for component in path {
+        nested.push(safe_component(component.as_ref())?);
+    }
+    Ok(nested)
+}
+
+fn allocate(file: &File, length: u64, allocation: Allocation) -> io::Result<()> {
+    match allocation {
+        Allocation::Sparse => file.set_len(length),
+        Allocation::Full => {
+            static ZEROES: [u8; 64 * 1024] = [0; 64 * 1024];
+            let mut file = file;
+            let mut remaining = length;
+            while remaining > 0 {
+                let len = remaining.min(ZEROES.len() as u64);
+                file.write_all(&ZEROES[..len as usize])?;
+                remaining -= len;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(unix)]
+fn set_executable(path: &Path) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    // Executable by everyone who can read it.
+    let mode = permissions.mode();
+    permissions.set_mode(mode | ((mode & 0o444) >> 2));
+    fs::set_permissions(path, permissions)
+}
+
+// Other platforms don't have an executable permission.
+#[cfg(not(unix))]
+fn set_executable(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Torrent;
+
+    #[test]
+    fn layout_rejects_escaping_paths() -> Result<(), serde_bencode::Error> {
+        let cats = b"d4:infod5:filesld6:lengthi16e4:pathl2:..8:cats.mkveee4:name4:cats6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+        let torrent: Torrent = serde_bencode::from_bytes(cats)?;
+
+        assert!(torrent.layout().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn layout_skips_padding_and_marks_executables() -> Result<(), serde_bencode::Error> {
+        let cats = b"d4:infod5:filesld4:attr1:x6:lengthi16e4:pathl7:meow.sheed4:attr1:p6:lengthi16368e4:pathl4:.pad5:16368eed6:lengthi8e4:pathl8:cats.nfoeee4:name4:cats6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+        let torrent: Torrent = serde_bencode::from_bytes(cats)?;
+        let layout = torrent.layout().expect("Paths are safe.");
+
+        assert_eq!(layout.len(), 2);
+        assert!(layout[0].executable);
+        assert_eq!(layout[1].path, std::path::Path::new("cats/cats.nfo"));
+        Ok(())
+    }
+}
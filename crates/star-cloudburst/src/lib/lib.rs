@@ -1,13 +1,57 @@
 //! `star-cloudburst` provides strongly typed data structures for serializing and deserializing torrents.
-#![feature(let_chains, once_cell_try)]
+//!
+//! ## Features
+//! - `arbitrary`: [arbitrary::Arbitrary] implementations that generate structurally valid torrents
+//!   for fuzzing and property testing.
+//! - `create`: Create torrents from files on disk with [create]. Implies `crypto`.
+//! - `crypto` (default): Calculate info hashes with RustCrypto digests, index torrents with
+//!   [library::TorrentLibrary], and check downloaded data with [verify].
+//! - `crypto-sign`: Sign torrents and verify their signatures with
+//!   [crypto::signature::Signature::verify]. Implies `crypto`.
+//! - `iter-extras` (default): Iterator adapters for hexadecimal nibbles.
+//! - `rayon`: Hash the pieces of [crypto::piecehasher::PieceHasher] concurrently. Implies `crypto`.
+//! - `regex`: Regular expression [retracker::TrackerRule]s.
+//! - `uri` (default): Validate URIs with the `http` crate and convert internationalized hosts to
+//!   punycode with `idna`. [uri::UriWrapper] is a plain [String] without this feature.
+//! - `binary`: Dependencies for the `cloudburst` binary.
+//!
+//! Disable the default features for a minimal build that only parses torrents.
 
+pub mod batch;
+pub mod borrowed;
+#[cfg(feature = "create")]
+pub mod create;
+pub mod createdby;
+pub mod creationdate;
+pub mod crossseed;
 pub mod crypto;
+pub mod dht;
+pub mod diff;
 pub mod files;
 pub mod hexadecimal;
+pub mod layout;
+#[cfg(feature = "crypto")]
+pub mod library;
+pub mod lint;
+pub mod magnet;
+pub mod metadata;
 pub mod metainfo;
+pub mod parseobserver;
+pub mod parseoptions;
+pub mod parsestats;
+pub mod peerid;
 pub mod pieces;
+pub mod repair;
+pub mod retracker;
+pub mod summary;
 pub mod torrent;
+pub mod torrenthandle;
+pub mod tracker;
 pub mod uri;
+pub mod validation;
+#[cfg(feature = "crypto")]
+pub mod verify;
+pub mod webseed;
 
 pub use pieces::{PieceLength, Pieces};
 pub use torrent::Torrent;
@@ -0,0 +1,324 @@
+//! A collection of torrents indexed by info hash.
+//!
+//! [TorrentLibrary] is the bookkeeping that torrent managers need on top of
+//! parsing: torrents are stored once and may be looked up by either their
+//! version 1 (SHA-1) or version 2 (SHA-256) info hash. Hybrid torrents are
+//! reachable through both.
+
+pub mod index;
+
+use crate::{
+    crypto::sha::{Sha1, Sha2},
+    metainfo::{infohash::InfoHashVersioned, TorrentVersion},
+    retracker::{Substituted, TrackerChange, TrackerRule},
+    Torrent,
+};
+use log::debug;
+use serde::de::value::Error as DeError;
+use std::collections::{hash_map, HashMap};
+
+const LIBRARY_TARGET: &str = "star_cloudburst::library::TorrentLibrary";
+
+/// Torrents indexed by their info hashes.
+///
+/// Torrents are stored by the SHA-256 hash of their info dict which is unique
+/// for every info dict regardless of meta version. Version 1 and hybrid
+/// torrents are additionally indexed by their SHA-1 info hash.
+///
+/// ```rust
+/// use star_cloudburst::library::TorrentLibrary;
+/// use serde_bencode::Error;
+///
+/// let cats = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+///
+/// let mut library = TorrentLibrary::new();
+/// library.insert_bytes(cats)?;
+///
+/// let torrent = library.iter().next().expect("One torrent was inserted.");
+/// let info_hash = torrent.info_hash()?;
+/// assert_eq!("cats.mkv", library.get(&info_hash).expect("Torrent is indexed.").name());
+/// # Ok::<(), Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TorrentLibrary {
+    torrents: HashMap<Sha2, Torrent>,
+    v1_index: HashMap<Sha1, Sha2>,
+}
+
+impl TorrentLibrary {
+    /// An empty library.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a library from many torrents.
+    ///
+    /// Torrents with the same info dict are deduplicated; the last one wins.
+    pub fn load<I>(torrents: I) -> Result<Self, serde_bencode::Error>
+    where
+        I: IntoIterator<Item = Torrent>,
+    {
+        let mut library = Self::new();
+        for torrent in torrents {
+            library.insert(torrent)?;
+        }
+
+        debug!(target: LIBRARY_TARGET, "Loaded {} torrents", library.len());
+        Ok(library)
+    }
+
+    /// Insert a torrent and return the torrent it replaced, if any.
+    ///
+    /// This calculates the torrent's info hash if it wasn't calculated already.
+    pub fn insert(&mut self, torrent: Torrent) -> Result<Option<Torrent>, serde_bencode::Error> {
+        let info_hash = torrent
+            .info_hash_internal
+            .get_or_calculate(&torrent.info, &torrent.info_raw)?;
+        let sha2 = info_hash.sha2.clone();
+
+        if torrent.info.version() != TorrentVersion::V2 {
+            self.v1_index.insert(info_hash.sha1.clone(), sha2.clone());
+        }
+
+        debug!(
+            target: LIBRARY_TARGET,
+            "Inserting {} ({sha2})",
+            torrent.name()
+        );
+        Ok(self.torrents.insert(sha2, torrent))
+    }
+
+    /// Parse a bencoded torrent and insert it.
+    #[inline]
+    pub fn insert_bytes(&mut self, bytes: &[u8]) -> Result<Option<Torrent>, serde_bencode::Error> {
+        self.insert(Torrent::from_bytes(bytes)?)
+    }
+
+    /// Look up a torrent by any of its info hashes.
+    ///
+    /// Hybrid info hashes only match if both hashes belong to the same torrent.
+    pub fn get(&self, info_hash: &InfoHashVersioned<'_>) -> Option<&Torrent> {
+        match info_hash {
+            InfoHashVersioned::V1(sha1) => self.get_v1(sha1),
+            InfoHashVersioned::V2(sha2) => self.get_v2(sha2),
+            InfoHashVersioned::Hybrid { sha1, sha2 } => self
+                .get_v2(sha2)
+                .filter(|_| self.v1_index.get(*sha1) == Some(*sha2)),
+        }
+    }
+
+    /// Look up a version 1 or hybrid torrent by its SHA-1 info hash.
+    #[inline]
+    pub fn get_v1(&self, sha1: &Sha1) -> Option<&Torrent> {
+        self.v1_index
+            .get(sha1)
+            .and_then(|sha2| self.torrents.get(sha2))
+    }
+
+    /// Look up a version 2 or hybrid torrent by its SHA-256 info hash.
+    #[inline]
+    pub fn get_v2(&self, sha2: &Sha2) -> Option<&Torrent> {
+        self.torrents.get(sha2).filter(|torrent| {
+            matches!(
+                torrent.info.version(),
+                TorrentVersion::V2 | TorrentVersion::Hybrid
+            )
+        })
+    }
+
+    /// Whether a torrent with the info hash is in the library.
+    #[inline]
+    pub fn contains(&self, info_hash: &InfoHashVersioned<'_>) -> bool {
+        self.get(info_hash).is_some()
+    }
+
+    /// Remove a torrent by any of its info hashes.
+    pub fn remove(&mut self, info_hash: &InfoHashVersioned<'_>) -> Option<Torrent> {
+        if !self.contains(info_hash) {
+            return None;
+        }
+
+        let sha2 = match info_hash {
+            InfoHashVersioned::V1(sha1) => self.v1_index.get(*sha1)?,
+            InfoHashVersioned::V2(sha2) | InfoHashVersioned::Hybrid { sha2, .. } => *sha2,
+        }
+        .clone();
+        let torrent = self.torrents.remove(&sha2)?;
+
+        // The info hash was cached on insertion.
+        if let Ok(info_hash) = torrent
+            .info_hash_internal
+            .get_or_calculate(&torrent.info, &torrent.info_raw)
+        {
+            self.v1_index.remove(&info_hash.sha1);
+        }
+
+        debug!(
+            target: LIBRARY_TARGET,
+            "Removed {} ({sha2})",
+            torrent.name()
+        );
+        Some(torrent)
+    }
+
+    /// Number of torrents in the library.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.torrents.len()
+    }
+
+    /// Whether the library is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.torrents.is_empty()
+    }
+
+    /// Iterator over the torrents in arbitrary order.
+    #[inline]
+    pub fn iter(&self) -> hash_map::Values<'_, Sha2, Torrent> {
+        self.torrents.values()
+    }
+
+    /// Replace trackers in every torrent according to `rules`.
+    ///
+    /// Returns the changes of each torrent that changed by its SHA-256 info hash. No torrent is
+    /// changed if any substituted tracker is invalid. Trackers aren't part of the info dict so the
+    /// index is unaffected. See [crate::retracker::substitute_trackers].
+    ///
+    /// ```rust
+    /// use star_cloudburst::{library::TorrentLibrary, retracker::TrackerRule};
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mews = b"d8:announce15:udp://mews.test4:infod4:name8:mews.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mut library = TorrentLibrary::new();
+    /// library.insert_bytes(cats)?;
+    /// library.insert_bytes(mews)?;
+    ///
+    /// let rules = [TrackerRule::Host {
+    ///     from: "cats.test".to_owned(),
+    ///     to: "purr.test".to_owned(),
+    /// }];
+    /// let changes = library.substitute_trackers(&rules)?;
+    /// assert_eq!(changes.len(), 1);
+    /// assert!(library
+    ///     .iter()
+    ///     .flat_map(|torrent| torrent.trackers())
+    ///     .all(|tracker| tracker.to_string() != "udp://cats.test"));
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn substitute_trackers(
+        &mut self,
+        rules: &[TrackerRule],
+    ) -> Result<Vec<(Sha2, Vec<TrackerChange>)>, DeError> {
+        let substituted = self
+            .torrents
+            .iter()
+            .map(|(sha2, torrent)| {
+                Substituted::new(torrent, rules).map(|subs| (sha2.clone(), subs))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let changes: Vec<_> = substituted
+            .into_iter()
+            .filter_map(|(sha2, substituted)| {
+                let torrent = self.torrents.get_mut(&sha2)?;
+                let changes = substituted.apply(torrent);
+                (!changes.is_empty()).then_some((sha2, changes))
+            })
+            .collect();
+
+        debug!(
+            target: LIBRARY_TARGET,
+            "Substituted trackers in {} torrents",
+            changes.len()
+        );
+        Ok(changes)
+    }
+
+    /// Iterator over the torrents' info hashes in arbitrary order.
+    pub fn info_hashes(&self) -> impl Iterator<Item = InfoHashVersioned<'_>> {
+        self.torrents
+            .values()
+            .filter_map(|torrent| torrent.info_hash().ok())
+    }
+}
+
+impl<'library> IntoIterator for &'library TorrentLibrary {
+    type Item = &'library Torrent;
+    type IntoIter = hash_map::Values<'library, Sha2, Torrent>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for TorrentLibrary {
+    type Item = Torrent;
+    type IntoIter = hash_map::IntoValues<Sha2, Torrent>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.torrents.into_values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TorrentLibrary;
+    use crate::Torrent;
+
+    const CATS: &[u8] = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    // Binary pieces aren't valid UTF-8.
+    const DOGS: &[u8] = b"d8:announce9:localhost4:infod4:name8:dogs.mkv6:pieces20:\xff\xfe\xfd\xfc\xfb\xfa\xf9\xf8\xf7\xf6\xf5\xf4\xf3\xf2\xf1\xf0\xef\xee\xed\xec12:piece lengthi16384eee";
+
+    #[test]
+    fn library_insert_get_remove() -> Result<(), serde_bencode::Error> {
+        let mut library = TorrentLibrary::new();
+        assert!(library.insert_bytes(CATS)?.is_none());
+        assert!(library.insert_bytes(DOGS)?.is_none());
+        // Same info dict so the torrent is replaced.
+        assert!(library.insert_bytes(CATS)?.is_some());
+        assert_eq!(library.len(), 2);
+
+        let dogs = Torrent::from_bytes(DOGS)?;
+        let info_hash = dogs.info_hash()?;
+        assert_eq!(library.get(&info_hash).map(Torrent::name), Some("dogs.mkv"));
+
+        let removed = library.remove(&info_hash).expect("Dogs was inserted.");
+        assert_eq!(removed, dogs);
+        assert!(!library.contains(&info_hash));
+        assert_eq!(library.len(), 1);
+        assert_eq!(library.info_hashes().count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn library_indexes_by_version() -> Result<(), serde_bencode::Error> {
+        // Version 1 with an unknown key is held by `MetaInfo::Hybrid`.
+        let mews = b"d4:infod6:lengthi42e4:name8:mews.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x007:wheelie4:meowee";
+        let purr = b"d4:infod9:file treed8:purr.mkvd0:d6:lengthi42e11:pieces root32:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00eee12:meta versioni2e4:name8:purr.mkv12:piece lengthi16384eee";
+
+        let mut library = TorrentLibrary::new();
+        library.insert_bytes(mews)?;
+        library.insert_bytes(purr)?;
+
+        let mews = Torrent::from_bytes(mews)?;
+        let mews_hashes = mews
+            .info_hash_internal
+            .get_or_calculate(&mews.info, &mews.info_raw)?;
+        assert!(library.get_v1(&mews_hashes.sha1).is_some());
+        assert!(library.get_v2(&mews_hashes.sha2).is_none());
+
+        let purr = Torrent::from_bytes(purr)?;
+        let purr_hashes = purr
+            .info_hash_internal
+            .get_or_calculate(&purr.info, &purr.info_raw)?;
+        assert!(library.get_v1(&purr_hashes.sha1).is_none());
+        assert!(library.get_v2(&purr_hashes.sha2).is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,521 @@
+//! Persistent cache of parsed torrent metadata.
+//!
+//! [LibraryIndex] remembers a few properties of every torrent file in a
+//! directory keyed by the file's path and modification time. Refreshing the
+//! index only parses files that are new or changed since the last refresh so
+//! rescanning a large library is cheap. The index is stored as bencode.
+
+use super::TorrentLibrary;
+use crate::{
+    crypto::sha::{Sha1, Sha2},
+    metainfo::infohash::InfoHashVersioned,
+    Torrent,
+};
+use log::{debug, error};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+const LIBRARYINDEX_TARGET: &str = "star_cloudburst::library::index::LibraryIndex";
+
+/// Cached metadata of one torrent file.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IndexEntry {
+    /// Modification time of the torrent file in nanoseconds since the Unix
+    /// epoch.
+    pub modified: u64,
+    /// SHA-1 info hash for version 1 and hybrid torrents.
+    #[serde(default, rename = "info hash v1")]
+    pub info_hash_v1: Option<Sha1>,
+    /// SHA-256 info hash for version 2 and hybrid torrents.
+    #[serde(default, rename = "info hash v2")]
+    pub info_hash_v2: Option<Sha2>,
+    /// Suggested name of the torrent file or directory.
+    pub name: String,
+    /// Total length of the shared files in bytes.
+    pub size: u64,
+    /// SHA-256 of the shared files' paths and lengths.
+    #[serde(rename = "files digest")]
+    pub files_digest: Sha2,
+}
+
+impl IndexEntry {
+    /// Collect the cached metadata of a torrent.
+    ///
+    /// This calculates the torrent's info hash if it wasn't calculated
+    /// already.
+    pub fn new(torrent: &Torrent, modified: u64) -> Result<Self, serde_bencode::Error> {
+        let (info_hash_v1, info_hash_v2) = match torrent.info_hash()? {
+            InfoHashVersioned::V1(sha1) => (Some(sha1.clone()), None),
+            InfoHashVersioned::V2(sha2) => (None, Some(sha2.clone())),
+            InfoHashVersioned::Hybrid { sha1, sha2 } => (Some(sha1.clone()), Some(sha2.clone())),
+        };
+
+        let mut size = 0u64;
+        let mut hasher = Sha256::new();
+        for (path, length) in torrent.info.file_list() {
+            size = size.saturating_add(length);
+            // Components are separated by a byte that can't appear in UTF-8.
+            for component in path {
+                hasher.update(component.as_bytes());
+                hasher.update([0xff]);
+            }
+            hasher.update(length.to_be_bytes());
+        }
+        let files_digest: [u8; 32] = hasher.finalize().into();
+
+        Ok(Self {
+            modified,
+            info_hash_v1,
+            info_hash_v2,
+            name: torrent.name().to_owned(),
+            size,
+            files_digest: files_digest.into(),
+        })
+    }
+
+    /// Info hash of the indexed torrent.
+    ///
+    /// Entries without either hash, which only a hand edited index may have,
+    /// yield `None`.
+    pub fn info_hash(&self) -> Option<InfoHashVersioned<'_>> {
+        match (&self.info_hash_v1, &self.info_hash_v2) {
+            (Some(sha1), Some(sha2)) => Some(InfoHashVersioned::Hybrid { sha1, sha2 }),
+            (Some(sha1), None) => Some(InfoHashVersioned::V1(sha1)),
+            (None, Some(sha2)) => Some(InfoHashVersioned::V2(sha2)),
+            (None, None) => None,
+        }
+    }
+
+    // Whether the indexed torrent has `info_hash`. Version 1 and version 2
+    // hashes match hybrid torrents like they do in [TorrentLibrary::get].
+    fn has_info_hash(&self, info_hash: &InfoHashVersioned<'_>) -> bool {
+        match info_hash {
+            InfoHashVersioned::V1(sha1) => self.info_hash_v1.as_ref() == Some(*sha1),
+            InfoHashVersioned::V2(sha2) => self.info_hash_v2.as_ref() == Some(*sha2),
+            InfoHashVersioned::Hybrid { sha1, sha2 } => {
+                self.info_hash_v1.as_ref() == Some(*sha1)
+                    && self.info_hash_v2.as_ref() == Some(*sha2)
+            }
+        }
+    }
+
+    // Whether both entries describe the same info dict.
+    #[inline]
+    fn same_torrent(&self, other: &Self) -> bool {
+        self.info_hash_v1 == other.info_hash_v1 && self.info_hash_v2 == other.info_hash_v2
+    }
+}
+
+/// Counts of what happened during [LibraryIndex::refresh].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RefreshStats {
+    /// New or changed files that were parsed.
+    pub parsed: usize,
+    /// Unchanged files that were served from the index without parsing.
+    pub cached: usize,
+    /// Index entries of files that no longer exist. Their torrents are removed
+    /// from the library too.
+    pub removed: usize,
+    /// Files that couldn't be listed, read, parsed, or inserted.
+    pub failed: usize,
+}
+
+/// Torrent metadata keyed by file path.
+///
+/// Paths that aren't valid UTF-8 aren't indexed and are parsed on every
+/// refresh.
+///
+/// The index answers questions about the library, such as which torrents it
+/// has, without parsing unchanged files. Torrents of unchanged files are only
+/// parsed into a [TorrentLibrary] when they're [LibraryIndex::load]ed.
+///
+/// ```rust,no_run
+/// use star_cloudburst::library::{index::LibraryIndex, TorrentLibrary};
+/// use std::path::Path;
+///
+/// let index_path = Path::new("library.index");
+/// let mut index = LibraryIndex::open(index_path)?;
+/// let mut library = TorrentLibrary::new();
+///
+/// let stats = index.refresh(Path::new("torrents"), &mut library)?;
+/// println!(
+///     "Parsed {} torrents; {} were unchanged",
+///     stats.parsed, stats.cached
+/// );
+/// index.save(index_path)?;
+///
+/// // Parse the first indexed torrent if this refresh didn't.
+/// if let Some(info_hash) = index.iter().find_map(|(_, entry)| entry.info_hash()) {
+///     let torrent = index.load(&info_hash, &mut library)?;
+///     println!("{}", torrent.map_or("", |torrent| torrent.name()));
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct LibraryIndex {
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+impl LibraryIndex {
+    /// Load an index from disk.
+    ///
+    /// A missing file yields an empty index.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        match fs::read(path) {
+            Ok(bytes) => serde_bencode::from_bytes(&bytes)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the index to disk.
+    ///
+    /// The index is written to a temporary file first so that a crash doesn't
+    /// corrupt the previous index.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let bytes =
+            serde_bencode::to_bytes(self).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, bytes)?;
+        fs::rename(temp, path)
+    }
+
+    /// Cached metadata for a torrent file if its modification time matches.
+    #[inline]
+    pub fn get(&self, path: &Path, modified: u64) -> Option<&IndexEntry> {
+        self.entries
+            .get(path.to_str()?)
+            .filter(|entry| entry.modified == modified)
+    }
+
+    /// Number of indexed torrent files.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the index is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterator over the indexed paths and their metadata.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &IndexEntry)> {
+        self.entries
+            .iter()
+            .map(|(path, entry)| (path.as_str(), entry))
+    }
+
+    /// Index entry and path of the torrent file with `info_hash`.
+    pub fn find(&self, info_hash: &InfoHashVersioned<'_>) -> Option<(&str, &IndexEntry)> {
+        self.iter()
+            .find(|(_, entry)| entry.has_info_hash(info_hash))
+    }
+
+    /// Torrent with `info_hash` from `library`, parsing its file and
+    /// inserting it first if it isn't there yet.
+    ///
+    /// Torrents that aren't indexed yield `None`. Files that were changed
+    /// since the last refresh so that they no longer have `info_hash` are an
+    /// error.
+    pub fn load<'library>(
+        &self,
+        info_hash: &InfoHashVersioned<'_>,
+        library: &'library mut TorrentLibrary,
+    ) -> io::Result<Option<&'library Torrent>> {
+        if library.contains(info_hash) {
+            return Ok(library.get(info_hash));
+        }
+        let Some((path, entry)) = self.find(info_hash) else {
+            return Ok(None);
+        };
+
+        let invalid_data = |e| io::Error::new(ErrorKind::InvalidData, e);
+        let torrent = Torrent::from_bytes(&fs::read(path)?).map_err(invalid_data)?;
+        if !IndexEntry::new(&torrent, entry.modified)
+            .map_err(invalid_data)?
+            .same_torrent(entry)
+        {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{path} changed since the index was refreshed"),
+            ));
+        }
+
+        debug!(target: LIBRARYINDEX_TARGET, "Loaded {path}: {}", entry.name);
+        library.insert(torrent).map_err(invalid_data)?;
+        Ok(library.get(info_hash))
+    }
+
+    /// Update the index with the `.torrent` files in a directory.
+    ///
+    /// New or changed files are parsed, indexed, and inserted into `library`.
+    /// Unchanged files keep their cached [IndexEntry] and aren't parsed, so
+    /// `library` only receives the torrents that changed; see
+    /// [LibraryIndex::load] for the others.
+    ///
+    /// Torrents of changed files and of files in `dir` that no longer exist are
+    /// removed from `library` along with their index entries unless another
+    /// indexed file has the same info dict. Files that fail to be listed, read,
+    /// parsed, or inserted are logged and counted but don't abort the refresh.
+    pub fn refresh(
+        &mut self,
+        dir: &Path,
+        library: &mut TorrentLibrary,
+    ) -> io::Result<RefreshStats> {
+        let mut stats = RefreshStats::default();
+        let mut seen = Vec::new();
+
+        for entry in dir.read_dir()? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    error!(
+                        target: LIBRARYINDEX_TARGET,
+                        "Failed listing {}\nError: {e}",
+                        dir.display()
+                    );
+                    stats.failed += 1;
+                    continue;
+                }
+            };
+            if !path.is_file() || path.extension().and_then(|ext| ext.to_str()) != Some("torrent") {
+                continue;
+            }
+
+            // Seen before checking it so that a transient error keeps its entry.
+            if let Some(key) = path.to_str() {
+                seen.push(key.to_owned());
+            }
+            let modified = match modified_nanos(&path) {
+                Ok(modified) => modified,
+                Err(e) => {
+                    error!(
+                        target: LIBRARYINDEX_TARGET,
+                        "Failed reading the modification time of {}\nError: {e}",
+                        path.display()
+                    );
+                    stats.failed += 1;
+                    continue;
+                }
+            };
+            if self.get(&path, modified).is_some() {
+                stats.cached += 1;
+                continue;
+            }
+
+            let parsed = fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| {
+                    let torrent = Torrent::from_bytes(&bytes).map_err(|e| e.to_string())?;
+                    let entry = IndexEntry::new(&torrent, modified).map_err(|e| e.to_string())?;
+                    library.insert(torrent).map_err(|e| e.to_string())?;
+                    Ok(entry)
+                });
+
+            match parsed {
+                Ok(entry) => {
+                    debug!(
+                        target: LIBRARYINDEX_TARGET,
+                        "Indexed {}: {}",
+                        path.display(),
+                        entry.name
+                    );
+                    if let Some(key) = path.to_str() {
+                        if let Some(old) = self.entries.insert(key.to_owned(), entry) {
+                            self.forget(&old, library);
+                        }
+                    }
+                    stats.parsed += 1;
+                }
+                Err(e) => {
+                    error!(
+                        target: LIBRARYINDEX_TARGET,
+                        "Failed indexing {}\nError: {e}",
+                        path.display()
+                    );
+                    // The previous contents of the file are stale.
+                    if let Some(old) = path.to_str().and_then(|key| self.entries.remove(key)) {
+                        self.forget(&old, library);
+                    }
+                    stats.failed += 1;
+                }
+            }
+        }
+
+        // Forget files in this directory that were deleted.
+        seen.sort_unstable();
+        let mut deleted = Vec::new();
+        self.entries.retain(|key, entry| {
+            let keep = Path::new(key).parent() != Some(dir) || seen.binary_search(key).is_ok();
+            if !keep {
+                deleted.push(entry.clone());
+            }
+            keep
+        });
+        for entry in &deleted {
+            self.forget(entry, library);
+        }
+        stats.removed = deleted.len();
+
+        debug!(target: LIBRARYINDEX_TARGET, "Refreshed {}: {stats:?}", dir.display());
+        Ok(stats)
+    }
+
+    // Remove the torrent of a replaced or deleted entry from `library` unless
+    // another file still indexes it.
+    fn forget(&self, old: &IndexEntry, library: &mut TorrentLibrary) {
+        if self.entries.values().any(|entry| entry.same_torrent(old)) {
+            return;
+        }
+
+        if let Some(torrent) = old
+            .info_hash()
+            .and_then(|info_hash| library.remove(&info_hash))
+        {
+            debug!(
+                target: LIBRARYINDEX_TARGET,
+                "Removed {} from the library",
+                torrent.name()
+            );
+        }
+    }
+}
+
+fn modified_nanos(path: &Path) -> io::Result<u64> {
+    let modified = fs::metadata(path)?.modified()?;
+    let nanos = modified
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    // Bencode integers are signed.
+    Ok(nanos.min(i64::MAX as u128) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LibraryIndex;
+    use crate::{library::TorrentLibrary, Torrent};
+    use std::{
+        fs::{self, File},
+        io,
+        time::{Duration, SystemTime},
+    };
+
+    const CATS: &[u8] = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\xff\xfe\xfd\xfc\xfb\xfa\xf9\xf8\xf7\xf6\xf5\xf4\xf3\xf2\xf1\xf0\xef\xee\xed\xec12:piece lengthi16384eee";
+    const DOGS: &[u8] = b"d8:announce9:localhost4:infod4:name8:dogs.mkv6:pieces20:\xff\xfe\xfd\xfc\xfb\xfa\xf9\xf8\xf7\xf6\xf5\xf4\xf3\xf2\xf1\xf0\xef\xee\xed\xec12:piece lengthi16384eee";
+    const MEWS: &[u8] = b"d8:announce9:localhost4:infod4:name8:mews.mkv6:pieces20:\xff\xfe\xfd\xfc\xfb\xfa\xf9\xf8\xf7\xf6\xf5\xf4\xf3\xf2\xf1\xf0\xef\xee\xed\xec12:piece lengthi16384eee";
+
+    #[test]
+    fn refresh_skips_unchanged_files() -> io::Result<()> {
+        let dir =
+            std::env::temp_dir().join(format!("star-cloudburst-index-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let cats = dir.join("cats.torrent");
+        fs::write(&cats, CATS)?;
+        fs::write(dir.join("cats.txt"), b"not a torrent")?;
+
+        let mut index = LibraryIndex::default();
+        let mut library = TorrentLibrary::new();
+
+        let stats = index.refresh(&dir, &mut library)?;
+        assert_eq!((stats.parsed, stats.cached), (1, 0));
+        assert_eq!(library.len(), 1);
+
+        let stats = index.refresh(&dir, &mut library)?;
+        assert_eq!((stats.parsed, stats.cached), (0, 1));
+
+        let index_path = dir.join("library.index");
+        index.save(&index_path)?;
+        assert_eq!(index, LibraryIndex::open(&index_path)?);
+
+        fs::remove_file(&cats)?;
+        let stats = index.refresh(&dir, &mut library)?;
+        assert_eq!(stats.removed, 1);
+        assert!(index.is_empty());
+
+        fs::remove_dir_all(&dir)
+    }
+
+    #[test]
+    fn refresh_keeps_library_in_sync() -> Result<(), Box<dyn std::error::Error>> {
+        let dir =
+            std::env::temp_dir().join(format!("star-cloudburst-index-sync-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let cats_path = dir.join("cats.torrent");
+        let dogs_path = dir.join("dogs.torrent");
+        fs::write(&cats_path, CATS)?;
+        fs::write(&dogs_path, DOGS)?;
+
+        let mut index = LibraryIndex::default();
+        let mut library = TorrentLibrary::new();
+        index.refresh(&dir, &mut library)?;
+        assert_eq!(library.len(), 2);
+
+        // A reopened index serves unchanged files without parsing them.
+        let index_path = dir.join("library.index");
+        index.save(&index_path)?;
+        let mut reopened = LibraryIndex::open(&index_path)?;
+        let mut fresh = TorrentLibrary::new();
+        let stats = reopened.refresh(&dir, &mut fresh)?;
+        assert_eq!((stats.parsed, stats.cached), (0, 2));
+        assert!(fresh.is_empty());
+
+        // Torrents are parsed on demand instead.
+        let cats = Torrent::from_bytes(CATS)?;
+        let (path, entry) = reopened.find(&cats.info_hash()?).expect("Cats is indexed.");
+        assert_eq!(
+            (path, entry.name.as_str()),
+            (cats_path.to_str().unwrap(), "cats.mkv")
+        );
+        let loaded = reopened.load(&cats.info_hash()?, &mut fresh)?;
+        assert_eq!(loaded.map(Torrent::name), Some("cats.mkv"));
+        assert_eq!(fresh.len(), 1);
+        fs::remove_file(&index_path)?;
+
+        // Files that fail don't stop the refresh.
+        let bad_path = dir.join("bad.torrent");
+        fs::write(&bad_path, b"not a torrent")?;
+        let stats = index.refresh(&dir, &mut library)?;
+        assert_eq!((stats.parsed, stats.cached, stats.failed), (0, 2, 1));
+        fs::remove_file(&bad_path)?;
+
+        // Replace cats with mews and delete dogs.
+        fs::write(&cats_path, MEWS)?;
+        File::options()
+            .write(true)
+            .open(&cats_path)?
+            .set_modified(SystemTime::now() + Duration::from_secs(60))?;
+        fs::remove_file(&dogs_path)?;
+
+        let stats = index.refresh(&dir, &mut library)?;
+        assert_eq!((stats.parsed, stats.cached, stats.removed), (1, 0, 1));
+        assert_eq!(index.len(), 1);
+        assert_eq!(library.len(), 1);
+
+        let (dogs, mews) = (Torrent::from_bytes(DOGS)?, Torrent::from_bytes(MEWS)?);
+        assert!(!library.contains(&cats.info_hash()?));
+        assert!(!library.contains(&dogs.info_hash()?));
+        assert!(library.contains(&mews.info_hash()?));
+
+        let stats = index.refresh(&dir, &mut library)?;
+        assert_eq!((stats.parsed, stats.cached, stats.removed), (0, 1, 0));
+        assert_eq!(library.len(), 1);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,155 @@
+//! Best practice warnings for torrents.
+//!
+//! Lints are about torrents that are valid but awkward: clients accept them,
+//! yet they're slow to check, leak passkeys, or can't find peers. Unlike
+//! parse errors, every lint is a judgement call that a tool may ignore.
+//! [Torrent::lint] reports them without changing the torrent; see
+//! [Torrent::repair] for fixes.
+
+use crate::{uri::UriWrapper, PieceLength, Torrent};
+use log::debug;
+use std::fmt::{self, Display, Formatter};
+
+const LINT_TARGET: &str = "star_cloudburst::Torrent::lint";
+
+/// Number of files above which a torrent is unwieldy for most clients.
+pub const MAX_REASONABLE_FILES: usize = 10_000;
+
+/// A best practice violation found by [Torrent::lint].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Lint {
+    /// The piece length is far from [PieceLength::recommended_for] the
+    /// torrent's size. See [PieceLength::is_unusual_for].
+    UnusualPieceLength {
+        piece_length: PieceLength,
+        recommended: PieceLength,
+    },
+    /// A tracker is announced to over plain HTTP, which exposes passkeys.
+    InsecureTracker(UriWrapper),
+    /// The torrent has neither trackers nor DHT nodes so peers can only be
+    /// found through DHT, PEX, or web seeds.
+    NoTrackersOrNodes,
+    /// The torrent has more than [MAX_REASONABLE_FILES] files.
+    TooManyFiles(usize),
+    /// The torrent's name or a file or directory name starts or ends with
+    /// whitespace, which some file systems strip.
+    UntrimmedName(String),
+}
+
+impl Display for Lint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Lint::UnusualPieceLength {
+                piece_length,
+                recommended,
+            } => write!(
+                f,
+                "piece length {} is far from the recommended {}",
+                piece_length.get(),
+                recommended.get()
+            ),
+            Lint::InsecureTracker(tracker) => write!(f, "tracker {tracker} doesn't use HTTPS"),
+            Lint::NoTrackersOrNodes => f.write_str("no trackers or DHT nodes"),
+            Lint::TooManyFiles(count) => write!(f, "{count} files"),
+            Lint::UntrimmedName(name) => write!(f, "name {name:?} has surrounding whitespace"),
+        }
+    }
+}
+
+impl Torrent {
+    /// Best practice violations in the order they're listed by [Lint].
+    ///
+    /// An empty list means nothing looked off.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{lint::Lint, Torrent};
+    ///
+    /// let cats = b"d8:announce25:http://cats.test/announce4:infod6:lengthi42e4:name9:cats.mkv 12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!(
+    ///     torrent.lint(),
+    ///     [
+    ///         Lint::InsecureTracker("http://cats.test/announce".parse()?),
+    ///         Lint::UntrimmedName("cats.mkv ".to_owned())
+    ///     ]
+    /// );
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn lint(&self) -> Vec<Lint> {
+        let mut lints = Vec::new();
+
+        let files = self.info.file_list();
+        let total_length = files
+            .iter()
+            .fold(0u64, |total, (_, length)| total.saturating_add(*length));
+        let piece_length = self.info.piece_length();
+        if piece_length.is_unusual_for(total_length) {
+            lints.push(Lint::UnusualPieceLength {
+                piece_length,
+                recommended: PieceLength::recommended_for(total_length),
+            });
+        }
+
+        lints.extend(
+            self.unique_trackers()
+                .filter(|tracker| {
+                    let tracker = tracker.to_string();
+                    tracker
+                        .get(..7)
+                        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"))
+                })
+                .cloned()
+                .map(Lint::InsecureTracker),
+        );
+
+        if self.trackers().next().is_none() && self.nodes.as_ref().is_none_or(Vec::is_empty) {
+            lints.push(Lint::NoTrackersOrNodes);
+        }
+
+        if files.len() > MAX_REASONABLE_FILES {
+            lints.push(Lint::TooManyFiles(files.len()));
+        }
+
+        let mut untrimmed: Vec<_> = std::iter::once(self.name())
+            .chain(files.iter().flat_map(|(path, _)| path.iter().copied()))
+            .filter(|name| name.trim() != *name)
+            .collect();
+        untrimmed.sort_unstable();
+        untrimmed.dedup();
+        lints.extend(
+            untrimmed
+                .into_iter()
+                .map(|name| Lint::UntrimmedName(name.to_owned())),
+        );
+
+        debug!(target: LINT_TARGET, "Linted {}: {lints:?}", self.name());
+        lints
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Lint;
+    use crate::{PieceLength, Torrent};
+
+    #[test]
+    fn lint_pieces_and_peers() -> Result<(), serde_bencode::Error> {
+        // 16 MiB pieces for 42 bytes and no way to find peers.
+        let cats = "d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16777216e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+        let torrent: Torrent = serde_bencode::from_str(cats)?;
+
+        assert_eq!(
+            torrent.lint(),
+            [
+                Lint::UnusualPieceLength {
+                    piece_length: PieceLength::new(16 * 1024 * 1024).unwrap(),
+                    recommended: PieceLength::new(16 * 1024).unwrap(),
+                },
+                Lint::NoTrackersOrNodes
+            ]
+        );
+        Ok(())
+    }
+}
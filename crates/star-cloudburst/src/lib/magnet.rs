@@ -0,0 +1,511 @@
+//! Magnet links.
+//!
+//! Magnet links identify a torrent by its info hash rather than sharing the
+//! torrent file. Clients download the info dict from peers
+//! ([BEP-0009](https://www.bittorrent.org/beps/bep_0009.html)) and use the link's display name
+//! and trackers until then.
+//!
+//! `magnet:?xt=urn:btih:<info hash>&dn=<name>&tr=<tracker>`
+//!
+//! Version 2 and hybrid torrents are identified by the multihash of their
+//! SHA-256 info hash instead of or in addition to the SHA-1 info hash
+//! ([BEP-0052](https://www.bittorrent.org/beps/bep_0052.html)).
+//!
+//! `magnet:?xt=urn:btmh:1220<info hash>`
+//!
+//! A [crate::Torrent] converts into a [MagnetLink] with [TryFrom]. Going the other way needs the
+//! info dict, so a magnet link is tracked as a pending [crate::torrenthandle::TorrentHandle] until
+//! [crate::torrenthandle::TorrentHandle::attach_info] completes it.
+
+use crate::{
+    crypto::{sha1::Sha1, sha2::Sha2},
+    hexadecimal::HexBytes,
+    uri::UriWrapper,
+};
+#[cfg(feature = "crypto")]
+use crate::{metainfo::infohash::InfoHashVersioned, Torrent};
+use log::error;
+use serde::de::{value::Error as DeError, Error as DeErrorTrait};
+use std::{
+    fmt::{self, Display, Formatter, Write},
+    str::FromStr,
+};
+
+const MAGNETLINK_PARSE_TARGET: &str = "star_cloudburst::magnet::MagnetLink::from_str";
+pub(crate) const MAGNET_PREFIX: &str = "magnet:?";
+const BTIH_URN: &str = "urn:btih:";
+const BTMH_URN: &str = "urn:btmh:";
+const BASE32_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Torrent identified by a magnet link.
+///
+/// Parsed links remember their parameters so that formatting a link writes the
+/// original string byte for byte. Fields that were changed are written in place
+/// of the parameter they came from and new fields are appended. Parameters that
+/// this type doesn't know, such as `ws` or `x.pe`, are kept in order. Equality
+/// only compares the fields.
+///
+/// ```rust
+/// use star_cloudburst::magnet::MagnetLink;
+///
+/// let magnet = "magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056&dn=Cats%20%26%20Mews&tr=udp%3A%2F%2Fcats.test%3A6969";
+/// let cats: MagnetLink = magnet.parse()?;
+///
+/// assert_eq!(cats.name.as_deref(), Some("Cats & Mews"));
+/// assert_eq!(cats.trackers[0].to_string(), "udp://cats.test:6969");
+/// assert_eq!(cats.to_string(), magnet);
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct MagnetLink {
+    /// Version 1 info hash from `xt=urn:btih:`.
+    pub info_hash: Option<Sha1>,
+    /// Version 2 info hash from `xt=urn:btmh:`.
+    pub info_hash_v2: Option<Sha2>,
+    /// Display name from `dn`.
+    pub name: Option<String>,
+    /// Trackers from `tr` in order.
+    pub trackers: Vec<UriWrapper>,
+    // Parameters in their original order. Empty if the link was built rather than parsed.
+    params: Vec<MagnetParam>,
+}
+
+// A parameter as it was written and its decoded value.
+#[derive(Debug, Clone)]
+struct MagnetParam {
+    raw: Box<str>,
+    value: MagnetParamValue,
+}
+
+#[derive(Debug, Clone)]
+enum MagnetParamValue {
+    InfoHash(Sha1),
+    InfoHashV2(Sha2),
+    Name(String),
+    Tracker(UriWrapper),
+    Unknown,
+}
+
+impl MagnetLink {
+    /// Magnet link for a version 1 info hash.
+    #[inline]
+    pub fn new(info_hash: Sha1) -> Self {
+        Self {
+            info_hash: Some(info_hash),
+            info_hash_v2: None,
+            name: None,
+            trackers: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Magnet link for a version 2 info hash.
+    #[inline]
+    pub fn new_v2(info_hash: Sha2) -> Self {
+        Self {
+            info_hash: None,
+            info_hash_v2: Some(info_hash),
+            name: None,
+            trackers: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+
+    /// Parameters that aren't represented by fields as they were written, such
+    /// as `ws=...`.
+    pub fn unknown_params(&self) -> impl Iterator<Item = &str> + '_ {
+        self.params
+            .iter()
+            .filter(|param| matches!(param.value, MagnetParamValue::Unknown))
+            .map(|param| param.raw.as_ref())
+    }
+}
+
+impl PartialEq for MagnetLink {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.info_hash == other.info_hash
+            && self.info_hash_v2 == other.info_hash_v2
+            && self.name == other.name
+            && self.trackers == other.trackers
+    }
+}
+
+impl Eq for MagnetLink {}
+
+#[cfg(feature = "crypto")]
+impl TryFrom<&Torrent> for MagnetLink {
+    type Error = serde_bencode::Error;
+
+    /// Magnet link with the [Torrent::info_hash], name, and unique trackers of `torrent`.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{magnet::MagnetLink, Torrent};
+    ///
+    /// let cats = b"d8:announce20:udp://cats.test:69694:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// let magnet = MagnetLink::try_from(&torrent)?;
+    /// assert_eq!(
+    ///     magnet.to_string(),
+    ///     "magnet:?xt=urn:btih:1af47a06d99e96aff4f2c3dbbe726093c6211538&dn=cats.mkv&tr=udp%3A%2F%2Fcats.test%3A6969"
+    /// );
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    fn try_from(torrent: &Torrent) -> Result<Self, Self::Error> {
+        let (info_hash, info_hash_v2) = match torrent.info_hash()? {
+            InfoHashVersioned::V1(sha1) => (Some(sha1.clone()), None),
+            InfoHashVersioned::V2(sha2) => (None, Some(sha2.clone())),
+            InfoHashVersioned::Hybrid { sha1, sha2 } => (Some(sha1.clone()), Some(sha2.clone())),
+        };
+
+        Ok(Self {
+            info_hash,
+            info_hash_v2,
+            name: Some(torrent.name().to_owned()),
+            trackers: torrent.unique_trackers().cloned().collect(),
+            params: Vec::new(),
+        })
+    }
+}
+
+impl FromStr for MagnetLink {
+    type Err = DeError;
+
+    fn from_str(magnet: &str) -> Result<Self, Self::Err> {
+        let params = magnet.strip_prefix(MAGNET_PREFIX).ok_or_else(|| {
+            error!(target: MAGNETLINK_PARSE_TARGET, "Not a magnet link: {magnet}");
+            DeError::custom(format!("magnet links start with {MAGNET_PREFIX}"))
+        })?;
+
+        let mut link = MagnetLink {
+            info_hash: None,
+            info_hash_v2: None,
+            name: None,
+            trackers: Vec::new(),
+            params: Vec::new(),
+        };
+        // Empty links don't have any parameters rather than one empty parameter.
+        for raw in params.split('&').filter(|_| !params.is_empty()) {
+            let value = match raw.split_once('=') {
+                Some(("xt", value)) if value.starts_with(BTIH_URN) => {
+                    let info_hash = decode_btih(&value[BTIH_URN.len()..])?;
+                    link.info_hash = Some(info_hash.clone());
+                    MagnetParamValue::InfoHash(info_hash)
+                }
+                Some(("xt", value)) if value.starts_with(BTMH_URN) => {
+                    let info_hash = Sha2::from_multihash(&value[BTMH_URN.len()..])?;
+                    link.info_hash_v2 = Some(info_hash.clone());
+                    MagnetParamValue::InfoHashV2(info_hash)
+                }
+                Some(("dn", value)) => {
+                    let name = percent_decode(&value.replace('+', " "))?;
+                    link.name = Some(name.clone());
+                    MagnetParamValue::Name(name)
+                }
+                Some(("tr", value)) => {
+                    let tracker: UriWrapper = percent_decode(value)?.parse()?;
+                    link.trackers.push(tracker.clone());
+                    MagnetParamValue::Tracker(tracker)
+                }
+                _ => MagnetParamValue::Unknown,
+            };
+            link.params.push(MagnetParam {
+                raw: raw.into(),
+                value,
+            });
+        }
+
+        if link.info_hash.is_none() && link.info_hash_v2.is_none() {
+            error!(
+                target: MAGNETLINK_PARSE_TARGET,
+                "Magnet link doesn't have an info hash: {magnet}"
+            );
+            return Err(DeError::missing_field("xt"));
+        }
+        Ok(link)
+    }
+}
+
+// Writes parameters separated by `&`.
+struct ParamWriter<'f, 'a> {
+    f: &'f mut Formatter<'a>,
+    first: bool,
+}
+
+impl ParamWriter<'_, '_> {
+    fn raw(&mut self, raw: &str) -> fmt::Result {
+        self.separator()?;
+        self.f.write_str(raw)
+    }
+
+    fn info_hash(&mut self, info_hash: &Sha1) -> fmt::Result {
+        self.separator()?;
+        write!(self.f, "xt={BTIH_URN}{info_hash}")
+    }
+
+    fn info_hash_v2(&mut self, info_hash: &Sha2) -> fmt::Result {
+        self.separator()?;
+        write!(self.f, "xt={BTMH_URN}{}", info_hash.to_multihash())
+    }
+
+    fn encoded(&mut self, key: &str, value: &str) -> fmt::Result {
+        self.separator()?;
+        write!(self.f, "{key}=")?;
+        percent_encode(self.f, value)
+    }
+
+    fn separator(&mut self) -> fmt::Result {
+        if !std::mem::take(&mut self.first) {
+            self.f.write_char('&')?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for MagnetLink {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(MAGNET_PREFIX)?;
+        let mut writer = ParamWriter { f, first: true };
+        let mut info_hash_written = false;
+        let mut info_hash_v2_written = false;
+        let mut name_written = false;
+        let mut trackers = self.trackers.iter();
+
+        // Unchanged parameters are written as they were parsed and changed parameters
+        // are re-encoded in the same position.
+        for param in &self.params {
+            match &param.value {
+                MagnetParamValue::InfoHash(parsed) => match &self.info_hash {
+                    Some(info_hash) if info_hash == parsed => writer.raw(&param.raw)?,
+                    Some(info_hash) if !info_hash_written => writer.info_hash(info_hash)?,
+                    _ => continue,
+                },
+                MagnetParamValue::InfoHashV2(parsed) => match &self.info_hash_v2 {
+                    Some(info_hash) if info_hash == parsed => writer.raw(&param.raw)?,
+                    Some(info_hash) if !info_hash_v2_written => writer.info_hash_v2(info_hash)?,
+                    _ => continue,
+                },
+                MagnetParamValue::Name(parsed) => match &self.name {
+                    Some(name) if name == parsed => writer.raw(&param.raw)?,
+                    Some(name) if !name_written => writer.encoded("dn", name)?,
+                    _ => continue,
+                },
+                MagnetParamValue::Tracker(parsed) => match trackers.next() {
+                    Some(tracker) if tracker == parsed => writer.raw(&param.raw)?,
+                    Some(tracker) => writer.encoded("tr", &tracker.to_string())?,
+                    None => {}
+                },
+                MagnetParamValue::Unknown => writer.raw(&param.raw)?,
+            }
+
+            match param.value {
+                MagnetParamValue::InfoHash(_) => info_hash_written = true,
+                MagnetParamValue::InfoHashV2(_) => info_hash_v2_written = true,
+                MagnetParamValue::Name(_) => name_written = true,
+                _ => {}
+            }
+        }
+
+        // Fields that weren't parsed from a parameter.
+        if let Some(info_hash) = self.info_hash.as_ref().filter(|_| !info_hash_written) {
+            writer.info_hash(info_hash)?;
+        }
+        if let Some(info_hash) = self.info_hash_v2.as_ref().filter(|_| !info_hash_v2_written) {
+            writer.info_hash_v2(info_hash)?;
+        }
+        if let Some(name) = self.name.as_ref().filter(|_| !name_written) {
+            writer.encoded("dn", name)?;
+        }
+        for tracker in trackers {
+            writer.encoded("tr", &tracker.to_string())?;
+        }
+
+        Ok(())
+    }
+}
+
+// Info hashes are 40 hexadecimal or 32 base32 characters.
+fn decode_btih(hash: &str) -> Result<Sha1, DeError> {
+    let bytes: [u8; 20] = match hash.len() {
+        40 => HexBytes::from_hex_str(hash)?.as_slice().try_into(),
+        32 => base32_decode(hash)?.as_slice().try_into(),
+        len => {
+            return Err(DeError::invalid_length(
+                len,
+                &"40 hex or 32 base32 characters",
+            ))
+        }
+    }
+    .map_err(|_| DeError::custom("info hash isn't 20 bytes"))?;
+
+    Ok(bytes.into())
+}
+
+fn base32_decode(encoded: &str) -> Result<Vec<u8>, DeError> {
+    let mut decoded = Vec::with_capacity(encoded.len() * 5 / 8);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for c in encoded.bytes() {
+        let value = BASE32_ALPHABET
+            .iter()
+            .position(|&letter| letter == c.to_ascii_uppercase())
+            .ok_or_else(|| DeError::custom(format!("invalid base32 character: {:?}", c as char)))?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+
+    Ok(decoded)
+}
+
+pub(crate) fn percent_decode(encoded: &str) -> Result<String, DeError> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut bytes = encoded.bytes();
+
+    while let Some(byte) = bytes.next() {
+        if byte == b'%' {
+            let hex = [bytes.next(), bytes.next()];
+            let [Some(upper), Some(lower)] = hex else {
+                return Err(DeError::custom(format!(
+                    "truncated percent encoding in {encoded:?}"
+                )));
+            };
+            decoded.extend_from_slice(
+                HexBytes::from_hex_str(
+                    std::str::from_utf8(&[upper, lower]).map_err(DeError::custom)?,
+                )?
+                .as_slice(),
+            );
+        } else {
+            decoded.push(byte);
+        }
+    }
+
+    String::from_utf8(decoded).map_err(DeError::custom)
+}
+
+// Encode everything but RFC 3986's unreserved characters.
+pub(crate) fn percent_encode<W: Write>(f: &mut W, decoded: &str) -> fmt::Result {
+    for byte in decoded.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            f.write_char(byte.into())?;
+        } else {
+            write!(f, "%{byte:02X}")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MagnetLink;
+
+    const CATS: &str = "magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056";
+
+    #[test]
+    fn roundtrip_unknown_params() {
+        let magnet = format!("{CATS}&x.pe=10.0.0.1:6881&dn=cats+%26+mews&tr=udp://cats.test:6969&ws=http%3a%2f%2fcats.test&flag");
+        let mut cats: MagnetLink = magnet.parse().unwrap();
+        assert_eq!(cats.name.as_deref(), Some("cats & mews"));
+        assert_eq!(cats.to_string(), magnet);
+        assert_eq!(
+            cats.unknown_params().collect::<Vec<_>>(),
+            ["x.pe=10.0.0.1:6881", "ws=http%3a%2f%2fcats.test", "flag"]
+        );
+
+        // Changed fields are written where they were.
+        cats.name = Some("mews".to_owned());
+        cats.trackers.push("udp://mews.test:6969".parse().unwrap());
+        assert_eq!(
+            cats.to_string(),
+            format!("{CATS}&x.pe=10.0.0.1:6881&dn=mews&tr=udp://cats.test:6969&ws=http%3a%2f%2fcats.test&flag&tr=udp%3A%2F%2Fmews.test%3A6969")
+        );
+
+        cats.name = None;
+        cats.trackers.clear();
+        assert_eq!(
+            cats.to_string(),
+            format!("{CATS}&x.pe=10.0.0.1:6881&ws=http%3a%2f%2fcats.test&flag")
+        );
+    }
+
+    #[test]
+    fn built_links() {
+        let cats: MagnetLink = CATS.parse().unwrap();
+        let mut built = MagnetLink::new(cats.info_hash.clone().unwrap());
+        assert_eq!(built, cats);
+
+        built.name = Some("cats".to_owned());
+        assert_eq!(built.to_string(), format!("{CATS}&dn=cats"));
+    }
+
+    #[test]
+    fn base32_info_hash() {
+        let hex: MagnetLink = "magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056"
+            .parse()
+            .unwrap();
+        let base32: MagnetLink = "magnet:?xt=urn:btih:ZHQVOY7XELZD5GFCTXWN7LRUDOMNKMCW"
+            .parse()
+            .unwrap();
+        assert_eq!(hex.info_hash, base32.info_hash);
+    }
+
+    #[test]
+    fn multihash_info_hash() {
+        let v2 = "magnet:?xt=urn:btmh:1220caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e&dn=bittorrent-v2-test";
+        let hybrid: MagnetLink = format!("{CATS}&xt=urn:btmh:1220caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e")
+            .parse()
+            .unwrap();
+        let mut v2: MagnetLink = v2.parse().unwrap();
+
+        assert_eq!(v2.info_hash, None);
+        assert_eq!(v2.info_hash_v2, hybrid.info_hash_v2);
+        assert!(hybrid.info_hash.is_some());
+
+        v2.name = None;
+        assert_eq!(
+            v2.to_string(),
+            "magnet:?xt=urn:btmh:1220caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e"
+        );
+        assert_eq!(
+            MagnetLink::new_v2(v2.info_hash_v2.clone().unwrap()).to_string(),
+            v2.to_string()
+        );
+    }
+
+    #[test]
+    fn reject_invalid_links() {
+        for invalid in [
+            "https://cats.test",
+            "magnet:?dn=cats",
+            "magnet:?xt=urn:btih:c9e15763",
+            "magnet:?xt=urn:btmh:1114c9e15763f722f23e98a29decdfae341b98d53056",
+            "magnet:?xt=urn:btih:c9e15763f722f23e98a29decdfae341b98d53056&dn=%E",
+        ] {
+            assert!(invalid.parse::<MagnetLink>().is_err(), "{invalid}");
+        }
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn torrent_versions() -> Result<(), serde_bencode::Error> {
+        use crate::Torrent;
+
+        let hybrid: Torrent = serde_bencode::from_str("d4:infod9:file treed8:cats.mkvd0:d6:lengthi42eeee6:lengthi42e12:meta versioni2e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee")?;
+        let magnet = MagnetLink::try_from(&hybrid)?;
+        assert!(magnet.info_hash.is_some() && magnet.info_hash_v2.is_some());
+        assert!(magnet.trackers.is_empty());
+
+        // Version 1 torrents with unknown keys are hybrids without a version 2 hash.
+        let v1: Torrent = serde_bencode::from_str("d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa9:publisher4:catsee")?;
+        let magnet = MagnetLink::try_from(&v1)?;
+        assert!(magnet.info_hash.is_some() && magnet.info_hash_v2.is_none());
+
+        Ok(())
+    }
+}
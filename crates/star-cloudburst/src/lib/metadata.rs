@@ -0,0 +1,325 @@
+//! Info dict exchange between peers.
+//!
+//! Peers that support the `ut_metadata` extension send each other the
+//! bencoded info dict in 16 KiB pieces so that torrents added from magnet
+//! links can be completed without a torrent file. The size of the info dict is
+//! announced as `metadata_size` in the extension handshake.
+//!
+//! [MetadataAssembler] collects received pieces and [MetadataPieces] splits an
+//! info dict for serving.
+//!
+//! BEPs:
+//! [BEP-0009](https://www.bittorrent.org/beps/bep_0009.html)
+//! [BEP-0010](https://www.bittorrent.org/beps/bep_0010.html)
+
+#[cfg(feature = "crypto")]
+use crate::torrenthandle::TorrentHandle;
+use crate::Torrent;
+#[cfg(feature = "crypto")]
+use log::debug;
+use log::warn;
+use serde::de::{value::Error as DeError, Error as DeErrorTrait};
+use std::sync::Arc;
+
+const ASSEMBLER_TARGET: &str = "star_cloudburst::metadata::MetadataAssembler";
+
+/// Length of every metadata piece except the last.
+pub const METADATA_PIECE_LENGTH: usize = 16 * 1024;
+
+/// Largest `metadata_size` that [MetadataAssembler] accepts.
+///
+/// Peers choose `metadata_size` so the buffer is limited to keep a peer from
+/// exhausting memory. Info dicts of even very large torrents are a few MiB.
+pub const MAX_METADATA_SIZE: usize = 16 * 1024 * 1024;
+
+/// Info dict split into metadata pieces.
+///
+/// ```rust
+/// use star_cloudburst::{metadata::METADATA_PIECE_LENGTH, Torrent};
+///
+/// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+/// let torrent = Torrent::from_bytes(cats)?;
+///
+/// let pieces = torrent.metadata_pieces()?;
+/// assert_eq!(pieces.len(), 1);
+/// assert_eq!(pieces.metadata_size(), cats.len() - 8);
+/// assert_eq!(pieces.get(0), Some(&cats[7..cats.len() - 1]));
+/// assert!(pieces.get(1).is_none());
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataPieces(Arc<[u8]>);
+
+impl MetadataPieces {
+    /// Split a bencoded info dict.
+    #[inline]
+    pub fn new(info_bytes: impl Into<Arc<[u8]>>) -> Self {
+        Self(info_bytes.into())
+    }
+
+    /// Length of the info dict in bytes, announced as `metadata_size`.
+    #[inline]
+    pub fn metadata_size(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Number of metadata pieces.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.0.len().div_ceil(METADATA_PIECE_LENGTH)
+    }
+
+    /// Whether the info dict is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Data of the piece at `index`.
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&[u8]> {
+        self.0.chunks(METADATA_PIECE_LENGTH).nth(index)
+    }
+
+    /// Iterator over the data of every piece in order.
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.0.chunks(METADATA_PIECE_LENGTH)
+    }
+
+    /// The whole bencoded info dict.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+/// Collects metadata pieces received from peers into an info dict.
+///
+/// Pieces may arrive in any order and from different peers. Completed info
+/// dicts are checked against the magnet link with
+/// [MetadataAssembler::attach].
+///
+/// ```rust
+/// use star_cloudburst::{metadata::MetadataAssembler, torrenthandle::TorrentHandle};
+///
+/// let info = b"d6:lengthi42e4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee";
+/// let mut handle: TorrentHandle =
+///     "magnet:?xt=urn:btih:d0c91f70c742a016a965192bb598c6cd9a5a6287".parse()?;
+///
+/// let mut assembler = MetadataAssembler::new(info.len())?;
+/// assert_eq!(assembler.missing().collect::<Vec<_>>(), [0]);
+/// assert!(assembler.insert(0, info)?);
+///
+/// # #[cfg(feature = "crypto")]
+/// # {
+/// let torrent = assembler.attach(&mut handle)?;
+/// assert_eq!(torrent.name(), "cats.mkv");
+/// # }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataAssembler {
+    buffer: Vec<u8>,
+    received: Vec<bool>,
+    remaining: usize,
+}
+
+impl MetadataAssembler {
+    /// Prepare to receive an info dict of `metadata_size` bytes.
+    ///
+    /// Sizes of zero or larger than [MAX_METADATA_SIZE] are errors.
+    pub fn new(metadata_size: usize) -> Result<Self, DeError> {
+        if metadata_size == 0 || metadata_size > MAX_METADATA_SIZE {
+            warn!(
+                target: ASSEMBLER_TARGET,
+                "Rejected metadata size of {metadata_size} bytes"
+            );
+            return Err(DeErrorTrait::custom(format!(
+                "metadata size should be between 1 and {MAX_METADATA_SIZE} bytes but is \
+                 {metadata_size}"
+            )));
+        }
+
+        let pieces = metadata_size.div_ceil(METADATA_PIECE_LENGTH);
+        Ok(Self {
+            buffer: vec![0; metadata_size],
+            received: vec![false; pieces],
+            remaining: pieces,
+        })
+    }
+
+    /// Length of the info dict in bytes.
+    #[inline]
+    pub fn metadata_size(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Number of metadata pieces.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.received.len()
+    }
+
+    /// Always false; info dicts have at least one piece.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.received.is_empty()
+    }
+
+    /// Whether every piece was received.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Indices of the pieces that still have to be requested.
+    pub fn missing(&self) -> impl Iterator<Item = usize> + '_ {
+        self.received
+            .iter()
+            .enumerate()
+            .filter_map(|(index, received)| (!received).then_some(index))
+    }
+
+    /// Store the piece at `index` and return whether the info dict is
+    /// complete.
+    ///
+    /// Every piece except the last must be [METADATA_PIECE_LENGTH] bytes.
+    /// Pieces that were already received are replaced.
+    pub fn insert(&mut self, index: usize, data: &[u8]) -> Result<bool, DeError> {
+        let start = index
+            .checked_mul(METADATA_PIECE_LENGTH)
+            .filter(|_| index < self.len())
+            .ok_or_else(|| {
+                DeErrorTrait::custom(format!(
+                    "metadata piece {index} is out of range for {} pieces",
+                    self.len()
+                ))
+            })?;
+        let end = self.buffer.len().min(start + METADATA_PIECE_LENGTH);
+        if data.len() != end - start {
+            return Err(DeErrorTrait::invalid_length(
+                data.len(),
+                &format!("metadata piece {index} to be {} bytes", end - start).as_str(),
+            ));
+        }
+
+        self.buffer[start..end].copy_from_slice(data);
+        if !std::mem::replace(&mut self.received[index], true) {
+            self.remaining -= 1;
+        }
+        Ok(self.is_complete())
+    }
+
+    /// The bencoded info dict if every piece was received.
+    #[inline]
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        self.is_complete().then_some(self.buffer)
+    }
+
+    /// Complete a pending torrent with the assembled info dict.
+    ///
+    /// The info dict must match the magnet link's info hashes (see
+    /// [TorrentHandle::attach_info]). If it doesn't, every piece is discarded
+    /// so that they're requested again, possibly from other peers.
+    #[cfg(feature = "crypto")]
+    pub fn attach<'handle>(
+        &mut self,
+        handle: &'handle mut TorrentHandle,
+    ) -> Result<&'handle Torrent, serde_bencode::Error> {
+        if !self.is_complete() {
+            return Err(serde_bencode::Error::Custom(format!(
+                "{} of {} metadata pieces are missing",
+                self.remaining,
+                self.len()
+            )));
+        }
+
+        if let Err(error) = handle.attach_info(&self.buffer) {
+            debug!(
+                target: ASSEMBLER_TARGET,
+                "Discarding {} metadata pieces: {error}",
+                self.len()
+            );
+            // Attaching to a complete handle fails regardless of the pieces.
+            if handle.is_pending() {
+                self.received.fill(false);
+                self.remaining = self.len();
+            }
+            return Err(error);
+        }
+        Ok(handle.torrent().expect("Info dict was just attached."))
+    }
+}
+
+impl Torrent {
+    /// Split the info dict into metadata pieces for peers.
+    ///
+    /// The info dict is sent as it was parsed if it wasn't modified so that
+    /// it matches the info hash.
+    pub fn metadata_pieces(&self) -> Result<MetadataPieces, serde_bencode::Error> {
+        match self.info_raw.bytes() {
+            Some(bytes) => Ok(MetadataPieces::new(bytes)),
+            None => serde_bencode::to_bytes(&self.info).map(MetadataPieces::new),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MetadataAssembler, MetadataPieces, METADATA_PIECE_LENGTH};
+
+    #[test]
+    fn split_and_assemble() -> Result<(), serde::de::value::Error> {
+        let info: Vec<u8> = (0..METADATA_PIECE_LENGTH * 2 + 42)
+            .map(|index| index as u8)
+            .collect();
+        let pieces = MetadataPieces::new(info.clone());
+        assert_eq!(pieces.len(), 3);
+        assert_eq!(pieces.iter().map(<[u8]>::len).last(), Some(42));
+
+        let mut assembler = MetadataAssembler::new(pieces.metadata_size())?;
+        assert!(assembler.insert(3, &[0; 42]).is_err());
+        assert!(assembler.insert(2, &[0; 41]).is_err());
+        assert!(assembler.insert(0, &[0; 42]).is_err());
+
+        // Out of order and repeated pieces.
+        assert!(!assembler.insert(2, pieces.get(2).unwrap())?);
+        assert!(!assembler.insert(2, pieces.get(2).unwrap())?);
+        assert!(!assembler.insert(0, pieces.get(0).unwrap())?);
+        assert_eq!(assembler.missing().collect::<Vec<_>>(), [1]);
+        assert!(assembler.clone().into_bytes().is_none());
+
+        assert!(assembler.insert(1, pieces.get(1).unwrap())?);
+        assert_eq!(assembler.into_bytes(), Some(info));
+        Ok(())
+    }
+
+    #[test]
+    fn metadata_size_limits() {
+        assert!(MetadataAssembler::new(0).is_err());
+        assert!(MetadataAssembler::new(super::MAX_METADATA_SIZE + 1).is_err());
+        assert_eq!(
+            MetadataAssembler::new(1).map(|assembler| assembler.len()),
+            Ok(1)
+        );
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn mismatched_info_discarded() -> Result<(), serde::de::value::Error> {
+        use crate::torrenthandle::TorrentHandle;
+
+        let info = b"d6:lengthi42e4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee";
+        let mut handle: TorrentHandle =
+            "magnet:?xt=urn:btih:0000000000000000000000000000000000000000".parse()?;
+
+        let mut assembler = MetadataAssembler::new(info.len())?;
+        assert!(assembler.attach(&mut handle).is_err());
+        assembler.insert(0, info)?;
+        assert!(assembler.attach(&mut handle).is_err());
+        assert!(!assembler.is_complete());
+        assert!(handle.is_pending());
+        Ok(())
+    }
+}
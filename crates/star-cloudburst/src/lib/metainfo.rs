@@ -1,39 +1,134 @@
 pub mod hybrid;
 pub mod infohash;
+pub mod merklev1;
 pub mod metav1;
 pub mod metav2;
-mod serde_bool_int;
+pub mod metaversion;
+pub(crate) mod rawinfo;
+pub(crate) mod serde_bool_int;
+pub mod torrentversion;
+pub(crate) mod valuede;
 
 pub use hybrid::Hybrid;
+pub use merklev1::MerkleV1;
 pub use metav1::MetaV1;
 pub use metav2::MetaV2;
+pub use metaversion::MetaVersion;
+pub use torrentversion::TorrentVersion;
 
-use crate::{files::filedisplayinfo::{AsFileDisplayInfo, FileDisplayInfoIter}, PieceLength};
-use serde::{Deserialize, Serialize};
+use crate::{
+    files::{
+        filedisplayinfo::{AsFileDisplayInfo, FileDisplayInfoIter},
+        MetaV1FileRepr,
+    },
+    PieceLength,
+};
+use log::trace;
+use serde::{
+    de::{Error as DeErrorTrait, Unexpected},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_bencode::value::Value;
+use valuede::ValueDeserializer;
+
+const METAINFO_DE_TARGET: &str = "star_cloudburst::metainfo::MetaInfo::deserialize";
+
+/// Keys of the info dict for every meta version.
+pub(crate) const INFO_FIELDS: &[&str] = &[
+    "files",
+    "file tree",
+    "length",
+    "meta version",
+    "md5sum",
+    "name",
+    "pieces",
+    "piece length",
+    "private",
+    "root hash",
+    "source",
+];
 
 /// Metainfo on files shared by torrents.
 ///
 /// The base structure is defined in [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html).
 /// Version 2.0 extensions to BEP-0003 are defined in [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html).
 ///
-/// More torrent versions may be added in the future so [Info] is non-exhaustive.
+/// More torrent versions may be added in the future so [MetaInfo] is non-exhaustive.
+///
+/// The variant is chosen from the keys of the info dict rather than by trying each variant in
+/// turn. Dicts that don't have the exact keys of [MetaV1], [MetaV2], or [MerkleV1] are kept as a
+/// [Hybrid].
 #[non_exhaustive]
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[serde(untagged)]
 pub enum MetaInfo {
     /// Meta version 1
     MetaV1(MetaV1),
     /// Meta version 2
     MetaV2(MetaV2),
+    /// Meta version 1 with a Merkle root hash instead of piece hashes.
+    MerkleV1(MerkleV1),
     /// Backwards compatible amalgamate of all versions.
     Hybrid(Hybrid),
 }
 
+impl<'de> Deserialize<'de> for MetaInfo {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let info = match Value::deserialize(deserializer)? {
+            Value::Dict(info) => info,
+            _ => {
+                return Err(DeErrorTrait::invalid_type(
+                    Unexpected::Other("a non-dict value"),
+                    &"an info dict",
+                ))
+            }
+        };
+
+        let variant = TorrentVersion::variant(&info);
+        trace!(
+            target: METAINFO_DE_TARGET,
+            "Deserializing MetaInfo as version {variant}."
+        );
+
+        // The chosen variant's own errors are more useful than "no variant matched".
+        let info = ValueDeserializer::from(Value::Dict(info));
+        match variant {
+            TorrentVersion::V1 => MetaV1::deserialize(info).map(MetaInfo::MetaV1),
+            TorrentVersion::MerkleV1 => MerkleV1::deserialize(info).map(MetaInfo::MerkleV1),
+            TorrentVersion::V2 => MetaV2::deserialize(info).map(MetaInfo::MetaV2),
+            TorrentVersion::Hybrid => Hybrid::deserialize(info).map(MetaInfo::Hybrid),
+        }
+        .map_err(DeErrorTrait::custom)
+    }
+}
+
 impl MetaInfo {
+    /// Format of the shared files regardless of the variant that holds them.
+    ///
+    /// A [Hybrid] without a file tree is [TorrentVersion::V1] and one without `pieces` is
+    /// [TorrentVersion::V2].
+    #[inline]
+    pub fn version(&self) -> TorrentVersion {
+        match self {
+            MetaInfo::MetaV1(_) => TorrentVersion::V1,
+            MetaInfo::MetaV2(_) => TorrentVersion::V2,
+            MetaInfo::MerkleV1(_) => TorrentVersion::MerkleV1,
+            MetaInfo::Hybrid(info) => match (&info.file_tree, &info.pieces) {
+                (None, _) => TorrentVersion::V1,
+                (Some(_), None) => TorrentVersion::V2,
+                (Some(_), Some(_)) => TorrentVersion::Hybrid,
+            },
+        }
+    }
+
     /// Meta info version agnostic iterator over basic file properties.
     ///
     /// This creates an iterator that yields [crate::files::filedisplayinfo::FileDisplayInfo].
-    pub fn iter_files(&self) -> FileDisplayInfoIter {
+    pub fn iter_files(&self) -> FileDisplayInfoIter<'_> {
         match self {
             MetaInfo::MetaV1(info) => {
                 let branches = info.as_file_display();
@@ -43,24 +138,87 @@ impl MetaInfo {
                 let branches = info.file_tree.as_file_display();
                 FileDisplayInfoIter { branches }
             }
+            MetaInfo::MerkleV1(info) => {
+                let branches = info.as_file_display();
+                FileDisplayInfoIter { branches }
+            }
             MetaInfo::Hybrid(info) => {
-                if let Some(tree) = &info.file_tree {
-                    let branches = tree.as_file_display();
-                    FileDisplayInfoIter { branches }
-                } else {
-                    panic!("Hybrid torrent doesn't have `FileTree`.\nFIX THIS LATER.");
-                }
+                let branches = info.as_file_display();
+                FileDisplayInfoIter { branches }
             }
         }
     }
 
-    /// Meta info version as a str.
+    /// Number of files yielded by [MetaInfo::iter_files] including padding files.
+    ///
+    /// Files are counted without building [crate::files::FileDisplayInfo]s.
+    pub fn file_count(&self) -> usize {
+        match self {
+            MetaInfo::MetaV1(info) => match &info.files {
+                MetaV1FileRepr::Single(_) => 1,
+                MetaV1FileRepr::Multiple(files) => files.len(),
+            },
+            MetaInfo::MetaV2(info) => info.file_tree.file_count(),
+            MetaInfo::MerkleV1(info) => info.files.as_ref().map_or(1, Vec::len),
+            MetaInfo::Hybrid(info) => match (&info.file_tree, &info.files) {
+                (Some(tree), _) => tree.file_count(),
+                (None, Some(files)) => files.len(),
+                (None, None) => 1,
+            },
+        }
+    }
+
+    /// Path components and length of every shared file.
+    ///
+    /// Unlike [MetaInfo::iter_files], this omits the root (`./`) and the empty names of version 2
+    /// files so that paths of every meta version are comparable. Padding files are skipped.
+    pub(crate) fn file_list(&self) -> Vec<(Vec<&str>, u64)> {
+        self.iter_files()
+            .filter(|file| !file.padding)
+            .map(|file| {
+                let path = file
+                    .file_path
+                    .into_iter()
+                    .chain([file.name])
+                    .filter(|part| !part.is_empty() && *part != "./")
+                    .collect();
+                (path, file.length)
+            })
+            .collect()
+    }
+
+    /// Total length in bytes of the shared files excluding padding files.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d4:infod5:filesld6:lengthi42e4:pathl8:cats.mkveed6:lengthi6e4:pathl8:mews.mkveee4:name4:cats12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    /// assert_eq!(torrent.info.total_length(), 48);
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn total_length(&self) -> u64 {
+        self.file_list()
+            .into_iter()
+            .fold(0u64, |total, (_, length)| total.saturating_add(length))
+    }
+
+    /// [MetaInfo::version] as a str.
     #[inline]
     pub fn meta_version_str(&self) -> &str {
+        self.version().as_str()
+    }
+
+    /// BEP-0052 meta version.
+    ///
+    /// Version 1 and Merkle torrents don't have a meta version.
+    #[inline]
+    pub fn meta_version(&self) -> Option<MetaVersion> {
         match self {
-            MetaInfo::MetaV1(_) => "1",
-            MetaInfo::MetaV2(_) => "2",
-            MetaInfo::Hybrid(_) => "hybrid",
+            MetaInfo::MetaV1(_) | MetaInfo::MerkleV1(_) => None,
+            MetaInfo::MetaV2(info) => Some(info.meta_version),
+            MetaInfo::Hybrid(info) => info.meta_version,
         }
     }
 
@@ -69,20 +227,151 @@ impl MetaInfo {
         match self {
             MetaInfo::MetaV1(info) => info.piece_length,
             MetaInfo::MetaV2(info) => info.piece_length,
-            MetaInfo::Hybrid(info) => info.piece_length
+            MetaInfo::MerkleV1(info) => info.piece_length,
+            MetaInfo::Hybrid(info) => info.piece_length,
+        }
+    }
+
+    /// Tracker specific `source` string.
+    #[inline]
+    pub fn source(&self) -> Option<&str> {
+        match self {
+            MetaInfo::MetaV1(info) => info.source.as_deref(),
+            MetaInfo::MetaV2(info) => info.source.as_deref(),
+            MetaInfo::MerkleV1(info) => info.source.as_deref(),
+            MetaInfo::Hybrid(info) => info.source.as_deref(),
+        }
+    }
+
+    /// Replace the `source` string and return the previous one.
+    ///
+    /// This changes the info hash. Prefer [crate::Torrent::set_source] which also resets the
+    /// cached info hash.
+    #[inline]
+    pub fn set_source(&mut self, source: Option<String>) -> Option<String> {
+        let current = match self {
+            MetaInfo::MetaV1(info) => &mut info.source,
+            MetaInfo::MetaV2(info) => &mut info.source,
+            MetaInfo::MerkleV1(info) => &mut info.source,
+            MetaInfo::Hybrid(info) => &mut info.source,
+        };
+        std::mem::replace(current, source)
+    }
+
+    /// Replace the `private` flag and return the previous one.
+    ///
+    /// This changes the info hash. Prefer [crate::create::make_private] which also resets the
+    /// cached info hash and strips sources of peers that private trackers forbid.
+    #[inline]
+    pub fn set_private(&mut self, private: Option<bool>) -> Option<bool> {
+        let current = match self {
+            MetaInfo::MetaV1(info) => &mut info.private,
+            MetaInfo::MetaV2(info) => &mut info.private,
+            MetaInfo::MerkleV1(info) => &mut info.private,
+            MetaInfo::Hybrid(info) => &mut info.private,
+        };
+        std::mem::replace(current, private)
+    }
+
+    /// Whether the torrent is restricted to private trackers.
+    ///
+    /// An absent `private` key is public.
+    #[inline]
+    pub fn is_private(&self) -> bool {
+        match self {
+            MetaInfo::MetaV1(info) => info.private,
+            MetaInfo::MetaV2(info) => info.private,
+            MetaInfo::MerkleV1(info) => info.private,
+            MetaInfo::Hybrid(info) => info.private,
         }
+        .unwrap_or_default()
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use serde_test::{assert_tokens, Token};
+    use super::{Hybrid, MetaInfo, TorrentVersion};
+    use crate::Torrent;
+
+    #[test]
+    fn private_absent_roundtrip() -> Result<(), serde_bencode::Error> {
+        let public = "d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+        let private = "d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei0eee";
+
+        for torrent_str in [public, private] {
+            let torrent: Torrent = serde_bencode::from_str(torrent_str)?;
+            assert!(!torrent.info.is_private());
+            assert_eq!(serde_bencode::to_string(&torrent)?, torrent_str);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn file_count_matches_iter_files() -> Result<(), serde_bencode::Error> {
+        let cats = "d4:infod9:file treed4:catsd8:cats.mkvd0:d6:lengthi42eee8:cats.nfod0:d6:lengthi8eeee4:mewsd0:d6:lengthi1eeee12:meta versioni2e4:name4:cats12:piece lengthi16384eee";
+        let torrent: Torrent = serde_bencode::from_str(cats)?;
+
+        let files = torrent.info.iter_files();
+        assert_eq!(files.len(), 3);
+        assert_eq!(files.count(), torrent.info.file_count());
+
+        Ok(())
+    }
+
+    #[test]
+    fn iter_files_hybrid_without_tree() -> Result<(), serde_bencode::Error> {
+        let cats = "d5:filesld6:lengthi42e4:pathl4:cats8:cats.mkveed6:lengthi6e4:pathl8:mews.mkveee4:name4:cats12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let info = MetaInfo::Hybrid(serde_bencode::from_str::<Hybrid>(cats)?);
+
+        let files: Vec<_> = info
+            .iter_files()
+            .map(|file| (file.file_path, file.name, file.length))
+            .collect();
+        assert_eq!(
+            files,
+            [(vec!["cats"], "cats.mkv", 42), (vec![], "mews.mkv", 6)]
+        );
+        assert_eq!(files.len(), info.file_count());
+
+        Ok(())
+    }
+
     #[test]
-    fn info_metav1_only() {}
+    fn info_metav1_only() -> Result<(), serde_bencode::Error> {
+        let cats =
+            "d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let info: MetaInfo = serde_bencode::from_str(cats)?;
+        assert!(matches!(info, MetaInfo::MetaV1(_)));
+        assert_eq!(info.version(), TorrentVersion::V1);
+
+        // Unknown keys are kept by the lenient hybrid but it's still version 1.
+        let cats = "d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa9:publisher4:catse";
+        let info: MetaInfo = serde_bencode::from_str(cats)?;
+        assert!(matches!(info, MetaInfo::Hybrid(_)));
+        assert_eq!(info.version(), TorrentVersion::V1);
+        assert_eq!(info.meta_version_str(), "1");
+
+        Ok(())
+    }
 
     #[test]
-    fn info_metav2_only() {}
+    fn info_metav2_only() -> Result<(), serde_bencode::Error> {
+        let cats = "d9:file treed8:cats.mkvd0:d6:lengthi42eeee12:meta versioni2e4:name4:cats12:piece lengthi16384ee";
+        let info: MetaInfo = serde_bencode::from_str(cats)?;
+        assert!(matches!(info, MetaInfo::MetaV2(_)));
+        assert_eq!(info.version(), TorrentVersion::V2);
+
+        Ok(())
+    }
 
     #[test]
-    fn info_hybrid() {}
+    fn info_hybrid() -> Result<(), serde_bencode::Error> {
+        let cats = "d9:file treed8:cats.mkvd0:d6:lengthi42eeee6:lengthi42e12:meta versioni2e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let info: MetaInfo = serde_bencode::from_str(cats)?;
+        assert!(matches!(info, MetaInfo::Hybrid(_)));
+        assert_eq!(info.version(), TorrentVersion::Hybrid);
+
+        Ok(())
+    }
 }
@@ -1,7 +1,10 @@
 use crate::{
-    crypto::{md5::Md5, sha1::Sha1},
+    crypto::md5::{optional_md5, Md5},
     files::{FileTree, FlatFile},
-    metainfo::serde_bool_int::{bool_from_int, bool_to_int},
+    metainfo::{
+        serde_bool_int::{bool_from_int, bool_to_int},
+        MetaVersion,
+    },
     pieces::{PieceLength, Pieces},
 };
 use serde::{Deserialize, Serialize};
@@ -12,8 +15,7 @@ use std::num::NonZeroU64;
 ///
 /// Hybrid torrents contain the info dicts for all torrent meta versions.
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
-#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub struct Hybrid {
     /// Files shared by version 1 or hybrid torrents.
     #[serde(default)]
@@ -28,12 +30,10 @@ pub struct Hybrid {
     /// Torrent file meta version
     ///
     /// This is specified in BEP-0052 which revises the original torrent format.
-    /// Meta version must be greater than or equal to 2. Meta version is increased for
-    /// major changes such as deprecating a hash algorithm in favor of a new algo.
     #[serde(default, rename = "meta version")]
-    pub meta_version: Option<u8>,
+    pub meta_version: Option<MetaVersion>,
     /// Shared file's MD5 hash.
-    #[serde(default)]
+    #[serde(default, deserialize_with = "optional_md5")]
     pub md5sum: Option<Md5>,
     /// Suggested name of the file or subdirectory to which to save multiple files.
     ///
@@ -54,17 +54,61 @@ pub struct Hybrid {
     /// Private torrents are only advertised on a private tracker. The swarm is limited to
     /// that particular tracker even if multiple trackers are specified. Torrent clients should
     /// disconnect from all peers if trackers are switched
+    ///
+    /// `None` if the key is absent, which is distinct from `Some(false)` because the key is part
+    /// of the info hash.
     #[serde(
         default,
         deserialize_with = "bool_from_int",
         serialize_with = "bool_to_int"
     )]
-    pub private: bool,
-    /// Merkle tree root hash.
+    pub private: Option<bool>,
+    /// Tracker specific string that makes the info hash unique per tracker.
     ///
-    /// [BEP-0030](https://www.bittorrent.org/beps/bep_0030.html) adds Merkle trees to reduce torrent file
-    /// sizes. Instead of a hash per piece, a Merkle torrent contains the root hash of the tree through which
-    /// the hashes of the subseqeuent pieces may be derived.
-    #[serde(default, rename = "root hash")]
-    pub root_hash: Option<Sha1>,
+    /// Private trackers add `source` so that a torrent uploaded to several trackers has a
+    /// different info hash on each.
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Hybrid {
+    // Hybrid torrents describe the same files in both formats so the version 1
+    // files are derived from the generated tree. Version 1 files can't have a
+    // length of zero so symlinks are only in the tree.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let file_tree: FileTree = u.arbitrary()?;
+        let files = file_tree
+            .iter_dfs()
+            .filter_map(|view| NonZeroU64::new(view.file_info.length).map(|length| (view, length)))
+            .map(|(view, length)| {
+                Ok(FlatFile {
+                    attr: view.file_info.attr.clone(),
+                    length,
+                    // Skip the root ("./"); the last directory is the file's name.
+                    path: view.directory[1..]
+                        .iter()
+                        .map(|&part| part.try_into())
+                        .collect::<Result<_, _>>()
+                        .map_err(|_| arbitrary::Error::IncorrectFormat)?,
+                    md5sum: None,
+                    sha1: None,
+                    symlink_path: None,
+                })
+            })
+            .collect::<arbitrary::Result<_>>()?;
+
+        Ok(Hybrid {
+            files: Some(files),
+            file_tree: Some(file_tree),
+            length: None,
+            meta_version: Some(MetaVersion::V2),
+            md5sum: None,
+            name: crate::files::arbitrary_path_component(u)?,
+            pieces: Some(u.arbitrary()?),
+            piece_length: u.arbitrary()?,
+            private: u.arbitrary()?,
+            source: u.arbitrary()?,
+        })
+    }
 }
@@ -5,8 +5,14 @@
 //!
 //! SHA256 hashes may be truncated to 20 bytes for backwards compatibility or other uses.
 
-use super::MetaInfo;
-use crate::crypto::{calculateinfohash::CalculateInfoHash, sha::Sha1, sha2::Sha2};
+use crate::crypto::{sha::Sha1, sha2::Sha2};
+#[cfg(feature = "crypto")]
+use crate::{
+    crypto::calculateinfohash::CalculateInfoHash,
+    metainfo::{rawinfo::RawInfo, MetaInfo},
+};
+#[cfg(feature = "crypto")]
+use std::sync::OnceLock;
 
 /// SHA-1 and SHA-2 256 hashes of a torrent's info dict.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -15,6 +21,7 @@ pub struct InfoHashAny {
     pub(crate) sha2: Sha2,
 }
 
+#[cfg(feature = "crypto")]
 impl InfoHashAny {
     #[inline]
     pub(crate) fn calculate_infohash(info_dict: &MetaInfo) -> Result<Self, serde_bencode::Error> {
@@ -24,9 +31,83 @@ impl InfoHashAny {
         })
     }
 
+    /// Hash an info dict exactly as it was received.
+    ///
+    /// Unknown fields are dropped while deserializing so hashing the original bytes is the only
+    /// way to match the info hash that the torrent was shared with.
+    pub(crate) fn from_info_bytes(info_bytes: &[u8]) -> Self {
+        use digest::Digest;
 
+        Self {
+            sha1: <[u8; 20]>::from(sha1::Sha1::digest(info_bytes)).into(),
+            sha2: <[u8; 32]>::from(sha2::Sha256::digest(info_bytes)).into(),
+        }
+    }
 }
 
+/// Lazily calculated [InfoHashAny].
+///
+/// The cache is cheap to clone (the hashes are copied if calculated) and is ignored for equality so
+/// two torrents are equal regardless of whether either has calculated its info hash.
+#[cfg(feature = "crypto")]
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InfoHashCache(OnceLock<InfoHashAny>);
+
+#[cfg(feature = "crypto")]
+impl InfoHashCache {
+    /// Whether the info hash hasn't been calculated yet.
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.get().is_none()
+    }
+
+    /// Forget the cached info hash after the info dict changed.
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.0.take();
+    }
+
+    /// Return the cached info hash or calculate and cache it.
+    ///
+    /// The original info dict is hashed if it's available. Errors aren't cached; a failed
+    /// calculation is retried on the next call.
+    pub(crate) fn get_or_calculate(
+        &self,
+        info_dict: &MetaInfo,
+        raw: &RawInfo,
+    ) -> Result<&InfoHashAny, serde_bencode::Error> {
+        if let Some(info_hash) = self.0.get() {
+            return Ok(info_hash);
+        }
+
+        let info_hash = match raw.bytes() {
+            Some(bytes) => InfoHashAny::from_info_bytes(bytes),
+            None => InfoHashAny::calculate_infohash(info_dict)?,
+        };
+        // Another thread may have won the race, but both calculated the same hash.
+        Ok(self.0.get_or_init(|| info_hash))
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl From<InfoHashAny> for InfoHashCache {
+    #[inline]
+    fn from(info_hash: InfoHashAny) -> Self {
+        Self(info_hash.into())
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl PartialEq for InfoHashCache {
+    #[inline]
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "crypto")]
+impl Eq for InfoHashCache {}
+
 /// Info hash specific to a torrent's info dict version.
 /// In other words, a version 1 only torrent will only have a [Sha1] hash.
 /// Equality is implemented similarly to [libtorrent](https://libtorrent.org/upgrade_to_2.0-ref.html)
@@ -43,17 +124,95 @@ pub enum InfoHashVersioned<'view> {
 impl PartialEq for InfoHashVersioned<'_> {
     fn eq(&self, other: &Self) -> bool {
         // Match whatever variant self is and check that other is the same.
-        if let InfoHashVersioned::V1(sha1_self) = self && let InfoHashVersioned::V1(sha1_other) = other {
-            sha1_self == sha1_other
+        match (self, other) {
+            (InfoHashVersioned::V1(sha1_self), InfoHashVersioned::V1(sha1_other)) => {
+                sha1_self == sha1_other
+            }
+            (InfoHashVersioned::V2(sha2_self), InfoHashVersioned::V2(sha2_other)) => {
+                sha2_self == sha2_other
+            }
+            (
+                InfoHashVersioned::Hybrid {
+                    sha1: sha1_self,
+                    sha2: sha2_self,
+                },
+                InfoHashVersioned::Hybrid {
+                    sha1: sha1_other,
+                    sha2: sha2_other,
+                },
+            ) => sha1_self == sha1_other && sha2_self == sha2_other,
+            _ => false,
         }
-        else if let InfoHashVersioned::V2(sha2_self) = self && let InfoHashVersioned::V2(sha2_other) = other {
-            sha2_self == sha2_other
-        }
-        else if let InfoHashVersioned::Hybrid { sha1: sha1_self, sha2: sha2_self } = self && let InfoHashVersioned::Hybrid { sha1: sha1_other, sha2: sha2_other } = other {
-            sha1_self == sha1_other && sha2_self == sha2_other
-        }
-        else {
-            false
+    }
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use super::{InfoHashAny, InfoHashVersioned};
+    use crate::{parseoptions::ParseOptions, Torrent};
+
+    const CATS: &[u8] = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+
+    #[test]
+    fn cloned_torrent_keeps_info_hash() -> Result<(), serde_bencode::Error> {
+        let torrent: Torrent = serde_bencode::from_bytes(CATS)?;
+        let uncached = torrent.clone();
+
+        let info_hash = torrent.info_hash()?;
+        let cached = torrent.clone();
+
+        assert_eq!(torrent, uncached);
+        assert!(!cached.info_hash_internal.is_empty());
+        assert_eq!(info_hash, cached.info_hash()?);
+        assert_eq!(info_hash, uncached.info_hash()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_info_fields_kept_in_info_hash() -> Result<(), Box<dyn std::error::Error>> {
+        let info = b"d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x007:wheelie4:meowe";
+        let cats = [&b"d4:info"[..], info, b"e"].concat();
+        let expected = InfoHashAny::from_info_bytes(info).sha1;
+
+        for mut torrent in [
+            Torrent::from_bytes(&cats)?,
+            Torrent::parse_with(&cats, ParseOptions::lenient())?,
+        ] {
+            // Trackers are outside of the info dict.
+            torrent.set_announce(Some("udp://cats.test".parse()?));
+            assert_eq!(torrent.info_hash()?, InfoHashVersioned::V1(&expected));
+
+            let reparsed = Torrent::from_bytes(&torrent.to_bytes()?)?;
+            assert_eq!(reparsed.info_hash()?, InfoHashVersioned::V1(&expected));
+
+            torrent.set_source(Some("MEOW".to_owned()));
+            assert_ne!(torrent.info_hash()?, InfoHashVersioned::V1(&expected));
         }
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsorted_info_dict_hashed_as_is() -> Result<(), Box<dyn std::error::Error>> {
+        // `name` should come after `length`.
+        let info = b"d4:name8:cats.mkv6:lengthi42e12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00e";
+        let cats = [&b"d8:announce9:localhost4:info"[..], info, b"e"].concat();
+        let expected = InfoHashAny::from_info_bytes(info).sha1;
+
+        let torrent = Torrent::from_bytes(&cats)?;
+        assert_eq!(torrent.info_hash()?, InfoHashVersioned::V1(&expected));
+        assert_eq!(torrent.to_bytes()?, cats);
+
+        let (stats_torrent, _) = Torrent::from_bytes_with_stats(&cats)?;
+        assert_eq!(stats_torrent.info_hash()?, InfoHashVersioned::V1(&expected));
+
+        // Modifying the info dict drops the original bytes.
+        let mut sorted = torrent.clone();
+        sorted.info_mut();
+        assert_ne!(sorted.info_hash()?, InfoHashVersioned::V1(&expected));
+        assert_ne!(sorted.to_bytes()?, cats);
+
+        Ok(())
     }
 }
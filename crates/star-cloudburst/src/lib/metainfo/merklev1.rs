@@ -0,0 +1,145 @@
+use crate::{
+    crypto::{
+        md5::{optional_md5, Md5},
+        sha1::Sha1,
+    },
+    files::FlatFile,
+    metainfo::serde_bool_int::{bool_from_int, bool_to_int},
+    pieces::PieceLength,
+};
+use log::{error, trace};
+use serde::{de::Error as DeErrorTrait, Deserialize, Deserializer, Serialize};
+use serde_with::skip_serializing_none;
+use std::num::NonZeroU64;
+
+const MERKLEV1_DE_TARGET: &str = "star_cloudburst::metainfo::MerkleV1::deserialize";
+
+/// Meta version 1 info dict of a Merkle torrent.
+///
+/// [BEP-0030](https://www.bittorrent.org/beps/bep_0030.html) replaces the concatenated piece
+/// hashes with the root hash of a Merkle tree over the pieces. Merkle torrents must have a
+/// `root hash` and must not have `pieces`. Like version 1 torrents, they share either `files` or a
+/// single file with a `length`.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MerkleV1 {
+    /// Files shared by a multiple file torrent.
+    pub files: Option<Vec<FlatFile>>,
+    /// Length of the file shared by a single file torrent.
+    pub length: Option<NonZeroU64>,
+    pub md5sum: Option<Md5>,
+    pub name: String,
+    #[serde(rename = "piece length")]
+    pub piece_length: PieceLength,
+    #[serde(serialize_with = "bool_to_int")]
+    pub private: Option<bool>,
+    /// Root hash of the Merkle tree over the pieces.
+    #[serde(rename = "root hash")]
+    pub root_hash: Sha1,
+    /// Tracker specific string that makes the info hash unique per tracker.
+    pub source: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for MerkleV1 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // `MerkleV1` without the `files` or `length` check.
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Unchecked {
+            #[serde(default)]
+            files: Option<Vec<FlatFile>>,
+            #[serde(default)]
+            length: Option<NonZeroU64>,
+            #[serde(default, deserialize_with = "optional_md5")]
+            md5sum: Option<Md5>,
+            name: String,
+            #[serde(rename = "piece length")]
+            piece_length: PieceLength,
+            #[serde(default, deserialize_with = "bool_from_int")]
+            private: Option<bool>,
+            #[serde(rename = "root hash")]
+            root_hash: Sha1,
+            #[serde(default)]
+            source: Option<String>,
+        }
+
+        trace!(target: MERKLEV1_DE_TARGET, "Deserializing MerkleV1.");
+        let unchecked = Unchecked::deserialize(deserializer)?;
+
+        if unchecked.files.is_some() == unchecked.length.is_some() {
+            error!(
+                target: MERKLEV1_DE_TARGET,
+                "Merkle torrent `{}` should have either `files` or `length`.", unchecked.name
+            );
+            return Err(DeErrorTrait::custom(
+                "Merkle torrents share either `files` or a single file with `length`",
+            ));
+        }
+
+        Ok(MerkleV1 {
+            files: unchecked.files,
+            length: unchecked.length,
+            md5sum: unchecked.md5sum,
+            name: unchecked.name,
+            piece_length: unchecked.piece_length,
+            private: unchecked.private,
+            root_hash: unchecked.root_hash,
+            source: unchecked.source,
+        })
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for MerkleV1 {
+    // Either multiple files or a single file but never both.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let (files, length) = if u.arbitrary()? {
+            let len = u.int_in_range(1..=8)?;
+            let files = (0..len)
+                .map(|_| u.arbitrary())
+                .collect::<arbitrary::Result<_>>()?;
+            (Some(files), None)
+        } else {
+            (None, Some(crate::files::arbitrary_length(u)?))
+        };
+
+        Ok(MerkleV1 {
+            files,
+            length,
+            md5sum: u.arbitrary()?,
+            name: crate::files::arbitrary_path_component(u)?,
+            piece_length: u.arbitrary()?,
+            private: u.arbitrary()?,
+            root_hash: u.arbitrary()?,
+            source: u.arbitrary()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MerkleV1;
+    use crate::metainfo::MetaInfo;
+
+    #[test]
+    fn merklev1_requires_root_hash_without_pieces() {
+        let merkle = "d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e9:root hash20:aaaaaaaaaaaaaaaaaaaae";
+        let pieces = "d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa9:root hash20:aaaaaaaaaaaaaaaaaaaae";
+        let no_root = "d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384ee";
+        let no_files = "d4:name8:cats.mkv12:piece lengthi16384e9:root hash20:aaaaaaaaaaaaaaaaaaaae";
+
+        let info: MerkleV1 = serde_bencode::from_str(merkle).unwrap();
+        assert_eq!(serde_bencode::to_string(&info).unwrap(), merkle);
+        assert!(matches!(
+            serde_bencode::from_str(merkle).unwrap(),
+            MetaInfo::MerkleV1(_)
+        ));
+
+        for invalid in [pieces, no_root, no_files] {
+            assert!(serde_bencode::from_str::<MerkleV1>(invalid).is_err());
+        }
+    }
+}
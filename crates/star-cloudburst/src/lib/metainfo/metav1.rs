@@ -1,28 +1,158 @@
 use crate::{
-    crypto::md5::Md5,
-    files::MetaV1FileRepr,
+    crypto::md5::{optional_md5, Md5},
+    files::{FlatFile, MetaV1FileRepr},
     metainfo::serde_bool_int::{bool_from_int, bool_to_int},
     pieces::{PieceLength, Pieces},
 };
-use serde::{Deserialize, Serialize};
+use log::{error, trace};
+use serde::{de::Error as DeErrorTrait, Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::skip_serializing_none;
+use std::num::NonZeroU64;
+
+const METAV1_DE_TARGET: &str = "star_cloudburst::metainfo::MetaV1::deserialize";
 
 /// Meta version 1 info dict.
-#[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
-#[serde(deny_unknown_fields)]
+///
+/// Multiple files are shared with `files` and a single file with `length`. Both are represented
+/// by [MetaV1FileRepr] so a dict with both or neither is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct MetaV1 {
     pub files: MetaV1FileRepr,
-    #[serde(default)]
     pub md5sum: Option<Md5>,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = crate::files::arbitrary_path_component)
+    )]
     pub name: String,
     pub pieces: Pieces,
-    #[serde(rename = "piece length")]
     pub piece_length: PieceLength,
-    #[serde(
-        default,
-        deserialize_with = "bool_from_int",
-        serialize_with = "bool_to_int"
-    )]
-    pub private: bool,
+    pub private: Option<bool>,
+    /// Tracker specific string that makes the info hash unique per tracker.
+    pub source: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for MetaV1 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // `MetaV1` with `files` and `length` as separate keys.
+        #[derive(Deserialize)]
+        #[serde(deny_unknown_fields)]
+        struct Unchecked {
+            #[serde(default)]
+            files: Option<Vec<FlatFile>>,
+            #[serde(default)]
+            length: Option<NonZeroU64>,
+            #[serde(default, deserialize_with = "optional_md5")]
+            md5sum: Option<Md5>,
+            name: String,
+            pieces: Pieces,
+            #[serde(rename = "piece length")]
+            piece_length: PieceLength,
+            #[serde(default, deserialize_with = "bool_from_int")]
+            private: Option<bool>,
+            #[serde(default)]
+            source: Option<String>,
+        }
+
+        trace!(target: METAV1_DE_TARGET, "Deserializing MetaV1.");
+        let unchecked = Unchecked::deserialize(deserializer)?;
+
+        let files = match (unchecked.files, unchecked.length) {
+            (Some(files), None) => MetaV1FileRepr::Multiple(files),
+            (None, Some(length)) => MetaV1FileRepr::Single(length),
+            _ => {
+                error!(
+                    target: METAV1_DE_TARGET,
+                    "Torrent `{}` should have either `files` or `length`.", unchecked.name
+                );
+                return Err(DeErrorTrait::custom(
+                    "version 1 torrents share either `files` or a single file with `length`",
+                ));
+            }
+        };
+
+        Ok(MetaV1 {
+            files,
+            md5sum: unchecked.md5sum,
+            name: unchecked.name,
+            pieces: unchecked.pieces,
+            piece_length: unchecked.piece_length,
+            private: unchecked.private,
+            source: unchecked.source,
+        })
+    }
+}
+
+impl Serialize for MetaV1 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // Borrowed `MetaV1` with `files` and `length` as separate keys.
+        #[skip_serializing_none]
+        #[derive(Serialize)]
+        struct Flattened<'a> {
+            files: Option<&'a [FlatFile]>,
+            length: Option<NonZeroU64>,
+            md5sum: Option<&'a Md5>,
+            name: &'a str,
+            pieces: &'a Pieces,
+            #[serde(rename = "piece length")]
+            piece_length: PieceLength,
+            #[serde(serialize_with = "bool_to_int")]
+            private: Option<bool>,
+            source: Option<&'a str>,
+        }
+
+        let (files, length) = match &self.files {
+            MetaV1FileRepr::Multiple(files) => (Some(files.as_slice()), None),
+            &MetaV1FileRepr::Single(length) => (None, Some(length)),
+        };
+
+        Flattened {
+            files,
+            length,
+            md5sum: self.md5sum.as_ref(),
+            name: &self.name,
+            pieces: &self.pieces,
+            piece_length: self.piece_length,
+            private: self.private,
+            source: self.source.as_deref(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MetaV1;
+    use crate::{files::MetaV1FileRepr, metainfo::MetaInfo};
+
+    #[test]
+    fn metav1_files_or_length() {
+        let single =
+            "d6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let multi = "d5:filesld6:lengthi42e4:pathl8:cats.mkveee4:name4:cats12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let both = "d5:filesld6:lengthi42e4:pathl8:cats.mkveee6:lengthi42e4:name4:cats12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+        let neither = "d4:name4:cats12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae";
+
+        let info: MetaV1 = serde_bencode::from_str(single).unwrap();
+        assert!(matches!(info.files, MetaV1FileRepr::Single(_)));
+        assert_eq!(serde_bencode::to_string(&info).unwrap(), single);
+
+        let info: MetaV1 = serde_bencode::from_str(multi).unwrap();
+        assert!(matches!(info.files, MetaV1FileRepr::Multiple(_)));
+        assert_eq!(serde_bencode::to_string(&info).unwrap(), multi);
+        assert!(matches!(
+            serde_bencode::from_str(multi).unwrap(),
+            MetaInfo::MetaV1(_)
+        ));
+
+        for invalid in [both, neither] {
+            assert!(serde_bencode::from_str::<MetaV1>(invalid).is_err());
+        }
+    }
 }
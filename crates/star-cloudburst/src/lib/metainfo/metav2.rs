@@ -1,21 +1,28 @@
 use crate::{
-    crypto::sha1::Sha1,
     files::FileTree,
-    metainfo::serde_bool_int::{bool_from_int, bool_to_int},
+    metainfo::{
+        serde_bool_int::{bool_from_int, bool_to_int},
+        MetaVersion,
+    },
     pieces::PieceLength,
 };
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
-use std::num::NonZeroU8;
 
 #[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[serde(deny_unknown_fields)]
 pub struct MetaV2 {
     #[serde(rename = "file tree")]
     pub file_tree: FileTree,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = crate::files::arbitrary_path_component)
+    )]
     pub name: String,
-    pub meta_version: NonZeroU8,
+    #[serde(rename = "meta version")]
+    pub meta_version: MetaVersion,
     #[serde(rename = "piece length")]
     pub piece_length: PieceLength,
     #[serde(
@@ -23,7 +30,8 @@ pub struct MetaV2 {
         deserialize_with = "bool_from_int",
         serialize_with = "bool_to_int"
     )]
-    pub private: bool,
-    #[serde(rename = "root hash")]
-    pub root_hash: Sha1,
+    pub private: Option<bool>,
+    /// Tracker specific string that makes the info hash unique per tracker.
+    #[serde(default)]
+    pub source: Option<String>,
 }
@@ -0,0 +1,93 @@
+use log::{error, trace};
+use serde::{
+    de::{Error as DeErrorTrait, Unexpected},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt::{self, Display, Formatter};
+
+const METAVERSION_DE_TARGET: &str = "star_cloudburst::metainfo::MetaVersion::deserialize";
+
+/// Torrent file meta version (`meta version`).
+///
+/// [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) introduces `meta version` which must
+/// be two or greater. Meta version is increased for major changes such as deprecating a hash
+/// algorithm in favor of a new algo. Version 1 torrents don't have the key.
+///
+/// ```rust
+/// use star_cloudburst::metainfo::MetaVersion;
+///
+/// assert_eq!(serde_bencode::from_str::<MetaVersion>("i2e").unwrap(), MetaVersion::V2);
+/// assert_eq!(serde_bencode::from_str::<MetaVersion>("i3e").unwrap(), MetaVersion::Future(3));
+/// assert!(serde_bencode::from_str::<MetaVersion>("i1e").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MetaVersion {
+    /// BEP-0052 torrents.
+    V2,
+    /// A version newer than two that this crate doesn't know about.
+    ///
+    /// The value should be greater than two.
+    Future(u8),
+}
+
+impl MetaVersion {
+    /// Meta version as an integer.
+    #[inline]
+    pub fn get(self) -> u8 {
+        match self {
+            MetaVersion::V2 => 2,
+            MetaVersion::Future(version) => version,
+        }
+    }
+}
+
+impl Display for MetaVersion {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get())
+    }
+}
+
+impl<'de> Deserialize<'de> for MetaVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        trace!(target: METAVERSION_DE_TARGET, "Deserializing MetaVersion.");
+
+        match u8::deserialize(deserializer)? {
+            2 => Ok(MetaVersion::V2),
+            version if version > 2 => Ok(MetaVersion::Future(version)),
+            version => {
+                error!(
+                    target: METAVERSION_DE_TARGET,
+                    "Invalid meta version: {version}."
+                );
+                Err(DeErrorTrait::invalid_value(
+                    Unexpected::Unsigned(version.into()),
+                    &"meta version should be two or greater",
+                ))
+            }
+        }
+    }
+}
+
+impl Serialize for MetaVersion {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u8(self.get())
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for MetaVersion {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(match u.int_in_range(2..=u8::MAX)? {
+            2 => MetaVersion::V2,
+            version => MetaVersion::Future(version),
+        })
+    }
+}
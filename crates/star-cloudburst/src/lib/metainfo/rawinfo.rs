@@ -0,0 +1,98 @@
+//! Bencoded info dict as it was received.
+//!
+//! [super::MetaInfo] only keeps the fields that this crate knows about and may
+//! normalize a few others while parsing leniently. Serializing it again can
+//! therefore produce a different info dict and, by extension, a different info
+//! hash. Keys of the original dict may also be out of order, which re-encoding
+//! silently fixes. The exact bytes of the original dict are sliced from the
+//! input by [crate::Torrent::from_bytes] and used for the info hash and
+//! [crate::Torrent::to_bytes] until the info dict is modified.
+
+use log::{trace, warn};
+use star_bert::parser::raw_dict_value;
+use std::sync::Arc;
+
+const RAWINFO_TARGET: &str = "star_cloudburst::metainfo::rawinfo";
+
+/// Key of the info dict in a torrent.
+const INFO_KEY: &[u8] = b"info";
+
+/// Bencoded info dict that a torrent was parsed from.
+///
+/// Like the info hash cache, it's ignored for equality and cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RawInfo(Option<Arc<[u8]>>);
+
+impl RawInfo {
+    /// Slice the info dict out of a bencoded torrent.
+    ///
+    /// The dict is empty if `torrent` isn't a dict with an `info` key. That
+    /// only happens for input that serde_bencode accepts but star-bert
+    /// doesn't, in which case the info hash falls back to the parsed dict.
+    pub(crate) fn from_torrent(torrent: &[u8]) -> Self {
+        match info_span(torrent) {
+            Some(info) => {
+                trace!(target: RAWINFO_TARGET, "Sliced {} byte info dict", info.len());
+                info.into()
+            }
+            None => {
+                warn!(
+                    target: RAWINFO_TARGET,
+                    "Info dict not found in the raw torrent; the info hash is calculated from the parsed dict"
+                );
+                Self::default()
+            }
+        }
+    }
+
+    /// Bencoded info dict if the torrent's info dict wasn't modified since it
+    /// was parsed.
+    #[inline]
+    pub(crate) fn bytes(&self) -> Option<&[u8]> {
+        self.0.as_deref()
+    }
+
+    /// Forget the original info dict after the parsed info dict changed.
+    #[inline]
+    pub(crate) fn clear(&mut self) {
+        self.0 = None;
+    }
+
+    /// Replace the info dict of the bencoded `torrent` with the original dict.
+    ///
+    /// `torrent` is returned as is if the original dict isn't available.
+    pub(crate) fn splice(&self, torrent: Vec<u8>) -> Option<Vec<u8>> {
+        let Some(raw) = self.bytes() else {
+            return Some(torrent);
+        };
+
+        let info = info_span(&torrent)?;
+        let start = info.as_ptr() as usize - torrent.as_ptr() as usize;
+        let end = start + info.len();
+        Some([&torrent[..start], raw, &torrent[end..]].concat())
+    }
+}
+
+impl From<&[u8]> for RawInfo {
+    #[inline]
+    fn from(bytes: &[u8]) -> Self {
+        Self(Some(bytes.into()))
+    }
+}
+
+impl PartialEq for RawInfo {
+    #[inline]
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for RawInfo {}
+
+// Exact bytes of the info dict in a bencoded torrent.
+#[inline]
+fn info_span(torrent: &[u8]) -> Option<&[u8]> {
+    raw_dict_value(INFO_KEY)(torrent)
+        .ok()
+        .and_then(|(_, info)| info)
+}
@@ -7,7 +7,9 @@ use serde::{
 const BOOLFROMINT_DE_TARGET: &str = "star_cloudburst::info::bool_from_int";
 
 /// Deserialize u8 to bool.
-pub(super) fn bool_from_int<'de, D>(deserializer: D) -> Result<bool, D::Error>
+///
+/// Absent fields are handled by `#[serde(default)]` so that they stay `None`.
+pub(crate) fn bool_from_int<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -18,8 +20,8 @@ where
 
     match u8::deserialize(deserializer) {
         Ok(maybe_bool) => match maybe_bool {
-            0 => Ok(false),
-            1 => Ok(true),
+            0 => Ok(Some(false)),
+            1 => Ok(Some(true)),
             nonbool => Err(DeErrorTrait::invalid_value(
                 Unexpected::Unsigned(nonbool as u64),
                 &"zero or one",
@@ -27,18 +29,23 @@ where
         },
         Err(error) => {
             debug!(target: BOOLFROMINT_DE_TARGET, "Deserializing `private` failed which most likely means the field doesn't exist. Documenting anyways.\nError: {error}");
-            Ok(false)
+            Ok(None)
         }
     }
 }
 
 /// Serialize bool to u8.
+///
+/// `None` should be skipped with `skip_serializing_none` so that absent fields stay absent.
 #[inline]
-pub(super) fn bool_to_int<S>(private: &bool, serializer: S) -> Result<S::Ok, S::Error>
+pub(crate) fn bool_to_int<S>(private: &Option<bool>, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    serializer.serialize_u8(*private as u8)
+    match private {
+        Some(private) => serializer.serialize_u8(*private as u8),
+        None => serializer.serialize_none(),
+    }
 }
 
 #[cfg(test)]
@@ -52,7 +59,7 @@ mod tests {
 
     #[test]
     fn bool_from_int_valid() -> Result<(), Box<dyn Error>> {
-        let states = [("i0e", false), ("i1e", true)];
+        let states = [("i0e", Some(false)), ("i1e", Some(true))];
 
         for (value, expected) in states {
             let mut deserializer = serde_bencode::Deserializer::new(value.as_bytes());
@@ -76,13 +83,13 @@ mod tests {
     #[test]
     fn bool_from_int_none() {
         let deserializer: StrDeserializer<'static, DeError> = "".into_deserializer();
-        bool_from_int(deserializer).unwrap();
+        assert_eq!(bool_from_int(deserializer).unwrap(), None);
     }
 
     #[test]
     fn int_from_bool() -> Result<(), serde_bencode::Error> {
         let mut serializer = serde_bencode::Serializer::new();
-        bool_to_int(&true, &mut serializer)?;
+        bool_to_int(&Some(true), &mut serializer)?;
 
         let bytes_ser = serializer.into_vec();
         assert!(bytes_ser == "i1e".as_bytes(), "`true` wasn't serialized");
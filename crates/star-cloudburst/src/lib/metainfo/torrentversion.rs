@@ -0,0 +1,133 @@
+use serde_bencode::value::Value;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+};
+
+// Keys declared by each strictly parsed variant. Other keys are only kept by
+// [crate::metainfo::Hybrid].
+const METAV1_FIELDS: &[&[u8]] = &[
+    b"files",
+    b"length",
+    b"md5sum",
+    b"name",
+    b"pieces",
+    b"piece length",
+    b"private",
+    b"source",
+];
+const MERKLEV1_FIELDS: &[&[u8]] = &[
+    b"files",
+    b"length",
+    b"md5sum",
+    b"name",
+    b"piece length",
+    b"private",
+    b"root hash",
+    b"source",
+];
+const METAV2_FIELDS: &[&[u8]] = &[
+    b"file tree",
+    b"meta version",
+    b"name",
+    b"piece length",
+    b"private",
+    b"source",
+];
+
+/// Torrent format described by an info dict.
+///
+/// Unlike [crate::metainfo::MetaInfo]'s variants, this is the format of the
+/// shared files rather than the type that holds them. For example, a
+/// [crate::metainfo::Hybrid] without a file tree is a version 1 torrent.
+///
+/// ```rust
+/// use star_cloudburst::{metainfo::TorrentVersion, Torrent};
+///
+/// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+/// let torrent = Torrent::from_bytes(cats)?;
+/// assert_eq!(torrent.info.version(), TorrentVersion::V1);
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TorrentVersion {
+    /// [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html) torrents with piece hashes.
+    V1,
+    /// [BEP-0030](https://www.bittorrent.org/beps/bep_0030.html) torrents with a Merkle root hash.
+    MerkleV1,
+    /// [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html) torrents with a file tree.
+    V2,
+    /// Torrents with both version 1 pieces and a version 2 file tree.
+    Hybrid,
+}
+
+impl TorrentVersion {
+    /// Short name of the version as it's [Display]ed.
+    #[inline]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TorrentVersion::V1 => "1",
+            TorrentVersion::MerkleV1 => "1 (merkle)",
+            TorrentVersion::V2 => "2",
+            TorrentVersion::Hybrid => "hybrid",
+        }
+    }
+
+    /// Format of the info dict `info` judging by which keys are present.
+    ///
+    /// `meta version` or `file tree` make a torrent version 2, and a version 2
+    /// torrent that also has `pieces`, `files`, or `length` is a hybrid.
+    /// Otherwise `root hash` makes a Merkle torrent and anything else is
+    /// version 1.
+    pub(crate) fn detect(info: &HashMap<Vec<u8>, Value>) -> Self {
+        let has = |key: &[u8]| info.contains_key(key);
+
+        if has(b"meta version") || has(b"file tree") {
+            if has(b"pieces") || has(b"files") || has(b"length") {
+                TorrentVersion::Hybrid
+            } else {
+                TorrentVersion::V2
+            }
+        } else if has(b"root hash") {
+            TorrentVersion::MerkleV1
+        } else {
+            TorrentVersion::V1
+        }
+    }
+
+    /// Variant of [crate::metainfo::MetaInfo] that holds `info`.
+    ///
+    /// The variant of the [TorrentVersion::detect]ed format is used if `info`
+    /// has every key that variant requires and none that it doesn't
+    /// declare. Anything else is kept as a [crate::metainfo::Hybrid] which
+    /// tolerates missing and unknown keys so that the torrent may
+    /// still be repaired.
+    pub(crate) fn variant(info: &HashMap<Vec<u8>, Value>) -> Self {
+        let has = |key: &[u8]| info.contains_key(key);
+
+        let version = Self::detect(info);
+        let (fields, complete) = match version {
+            TorrentVersion::V1 => (
+                METAV1_FIELDS,
+                has(b"pieces") && has(b"files") != has(b"length"),
+            ),
+            TorrentVersion::MerkleV1 => (MERKLEV1_FIELDS, has(b"files") != has(b"length")),
+            TorrentVersion::V2 => (METAV2_FIELDS, has(b"meta version") && has(b"file tree")),
+            TorrentVersion::Hybrid => return TorrentVersion::Hybrid,
+        };
+
+        if complete && info.keys().all(|key| fields.contains(&key.as_slice())) {
+            version
+        } else {
+            TorrentVersion::Hybrid
+        }
+    }
+}
+
+impl Display for TorrentVersion {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
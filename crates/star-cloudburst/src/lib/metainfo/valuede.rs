@@ -0,0 +1,144 @@
+//! Deserialize types from an already parsed bencode [Value].
+//!
+//! [super::MetaInfo] has to look at the keys of the info dict before it knows
+//! which variant to deserialize. [ValueDeserializer] deserializes the chosen
+//! variant from the buffered [Value] rather than encoding and parsing the dict
+//! again. It calls the same visitor methods as serde_bencode's deserializer so
+//! that types behave the same either way.
+
+use serde::{
+    de::{
+        value::{MapDeserializer, SeqDeserializer},
+        Error as DeErrorTrait, IntoDeserializer, Unexpected, Visitor,
+    },
+    forward_to_deserialize_any, Deserializer,
+};
+use serde_bencode::{value::Value, Error};
+
+/// [Deserializer] over an owned [Value].
+pub(crate) struct ValueDeserializer(Value);
+
+impl<'de> IntoDeserializer<'de, Error> for ValueDeserializer {
+    type Deserializer = Self;
+
+    #[inline]
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+impl From<Value> for ValueDeserializer {
+    #[inline]
+    fn from(value: Value) -> Self {
+        Self(value)
+    }
+}
+
+impl<'de> Deserializer<'de> for ValueDeserializer {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Int(int) => visitor.visit_i64(int),
+            Value::Bytes(bytes) => visitor.visit_byte_buf(bytes),
+            Value::List(list) => {
+                visitor.visit_seq(SeqDeserializer::new(list.into_iter().map(Self)))
+            }
+            Value::Dict(dict) => visitor.visit_map(MapDeserializer::new(
+                dict.into_iter()
+                    .map(|(key, value)| (Self(Value::Bytes(key)), Self(value))),
+            )),
+        }
+    }
+
+    // Present values are always `Some`; absent fields are handled by `#[serde(default)]`.
+    #[inline]
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    #[inline]
+    fn deserialize_newtype_struct<V>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Bytes(bytes) => match String::from_utf8(bytes) {
+                Ok(string) => visitor.visit_string(string),
+                Err(e) => Err(DeErrorTrait::invalid_value(
+                    Unexpected::Bytes(e.as_bytes()),
+                    &"utf-8 string",
+                )),
+            },
+            value => Err(DeErrorTrait::invalid_type(unexpected(&value), &"bytes")),
+        }
+    }
+
+    #[inline]
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    #[inline]
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    // Only unit variants, which are encoded as strings, are supported.
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Bytes(bytes) => {
+                let variant = String::from_utf8(bytes).map_err(|e| {
+                    DeErrorTrait::invalid_value(Unexpected::Bytes(e.as_bytes()), &"utf-8 string")
+                })?;
+                visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(variant))
+            }
+            value => Self(value).deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool char i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 unit bytes byte_buf seq map
+        unit_struct tuple tuple_struct ignored_any struct
+    }
+}
+
+fn unexpected(value: &Value) -> Unexpected<'_> {
+    match value {
+        Value::Int(int) => Unexpected::Signed(*int),
+        Value::Bytes(bytes) => Unexpected::Bytes(bytes),
+        Value::List(_) => Unexpected::Seq,
+        Value::Dict(_) => Unexpected::Map,
+    }
+}
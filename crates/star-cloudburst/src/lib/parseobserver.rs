@@ -0,0 +1,125 @@
+//! Telemetry about parsed torrents.
+//!
+//! Ingestion pipelines often want to know how many torrents in their corpus
+//! carry unknown fields or rely on lenient parsing. [ParseObserver] receives a
+//! [ParseEvent] for each of those so that the pipeline doesn't have to scrape
+//! logs. Observers are passed to [crate::Torrent::parse_observed].
+
+use std::cell::RefCell;
+
+/// Notable things that happened while parsing a torrent.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseEvent {
+    /// A field that isn't declared by this crate's types.
+    ///
+    /// The path is dot separated dict keys such as `info.files.wheelie`.
+    UnknownField(String),
+    /// A nonconforming value was accepted because of a lenient
+    /// [crate::parseoptions::ParseOptions].
+    LenientFix {
+        /// Name of the field, such as `piece length`.
+        field: &'static str,
+        detail: String,
+    },
+    /// A value that parsed fine but is likely wrong.
+    SuspiciousValue {
+        /// Name of the field, such as `creation date`.
+        field: &'static str,
+        detail: String,
+    },
+}
+
+/// Receives [ParseEvent]s.
+///
+/// Closures that take a `&ParseEvent` are observers.
+pub trait ParseObserver {
+    fn observe(&mut self, event: &ParseEvent);
+}
+
+impl<F> ParseObserver for F
+where
+    F: FnMut(&ParseEvent),
+{
+    #[inline]
+    fn observe(&mut self, event: &ParseEvent) {
+        self(event)
+    }
+}
+
+thread_local! {
+    // None unless a parse on this thread is observed.
+    static EVENTS: RefCell<Option<Vec<ParseEvent>>> = const { RefCell::new(None) };
+}
+
+/// Whether a parse on this thread is observed.
+#[inline]
+pub(crate) fn observing() -> bool {
+    EVENTS.with_borrow(Option::is_some)
+}
+
+/// Record an event if the parse is observed.
+///
+/// The event is only built if it's recorded. Identical events are recorded once because untagged
+/// enums may deserialize the same value for several variants.
+pub(crate) fn report(event: impl FnOnce() -> ParseEvent) {
+    EVENTS.with_borrow_mut(|events| {
+        if let Some(events) = events {
+            let event = event();
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+    })
+}
+
+/// Run `f` while recording events and pass them to `observer` afterwards.
+pub(crate) fn observe<T>(observer: &mut dyn ParseObserver, f: impl FnOnce() -> T) -> T {
+    // Restores the enclosing scope's events even if `f` panics.
+    struct Restore(Option<Vec<ParseEvent>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            EVENTS.set(self.0.take());
+        }
+    }
+
+    let restore = Restore(EVENTS.replace(Some(Vec::new())));
+    let result = f();
+    let events = EVENTS.replace(None).unwrap_or_default();
+    drop(restore);
+
+    for event in &events {
+        observer.observe(event);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{observing, ParseEvent};
+    use crate::{parseoptions::ParseOptions, Torrent};
+
+    #[test]
+    fn observe_lenient_fixes() -> Result<(), serde_bencode::Error> {
+        let cats = b"d4:infod6:lengthi42e6:md5sum32:D41D8CD98F00B204E9800998ECF8427E4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee5:nodesl14:cats.test:6881ee";
+
+        let mut events = Vec::new();
+        Torrent::parse_observed(cats, ParseOptions::lenient(), &mut |event: &ParseEvent| {
+            events.push(event.clone())
+        })?;
+        assert!(!observing());
+
+        let fields: Vec<_> = events
+            .iter()
+            .filter_map(|event| match event {
+                ParseEvent::LenientFix { field, .. } => Some(*field),
+                _ => None,
+            })
+            .collect();
+        assert!(fields.contains(&"md5sum"), "{events:?}");
+        assert!(fields.contains(&"nodes"), "{events:?}");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,349 @@
+//! Runtime options for parsing torrents.
+//!
+//! Torrent files in the wild carry all sorts of exotic fields owing to the many
+//! clients with their own needs. [ParseOptions] decides at runtime whether
+//! those fields are ignored, reported, or rejected so that debug and release
+//! builds accept the same torrents.
+
+use crate::{
+    crypto::signature::SIGNATURE_FIELDS,
+    files::{filetree::FILETREEINFO_FIELDS, flatfile::FLATFILE_FIELDS},
+    metainfo::INFO_FIELDS,
+    torrent::TORRENT_FIELDS,
+};
+use log::{debug, trace};
+use serde_bencode::value::Value;
+use std::cell::Cell;
+
+const UNKNOWNFIELDS_TARGET: &str = "star_cloudburst::parseoptions::unknown_fields";
+
+/// Nonstandard fields written by popular clients.
+///
+/// These fields are accepted even with [UnknownFieldPolicy::Reject]. Top level
+/// fields are kept in [crate::Torrent::nonstandard] so that they survive
+/// serialization. Paths are dot separated like the paths of unknown fields.
+pub const NONSTANDARD_FIELDS: &[&str] = &[
+    // UTF-8 copies of fields written by BitComet and others.
+    "announce.utf-8",
+    "comment.utf-8",
+    "created by.utf-8",
+    "info.name.utf-8",
+    "info.files.path.utf-8",
+    "info.publisher",
+    "info.publisher.utf-8",
+    "info.publisher-url",
+    "info.publisher-url.utf-8",
+    // BitComet.
+    "locale",
+    "title",
+    // IPv6 DHT nodes from libtorrent.
+    "nodes6",
+];
+
+/// Options for [crate::Torrent::parse_with].
+///
+/// By default unknown fields are ignored while values such as the piece length
+/// are validated against the spec.
+///
+/// ```rust
+/// use star_cloudburst::{parseoptions::ParseOptions, Torrent};
+///
+/// let hamsters = b"d4:infod4:name12:hamsters.mkv6:lengthi42e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee7:wheelie3:yese";
+///
+/// assert!(Torrent::parse_with(hamsters, ParseOptions::default()).is_ok());
+/// assert!(Torrent::parse_with(hamsters, ParseOptions::strict()).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// How to handle fields which aren't declared by the types in this crate.
+    pub unknown_fields: UnknownFieldPolicy,
+    /// Accept any positive piece length rather than only powers of two of at
+    /// least 16 KiB. Nonconforming lengths are logged as warnings.
+    pub lenient_piece_length: bool,
+    /// Accept `nodes` entries written as `host:port` strings or as lists with
+    /// extra elements. Nonconforming nodes are logged as warnings.
+    pub lenient_nodes: bool,
+    /// Accept `announce-list` written as a flat list of URLs rather than a
+    /// list of tiers. Each URL becomes its own tier.
+    pub lenient_announce_list: bool,
+    /// How to handle `md5sum` values that aren't 32 lowercase hexadecimal
+    /// characters.
+    pub md5sum: Md5Policy,
+    /// Nonstandard fields to accept in addition to [NONSTANDARD_FIELDS], such
+    /// as `info.x_cross_seed`. Top level fields are kept like the built in
+    /// ones.
+    pub tolerated_fields: &'static [&'static str],
+}
+
+/// Handling of fields that aren't declared by this crate's types.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnknownFieldPolicy {
+    /// Drop unknown fields silently.
+    #[default]
+    Ignore,
+    /// Drop unknown fields but collect their paths into the
+    /// [crate::validation::ValidationReport] of [crate::Torrent::parse_report].
+    Report,
+    /// Reject torrents with unknown fields. The error lists every unknown
+    /// field rather than only the first.
+    Reject,
+}
+
+/// Validation of `md5sum` fields.
+///
+/// Some clients write `md5sum` as uppercase hexadecimal, raw bytes, or
+/// garbage.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Md5Policy {
+    /// Only accept 32 lowercase hexadecimal characters as per the spec.
+    Strict,
+    /// Also accept uppercase hexadecimal and raw 16 byte hashes. The original
+    /// bytes are kept so that the info hash doesn't change.
+    #[default]
+    Normalize,
+    /// Like [Md5Policy::Normalize] but invalid hashes are dropped rather than
+    /// failing the torrent.
+    Ignore,
+}
+
+const DEFAULT_OPTIONS: ParseOptions = ParseOptions {
+    unknown_fields: UnknownFieldPolicy::Ignore,
+    lenient_piece_length: false,
+    lenient_nodes: false,
+    lenient_announce_list: false,
+    md5sum: Md5Policy::Normalize,
+    tolerated_fields: &[],
+};
+
+impl Default for ParseOptions {
+    #[inline]
+    fn default() -> Self {
+        DEFAULT_OPTIONS
+    }
+}
+
+impl ParseOptions {
+    /// Options that reject unknown fields and nonconforming values.
+    #[inline]
+    pub fn strict() -> Self {
+        Self {
+            unknown_fields: UnknownFieldPolicy::Reject,
+            md5sum: Md5Policy::Strict,
+            ..Self::default()
+        }
+    }
+
+    /// Accept `fields` in addition to [NONSTANDARD_FIELDS].
+    ///
+    /// ```rust
+    /// use star_cloudburst::{parseoptions::ParseOptions, Torrent};
+    ///
+    /// let seeded = b"d4:infod4:name8:cats.mkv6:lengthi42e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee7:x_seeds4:catse";
+    /// assert!(Torrent::parse_with(seeded, ParseOptions::strict()).is_err());
+    ///
+    /// let options = ParseOptions::strict().tolerate(&["x_seeds"]);
+    /// let torrent = Torrent::parse_with(seeded, options)?;
+    /// assert!(torrent.nonstandard.contains_key("x_seeds"));
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    #[inline]
+    pub fn tolerate(self, fields: &'static [&'static str]) -> Self {
+        Self {
+            tolerated_fields: fields,
+            ..self
+        }
+    }
+
+    /// Whether the field at `path` is a nonstandard field that's accepted.
+    #[inline]
+    pub fn is_tolerated(&self, path: &str) -> bool {
+        NONSTANDARD_FIELDS.contains(&path) || self.tolerated_fields.contains(&path)
+    }
+
+    /// Options that accept nonconforming values found in real world torrents.
+    #[inline]
+    pub fn lenient() -> Self {
+        Self {
+            lenient_piece_length: true,
+            lenient_nodes: true,
+            lenient_announce_list: true,
+            md5sum: Md5Policy::Ignore,
+            ..Self::default()
+        }
+    }
+}
+
+thread_local! {
+    static CURRENT_OPTIONS: Cell<ParseOptions> = const { Cell::new(DEFAULT_OPTIONS) };
+}
+
+/// Options of the parse in progress on this thread.
+///
+/// `Deserialize` impls can't take arguments so [crate::Torrent::parse_with]
+/// stashes its options here for the duration of the parse.
+#[inline]
+pub(crate) fn current() -> ParseOptions {
+    CURRENT_OPTIONS.get()
+}
+
+/// Run `f` with `options` as the [current] options.
+pub(crate) fn with_options<T>(options: ParseOptions, f: impl FnOnce() -> T) -> T {
+    // Restores the previous options even if `f` panics.
+    struct Restore(ParseOptions);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT_OPTIONS.set(self.0);
+        }
+    }
+
+    let _restore = Restore(CURRENT_OPTIONS.replace(options));
+    f()
+}
+
+/// Find the paths of all fields in a bencoded torrent that aren't declared by
+/// this crate's types.
+///
+/// Paths are dot separated dict keys, such as `info.files.wheelie`. Path
+/// components are lossily converted to UTF-8. Fields tolerated by `options`
+/// are skipped. The result is sorted.
+pub(crate) fn unknown_fields(torrent: &Value, options: &ParseOptions) -> Vec<String> {
+    let mut unknown = Vec::new();
+
+    if let Value::Dict(dict) = torrent {
+        for (key, value) in dict {
+            let path = String::from_utf8_lossy(key);
+            match (path.as_ref(), value) {
+                ("info", Value::Dict(_)) => unknown_info_fields(value, &mut unknown),
+                ("signatures", Value::Dict(signatures)) => {
+                    for (name, signature) in signatures {
+                        let path = format!("signatures.{}", String::from_utf8_lossy(name));
+                        unknown_dict_fields(signature, &path, SIGNATURE_FIELDS, &mut unknown);
+                    }
+                }
+                (path, _) if !TORRENT_FIELDS.contains(&path) => unknown.push(path.to_owned()),
+                _ => {}
+            }
+        }
+    }
+
+    unknown.retain(|path| !options.is_tolerated(path));
+    unknown.sort();
+    debug!(
+        target: UNKNOWNFIELDS_TARGET,
+        "Found {} unknown fields",
+        unknown.len()
+    );
+    unknown
+}
+
+fn unknown_info_fields(info: &Value, unknown: &mut Vec<String>) {
+    unknown_dict_fields(info, "info", INFO_FIELDS, unknown);
+
+    if let Value::Dict(dict) = info {
+        if let Some(Value::List(files)) = dict.get(b"files".as_slice()) {
+            for file in files {
+                unknown_dict_fields(file, "info.files", FLATFILE_FIELDS, unknown);
+            }
+        }
+
+        if let Some(tree) = dict.get(b"file tree".as_slice()) {
+            unknown_file_tree_fields(tree, "info.file tree", unknown);
+        }
+    }
+}
+
+// File trees are nested dicts of directories where a file is a dict with an
+// empty key.
+fn unknown_file_tree_fields(tree: &Value, path: &str, unknown: &mut Vec<String>) {
+    if let Value::Dict(dict) = tree {
+        for (key, value) in dict {
+            if key.is_empty() {
+                unknown_dict_fields(value, path, FILETREEINFO_FIELDS, unknown);
+            } else {
+                let path = format!("{path}.{}", String::from_utf8_lossy(key));
+                unknown_file_tree_fields(value, &path, unknown);
+            }
+        }
+    }
+}
+
+fn unknown_dict_fields(value: &Value, path: &str, known: &[&str], unknown: &mut Vec<String>) {
+    if let Value::Dict(dict) = value {
+        for key in dict.keys() {
+            let key = String::from_utf8_lossy(key);
+            if !known.contains(&key.as_ref()) {
+                trace!(target: UNKNOWNFIELDS_TARGET, "Unknown field: {path}.{key}");
+                unknown.push(format!("{path}.{key}"));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{current, unknown_fields, ParseOptions};
+    use crate::Torrent;
+    use serde_bencode::value::Value;
+
+    #[test]
+    fn unknown_fields_nested() -> Result<(), serde_bencode::Error> {
+        let torrent = "d4:infod9:file treed4:dir1d9:fileA.txtd0:d6:lengthi1024e5:colori1eeeee5:filesld6:lengthi1e4:pathl1:ae5:tastei0eee4:name4:test12:piece lengthi16384e5:shinyi1ee7:wheelie3:yese";
+        let torrent: Value = serde_bencode::from_str(torrent)?;
+
+        assert_eq!(
+            unknown_fields(&torrent, &ParseOptions::default()),
+            [
+                "info.file tree.dir1.fileA.txt.color",
+                "info.files.taste",
+                "info.shiny",
+                "wheelie",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn nonstandard_fields_preserved() -> Result<(), serde_bencode::Error> {
+        let bitcomet = b"d4:infod6:lengthi42e4:name8:cats.mkv10:name.utf-88:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae6:locale2:en6:nodes6le5:title4:catse";
+        let torrent = Torrent::parse_with(bitcomet, ParseOptions::strict())?;
+
+        assert_eq!(
+            torrent.nonstandard.keys().collect::<Vec<_>>(),
+            ["locale", "nodes6", "title"]
+        );
+        assert_eq!(
+            torrent.nonstandard.get("title"),
+            Some(&Value::Bytes(b"cats".to_vec()))
+        );
+        assert!(serde_bencode::to_bytes(&torrent)?
+            .windows(b"5:title4:cats".len())
+            .any(|window| window == b"5:title4:cats"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn lenient_piece_length() {
+        let odd = b"d4:infod4:name8:cats.mkv6:lengthi42e6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi1000eee";
+        let tiny = b"d4:infod4:name8:cats.mkv6:lengthi42e6:pieces20:aaaaaaaaaaaaaaaaaaaa12:piece lengthi16eee";
+
+        for torrent in [odd.as_slice(), tiny] {
+            assert!(Torrent::parse_with(torrent, ParseOptions::default()).is_err());
+            assert!(Torrent::parse_with(torrent, ParseOptions::lenient()).is_ok());
+        }
+        assert_eq!(current(), ParseOptions::default());
+    }
+
+    #[test]
+    fn lenient_flat_announce_list() {
+        let flat = b"d13:announce-listl15:udp://cats.test15:udp://mews.teste4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+        assert!(Torrent::parse_with(flat, ParseOptions::default()).is_err());
+
+        let torrent = Torrent::parse_with(flat, ParseOptions::lenient()).unwrap();
+        let tiers = torrent.announce_list.unwrap();
+        assert_eq!(tiers.len(), 2);
+        assert!(tiers.iter().all(|tier| tier.len() == 1));
+    }
+}
@@ -0,0 +1,187 @@
+//! Statistics collected while parsing a torrent.
+//!
+//! [ParseStats] is returned by [crate::Torrent::from_bytes_with_stats] so that
+//! services ingesting many torrents can monitor the shape of their inputs and
+//! alert on pathological files (huge file counts, slow parses, et cetera).
+
+use crate::{files::FlatFile, metainfo::MetaInfo};
+use log::debug;
+use serde::{
+    de::{Error as DeErrorTrait, IgnoredAny, MapAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_bytes::ByteBuf;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::{self, Formatter},
+    time::Duration,
+};
+
+const PARSESTATS_TARGET: &str = "star_cloudburst::parsestats::ParseStats";
+
+/// Statistics on a single parsed torrent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// Length of the bencoded input in bytes.
+    pub bytes_read: usize,
+    /// Number of keys in the top level torrent dict, including unknown keys.
+    pub torrent_fields: usize,
+    /// Number of keys in the info dict, including unknown keys.
+    pub info_fields: usize,
+    /// Number of files shared by the torrent, excluding padding files.
+    pub file_count: usize,
+    /// Number of padding files.
+    pub padding_count: usize,
+    /// Time spent in each parsing phase.
+    pub durations: PhaseDurations,
+}
+
+/// Wall clock time spent per parsing phase.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhaseDurations {
+    /// Shallow scan that counts the raw dict keys.
+    pub scan: Duration,
+    /// Strongly typed deserialization into [crate::Torrent].
+    pub deserialize: Duration,
+    /// Info hash calculation. This is always zero without the `crypto` feature.
+    pub info_hash: Duration,
+}
+
+impl PhaseDurations {
+    /// Sum of all phases.
+    #[inline]
+    pub fn total(&self) -> Duration {
+        self.scan + self.deserialize + self.info_hash
+    }
+}
+
+// Shallow view of a torrent that counts the top level and info dict keys in one pass.
+struct KeyScan {
+    torrent_fields: usize,
+    info_fields: usize,
+}
+
+impl<'de> Deserialize<'de> for KeyScan {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(KeyScanVisitor)
+    }
+}
+
+struct KeyScanVisitor;
+
+impl<'de> Visitor<'de> for KeyScanVisitor {
+    type Value = KeyScan;
+
+    fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+        formatter.write_str("a torrent dict")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut keys = HashSet::new();
+        let mut info_fields = None;
+
+        while let Some(key) = map.next_key::<ByteBuf>()? {
+            if key.as_slice() == b"info" {
+                info_fields = Some(map.next_value::<HashMap<ByteBuf, IgnoredAny>>()?.len());
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+            keys.insert(key);
+        }
+
+        Ok(KeyScan {
+            torrent_fields: keys.len(),
+            info_fields: info_fields.ok_or_else(|| DeErrorTrait::missing_field("info"))?,
+        })
+    }
+}
+
+impl ParseStats {
+    /// Count the top level and info dict keys of a bencoded torrent.
+    pub(crate) fn scan(&mut self, bytes: &[u8]) -> Result<(), serde_bencode::Error> {
+        self.bytes_read = bytes.len();
+        let scan: KeyScan = serde_bencode::from_bytes(bytes)?;
+        self.torrent_fields = scan.torrent_fields;
+        self.info_fields = scan.info_fields;
+
+        debug!(
+            target: PARSESTATS_TARGET,
+            "Scanned {} bytes: {} torrent fields, {} info fields",
+            self.bytes_read,
+            self.torrent_fields,
+            self.info_fields
+        );
+        Ok(())
+    }
+
+    /// Count the files shared by the torrent.
+    pub(crate) fn count_files(&mut self, info: &MetaInfo) {
+        let padding: Vec<bool> = match info {
+            MetaInfo::MetaV1(_) | MetaInfo::MerkleV1(_) => {
+                info.iter_files().map(|file| file.padding).collect()
+            }
+            MetaInfo::MetaV2(dict) => dict
+                .file_tree
+                .iter_dfs()
+                .map(|view| view.file_info.is_padding())
+                .collect(),
+            MetaInfo::Hybrid(dict) => match (&dict.file_tree, &dict.files) {
+                (Some(tree), _) => tree
+                    .iter_dfs()
+                    .map(|view| view.file_info.is_padding())
+                    .collect(),
+                (None, Some(files)) => files.iter().map(FlatFile::is_padding).collect(),
+                (None, None) => vec![false],
+            },
+        };
+
+        self.padding_count = padding.iter().filter(|&&padding| padding).count();
+        self.file_count = padding.len() - self.padding_count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ParseStats;
+    use crate::Torrent;
+
+    const CATS: &[u8] = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+
+    #[test]
+    fn scan_counts_keys() -> Result<(), serde_bencode::Error> {
+        let mut stats = ParseStats::default();
+        stats.scan(CATS)?;
+
+        assert_eq!(stats.bytes_read, CATS.len());
+        assert_eq!(stats.torrent_fields, 2);
+        assert_eq!(stats.info_fields, 3);
+
+        // Unknown keys count too.
+        let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x007:wheelie4:meowe7:wheelie3:yese";
+        stats.scan(cats)?;
+        assert_eq!(stats.torrent_fields, 2);
+        assert_eq!(stats.info_fields, 5);
+
+        assert!(stats.scan(b"d8:announce9:localhoste").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn count_files_skips_padding() -> Result<(), serde_bencode::Error> {
+        let cats = b"d4:infod5:filesld6:lengthi16000e4:pathl8:cats.mkveed6:lengthi384e4:pathl36:_____padding_file_0_if you see this_eed4:attr1:p6:lengthi384e4:pathl4:.pad3:384eed6:lengthi8e4:pathl8:cats.nfoeee4:name4:cats6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+        let (torrent, stats) = Torrent::from_bytes_with_stats(cats)?;
+
+        assert_eq!(stats.file_count, 2);
+        assert_eq!(stats.padding_count, 2);
+        assert_eq!(torrent.info.file_list().len(), 2);
+
+        Ok(())
+    }
+}
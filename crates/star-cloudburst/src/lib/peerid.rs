@@ -0,0 +1,282 @@
+//! Peer IDs and the clients that generated them.
+//!
+//! Peer IDs are 20 arbitrary bytes, but most clients start them with their name
+//! and version in one of two conventions described by
+//! [BEP-0020](https://www.bittorrent.org/beps/bep_0020.html):
+//!
+//! * Azureus style: `-`, a two character client code, four version characters,
+//!   and `-` such as `-qB4650-`.
+//! * Shadow style: a one character client code followed by up to five version
+//!   characters and padded with `-` such as `S58B-----`.
+
+use crate::hexadecimal::HexBytes;
+use log::error;
+use serde::{
+    de::{value::Error as DeError, Error as DeErrorTrait},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use std::fmt::{self, Display, Formatter};
+
+const PEERID_DE_TARGET: &str = "star_cloudburst::peerid::PeerId::deserialize";
+const PEER_ID_LEN: usize = 20;
+
+// Clients that use Azureus style IDs.
+const AZUREUS_CLIENTS: &[(&str, &str)] = &[
+    ("7T", "aTorrent"),
+    ("AG", "Ares"),
+    ("AZ", "Azureus"),
+    ("BC", "BitComet"),
+    ("BI", "BiglyBT"),
+    ("BT", "BitTorrent"),
+    ("DE", "Deluge"),
+    ("FD", "Free Download Manager"),
+    ("FW", "FrostWire"),
+    ("KT", "KTorrent"),
+    ("LT", "libtorrent (Rasterbar)"),
+    ("lt", "libTorrent (Rakshasa)"),
+    ("PI", "PicoTorrent"),
+    ("qB", "qBittorrent"),
+    ("SD", "Thunder"),
+    ("TR", "Transmission"),
+    ("UM", "µTorrent for Mac"),
+    ("UT", "µTorrent"),
+    ("UW", "µTorrent Web"),
+    ("WW", "WebTorrent"),
+    ("XL", "Xunlei"),
+];
+
+// Clients that use Shadow style IDs.
+const SHADOW_CLIENTS: &[(&str, &str)] = &[
+    ("A", "ABC"),
+    ("O", "Osprey Permaseed"),
+    ("Q", "BTQueue"),
+    ("R", "Tribler"),
+    ("S", "Shadow's client"),
+    ("T", "BitTornado"),
+    ("U", "UPnP NAT Bit Torrent"),
+];
+
+/// Convention a client used to encode its name and version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PeerIdStyle {
+    Azureus,
+    Shadow,
+}
+
+/// Client decoded from a [PeerId].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PeerClient {
+    pub style: PeerIdStyle,
+    /// Client code such as `qB`.
+    pub code: String,
+    /// Name of the client if the code is known.
+    pub name: Option<&'static str>,
+    /// Dot separated version such as `4.6.5.0`.
+    pub version: String,
+}
+
+impl Display for PeerClient {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name.unwrap_or(&self.code), self.version)
+    }
+}
+
+/// 20 byte ID that a peer sends to trackers and other peers.
+///
+/// ```rust
+/// use star_cloudburst::peerid::PeerId;
+///
+/// let peer_id = PeerId::try_from(&b"-qB4650-cats.meow.mew"[..20])?;
+/// let client = peer_id.client().expect("qBittorrent peer ID.");
+/// assert_eq!(client.to_string(), "qBittorrent 4.6.5.0");
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct PeerId([u8; PEER_ID_LEN]);
+
+impl PeerId {
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8; PEER_ID_LEN] {
+        &self.0
+    }
+
+    /// Client name and version if the ID follows a known convention.
+    ///
+    /// Azureus style IDs with unknown codes are still decoded, but Shadow style
+    /// IDs need a known code because there isn't a delimiter that
+    /// distinguishes them from random bytes.
+    #[inline]
+    pub fn client(&self) -> Option<PeerClient> {
+        self.azureus().or_else(|| self.shadow())
+    }
+
+    fn azureus(&self) -> Option<PeerClient> {
+        let [b'-', code @ .., b'-'] = &self.0[..8] else {
+            return None;
+        };
+        let (code, version) = code.split_at(2);
+        let code = std::str::from_utf8(code)
+            .ok()
+            .filter(|code| code.chars().all(|c| c.is_ascii_alphanumeric()))?;
+        let version = version
+            .iter()
+            .map(|&c| decode_version_char(c).filter(|&part| part < 36))
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(PeerClient {
+            style: PeerIdStyle::Azureus,
+            code: code.to_owned(),
+            name: client_name(AZUREUS_CLIENTS, code),
+            version: join_version(&version),
+        })
+    }
+
+    fn shadow(&self) -> Option<PeerClient> {
+        let code = std::str::from_utf8(&self.0[..1]).ok()?;
+        let name = client_name(SHADOW_CLIENTS, code)?;
+
+        // Up to five version characters padded by at least two dashes.
+        let version: Vec<_> = self.0[1..6]
+            .iter()
+            .map_while(|&c| decode_version_char(c))
+            .collect();
+        if version.is_empty() || self.0[1 + version.len()..].get(..2)? != b"--" {
+            return None;
+        }
+
+        Some(PeerClient {
+            style: PeerIdStyle::Shadow,
+            code: code.to_owned(),
+            name: Some(name),
+            version: join_version(&version),
+        })
+    }
+}
+
+// Version characters are 0-9, A-Z, a-z, and `.` for 0 to 62.
+fn decode_version_char(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'Z' => Some(c - b'A' + 10),
+        b'a'..=b'z' => Some(c - b'a' + 36),
+        b'.' => Some(62),
+        _ => None,
+    }
+}
+
+fn join_version(parts: &[u8]) -> String {
+    parts
+        .iter()
+        .map(u8::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn client_name(clients: &[(&str, &'static str)], code: &str) -> Option<&'static str> {
+    clients
+        .iter()
+        .find_map(|&(known, name)| (known == code).then_some(name))
+}
+
+impl From<[u8; PEER_ID_LEN]> for PeerId {
+    #[inline]
+    fn from(bytes: [u8; PEER_ID_LEN]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl TryFrom<&[u8]> for PeerId {
+    type Error = DeError;
+
+    #[inline]
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        bytes
+            .try_into()
+            .map(Self)
+            .map_err(|_| DeError::invalid_length(bytes.len(), &"20 bytes"))
+    }
+}
+
+impl Display for PeerId {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        <HexBytes as Display>::fmt(&self.0.into(), f)
+    }
+}
+
+impl<'de> Deserialize<'de> for PeerId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let bytes = ByteBuf::deserialize(deserializer)?;
+        bytes.as_slice().try_into().map_err(|e| {
+            error!(target: PEERID_DE_TARGET, "Invalid peer ID: {e}");
+            D::Error::custom(e)
+        })
+    }
+}
+
+impl Serialize for PeerId {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PeerId, PeerIdStyle};
+
+    fn peer_id(prefix: &[u8]) -> PeerId {
+        let mut bytes = [b'x'; 20];
+        bytes[..prefix.len()].copy_from_slice(prefix);
+        bytes.into()
+    }
+
+    #[test]
+    fn decode_clients() {
+        let transmission = peer_id(b"-TR3000-").client().unwrap();
+        assert_eq!(transmission.style, PeerIdStyle::Azureus);
+        assert_eq!(transmission.name, Some("Transmission"));
+        assert_eq!(transmission.version, "3.0.0.0");
+
+        let unknown = peer_id(b"-ZZ12A0-").client().unwrap();
+        assert_eq!((unknown.name, unknown.code.as_str()), (None, "ZZ"));
+        assert_eq!(unknown.version, "1.2.10.0");
+
+        let shadow = peer_id(b"S58B-----").client().unwrap();
+        assert_eq!(shadow.style, PeerIdStyle::Shadow);
+        assert_eq!(shadow.to_string(), "Shadow's client 5.8.11");
+
+        let bittornado = peer_id(b"T03I--").client().unwrap();
+        assert_eq!(bittornado.version, "0.3.18");
+    }
+
+    #[test]
+    fn reject_unknown_conventions() {
+        assert_eq!(peer_id(b"").client(), None);
+        assert_eq!(peer_id(b"-qB46!0-").client(), None);
+        // Unknown Shadow style code.
+        assert_eq!(peer_id(b"Z58B-----").client(), None);
+        // Shadow style IDs are padded with dashes.
+        assert_eq!(peer_id(b"S58B").client(), None);
+    }
+
+    #[test]
+    fn peer_id_bencode() {
+        let peer_id = peer_id(b"-qB4650-");
+        let encoded = serde_bencode::to_bytes(&peer_id).unwrap();
+        assert_eq!(&encoded, b"20:-qB4650-xxxxxxxxxxxx");
+        assert_eq!(
+            serde_bencode::from_bytes::<PeerId>(&encoded).unwrap(),
+            peer_id
+        );
+        assert!(serde_bencode::from_bytes::<PeerId>(b"3:-qB").is_err());
+    }
+}
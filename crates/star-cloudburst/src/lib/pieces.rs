@@ -1,21 +1,94 @@
-use crate::{crypto::sha1::Sha1, hexadecimal::HexBytes};
-use log::{error, trace};
+use crate::{
+    crypto::sha1::Sha1,
+    parseobserver::{self, ParseEvent},
+    parseoptions,
+};
+use log::{error, trace, warn};
 use serde::{
     de::{Error as DeErrorTrait, Unexpected},
-    Deserialize, Deserializer, Serialize,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
-use std::{fmt::Debug, num::NonZeroU64};
+use std::{fmt::Debug, num::NonZeroU64, sync::Arc};
 
 const PIECES_DE_TARGET: &str = "star_cloudburst::Piece::deserialize";
 const PIECELENGTH_DE_TARGET: &str = "star_cloudburst::PieceLength::deserialize";
 
+/// Smallest piece length allowed by the spec.
+pub const MIN_PIECE_LENGTH: u64 = 16 * 1024;
+
+/// Largest piece length recommended by [PieceLength::recommended_for].
+///
+/// Larger pieces are valid but clients have to download and verify a lot of
+/// data before sharing any of it.
+pub const MAX_RECOMMENDED_PIECE_LENGTH: u64 = 16 * 1024 * 1024;
+
+// Piece lengths are doubled until there are at most this many pieces.
+const MAX_RECOMMENDED_PIECES: u64 = 2000;
+
 /// Number of bytes per piece.
 ///
-/// According to the spec, piece length should be greater than 16 KiB and is always a power of two.
+/// According to the spec, piece length should be at least 16 KiB and is always a power of two.
+/// Some torrents in the wild violate this. Those are only accepted with
+/// [crate::parseoptions::ParseOptions::lenient_piece_length].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
 #[serde(transparent)]
 pub struct PieceLength(NonZeroU64);
 
+impl PieceLength {
+    /// Piece length that follows the spec.
+    ///
+    /// Returns `None` if `piece_length` is less than 16 KiB or not a power of two.
+    #[inline]
+    pub fn new(piece_length: u64) -> Option<Self> {
+        NonZeroU64::new(piece_length)
+            .filter(|piece_length| {
+                piece_length.get() >= MIN_PIECE_LENGTH && piece_length.is_power_of_two()
+            })
+            .map(PieceLength)
+    }
+
+    /// Recommended piece length for a torrent that shares `total_size` bytes.
+    ///
+    /// The piece length is the smallest power of two that splits `total_size` into at most 2000
+    /// pieces, which leaves 1000 to 2000 pieces for all but the smallest and largest torrents. It's
+    /// clamped to 16 KiB to 16 MiB.
+    ///
+    /// ```rust
+    /// use star_cloudburst::PieceLength;
+    ///
+    /// const GIB: u64 = 1024 * 1024 * 1024;
+    ///
+    /// assert_eq!(PieceLength::recommended_for(0).get(), 16 * 1024);
+    /// assert_eq!(PieceLength::recommended_for(GIB).get(), 1024 * 1024);
+    /// assert_eq!(PieceLength::recommended_for(100 * GIB).get(), 16 * 1024 * 1024);
+    /// ```
+    pub fn recommended_for(total_size: u64) -> Self {
+        let mut piece_length = MIN_PIECE_LENGTH;
+        while piece_length < MAX_RECOMMENDED_PIECE_LENGTH
+            && total_size.div_ceil(piece_length) > MAX_RECOMMENDED_PIECES
+        {
+            piece_length *= 2;
+        }
+
+        Self::new(piece_length).expect("powers of two of at least 16 KiB are valid")
+    }
+
+    /// Whether this piece length is far from [PieceLength::recommended_for] `total_size`.
+    ///
+    /// Piece lengths within a factor of four of the recommendation are fine; further away means
+    /// either a huge number of pieces or very coarse ones.
+    #[inline]
+    pub fn is_unusual_for(self, total_size: u64) -> bool {
+        let recommended = Self::recommended_for(total_size).get();
+        self.get() > recommended * 4 || self.get() * 4 < recommended
+    }
+
+    #[inline]
+    pub fn get(self) -> u64 {
+        self.0.get()
+    }
+}
+
 impl<'de> Deserialize<'de> for PieceLength {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -25,7 +98,17 @@ impl<'de> Deserialize<'de> for PieceLength {
 
         let piece_length = NonZeroU64::deserialize(deserializer)?;
 
-        if piece_length.get() >= 16 && piece_length.is_power_of_two() {
+        if piece_length.get() >= MIN_PIECE_LENGTH && piece_length.is_power_of_two() {
+            Ok(PieceLength(piece_length))
+        } else if parseoptions::current().lenient_piece_length {
+            warn!(
+                target: PIECELENGTH_DE_TARGET,
+                "Accepting nonconforming piece length: {piece_length}."
+            );
+            parseobserver::report(|| ParseEvent::LenientFix {
+                field: "piece length",
+                detail: format!("accepted {piece_length}"),
+            });
             Ok(PieceLength(piece_length))
         } else {
             error!(
@@ -34,7 +117,7 @@ impl<'de> Deserialize<'de> for PieceLength {
             );
             Err(DeErrorTrait::invalid_value(
                 Unexpected::Unsigned(piece_length.into()),
-                &"piece length should be greater than 16 and a power of two",
+                &"piece length should be at least 16 KiB and a power of two",
             ))
         }
     }
@@ -43,9 +126,11 @@ impl<'de> Deserialize<'de> for PieceLength {
 /// Per file SHA-1 hashes.
 ///
 /// The BitTorrent spec specifies `pieces` as a single byte buffer that is a multiple of 20.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
-#[serde(transparent)]
-pub struct Pieces(HexBytes);
+///
+/// The buffer may be several megabytes, so it's reference counted. Cloning [Pieces] or a
+/// [crate::Torrent] doesn't copy it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pieces(Arc<[u8]>);
 
 impl<'de> Deserialize<'de> for Pieces {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -55,8 +140,8 @@ impl<'de> Deserialize<'de> for Pieces {
         trace!(target: PIECES_DE_TARGET, "Deserializing Pieces.");
 
         // This is already a byte string so I can't really validate it.
-        let pieces: HexBytes = match serde_bytes::ByteBuf::deserialize(deserializer) {
-            Ok(bytes) => HexBytes::new(bytes),
+        let pieces: Arc<[u8]> = match serde_bytes::ByteBuf::deserialize(deserializer) {
+            Ok(bytes) => bytes.into_vec().into(),
             Err(e) => {
                 error!(
                     target: PIECES_DE_TARGET,
@@ -68,7 +153,7 @@ impl<'de> Deserialize<'de> for Pieces {
 
         // `pieces` must be a multiple of 20 because they're SHA-1 hashes.
         let len = pieces.len();
-        if len % 20 == 0 {
+        if len.is_multiple_of(20) {
             Ok(Pieces(pieces))
         } else {
             error!(
@@ -83,11 +168,50 @@ impl<'de> Deserialize<'de> for Pieces {
     }
 }
 
+impl Serialize for Pieces {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
 impl Pieces {
+    /// Concatenate SHA-1 piece hashes.
+    #[inline]
+    pub fn from_hashes(hashes: &[[u8; 20]]) -> Self {
+        Pieces(hashes.concat().into())
+    }
+
+    /// Concatenated hashes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Hash of the piece at `index` without copying it.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Pieces;
+    ///
+    /// let pieces = Pieces::from_hashes(&[[1; 20], [2; 20]]);
+    /// assert_eq!(pieces.get(1), Some(&[2; 20]));
+    /// assert_eq!(pieces.get(2), None);
+    /// ```
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&[u8; 20]> {
+        let start = index.checked_mul(20)?;
+        self.0
+            .get(start..start.checked_add(20)?)
+            .map(|hash| hash.try_into().expect("Slice is 20 bytes."))
+    }
+
     /// Iterator over chunks of 20 bytes.
     #[inline]
     pub fn iter_pieces_bytes(&self) -> impl Iterator<Item = &[u8]> + '_ {
-        self.0.as_slice().chunks(20)
+        self.0.chunks(20)
     }
 
     /// Iterator over bytes wrapped in [Sha1Hash].
@@ -110,3 +234,48 @@ impl Pieces {
         self.0.is_empty()
     }
 }
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PieceLength {
+    // Powers of two from 16 KiB to 16 MiB.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let exponent = u.int_in_range(14..=24)?;
+        Ok(PieceLength(
+            NonZeroU64::new(1 << exponent).expect("Powers of two are never zero."),
+        ))
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Pieces {
+    // At least one SHA-1 hash.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let len = u.int_in_range(1..=64)?;
+        let pieces = (0..len)
+            .map(|_| u.arbitrary::<[u8; 20]>())
+            .collect::<arbitrary::Result<Vec<_>>>()?
+            .concat();
+        Ok(Pieces(pieces.into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PieceLength, MAX_RECOMMENDED_PIECE_LENGTH, MIN_PIECE_LENGTH};
+
+    #[test]
+    fn recommended_piece_counts() {
+        // Sizes between the bounds always land on 1000 to 2000 pieces.
+        let lower = MIN_PIECE_LENGTH * 2000;
+        let upper = MAX_RECOMMENDED_PIECE_LENGTH * 2000;
+        for total_size in [lower + 1, 700_000_000, 4_700_000_000, 25_000_000_000, upper] {
+            let piece_length = PieceLength::recommended_for(total_size);
+            let pieces = total_size.div_ceil(piece_length.get());
+            assert!((1000..=2000).contains(&pieces), "{total_size}: {pieces}");
+            assert!(!piece_length.is_unusual_for(total_size));
+        }
+
+        let tiny = PieceLength::new(MIN_PIECE_LENGTH).unwrap();
+        assert!(tiny.is_unusual_for(upper));
+    }
+}
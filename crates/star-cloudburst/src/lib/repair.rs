@@ -0,0 +1,143 @@
+//! Fix common malformations in torrents.
+//!
+//! Torrent creators and editors routinely produce torrents with duplicate or
+//! misplaced trackers and other small mistakes that clients tolerate but that
+//! make the torrent awkward to work with. [Torrent::repair] fixes the well
+//! known ones and reports what it changed.
+
+use crate::{
+    metainfo::{MetaInfo, MetaVersion},
+    Torrent,
+};
+use log::debug;
+use std::collections::HashSet;
+
+const REPAIR_TARGET: &str = "star_cloudburst::Torrent::repair";
+
+/// A change made by [Torrent::repair].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repair {
+    /// Trackers listed more than once in `announce-list` were removed.
+    DuplicateTrackersRemoved(usize),
+    /// Tiers without trackers were removed from `announce-list`.
+    EmptyTiersRemoved(usize),
+    /// The tier containing `announce` was moved to the front of `announce-list`.
+    AnnounceTierMoved,
+    /// `announce` was missing from `announce-list` and was added as the first
+    /// tier. Clients that support `announce-list` ignore `announce`.
+    AnnounceTierAdded,
+    /// Empty URLs were removed from `url-list`.
+    EmptyWebSeedsRemoved(usize),
+    /// A hybrid info dict with a `file tree` was missing `meta version`.
+    ///
+    /// This changes the info hash.
+    MetaVersionAdded,
+}
+
+impl Torrent {
+    /// Fix well known malformations in place.
+    ///
+    /// Trackers keep their first position in tier order. The info dict is only
+    /// changed if it's invalid without the repair. Returns the repairs in the
+    /// order they were made; an empty list means the torrent was fine.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{repair::Repair, Torrent};
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test13:announce-listll15:udp://mews.testel15:udp://mews.test15:udp://cats.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!(
+    ///     torrent.repair(),
+    ///     [Repair::DuplicateTrackersRemoved(1), Repair::AnnounceTierMoved]
+    /// );
+    /// assert!(torrent.repair().is_empty());
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn repair(&mut self) -> Vec<Repair> {
+        let mut repairs = Vec::new();
+
+        if let Some(tiers) = &mut self.announce_list {
+            let mut seen = HashSet::new();
+            let mut duplicates = 0;
+            for tier in tiers.iter_mut() {
+                let before = tier.len();
+                tier.retain(|tracker| seen.insert(tracker.clone()));
+                duplicates += before - tier.len();
+            }
+            if duplicates > 0 {
+                repairs.push(Repair::DuplicateTrackersRemoved(duplicates));
+            }
+
+            let before = tiers.len();
+            tiers.retain(|tier| !tier.is_empty());
+            if tiers.len() < before {
+                repairs.push(Repair::EmptyTiersRemoved(before - tiers.len()));
+            }
+
+            if let Some(announce) = &self.announce {
+                match tiers.iter().position(|tier| tier.contains(announce)) {
+                    Some(0) => {}
+                    Some(position) => {
+                        let tier = tiers.remove(position);
+                        tiers.insert(0, tier);
+                        repairs.push(Repair::AnnounceTierMoved);
+                    }
+                    None if !tiers.is_empty() => {
+                        tiers.insert(0, vec![announce.clone()]);
+                        repairs.push(Repair::AnnounceTierAdded);
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        if let Some(url_list) = &mut self.url_list {
+            let before = url_list.len();
+            url_list.retain(|url| !url.to_string().trim().is_empty());
+            if url_list.len() < before {
+                repairs.push(Repair::EmptyWebSeedsRemoved(before - url_list.len()));
+            }
+        }
+
+        if let MetaInfo::Hybrid(hybrid) = &self.info {
+            if hybrid.file_tree.is_some() && hybrid.meta_version.is_none() {
+                if let MetaInfo::Hybrid(hybrid) = self.info_mut() {
+                    hybrid.meta_version = Some(MetaVersion::V2);
+                }
+                repairs.push(Repair::MetaVersionAdded);
+            }
+        }
+
+        debug!(target: REPAIR_TARGET, "Repaired {}: {repairs:?}", self.name());
+        repairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Repair;
+    use crate::{metainfo::MetaVersion, Torrent};
+
+    #[test]
+    fn repair_tiers_and_meta_version() -> Result<(), serde_bencode::Error> {
+        let cats = "d8:announce15:udp://cats.test13:announce-listllel15:udp://mews.testee4:infod9:file treed8:cats.mkvd0:d6:lengthi42eeee4:name4:cats12:piece lengthi16384eee";
+        let mut torrent: Torrent = serde_bencode::from_str(cats)?;
+
+        assert_eq!(
+            torrent.repair(),
+            [
+                Repair::EmptyTiersRemoved(1),
+                Repair::AnnounceTierAdded,
+                Repair::MetaVersionAdded
+            ]
+        );
+        assert_eq!(torrent.announce_list.as_ref().map(Vec::len), Some(2));
+        assert_eq!(torrent.info.meta_version(), Some(MetaVersion::V2));
+        assert!(torrent.repair().is_empty());
+
+        Ok(())
+    }
+}
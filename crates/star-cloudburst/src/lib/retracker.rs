@@ -0,0 +1,271 @@
+//! Substitute trackers with mapping rules.
+//!
+//! Trackers move to new domains, switch to HTTPS, or change their announce
+//! paths, and every torrent that lists the old tracker needs to be rewritten.
+//! [TrackerRule]s map old trackers to new ones, and [substitute_trackers]
+//! applies them to `announce` and `announce-list`. Trackers aren't part of the
+//! info dict so substitutions never change the info hash.
+
+use crate::{uri::UriWrapper, Torrent};
+use log::debug;
+#[cfg(feature = "regex")]
+use regex::Regex;
+use serde::de::value::Error as DeError;
+
+const SUBSTITUTE_TARGET: &str = "star_cloudburst::retracker::substitute_trackers";
+
+/// Rule that maps matching trackers to a new tracker.
+#[derive(Debug, Clone)]
+pub enum TrackerRule {
+    /// Replace trackers equal to `from` with `to`.
+    Exact { from: UriWrapper, to: UriWrapper },
+    /// Replace the host of trackers on `from` with `to`.
+    ///
+    /// Hosts are compared case insensitively. The scheme, port, path, and
+    /// query such as a passkey are kept.
+    Host { from: String, to: String },
+    /// Replace the first match of `pattern` in the tracker's URL.
+    ///
+    /// `replacement` may refer to capture groups like [Regex::replace].
+    #[cfg(feature = "regex")]
+    Regex { pattern: Regex, replacement: String },
+}
+
+impl TrackerRule {
+    /// The substituted tracker if the rule matches `tracker`.
+    pub fn apply(&self, tracker: &UriWrapper) -> Result<Option<UriWrapper>, DeError> {
+        match self {
+            TrackerRule::Exact { from, to } => Ok((tracker == from).then(|| to.clone())),
+            TrackerRule::Host { from, to } => {
+                let url = tracker.to_string();
+                match host_range(&url) {
+                    Some(host) if url[host.clone()].eq_ignore_ascii_case(from) => {
+                        let mut substituted = url;
+                        substituted.replace_range(host, to);
+                        substituted.parse().map(Some)
+                    }
+                    _ => Ok(None),
+                }
+            }
+            #[cfg(feature = "regex")]
+            TrackerRule::Regex {
+                pattern,
+                replacement,
+            } => {
+                let url = tracker.to_string();
+                if pattern.is_match(&url) {
+                    pattern.replace(&url, replacement).parse().map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+}
+
+/// A tracker replaced by [substitute_trackers].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackerChange {
+    /// Tier of `announce-list` or `None` for `announce`.
+    pub tier: Option<usize>,
+    pub old: UriWrapper,
+    pub new: UriWrapper,
+}
+
+// Trackers of a torrent after substitution.
+pub(crate) struct Substituted {
+    announce: Option<UriWrapper>,
+    announce_list: Option<Vec<Vec<UriWrapper>>>,
+    changes: Vec<TrackerChange>,
+}
+
+impl Substituted {
+    pub(crate) fn new(torrent: &Torrent, rules: &[TrackerRule]) -> Result<Self, DeError> {
+        let mut changes = Vec::new();
+
+        let announce = torrent
+            .announce
+            .as_ref()
+            .map(|tracker| substitute(tracker, None, rules, &mut changes))
+            .transpose()?;
+        let announce_list = torrent
+            .announce_list
+            .as_ref()
+            .map(|tiers| {
+                tiers
+                    .iter()
+                    .enumerate()
+                    .map(|(tier, trackers)| {
+                        trackers
+                            .iter()
+                            .map(|tracker| substitute(tracker, Some(tier), rules, &mut changes))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+
+        Ok(Self {
+            announce,
+            announce_list,
+            changes,
+        })
+    }
+
+    pub(crate) fn apply(self, torrent: &mut Torrent) -> Vec<TrackerChange> {
+        if !self.changes.is_empty() {
+            torrent.announce = self.announce;
+            torrent.announce_list = self.announce_list;
+        }
+        self.changes
+    }
+}
+
+/// Replace trackers in `announce` and `announce-list` according to `rules`.
+///
+/// Each tracker is replaced by the first rule that matches it. The torrent is
+/// unchanged if a substituted tracker isn't a valid URI. Substitutions may
+/// leave duplicate trackers which [Torrent::repair] removes.
+///
+/// ```rust
+/// use star_cloudburst::{
+///     retracker::{substitute_trackers, TrackerRule},
+///     Torrent,
+/// };
+///
+/// let cats = b"d8:announce31:http://cats.test/announce?pk=4213:announce-listll31:http://cats.test/announce?pk=42el15:udp://mews.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+/// let mut torrent = Torrent::from_bytes(cats)?;
+///
+/// let rules = [
+///     TrackerRule::Host {
+///         from: "cats.test".to_owned(),
+///         to: "purr.test".to_owned(),
+///     },
+///     TrackerRule::Exact {
+///         from: "udp://mews.test".parse()?,
+///         to: "udp://meow.test".parse()?,
+///     },
+/// ];
+/// let changes = substitute_trackers(&mut torrent, &rules)?;
+/// assert_eq!(changes.len(), 3);
+///
+/// let trackers: Vec<_> = torrent.unique_trackers().map(ToString::to_string).collect();
+/// assert_eq!(
+///     trackers,
+///     ["http://purr.test/announce?pk=42", "udp://meow.test"]
+/// );
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn substitute_trackers(
+    torrent: &mut Torrent,
+    rules: &[TrackerRule],
+) -> Result<Vec<TrackerChange>, DeError> {
+    Substituted::new(torrent, rules).map(|substituted| substituted.apply(torrent))
+}
+
+fn substitute(
+    tracker: &UriWrapper,
+    tier: Option<usize>,
+    rules: &[TrackerRule],
+    changes: &mut Vec<TrackerChange>,
+) -> Result<UriWrapper, DeError> {
+    for rule in rules {
+        if let Some(new) = rule.apply(tracker)? {
+            if new != *tracker {
+                debug!(target: SUBSTITUTE_TARGET, "{tracker} => {new}");
+                changes.push(TrackerChange {
+                    tier,
+                    old: tracker.clone(),
+                    new: new.clone(),
+                });
+            }
+            return Ok(new);
+        }
+    }
+    Ok(tracker.clone())
+}
+
+// Byte range of the host in `url` excluding user info, port, and IPv6 brackets.
+fn host_range(url: &str) -> Option<std::ops::Range<usize>> {
+    let authority_start = url.find("://")? + 3;
+    let authority_len = url[authority_start..]
+        .find(['/', '?', '#'])
+        .unwrap_or(url.len() - authority_start);
+    let authority = &url[authority_start..authority_start + authority_len];
+    let host_start = authority.rfind('@').map_or(0, |at| at + 1);
+    let host = &authority[host_start..];
+
+    let start = authority_start + host_start;
+    match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.find(']').map(|end| start + 1..start + 1 + end),
+        None => Some(start..start + host.find(':').unwrap_or(host.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::host_range;
+
+    #[test]
+    fn host_ranges() {
+        for (url, host) in [
+            ("udp://cats.test:6969/announce", "cats.test"),
+            ("http://user:pw@cats.test?pk=1", "cats.test"),
+            ("http://[::1]:80/announce", "::1"),
+            ("https://cats.test", "cats.test"),
+        ] {
+            assert_eq!(host_range(url).map(|range| &url[range]), Some(host));
+        }
+        assert_eq!(host_range("cats.test"), None);
+    }
+
+    // Without `uri`, any string is a valid tracker.
+    #[cfg(feature = "uri")]
+    #[test]
+    fn invalid_substitution_unchanged() -> Result<(), Box<dyn std::error::Error>> {
+        use super::{substitute_trackers, TrackerRule};
+        use crate::Torrent;
+
+        let cats = b"d8:announce15:udp://cats.test13:announce-listll15:udp://cats.testel15:udp://mews.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+        let mut torrent: Torrent = serde_bencode::from_bytes(cats)?;
+
+        let rules = [
+            TrackerRule::Host {
+                from: "CATS.test".to_owned(),
+                to: "purr.test".to_owned(),
+            },
+            TrackerRule::Host {
+                from: "mews.test".to_owned(),
+                to: "mews test".to_owned(),
+            },
+        ];
+        let before = torrent.clone();
+        assert!(substitute_trackers(&mut torrent, &rules).is_err());
+        assert_eq!(torrent, before);
+
+        let changes = substitute_trackers(&mut torrent, &rules[..1])?;
+        assert_eq!(
+            changes.iter().map(|change| change.tier).collect::<Vec<_>>(),
+            [None, Some(0)]
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_captures() -> Result<(), Box<dyn std::error::Error>> {
+        use super::TrackerRule;
+
+        let tracker = "http://cats.test/announce/42".parse()?;
+        let rule = TrackerRule::Regex {
+            pattern: regex::Regex::new(r"^http://cats\.test/announce/(\d+)$")?,
+            replacement: "https://cats.test/$1/announce".to_owned(),
+        };
+
+        assert_eq!(
+            rule.apply(&tracker)?.map(|new| new.to_string()).as_deref(),
+            Some("https://cats.test/42/announce")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,177 @@
+//! Human readable descriptions of torrents.
+//!
+//! [TorrentSummary] formats a torrent with configurable [Verbosity]. The
+//! default only prints counts so that it stays short for torrents with
+//! thousands of files. [Display] for [Torrent] uses the default summary.
+
+use crate::Torrent;
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Display, Formatter},
+};
+
+/// How much of a torrent [TorrentSummary] prints.
+///
+/// Every level includes the fields of the levels before it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Verbosity {
+    /// Name only.
+    Name,
+    /// Name, total size, number of trackers, and number of files.
+    #[default]
+    Counts,
+    /// Counts and the directories at the root of the torrent.
+    TopLevel,
+    /// Every file along with the meta version, info hash, and piece length.
+    Verbose,
+}
+
+/// Description of a torrent with configurable [Verbosity].
+///
+/// ```rust
+/// use star_cloudburst::{summary::Verbosity, Torrent};
+///
+/// let cats = b"d8:announce9:localhost4:infod5:filesld6:lengthi42e4:pathl4:cats8:cats.mkveed6:lengthi6e4:pathl8:mews.mkveee4:name4:cats12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+/// let torrent = Torrent::from_bytes(cats)?;
+///
+/// assert_eq!(torrent.summary().with_verbosity(Verbosity::Name).to_string(), "cats");
+/// assert_eq!(
+///     torrent.to_string(),
+///     "cats\nSize: 48 bytes\nTrackers: 1\nFiles: 2"
+/// );
+/// assert_eq!(
+///     torrent.summary().with_verbosity(Verbosity::TopLevel).to_string(),
+///     "cats\nSize: 48 bytes\nTrackers: 1\nFiles: 2\nDirectories: cats/"
+/// );
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TorrentSummary<'torrent> {
+    torrent: &'torrent Torrent,
+    verbosity: Verbosity,
+}
+
+impl<'torrent> TorrentSummary<'torrent> {
+    /// Summarize `torrent` with the default [Verbosity].
+    #[inline]
+    pub fn new(torrent: &'torrent Torrent) -> Self {
+        Self {
+            torrent,
+            verbosity: Verbosity::default(),
+        }
+    }
+
+    /// Print the fields of `verbosity`.
+    #[inline]
+    pub fn with_verbosity(self, verbosity: Verbosity) -> Self {
+        Self { verbosity, ..self }
+    }
+
+    /// Print every file; same as [Verbosity::Verbose].
+    #[inline]
+    pub fn verbose(self) -> Self {
+        self.with_verbosity(Verbosity::Verbose)
+    }
+
+    /// Selected [Verbosity].
+    #[inline]
+    pub fn verbosity(&self) -> Verbosity {
+        self.verbosity
+    }
+
+    /// Directories at the root of the torrent in sorted order.
+    ///
+    /// Files at the root aren't included. Single file torrents have none.
+    pub fn top_level_dirs(&self) -> BTreeSet<&'torrent str> {
+        self.torrent
+            .info
+            .file_list()
+            .into_iter()
+            .filter(|(path, _)| path.len() > 1)
+            .map(|(path, _)| path[0])
+            .collect()
+    }
+
+    fn fmt_verbose(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let torrent = self.torrent;
+        let mut debug = f.debug_struct(&format!("Torrent: {}", torrent.name()));
+        debug
+            .field("Meta info version", &torrent.info.meta_version_str())
+            .field("Files", {
+                let files: Vec<_> = torrent.info.iter_files().collect();
+                &format!("{files:#?}")
+            });
+        #[cfg(feature = "crypto")]
+        debug.field("Info hash", &torrent.info_hash());
+        debug
+            .field("Piece length", &torrent.info.piece_length())
+            .finish()
+    }
+}
+
+impl Display for TorrentSummary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let torrent = self.torrent;
+        match self.verbosity {
+            Verbosity::Name => return f.write_str(torrent.name()),
+            Verbosity::Verbose => return self.fmt_verbose(f),
+            Verbosity::Counts | Verbosity::TopLevel => {}
+        }
+
+        write!(
+            f,
+            "{}\nSize: {} bytes\nTrackers: {}\nFiles: {}",
+            torrent.name(),
+            torrent.info.total_length(),
+            torrent.unique_trackers().count(),
+            torrent.info.file_count()
+        )?;
+
+        if self.verbosity == Verbosity::TopLevel {
+            let dirs = self.top_level_dirs();
+            if !dirs.is_empty() {
+                f.write_str("\nDirectories:")?;
+                for (index, dir) in dirs.into_iter().enumerate() {
+                    let separator = if index == 0 { " " } else { ", " };
+                    write!(f, "{separator}{dir}/")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Torrent {
+    /// Describe the torrent with [TorrentSummary].
+    ///
+    /// Call [TorrentSummary::verbose] to list every file.
+    #[inline]
+    pub fn summary(&self) -> TorrentSummary<'_> {
+        TorrentSummary::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Verbosity;
+    use crate::Torrent;
+
+    #[test]
+    fn verbose_lists_files() -> Result<(), serde_bencode::Error> {
+        let cats = b"d4:infod5:filesld6:lengthi42e4:pathl8:cats.mkveed6:lengthi6e4:pathl4:mews8:mews.mkveee4:name4:cats12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+        let torrent: Torrent = serde_bencode::from_bytes(cats)?;
+
+        let summary = torrent.summary();
+        assert_eq!(summary.verbosity(), Verbosity::Counts);
+        assert!(!summary.to_string().contains("cats.mkv"));
+        assert_eq!(
+            summary.top_level_dirs().into_iter().collect::<Vec<_>>(),
+            ["mews"]
+        );
+
+        let verbose = summary.verbose().to_string();
+        assert!(verbose.starts_with("Torrent: cats"));
+        assert!(verbose.contains("cats.mkv") && verbose.contains("mews.mkv"));
+        Ok(())
+    }
+}
@@ -1,24 +1,69 @@
 use crate::{
+    creationdate::DateClass,
     crypto::signature::Signature,
     hexadecimal::HexBytes,
-    metainfo::{
-        infohash::{InfoHashAny, InfoHashVersioned},
-        MetaInfo,
-    },
+    metainfo::{rawinfo::RawInfo, MetaInfo},
+    parseobserver::{self, ParseEvent, ParseObserver},
+    parseoptions::{self, unknown_fields, with_options, ParseOptions, UnknownFieldPolicy},
+    parsestats::ParseStats,
     uri::uriwrapper::UriWrapper,
     uri::Node,
+    validation::ValidationReport,
+};
+#[cfg(feature = "crypto")]
+use crate::{
+    crypto::sha::{Sha1, Sha2},
+    metainfo::{
+        infohash::{InfoHashCache, InfoHashVersioned},
+        TorrentVersion,
+    },
 };
-use log::debug;
-use serde::{Deserialize, Serialize};
+use log::{debug, error, warn};
+use serde::{
+    de::{Error as DeErrorTrait, SeqAccess, Unexpected, Visitor},
+    ser::Error as SerErrorTrait,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bencode::value::Value;
+use serde_bytes::ByteBuf;
 use serde_with::skip_serializing_none;
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{self, Display, Formatter},
-    sync::{OnceLock},
+    fs, io,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, Receiver},
+        Arc, Mutex, PoisonError,
+    },
+    thread,
+    time::Instant,
 };
 
 // Log target
+#[cfg(feature = "crypto")]
 const TORRENT_TARGET: &str = "star_cloudburst::Torrent::info_hash";
+const TORRENT_PARSE_TARGET: &str = "star_cloudburst::Torrent::parse_with";
+const TORRENT_PARSE_MANY_TARGET: &str = "star_cloudburst::torrent::parse_many";
+const ANNOUNCE_TIERS_DE_TARGET: &str = "star_cloudburst::Torrent::announce_tiers";
+
+/// Keys of the top level torrent dict.
+pub(crate) const TORRENT_FIELDS: &[&str] = &[
+    "announce",
+    "announce-list",
+    "created by",
+    "comment",
+    "creation date",
+    "encoding",
+    "httpseeds",
+    "info",
+    "nodes",
+    "piece layers",
+    "publisher-url",
+    "signatures",
+    "url-list",
+];
 
 // Based on BEPs as well as:
 // https://en.wikipedia.org/wiki/Torrent_file#File_structure
@@ -28,11 +73,15 @@ const TORRENT_TARGET: &str = "star_cloudburst::Torrent::info_hash";
 /// Torrent metadata such as the announce urls or DHT [`Node`]s.
 ///
 /// Types are validated during parsing when possible so that invalid states are impossible. Fields that aren't declared below are
-/// ignored when built with `--release`.
+/// ignored unless the torrent is parsed with [ParseOptions::unknown_fields].
+///
+/// Parse torrents with [Torrent::from_bytes] or [Torrent::parse_with] rather than deserializing them
+/// with Serde directly. Both keep the exact bytes of the info dict, which is hashed instead of the
+/// parsed dict so that fields this crate drops and keys that are out of order don't change the info
+/// hash. [Torrent::to_bytes] writes the original info dict back.
 /// Defined in [BEP-0003](https://www.bittorrent.org/beps/bep_0003.html) and [BEP-0052](https://www.bittorrent.org/beps/bep_0052.html).
-#[skip_serializing_none]
-#[derive(Debug, Deserialize, Serialize)]
-#[cfg_attr(debug_assertions, serde(deny_unknown_fields))]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct Torrent {
     /// Tracker URL.
     ///
@@ -43,7 +92,7 @@ pub struct Torrent {
     ///
     /// https://www.bittorrent.org/beps/bep_0012.html
     /// The announce URLs are represented as a list of lists of URLs.
-    #[serde(default, rename = "announce-list")]
+    #[serde(default, rename = "announce-list", deserialize_with = "announce_tiers")]
     pub announce_list: Option<Vec<Vec<UriWrapper>>>,
     /// Torrent creator or original uploader.
     #[serde(default, rename = "created by")]
@@ -53,6 +102,7 @@ pub struct Torrent {
     pub comment: Option<String>,
     /// Torrent creation date as a Unix timestamp.
     #[serde(default, rename = "creation date")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_creation_date))]
     pub creation_date: Option<u64>,
     /// String encoding scheme of `Info::pieces`.
     #[serde(default)]
@@ -65,10 +115,18 @@ pub struct Torrent {
     ///
     /// The info dict contains integral data on the files shared by the torrent.
     /// This includes suggested names as well as file hashes.
+    ///
+    /// Use [Torrent::info_mut] to modify the info dict so that the info hash is recalculated.
     pub info: MetaInfo,
+    /// Info dict exactly as it was parsed by [Torrent::from_bytes].
+    #[serde(skip)]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) info_raw: RawInfo,
     /// SHA hash of the torrent's meta info dict.
+    #[cfg(feature = "crypto")]
     #[serde(skip)]
-    info_hash_internal: OnceLock<InfoHashAny>,
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub(crate) info_hash_internal: InfoHashCache,
     /// Nodes for distributed hash tables (DHT).
     ///
     /// `nodes` is required for a tracker-less torrent file but optional otherwise.
@@ -88,6 +146,108 @@ pub struct Torrent {
     /// https://getright.com/seedtorrent.html
     #[serde(default, rename = "url-list")]
     pub url_list: Option<HashSet<UriWrapper>>,
+    /// Nonstandard top level fields that are tolerated, such as BitComet's `title`.
+    ///
+    /// See [crate::parseoptions::NONSTANDARD_FIELDS] and [ParseOptions::tolerated_fields]. Other unknown fields are
+    /// dropped.
+    #[serde(flatten, deserialize_with = "nonstandard_fields")]
+    #[cfg_attr(feature = "arbitrary", arbitrary(default))]
+    pub nonstandard: BTreeMap<String, Value>,
+}
+
+// Keep the unknown top level fields that are tolerated by the current options.
+fn nonstandard_fields<'de, D>(deserializer: D) -> Result<BTreeMap<String, Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let options = parseoptions::current();
+    let fields = HashMap::<ByteBuf, Value>::deserialize(deserializer)?;
+
+    Ok(fields
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let key = String::from_utf8(key.into_vec()).ok()?;
+            options.is_tolerated(&key).then_some((key, value))
+        })
+        .collect())
+}
+
+// Deserialize `announce-list` which lenient parsing also accepts as a flat list of URLs.
+fn announce_tiers<'de, D>(deserializer: D) -> Result<Option<Vec<Vec<UriWrapper>>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // A tier or a lone URL from a flat list.
+    struct Tier(Vec<UriWrapper>);
+
+    impl<'de> Deserialize<'de> for Tier {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserializer.deserialize_any(TierVisitor)
+        }
+    }
+
+    struct TierVisitor;
+
+    impl<'de> Visitor<'de> for TierVisitor {
+        type Value = Tier;
+
+        fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+            formatter.write_str("a list of announce URLs")
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut tier = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+            while let Some(tracker) = seq.next_element()? {
+                tier.push(tracker);
+            }
+            Ok(Tier(tier))
+        }
+
+        fn visit_str<E>(self, tracker: &str) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            if !parseoptions::current().lenient_announce_list {
+                return Err(DeErrorTrait::invalid_type(Unexpected::Str(tracker), &self));
+            }
+
+            warn!(
+                target: ANNOUNCE_TIERS_DE_TARGET,
+                "Wrapping flat `announce-list` URL into its own tier: {tracker}"
+            );
+            parseobserver::report(|| ParseEvent::LenientFix {
+                field: "announce-list",
+                detail: format!("wrapped `{tracker}` into its own tier"),
+            });
+            Ok(Tier(vec![tracker.parse().map_err(DeErrorTrait::custom)?]))
+        }
+
+        fn visit_bytes<E>(self, tracker: &[u8]) -> Result<Self::Value, E>
+        where
+            E: DeErrorTrait,
+        {
+            std::str::from_utf8(tracker)
+                .map_err(|_| DeErrorTrait::invalid_type(Unexpected::Bytes(tracker), &self))
+                .and_then(|tracker| self.visit_str(tracker))
+        }
+    }
+
+    let tiers: Vec<Tier> = Deserialize::deserialize(deserializer)?;
+    Ok(Some(tiers.into_iter().map(|Tier(tier)| tier).collect()))
+}
+
+// Bencode integers are signed.
+#[cfg(feature = "arbitrary")]
+fn arbitrary_creation_date(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Option<u64>> {
+    u.arbitrary::<Option<()>>()?
+        .map(|_| u.int_in_range(0..=i64::MAX as u64))
+        .transpose()
 }
 
 impl Torrent {
@@ -98,7 +258,7 @@ impl Torrent {
     /// use star_cloudburst::{metainfo::{MetaInfo, MetaV1}, Torrent};
     /// use serde_bencode::Error;
     ///
-    /// let cats = "d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16eee";
+    /// let cats = "d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
     /// let torrent: Torrent = serde_bencode::from_str(cats)?;
     ///
     /// assert_eq!("cats.mkv", torrent.name());
@@ -108,46 +268,714 @@ impl Torrent {
         match self.info {
             MetaInfo::MetaV1(ref dict) => dict.name.as_str(),
             MetaInfo::MetaV2(ref dict) => dict.name.as_str(),
+            MetaInfo::MerkleV1(ref dict) => dict.name.as_str(),
             MetaInfo::Hybrid(ref dict) => dict.name.as_str(),
         }
     }
 
-    /// Meta info SHA hash.
-    /// This is highly subject to change.
+    /// Replace the info dict's `source` and return the previous one.
+    ///
+    /// `source` is part of the info dict so the info hash is recalculated on the next call to
+    /// [Torrent::info_hash].
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384e6:source4:MEOWee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    /// assert_eq!(torrent.info.source(), Some("MEOW"));
+    ///
+    /// # #[cfg(feature = "crypto")]
+    /// # {
+    /// let meow = format!("{:?}", torrent.info_hash()?);
+    /// assert_eq!(torrent.set_source(Some("PURR".to_owned())).as_deref(), Some("MEOW"));
+    /// assert_ne!(format!("{:?}", torrent.info_hash()?), meow);
+    /// # }
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn set_source(&mut self, source: Option<String>) -> Option<String> {
+        self.info_mut().set_source(source)
+    }
+
+    /// Mutable access to the info dict.
+    ///
+    /// The original info dict is discarded, so the info hash is recalculated and the torrent is
+    /// serialized from [Torrent::info] afterward. Writing to [Torrent::info] directly keeps the
+    /// stale info hash.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x007:wheelie4:meowee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// // Unknown info fields are kept until the info dict is modified.
+    /// assert_eq!(torrent.to_bytes()?, cats);
+    /// torrent.info_mut().set_private(Some(true));
+    /// assert!(!torrent.to_bytes()?.ends_with(b"7:wheelie4:meowee"));
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    pub fn info_mut(&mut self) -> &mut MetaInfo {
+        #[cfg(feature = "crypto")]
+        self.info_hash_internal.clear();
+        self.info_raw.clear();
+        &mut self.info
+    }
+
+    /// Iterator over every tracker in tier order.
+    ///
+    /// `announce` comes first followed by each tier of `announce-list`. Trackers that are listed
+    /// more than once are yielded more than once; see [Torrent::unique_trackers].
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test13:announce-listll15:udp://cats.testel15:udp://mews.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!(torrent.trackers().count(), 3);
+    /// let unique: Vec<_> = torrent.unique_trackers().map(ToString::to_string).collect();
+    /// assert_eq!(unique, ["udp://cats.test", "udp://mews.test"]);
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[inline]
+    pub fn trackers(&self) -> impl Iterator<Item = &UriWrapper> + '_ {
+        self.announce
+            .iter()
+            .chain(self.announce_list.iter().flatten().flatten())
+    }
+
+    /// [Torrent::trackers] without duplicates.
+    ///
+    /// Each tracker is yielded at its first position in tier order.
+    #[inline]
+    pub fn unique_trackers(&self) -> impl Iterator<Item = &UriWrapper> + '_ {
+        let mut seen = HashSet::new();
+        self.trackers().filter(move |tracker| seen.insert(*tracker))
+    }
+
+    /// Set or remove `announce` and return the old tracker.
+    ///
+    /// Clients that support `announce-list` ignore `announce` (BEP-0012). A new tracker that isn't
+    /// listed in any tier is inserted as its own tier at the front of `announce-list` so that those
+    /// clients still try it first. Trackers aren't part of the info dictionary, so the info hash
+    /// doesn't change.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test13:announce-listll15:udp://cats.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// let old = torrent.set_announce(Some("udp://mews.test".parse()?));
+    /// assert_eq!(old.map(|old| old.to_string()).as_deref(), Some("udp://cats.test"));
+    ///
+    /// let trackers: Vec<_> = torrent.unique_trackers().map(ToString::to_string).collect();
+    /// assert_eq!(trackers, ["udp://mews.test", "udp://cats.test"]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn set_announce(&mut self, announce: Option<UriWrapper>) -> Option<UriWrapper> {
+        if let (Some(new), Some(tiers)) = (&announce, &mut self.announce_list) {
+            if !tiers.iter().flatten().any(|tracker| tracker == new) {
+                tiers.insert(0, vec![new.clone()]);
+            }
+        }
+        std::mem::replace(&mut self.announce, announce)
+    }
+
+    /// Append a tier of trackers to `announce-list`.
+    ///
+    /// Empty tiers are ignored. If the torrent only has `announce`, it becomes the first tier so
+    /// that clients which support `announce-list` don't lose it. If the torrent has no trackers at
+    /// all, the first tracker of `tier` is also used as `announce` for older clients.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// torrent.push_announce_tier(vec!["udp://mews.test".parse()?, "udp://purr.test".parse()?]);
+    /// let tiers: Vec<Vec<_>> = torrent
+    ///     .announce_list
+    ///     .iter()
+    ///     .flatten()
+    ///     .map(|tier| tier.iter().map(ToString::to_string).collect())
+    ///     .collect();
+    /// assert_eq!(tiers, [vec!["udp://cats.test"], vec!["udp://mews.test", "udp://purr.test"]]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn push_announce_tier(&mut self, tier: Vec<UriWrapper>) {
+        if tier.is_empty() {
+            return;
+        }
+
+        if self.announce.is_none() && self.announce_list.is_none() {
+            self.announce = Some(tier[0].clone());
+        }
+        let announce = &self.announce;
+        self.announce_list
+            .get_or_insert_with(|| {
+                announce
+                    .iter()
+                    .map(|tracker| vec![tracker.clone()])
+                    .collect()
+            })
+            .push(tier);
+    }
+
+    /// Replace every occurrence of `old` with `new` and return the number of trackers replaced.
+    ///
+    /// Replaced trackers keep their tier and position. [TrackerRule](crate::retracker::TrackerRule)
+    /// covers substitutions by host or pattern.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test13:announce-listll15:udp://cats.testel15:udp://mews.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// let replaced = torrent.replace_tracker(&"udp://cats.test".parse()?, "udp://purr.test".parse()?);
+    /// assert_eq!(replaced, 2);
+    ///
+    /// let trackers: Vec<_> = torrent.trackers().map(ToString::to_string).collect();
+    /// assert_eq!(trackers, ["udp://purr.test", "udp://purr.test", "udp://mews.test"]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn replace_tracker(&mut self, old: &UriWrapper, new: UriWrapper) -> usize {
+        let mut replaced = 0;
+        let announce_list = self.announce_list.iter_mut().flatten().flatten();
+        for tracker in self.announce.iter_mut().chain(announce_list) {
+            if tracker == old {
+                *tracker = new.clone();
+                replaced += 1;
+            }
+        }
+        replaced
+    }
+
+    /// Remove duplicate trackers from `announce-list` and return the number removed.
+    ///
+    /// Each tracker is kept at its first position in tier order. Tiers left empty are removed and
+    /// `announce-list` is removed if no tiers remain. `announce` is left as is because it's
+    /// conventionally repeated as the first tracker of `announce-list`.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// let cats = b"d8:announce15:udp://cats.test13:announce-listll15:udp://cats.test15:udp://mews.testel15:udp://mews.testee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let mut torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!(torrent.dedup_trackers(), 1);
+    /// assert_eq!(torrent.trackers().count(), 3);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn dedup_trackers(&mut self) -> usize {
+        let Some(tiers) = &mut self.announce_list else {
+            return 0;
+        };
+
+        let mut seen = HashSet::new();
+        let mut removed = 0;
+        for tier in tiers.iter_mut() {
+            tier.retain(|tracker| {
+                let first = seen.insert(tracker.clone());
+                removed += usize::from(!first);
+                first
+            });
+        }
+        tiers.retain(|tier| !tier.is_empty());
+
+        if tiers.is_empty() {
+            self.announce_list = None;
+        }
+        removed
+    }
+
+    /// Deserialize a torrent from bencoded bytes with the default [ParseOptions].
+    ///
+    /// The info dict is kept exactly as it appears in `bytes` so that the info hash matches the
+    /// torrent even if its keys are out of order or it has fields that [MetaInfo] drops.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// // `name` sorts after `length`.
+    /// let cats = b"d4:infod4:name8:cats.mkv6:lengthi42e12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!("cats.mkv", torrent.name());
+    /// assert_eq!(torrent.to_bytes()?, cats);
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    #[inline]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, serde_bencode::Error> {
+        Self::parse_with(bytes, ParseOptions::default())
+    }
+
+    /// Serialize the torrent to bencoded bytes with the info dict it was parsed from.
+    ///
+    /// [Serialize] can't write raw bytes, so it writes the info dict with sorted keys. This writes
+    /// the original dict instead unless the info dict was modified, which keeps the info hash of the
+    /// written torrent the same as the parsed one.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, serde_bencode::Error> {
+        let bytes = serde_bencode::to_bytes(self)?;
+        self.info_raw
+            .splice(bytes)
+            .ok_or_else(|| SerErrorTrait::custom("serialized torrent doesn't have an info dict"))
+    }
+
+    /// Deserialize a torrent from bencoded bytes with runtime [ParseOptions].
+    ///
+    /// Strict parsing reports every unknown field in the error message rather than only the first.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{parseoptions::ParseOptions, Torrent};
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let torrent = Torrent::parse_with(cats, ParseOptions::strict())?;
+    ///
+    /// assert_eq!("cats.mkv", torrent.name());
+    /// # Ok::<(), Error>(())
+    /// ```
+    #[inline]
+    pub fn parse_with(bytes: &[u8], options: ParseOptions) -> Result<Self, serde_bencode::Error> {
+        Self::parse_report(bytes, options).map(|(torrent, _)| torrent)
+    }
+
+    /// Deserialize a torrent like [Torrent::parse_with] and return a [ValidationReport] of what
+    /// was found along the way.
+    ///
+    /// Unknown fields are collected with [UnknownFieldPolicy::Report].
+    ///
+    /// ```rust
+    /// use star_cloudburst::{
+    ///     parseoptions::{ParseOptions, UnknownFieldPolicy},
+    ///     Torrent,
+    /// };
+    ///
+    /// let cats = b"d4:infod9:file treed4:dir1d8:cats.mkvd0:d6:lengthi42e11:pieces root32:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x005:colori1eeeee12:meta versioni2e4:name4:cats12:piece lengthi16384ee7:wheelie3:yese";
+    /// let options = ParseOptions {
+    ///     unknown_fields: UnknownFieldPolicy::Report,
+    ///     ..ParseOptions::default()
+    /// };
+    /// let (torrent, report) = Torrent::parse_report(cats, options)?;
+    ///
+    /// assert_eq!("cats", torrent.name());
+    /// assert_eq!(
+    ///     report.unknown_fields,
+    ///     ["info.file tree.dir1.cats.mkv.color", "wheelie"]
+    /// );
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    pub fn parse_report(
+        bytes: &[u8],
+        options: ParseOptions,
+    ) -> Result<(Self, ValidationReport), serde_bencode::Error> {
+        let mut report = ValidationReport::default();
+
+        if options.unknown_fields != UnknownFieldPolicy::Ignore || parseobserver::observing() {
+            let value = serde_bencode::from_bytes(bytes)?;
+            let unknown = unknown_fields(&value, &options);
+            for path in &unknown {
+                parseobserver::report(|| ParseEvent::UnknownField(path.clone()));
+            }
+
+            if options.unknown_fields == UnknownFieldPolicy::Reject && !unknown.is_empty() {
+                error!(
+                    target: TORRENT_PARSE_TARGET,
+                    "Strict parsing failed due to unknown fields: {unknown:?}"
+                );
+                return Err(DeErrorTrait::custom(format!(
+                    "unknown fields in strict mode: {}",
+                    unknown.join(", ")
+                )));
+            }
+
+            if options.unknown_fields == UnknownFieldPolicy::Report {
+                report.unknown_fields = unknown;
+            }
+        }
+
+        let mut torrent: Self = with_options(options, || serde_bencode::from_bytes(bytes))?;
+        torrent.info_raw = RawInfo::from_torrent(bytes);
+
+        if let Some(class) = torrent.creation_date_class() {
+            if !matches!(class, DateClass::Seconds(_)) {
+                parseobserver::report(|| ParseEvent::SuspiciousValue {
+                    field: "creation date",
+                    detail: format!("{class:?}"),
+                });
+            }
+        }
+
+        Ok((torrent, report))
+    }
+
+    /// Deserialize a torrent like [Torrent::parse_with] and pass notable [ParseEvent]s to
+    /// `observer`.
+    ///
+    /// Events are passed after parsing, even if parsing failed.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{
+    ///     parseobserver::ParseEvent,
+    ///     parseoptions::ParseOptions,
+    ///     Torrent,
+    /// };
+    ///
+    /// let hamsters = b"d4:infod4:name12:hamsters.mkv6:lengthi42e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi1000ee7:wheelie3:yese";
+    /// let mut events = Vec::new();
+    /// let torrent = Torrent::parse_observed(hamsters, ParseOptions::lenient(), &mut |event: &ParseEvent| {
+    ///     events.push(event.clone())
+    /// });
+    ///
+    /// assert!(torrent.is_ok());
+    /// assert_eq!(events[0], ParseEvent::UnknownField("wheelie".to_owned()));
+    /// assert!(matches!(events[1], ParseEvent::LenientFix { field: "piece length", .. }));
+    /// ```
+    pub fn parse_observed(
+        bytes: &[u8],
+        options: ParseOptions,
+        observer: &mut dyn ParseObserver,
+    ) -> Result<Self, serde_bencode::Error> {
+        parseobserver::observe(observer, || Self::parse_with(bytes, options))
+    }
+
+    /// Deserialize a torrent from bencoded bytes and collect [ParseStats] along the way.
+    ///
+    /// The info hash is calculated eagerly so that its cost is included in the stats if the `crypto`
+    /// feature is enabled.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d8:announce9:localhost4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee";
+    /// let (torrent, stats) = Torrent::from_bytes_with_stats(cats)?;
+    ///
+    /// assert_eq!("cats.mkv", torrent.name());
+    /// assert_eq!(stats.file_count, 1);
+    /// assert_eq!(stats.bytes_read, cats.len());
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn from_bytes_with_stats(bytes: &[u8]) -> Result<(Self, ParseStats), serde_bencode::Error> {
+        let mut stats = ParseStats::default();
+
+        let start = Instant::now();
+        stats.scan(bytes)?;
+        stats.durations.scan = start.elapsed();
+
+        let start = Instant::now();
+        let mut torrent: Torrent = serde_bencode::from_bytes(bytes)?;
+        torrent.info_raw = RawInfo::from_torrent(bytes);
+        stats.durations.deserialize = start.elapsed();
+
+        #[cfg(feature = "crypto")]
+        {
+            let start = Instant::now();
+            torrent.info_hash()?;
+            stats.durations.info_hash = start.elapsed();
+        }
+
+        stats.count_files(&torrent.info);
+        Ok((torrent, stats))
+    }
+
+    /// SHA hashes of the info dict for the torrent's version.
+    ///
+    /// The hashes are calculated on the first call and cached. Modifying the info dict with
+    /// [Torrent::info_mut] or another editing method resets the cache, but writing to
+    /// [Torrent::info] directly doesn't. Calculation errors aren't cached. Clones share nothing;
+    /// a clone keeps the hashes that were already calculated.
+    #[cfg(feature = "crypto")]
     pub fn info_hash(&self) -> Result<InfoHashVersioned<'_>, serde_bencode::Error> {
-        // TODO: I don't like that I have to take a mutable reference to Self.
-        // I can probably get away with a RefCell since I only need to mutate info_hash once.
-        let info_hash = self.info_hash_internal.get_or_try_init(|| {
-            // Side effect sin.
+        if self.info_hash_internal.is_empty() {
             debug!(
                 target: TORRENT_TARGET,
                 "Info hash doesn't exist on {}. Calculating now.",
                 self.name()
             );
-            InfoHashAny::calculate_infohash(&self.info)
-        })?;
+        }
+        let info_hash = self
+            .info_hash_internal
+            .get_or_calculate(&self.info, &self.info_raw)?;
+
+        // Hybrid dicts that only describe one version only have that version's hash.
+        match self.info.version() {
+            TorrentVersion::V1 | TorrentVersion::MerkleV1 => {
+                Ok(InfoHashVersioned::V1(&info_hash.sha1))
+            }
+            TorrentVersion::V2 => Ok(InfoHashVersioned::V2(&info_hash.sha2)),
+            TorrentVersion::Hybrid => Ok(InfoHashVersioned::Hybrid {
+                sha1: &info_hash.sha1,
+                sha2: &info_hash.sha2,
+            }),
+        }
+    }
+
+    /// SHA-1 info hash of version 1 and hybrid torrents.
+    ///
+    /// ```rust
+    /// use star_cloudburst::Torrent;
+    ///
+    /// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!(
+    ///     torrent.info_hash_v1_hex()?.as_deref(),
+    ///     Some("1af47a06d99e96aff4f2c3dbbe726093c6211538")
+    /// );
+    /// assert!(torrent.info_hash_v2()?.is_none());
+    /// # Ok::<(), serde_bencode::Error>(())
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn info_hash_v1(&self) -> Result<Option<&Sha1>, serde_bencode::Error> {
+        match self.info_hash()? {
+            InfoHashVersioned::V1(sha1) | InfoHashVersioned::Hybrid { sha1, .. } => Ok(Some(sha1)),
+            InfoHashVersioned::V2(_) => Ok(None),
+        }
+    }
+
+    /// SHA-256 info hash of version 2 and hybrid torrents.
+    #[cfg(feature = "crypto")]
+    pub fn info_hash_v2(&self) -> Result<Option<&Sha2>, serde_bencode::Error> {
+        match self.info_hash()? {
+            InfoHashVersioned::V2(sha2) | InfoHashVersioned::Hybrid { sha2, .. } => Ok(Some(sha2)),
+            InfoHashVersioned::V1(_) => Ok(None),
+        }
+    }
+
+    /// [Torrent::info_hash_v1] as lowercase hex.
+    #[cfg(feature = "crypto")]
+    #[inline]
+    pub fn info_hash_v1_hex(&self) -> Result<Option<String>, serde_bencode::Error> {
+        self.info_hash_v1()
+            .map(|sha1| sha1.map(ToString::to_string))
+    }
+
+    /// [Torrent::info_hash_v2] as lowercase hex.
+    #[cfg(feature = "crypto")]
+    #[inline]
+    pub fn info_hash_v2_hex(&self) -> Result<Option<String>, serde_bencode::Error> {
+        self.info_hash_v2()
+            .map(|sha2| sha2.map(ToString::to_string))
+    }
+
+    /// [Torrent::info_hash_v2] truncated to 20 bytes.
+    ///
+    /// Trackers, the DHT, and the peer handshake identify version 2 torrents by the truncated hash
+    /// (BEP-0052).
+    #[cfg(feature = "crypto")]
+    #[inline]
+    pub fn info_hash_v2_truncated(&self) -> Result<Option<Sha1>, serde_bencode::Error> {
+        self.info_hash_v2().map(|sha2| sha2.map(Sha2::truncate))
+    }
+}
+
+// Torrent as it's serialized with the fields of the original info dict if it wasn't modified.
+// Serde sorts the keys of the original dict; Torrent::to_bytes splices in the exact bytes.
+#[skip_serializing_none]
+#[derive(Serialize)]
+struct TorrentRef<'a> {
+    announce: Option<&'a UriWrapper>,
+    #[serde(rename = "announce-list")]
+    announce_list: Option<&'a Vec<Vec<UriWrapper>>>,
+    #[serde(rename = "created by")]
+    created_by: Option<&'a String>,
+    comment: Option<&'a String>,
+    #[serde(rename = "creation date")]
+    creation_date: Option<u64>,
+    encoding: Option<&'a String>,
+    httpseeds: Option<&'a Vec<UriWrapper>>,
+    info: InfoRef<'a>,
+    nodes: Option<&'a Vec<Node>>,
+    #[serde(rename = "piece layers")]
+    piece_layers: Option<&'a HashMap<HexBytes, HexBytes>>,
+    #[serde(rename = "publisher-url")]
+    publisher_url: Option<&'a UriWrapper>,
+    signatures: Option<&'a HashMap<String, Signature>>,
+    #[serde(rename = "url-list")]
+    url_list: Option<&'a HashSet<UriWrapper>>,
+    #[serde(flatten)]
+    nonstandard: &'a BTreeMap<String, Value>,
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum InfoRef<'a> {
+    Raw(Value),
+    Parsed(&'a MetaInfo),
+}
+
+impl Serialize for Torrent {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let info = match self.info_raw.bytes() {
+            Some(bytes) => {
+                InfoRef::Raw(serde_bencode::from_bytes(bytes).map_err(SerErrorTrait::custom)?)
+            }
+            None => InfoRef::Parsed(&self.info),
+        };
 
-            match self.info {
-                MetaInfo::MetaV1(_) => Ok(InfoHashVersioned::V1(&info_hash.sha1)),
-                MetaInfo::MetaV2(_) => Ok(InfoHashVersioned::V2(&info_hash.sha2)),
-                MetaInfo::Hybrid(_) => Ok(InfoHashVersioned::Hybrid {
-                    sha1: &info_hash.sha1,
-                    sha2: &info_hash.sha2,
-                }),
+        TorrentRef {
+            announce: self.announce.as_ref(),
+            announce_list: self.announce_list.as_ref(),
+            created_by: self.created_by.as_ref(),
+            comment: self.comment.as_ref(),
+            creation_date: self.creation_date,
+            encoding: self.encoding.as_ref(),
+            httpseeds: self.httpseeds.as_ref(),
+            info,
+            nodes: self.nodes.as_ref(),
+            piece_layers: self.piece_layers.as_ref(),
+            publisher_url: self.publisher_url.as_ref(),
+            signatures: self.signatures.as_ref(),
+            url_list: self.url_list.as_ref(),
+            nonstandard: &self.nonstandard,
         }
+        .serialize(serializer)
     }
 }
 
 impl Display for Torrent {
+    /// Short [crate::summary::TorrentSummary]; see [Torrent::summary] for the full file list.
+    #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct(&format!("Torrent: {}", self.name()))
-            .field("Meta info version", &self.info.meta_version_str())
-            .field("Files", {
-                let files: Vec<_> = self.info.iter_files().collect();
-                &format!("{files:#?}")
+        self.summary().fmt(f)
+    }
+}
+
+/// Iterator over torrents parsed by [parse_many] in the order they finish.
+///
+/// Dropping the iterator stops the workers after their current file.
+#[derive(Debug)]
+pub struct ParseMany {
+    results: Receiver<(PathBuf, io::Result<Torrent>)>,
+}
+
+impl Iterator for ParseMany {
+    type Item = (PathBuf, io::Result<Torrent>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.results.recv().ok()
+    }
+}
+
+/// Read and parse torrent files on a pool of threads.
+///
+/// Results are yielded as soon as each file is parsed, so the order is
+/// unspecified. Files that fail to parse are [io::ErrorKind::InvalidData]
+/// errors. One thread per available core is spawned, but never more than the
+/// number of paths.
+///
+/// ```rust
+/// use star_cloudburst::{parseoptions::ParseOptions, torrent::parse_many};
+///
+/// let cats = std::env::temp_dir().join("star_cloudburst_parse_many_cats.torrent");
+/// std::fs::write(&cats, b"d4:infod6:lengthi42e4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384eee")?;
+/// let missing = std::env::temp_dir().join("star_cloudburst_parse_many_missing.torrent");
+///
+/// let mut parsed = 0;
+/// for (path, result) in parse_many([cats.clone(), missing.clone()], ParseOptions::default()) {
+///     match result {
+///         Ok(torrent) => {
+///             assert_eq!(path, cats);
+///             assert_eq!(torrent.name(), "cats.mkv");
+///             parsed += 1;
+///         }
+///         Err(_) => assert_eq!(path, missing),
+///     }
+/// }
+/// assert_eq!(parsed, 1);
+/// # std::fs::remove_file(cats)?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn parse_many<I>(paths: I, options: ParseOptions) -> ParseMany
+where
+    I: IntoIterator<Item = PathBuf>,
+{
+    let paths: Vec<_> = paths.into_iter().collect();
+    let workers = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(paths.len());
+    debug!(
+        target: TORRENT_PARSE_MANY_TARGET,
+        "Parsing {} torrents on {workers} threads",
+        paths.len()
+    );
+
+    let queue = Arc::new(Mutex::new(paths.into_iter()));
+    let (sender, results) = mpsc::channel();
+
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let sender = sender.clone();
+        thread::spawn(move || loop {
+            // The guard is dropped before parsing so that workers don't wait on each other.
+            let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+            let Some(path) = next else {
+                break;
+            };
+
+            let result = fs::read(&path).and_then(|bytes| {
+                Torrent::parse_with(&bytes, options)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            });
+            if let Err(e) = &result {
+                error!(
+                    target: TORRENT_PARSE_MANY_TARGET,
+                    "Failed parsing {}\nError: {e}",
+                    path.display()
+                );
+            }
+
+            // The receiver was dropped so nobody wants the rest.
+            if sender.send((path, result)).is_err() {
+                break;
+            }
+        });
+    }
+
+    ParseMany { results }
+}
+
+#[cfg(all(test, feature = "arbitrary"))]
+mod tests {
+    use super::Torrent;
+    use arbitrary::{Arbitrary, Unstructured};
+
+    // Deterministic noise so failures are reproducible.
+    fn noise(seed: u64, len: usize) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (state >> 56) as u8
             })
-            .field("Info hash", &self.info_hash())
-            .field("Piece length", &self.info.piece_length())
-            .finish()
+            .collect()
+    }
+
+    #[test]
+    fn arbitrary_torrents_roundtrip_bencode() -> Result<(), serde_bencode::Error> {
+        for seed in 0..64 {
+            let data = noise(seed, 4096);
+            let torrent = Torrent::arbitrary(&mut Unstructured::new(&data))
+                .expect("4 KiB is enough to generate a torrent.");
+            let torrent_se = serde_bencode::to_bytes(&torrent)?;
+            let torrent_de: Torrent = serde_bencode::from_bytes(&torrent_se)?;
+            assert_eq!(torrent.name(), torrent_de.name());
+        }
+
+        Ok(())
     }
 }
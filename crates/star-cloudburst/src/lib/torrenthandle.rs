@@ -0,0 +1,235 @@
+//! Torrents that may still be waiting for their info dict.
+//!
+//! Magnet links only describe a torrent by its info hash, display name, and
+//! trackers. The info dict is downloaded later from peers. [TorrentHandle]
+//! represents both states so that clients can track magnet links and complete
+//! torrents together.
+
+#[cfg(feature = "crypto")]
+use crate::metainfo::{infohash::InfoHashAny, MetaInfo};
+use crate::{magnet::MagnetLink, uri::UriWrapper, Torrent};
+use either::Either;
+#[cfg(feature = "crypto")]
+use log::{debug, error};
+use serde::de::value::Error as DeError;
+#[cfg(feature = "crypto")]
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+#[cfg(feature = "crypto")]
+const ATTACH_INFO_TARGET: &str = "star_cloudburst::torrenthandle::TorrentHandle::attach_info";
+
+/// A complete torrent or a magnet link that's waiting for its info dict.
+///
+/// ```rust
+/// use star_cloudburst::torrenthandle::TorrentHandle;
+///
+/// let info = b"d6:lengthi42e4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee";
+/// let mut handle: TorrentHandle =
+///     "magnet:?xt=urn:btih:d0c91f70c742a016a965192bb598c6cd9a5a6287&dn=cats&tr=udp://cats.test:6969"
+///         .parse()?;
+/// assert_eq!(handle.name(), Some("cats"));
+///
+/// # #[cfg(feature = "crypto")]
+/// # {
+/// let torrent = handle.attach_info(info)?;
+/// assert_eq!(torrent.name(), "cats.mkv");
+/// assert_eq!(torrent.announce.as_ref().unwrap().to_string(), "udp://cats.test:6969");
+/// # }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TorrentHandle {
+    /// Torrent with its info dict.
+    Complete(Box<Torrent>),
+    /// Magnet link that doesn't have the info dict yet.
+    Pending(MagnetLink),
+}
+
+impl TorrentHandle {
+    /// Whether the info dict is still missing.
+    #[inline]
+    pub fn is_pending(&self) -> bool {
+        matches!(self, TorrentHandle::Pending(_))
+    }
+
+    /// The torrent if the info dict is available.
+    #[inline]
+    pub fn torrent(&self) -> Option<&Torrent> {
+        match self {
+            TorrentHandle::Complete(torrent) => Some(torrent),
+            TorrentHandle::Pending(_) => None,
+        }
+    }
+
+    /// Name of the torrent or the magnet link's display name.
+    #[inline]
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            TorrentHandle::Complete(torrent) => Some(torrent.name()),
+            TorrentHandle::Pending(magnet) => magnet.name.as_deref(),
+        }
+    }
+
+    /// Trackers of the torrent or the magnet link.
+    pub fn trackers(&self) -> impl Iterator<Item = &UriWrapper> + '_ {
+        match self {
+            TorrentHandle::Complete(torrent) => Either::Left(torrent.trackers()),
+            TorrentHandle::Pending(magnet) => Either::Right(magnet.trackers.iter()),
+        }
+    }
+
+    /// Complete a pending torrent with an info dict received from peers.
+    ///
+    /// `info_bytes` is the bencoded info dict. It's hashed as is and must match
+    /// the magnet link's info hashes. The magnet link's trackers become the
+    /// torrent's trackers with one tracker per tier.
+    ///
+    /// Torrents that are already complete are an error.
+    #[cfg(feature = "crypto")]
+    pub fn attach_info(&mut self, info_bytes: &[u8]) -> Result<&Torrent, serde_bencode::Error> {
+        let magnet = match self {
+            TorrentHandle::Pending(magnet) => magnet,
+            TorrentHandle::Complete(torrent) => {
+                error!(
+                    target: ATTACH_INFO_TARGET,
+                    "{} already has an info dict",
+                    torrent.name()
+                );
+                return Err(serde_bencode::Error::Custom(
+                    "torrent already has an info dict".to_owned(),
+                ));
+            }
+        };
+
+        // Magnet links have at least one of the hashes and every hash they have must
+        // match.
+        let info_hash = InfoHashAny::from_info_bytes(info_bytes);
+        let sha1_matches = magnet
+            .info_hash
+            .as_ref()
+            .is_none_or(|sha1| sha1 == &info_hash.sha1);
+        let sha2_matches = magnet
+            .info_hash_v2
+            .as_ref()
+            .is_none_or(|sha2| sha2 == &info_hash.sha2);
+        if !(sha1_matches && sha2_matches) {
+            error!(
+                target: ATTACH_INFO_TARGET,
+                "Info dict hashes to {} and {} rather than {:?} and {:?}",
+                info_hash.sha1,
+                info_hash.sha2,
+                magnet.info_hash,
+                magnet.info_hash_v2
+            );
+            return Err(serde_bencode::Error::Custom(format!(
+                "info dict hashes to {} and {} which doesn't match the magnet link",
+                info_hash.sha1, info_hash.sha2
+            )));
+        }
+
+        let info: MetaInfo = serde_bencode::from_bytes(info_bytes)?;
+        let trackers = std::mem::take(&mut magnet.trackers);
+        let torrent = Torrent {
+            announce: trackers.first().cloned(),
+            announce_list: (trackers.len() > 1)
+                .then(|| trackers.into_iter().map(|tracker| vec![tracker]).collect()),
+            created_by: None,
+            comment: None,
+            creation_date: None,
+            encoding: None,
+            httpseeds: None,
+            info,
+            info_hash_internal: info_hash.into(),
+            info_raw: info_bytes.into(),
+            nodes: None,
+            piece_layers: None,
+            publisher_url: None,
+            signatures: None,
+            url_list: None,
+            nonstandard: BTreeMap::new(),
+        };
+        debug!(
+            target: ATTACH_INFO_TARGET,
+            "Attached info dict for {}",
+            torrent.name()
+        );
+
+        *self = TorrentHandle::Complete(Box::new(torrent));
+        Ok(self.torrent().expect("Torrent was just completed."))
+    }
+}
+
+impl From<Torrent> for TorrentHandle {
+    #[inline]
+    fn from(torrent: Torrent) -> Self {
+        TorrentHandle::Complete(Box::new(torrent))
+    }
+}
+
+impl From<MagnetLink> for TorrentHandle {
+    #[inline]
+    fn from(magnet: MagnetLink) -> Self {
+        TorrentHandle::Pending(magnet)
+    }
+}
+
+impl FromStr for TorrentHandle {
+    type Err = DeError;
+
+    /// Parse a magnet link into a pending torrent.
+    #[inline]
+    fn from_str(magnet: &str) -> Result<Self, Self::Err> {
+        magnet.parse().map(TorrentHandle::Pending)
+    }
+}
+
+#[cfg(all(test, feature = "crypto"))]
+mod tests {
+    use super::TorrentHandle;
+    use crate::metainfo::infohash::{InfoHashAny, InfoHashVersioned};
+
+    const INFO: &[u8] = b"d6:lengthi42e4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee";
+
+    #[test]
+    fn attach_info_checks_hash() {
+        let magnet = "magnet:?xt=urn:btih:0000000000000000000000000000000000000000";
+        let mut handle: TorrentHandle = magnet.parse().unwrap();
+
+        assert!(handle.attach_info(INFO).is_err());
+        assert!(handle.is_pending());
+
+        // Both hashes of hybrid links are checked.
+        let InfoHashAny { sha1, sha2 } = InfoHashAny::from_info_bytes(INFO);
+        let mut handle: TorrentHandle = format!(
+            "magnet:?xt=urn:btih:{sha1}&xt=urn:btmh:{}",
+            sha2.to_multihash()
+        )
+        .parse()
+        .unwrap();
+        assert!(handle.clone().attach_info(INFO).is_ok());
+
+        let TorrentHandle::Pending(magnet) = &mut handle else {
+            unreachable!()
+        };
+        magnet.info_hash_v2 = Some([0; 32].into());
+        assert!(handle.attach_info(INFO).is_err());
+    }
+
+    #[test]
+    fn attach_info_keeps_original_hash() {
+        // Unknown fields are dropped while parsing but are part of the info hash.
+        let info = [&INFO[..INFO.len() - 1], b"7:wheelie4:meowe"].concat();
+        let info_hash = InfoHashAny::from_info_bytes(&info).sha1;
+        let mut handle: TorrentHandle = format!("magnet:?xt=urn:btih:{info_hash}").parse().unwrap();
+
+        let torrent = handle.attach_info(&info).unwrap();
+        match torrent.info_hash().unwrap() {
+            InfoHashVersioned::V1(sha1) | InfoHashVersioned::Hybrid { sha1, .. } => {
+                assert_eq!(sha1, &info_hash)
+            }
+            InfoHashVersioned::V2(_) => panic!("Version 1 info dict."),
+        }
+        assert!(handle.attach_info(&info).is_err());
+    }
+}
@@ -0,0 +1,361 @@
+//! Tracker announce responses.
+//!
+//! HTTP trackers respond to announces with a bencoded dict
+//! ([BEP-0003](https://www.bittorrent.org/beps/bep_0003.html)). Successful responses have peers
+//! and the interval between announces. Failed responses only need a
+//! `failure reason`, but trackers may add `retry in`
+//! ([BEP-0031](https://www.bittorrent.org/beps/bep_0031.html)) to tell clients when to try
+//! again or to stop announcing.
+
+use crate::peerid::PeerId;
+use log::{debug, error};
+use serde::{
+    de::{Error as DeErrorTrait, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_bytes::ByteBuf;
+use serde_with::skip_serializing_none;
+use std::{
+    fmt::{self, Formatter},
+    time::Duration,
+};
+
+const RETRYIN_DE_TARGET: &str = "star_cloudburst::tracker::RetryIn::deserialize";
+const FAILURE_TARGET: &str = "star_cloudburst::tracker::AnnounceResponse::failure";
+
+// Failure reasons that won't change by announcing again, such as a torrent that
+// was removed from the tracker or a revoked passkey. Compared case
+// insensitively.
+const FATAL_REASONS: &[&str] = &[
+    "unregistered torrent",
+    "torrent not registered",
+    "torrent not found",
+    "unknown torrent",
+    "invalid passkey",
+    "passkey not found",
+    "banned",
+];
+
+/// When a tracker wants clients to announce again after a failure.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RetryIn {
+    /// Announce again after this many minutes.
+    Minutes(u64),
+    /// Don't announce to this tracker again.
+    Never,
+}
+
+impl<'de> Deserialize<'de> for RetryIn {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RetryInVisitor;
+
+        impl Visitor<'_> for RetryInVisitor {
+            type Value = RetryIn;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                formatter.write_str("minutes or \"never\"")
+            }
+
+            fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                Ok(RetryIn::Minutes(v))
+            }
+
+            fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                v.try_into().map(RetryIn::Minutes).map_err(|_| {
+                    error!(target: RETRYIN_DE_TARGET, "Negative retry in: {v}");
+                    E::invalid_value(Unexpected::Signed(v), &self)
+                })
+            }
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                if v == "never" {
+                    Ok(RetryIn::Never)
+                } else {
+                    error!(target: RETRYIN_DE_TARGET, "Invalid retry in: {v}");
+                    Err(E::invalid_value(Unexpected::Str(v), &self))
+                }
+            }
+
+            // Bencode doesn't distinguish strings from bytes.
+            fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+            where
+                E: DeErrorTrait,
+            {
+                std::str::from_utf8(v)
+                    .map_err(|_| E::invalid_value(Unexpected::Bytes(v), &self))
+                    .and_then(|v| self.visit_str(v))
+            }
+        }
+
+        deserializer.deserialize_any(RetryInVisitor)
+    }
+}
+
+impl Serialize for RetryIn {
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            RetryIn::Minutes(minutes) => serializer.serialize_u64(*minutes),
+            RetryIn::Never => serializer.serialize_str("never"),
+        }
+    }
+}
+
+/// Peer from a non-compact peer list.
+#[skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PeerInfo {
+    #[serde(rename = "peer id")]
+    pub peer_id: Option<PeerId>,
+    /// IP address or DNS name.
+    pub ip: String,
+    pub port: u16,
+}
+
+/// Peers as a list of dicts or as compact bytes.
+///
+/// Compact peers are six bytes per IPv4 peer: the address followed by the port
+/// in network byte order ([BEP-0023](https://www.bittorrent.org/beps/bep_0023.html)).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum Peers {
+    Compact(ByteBuf),
+    List(Vec<PeerInfo>),
+}
+
+/// Why an announce failed and whether announcing again may succeed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnnounceFailure<'response> {
+    /// Announce again later. `retry_in` is set if the tracker asked for a
+    /// delay.
+    Retryable {
+        reason: &'response str,
+        retry_in: Option<Duration>,
+    },
+    /// Stop announcing to this tracker.
+    Fatal { reason: &'response str },
+}
+
+impl<'response> AnnounceFailure<'response> {
+    /// Failure reason sent by the tracker.
+    #[inline]
+    pub fn reason(&self) -> &'response str {
+        match self {
+            AnnounceFailure::Retryable { reason, .. } | AnnounceFailure::Fatal { reason } => reason,
+        }
+    }
+
+    #[inline]
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AnnounceFailure::Retryable { .. })
+    }
+}
+
+/// Response to an HTTP announce.
+///
+/// ```rust
+/// use star_cloudburst::tracker::{AnnounceFailure, AnnounceResponse};
+/// use std::time::Duration;
+///
+/// let response: AnnounceResponse =
+///     serde_bencode::from_bytes(b"d14:failure reason10:overloaded8:retry ini5ee")?;
+/// assert_eq!(
+///     response.failure(),
+///     Some(AnnounceFailure::Retryable {
+///         reason: "overloaded",
+///         retry_in: Some(Duration::from_secs(300))
+///     })
+/// );
+///
+/// let response: AnnounceResponse =
+///     serde_bencode::from_bytes(b"d14:failure reason20:unregistered torrente")?;
+/// assert!(!response.failure().unwrap().is_retryable());
+/// # Ok::<(), serde_bencode::Error>(())
+/// ```
+#[skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AnnounceResponse {
+    /// Human readable error. The other keys may be missing if this is set.
+    #[serde(rename = "failure reason")]
+    pub failure_reason: Option<String>,
+    /// Human readable warning for a successful announce.
+    #[serde(rename = "warning message")]
+    pub warning_message: Option<String>,
+    /// Seconds that clients should wait between announces.
+    pub interval: Option<u64>,
+    /// Seconds that clients must wait between announces.
+    #[serde(rename = "min interval")]
+    pub min_interval: Option<u64>,
+    /// When to announce again after a failure.
+    #[serde(rename = "retry in")]
+    pub retry_in: Option<RetryIn>,
+    /// ID that should be sent back with the next announce.
+    #[serde(rename = "tracker id")]
+    pub tracker_id: Option<String>,
+    /// Number of seeders.
+    pub complete: Option<u64>,
+    /// Number of leechers.
+    pub incomplete: Option<u64>,
+    pub peers: Option<Peers>,
+    /// Compact IPv6 peers ([BEP-0007](https://www.bittorrent.org/beps/bep_0007.html)).
+    #[serde(default, with = "serde_bytes")]
+    pub peers6: Option<Vec<u8>>,
+}
+
+impl AnnounceResponse {
+    /// Whether the tracker rejected the announce.
+    #[inline]
+    pub fn is_failure(&self) -> bool {
+        self.failure_reason.is_some()
+    }
+
+    /// Classify a failed announce.
+    ///
+    /// `retry in` decides whether the failure is retryable if the tracker sent
+    /// it. Otherwise, failures are retryable unless the reason is known to
+    /// be permanent, such as an unregistered torrent or an invalid passkey.
+    ///
+    /// Returns [None] if the announce succeeded.
+    pub fn failure(&self) -> Option<AnnounceFailure<'_>> {
+        let reason = self.failure_reason.as_deref()?;
+
+        let failure = match self.retry_in {
+            Some(RetryIn::Never) => AnnounceFailure::Fatal { reason },
+            Some(RetryIn::Minutes(minutes)) => AnnounceFailure::Retryable {
+                reason,
+                retry_in: Some(Duration::from_secs(minutes.saturating_mul(60))),
+            },
+            None if is_fatal_reason(reason) => AnnounceFailure::Fatal { reason },
+            None => AnnounceFailure::Retryable {
+                reason,
+                retry_in: None,
+            },
+        };
+        debug!(target: FAILURE_TARGET, "Announce failed: {failure:?}");
+
+        Some(failure)
+    }
+
+    /// How long to wait before announcing again after a successful announce.
+    ///
+    /// This is `interval` unless `min interval` is longer.
+    #[inline]
+    pub fn next_announce(&self) -> Option<Duration> {
+        self.interval
+            .max(self.min_interval)
+            .map(Duration::from_secs)
+    }
+
+    /// Shortest time the tracker allows between announces.
+    #[inline]
+    pub fn min_announce(&self) -> Option<Duration> {
+        self.min_interval.map(Duration::from_secs)
+    }
+}
+
+fn is_fatal_reason(reason: &str) -> bool {
+    let reason = reason.to_lowercase();
+    FATAL_REASONS.iter().any(|fatal| reason.contains(fatal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnnounceFailure, AnnounceResponse, Peers, RetryIn};
+    use std::time::Duration;
+
+    #[test]
+    fn successful_announce() {
+        let response: AnnounceResponse = serde_bencode::from_bytes(
+            b"d8:completei3e10:incompletei1e8:intervali1800e12:min intervali3600e5:peers6:\x7f\x00\x00\x01\x1a\xe115:warning message4:mewse",
+        )
+        .unwrap();
+
+        assert!(response.failure().is_none());
+        assert_eq!(response.warning_message.as_deref(), Some("mews"));
+        assert_eq!(response.next_announce(), Some(Duration::from_secs(3600)));
+        assert_eq!(response.min_announce(), Some(Duration::from_secs(3600)));
+        assert_eq!(
+            response.peers,
+            Some(Peers::Compact(b"\x7f\x00\x00\x01\x1a\xe1".to_vec().into()))
+        );
+
+        let list: AnnounceResponse =
+            serde_bencode::from_bytes(b"d8:intervali900e5:peersld2:ip9:127.0.0.14:porti6881eeee")
+                .unwrap();
+        let Some(Peers::List(peers)) = list.peers else {
+            panic!("Expected a peer list: {:?}", list.peers);
+        };
+        assert_eq!((peers[0].ip.as_str(), peers[0].port), ("127.0.0.1", 6881));
+    }
+
+    #[test]
+    fn classify_failures() {
+        let never: AnnounceResponse =
+            serde_bencode::from_bytes(b"d14:failure reason10:go away :(8:retry in5:nevere")
+                .unwrap();
+        assert_eq!(never.retry_in, Some(RetryIn::Never));
+        assert_eq!(
+            never.failure(),
+            Some(AnnounceFailure::Fatal {
+                reason: "go away :("
+            })
+        );
+
+        // Fatal reasons are overridden by retry in.
+        let retry = AnnounceResponse {
+            failure_reason: Some("Torrent not registered with this tracker".to_owned()),
+            ..Default::default()
+        };
+        assert!(!retry.failure().unwrap().is_retryable());
+        let retry = AnnounceResponse {
+            retry_in: Some(RetryIn::Minutes(1)),
+            ..retry
+        };
+        assert!(retry.failure().unwrap().is_retryable());
+
+        let unknown = AnnounceResponse {
+            failure_reason: Some("database timeout".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            unknown.failure(),
+            Some(AnnounceFailure::Retryable {
+                reason: "database timeout",
+                retry_in: None
+            })
+        );
+
+        assert!(serde_bencode::from_bytes::<AnnounceResponse>(b"d8:retry in4:soone").is_err());
+    }
+
+    #[test]
+    fn retry_in_roundtrip() {
+        for retry_in in [RetryIn::Minutes(30), RetryIn::Never] {
+            let response = AnnounceResponse {
+                failure_reason: Some("overloaded".to_owned()),
+                retry_in: Some(retry_in),
+                ..Default::default()
+            };
+            let encoded = serde_bencode::to_bytes(&response).unwrap();
+            assert_eq!(
+                serde_bencode::from_bytes::<AnnounceResponse>(&encoded).unwrap(),
+                response
+            );
+        }
+    }
+}
@@ -1,7 +1,9 @@
 //! Representations of URIs (Uniform Resource Identifiers) as they're used in torrents for trackers, HTTP seeds, Kademlia, et cetera.
 
+pub mod compactnode;
 pub mod node;
 pub mod uriwrapper;
 
-pub use uriwrapper::UriWrapper;
+pub use compactnode::CompactNode;
 pub use node::Node;
+pub use uriwrapper::UriWrapper;
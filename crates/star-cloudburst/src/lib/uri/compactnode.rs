@@ -0,0 +1,177 @@
+//! Compact node info as used by DHT (KRPC) messages.
+//!
+//! [BEP-0005](https://www.bittorrent.org/beps/bep_0005.html) encodes nodes as
+//! a 20 byte node ID followed by a compact IPv4 address and port (26 bytes).
+//! [BEP-0032](https://www.bittorrent.org/beps/bep_0032.html) adds IPv6 nodes
+//! which are 38 bytes. The ports are big endian.
+
+use crate::uri::node::Node;
+use log::trace;
+use serde::de::{value::Error as DeError, Error as DeErrorTrait};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+/// Length of a DHT node ID.
+pub const NODE_ID_LEN: usize = 20;
+/// Length of one compact IPv4 node info entry.
+pub const COMPACT_NODE_V4_LEN: usize = NODE_ID_LEN + 4 + 2;
+/// Length of one compact IPv6 node info entry.
+pub const COMPACT_NODE_V6_LEN: usize = NODE_ID_LEN + 16 + 2;
+
+const COMPACTNODE_TARGET: &str = "star_cloudburst::uri::compactnode";
+
+/// A DHT node and its ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactNode {
+    /// 160 bit node ID.
+    pub id: [u8; NODE_ID_LEN],
+    /// Node address.
+    pub node: Node,
+}
+
+impl CompactNode {
+    /// Encode the node as 26 (IPv4) or 38 (IPv6) bytes.
+    ///
+    /// Nodes with domain names can't be encoded.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, DeError> {
+        let addr = self.node.socket_addr().ok_or_else(|| {
+            DeErrorTrait::custom(format!(
+                "node `{}` isn't an I.P. address and port",
+                self.node.as_uri()
+            ))
+        })?;
+
+        let mut bytes = Vec::with_capacity(COMPACT_NODE_V6_LEN);
+        bytes.extend_from_slice(&self.id);
+        match addr.ip() {
+            IpAddr::V4(ip) => bytes.extend_from_slice(&ip.octets()),
+            IpAddr::V6(ip) => bytes.extend_from_slice(&ip.octets()),
+        }
+        bytes.extend_from_slice(&addr.port().to_be_bytes());
+        Ok(bytes)
+    }
+}
+
+/// Encode nodes of one address family into compact node info.
+///
+/// KRPC messages store IPv4 nodes in `nodes` and IPv6 nodes in `nodes6` so
+/// mixing families is an error.
+///
+/// ```rust
+/// use star_cloudburst::uri::compactnode::{decode_compact_v4, encode_compact, CompactNode};
+/// use std::net::SocketAddr;
+///
+/// let addr: SocketAddr = "127.0.0.1:6881".parse().unwrap();
+/// let nodes = vec![CompactNode {
+///     id: [7; 20],
+///     node: addr.into(),
+/// }];
+///
+/// let compact = encode_compact(&nodes)?;
+/// assert_eq!(compact.len(), 26);
+/// assert_eq!(decode_compact_v4(&compact)?, nodes);
+/// # Ok::<(), serde::de::value::Error>(())
+/// ```
+pub fn encode_compact(nodes: &[CompactNode]) -> Result<Vec<u8>, DeError> {
+    let mut compact = Vec::with_capacity(nodes.len() * COMPACT_NODE_V4_LEN);
+    let mut entry_len = None;
+
+    for node in nodes {
+        let bytes = node.to_bytes()?;
+        if *entry_len.get_or_insert(bytes.len()) != bytes.len() {
+            return Err(DeErrorTrait::custom(
+                "compact node info can't mix IPv4 and IPv6 nodes",
+            ));
+        }
+        compact.extend_from_slice(&bytes);
+    }
+
+    trace!(
+        target: COMPACTNODE_TARGET,
+        "Encoded {} nodes into {} bytes",
+        nodes.len(),
+        compact.len()
+    );
+    Ok(compact)
+}
+
+/// Decode compact IPv4 node info (`nodes`).
+pub fn decode_compact_v4(bytes: &[u8]) -> Result<Vec<CompactNode>, DeError> {
+    decode_compact::<COMPACT_NODE_V4_LEN>(bytes, |ip| {
+        let octets: [u8; 4] = ip.try_into().expect("IPv4 addresses are four bytes.");
+        Ipv4Addr::from(octets).into()
+    })
+}
+
+/// Decode compact IPv6 node info (`nodes6`).
+pub fn decode_compact_v6(bytes: &[u8]) -> Result<Vec<CompactNode>, DeError> {
+    decode_compact::<COMPACT_NODE_V6_LEN>(bytes, |ip| {
+        let octets: [u8; 16] = ip.try_into().expect("IPv6 addresses are sixteen bytes.");
+        Ipv6Addr::from(octets).into()
+    })
+}
+
+fn decode_compact<const LEN: usize>(
+    bytes: &[u8],
+    to_ip: impl Fn(&[u8]) -> IpAddr,
+) -> Result<Vec<CompactNode>, DeError> {
+    if !bytes.len().is_multiple_of(LEN) {
+        return Err(DeErrorTrait::invalid_length(
+            bytes.len(),
+            &format!("a multiple of {LEN}").as_str(),
+        ));
+    }
+
+    let nodes: Vec<_> = bytes
+        .chunks_exact(LEN)
+        .map(|entry| {
+            let (id, addr) = entry.split_at(NODE_ID_LEN);
+            let (ip, port) = addr.split_at(addr.len() - 2);
+            let port = u16::from_be_bytes([port[0], port[1]]);
+
+            CompactNode {
+                id: id.try_into().expect("Node IDs are twenty bytes."),
+                node: SocketAddr::new(to_ip(ip), port).into(),
+            }
+        })
+        .collect();
+
+    trace!(target: COMPACTNODE_TARGET, "Decoded {} nodes", nodes.len());
+    Ok(nodes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_compact_v4, decode_compact_v6, encode_compact, CompactNode};
+    use std::net::SocketAddr;
+
+    fn compact_node(id: u8, addr: &str) -> CompactNode {
+        let addr: SocketAddr = addr.parse().unwrap();
+        CompactNode {
+            id: [id; 20],
+            node: addr.into(),
+        }
+    }
+
+    #[test]
+    fn compact_v6_roundtrip() {
+        let nodes = vec![
+            compact_node(1, "[2001:db8::1]:6881"),
+            compact_node(2, "[::1]:51413"),
+        ];
+
+        let compact = encode_compact(&nodes).unwrap();
+        assert_eq!(compact.len(), 76);
+        assert_eq!(&compact[36..38], &6881u16.to_be_bytes());
+        assert_eq!(decode_compact_v6(&compact).unwrap(), nodes);
+    }
+
+    #[test]
+    fn compact_errors() {
+        let mixed = [
+            compact_node(1, "127.0.0.1:6881"),
+            compact_node(2, "[::1]:6881"),
+        ];
+        assert!(encode_compact(&mixed).is_err());
+        assert!(decode_compact_v4(&[0; 27]).is_err());
+    }
+}
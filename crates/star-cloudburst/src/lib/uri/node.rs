@@ -1,13 +1,23 @@
-use crate::uri::uriwrapper::UriWrapper;
+use crate::{
+    parseobserver::{self, ParseEvent},
+    parseoptions,
+    uri::uriwrapper::UriWrapper,
+};
+#[cfg(feature = "uri")]
 use http::Uri;
-use log::{debug, trace};
+#[cfg(feature = "uri")]
+use log::debug;
+use log::{trace, warn};
 use serde::{
-    de::{Error as DeErrorTrait, Unexpected},
+    de::{Error as DeErrorTrait, IgnoredAny, SeqAccess, Unexpected, Visitor},
     ser::Error as SerError,
     Deserialize, Deserializer, Serialize, Serializer,
 };
+#[cfg(feature = "uri")]
 use std::borrow::Borrow;
+use std::{fmt, net::SocketAddr};
 
+#[cfg(feature = "uri")]
 const AUTHORITY_INVARIANT: &str = "a valid authority from UriWrapper (this shouldn't ever happen)";
 const PORT_INVARIANT: &str = "Port is missing but is canonically always available.";
 const NODE_SER_TARGET: &str = "star_cloudburst::uri::Node::serialize";
@@ -26,8 +36,46 @@ impl Node {
     pub fn as_uri(&self) -> &UriWrapper {
         &self.0
     }
+
+    /// The node's socket address if the host is an I.P. address.
+    ///
+    /// Nodes with domain names return `None` because resolving them requires DNS.
+    #[cfg(feature = "uri")]
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        let uri: &Uri = self.0.borrow();
+        // IPv6 hosts are bracketed in URIs.
+        let host = uri.host()?.trim_start_matches('[').trim_end_matches(']');
+        Some(SocketAddr::new(host.parse().ok()?, uri.port_u16()?))
+    }
+
+    /// The node's socket address if the host is an I.P. address.
+    ///
+    /// Nodes with domain names return `None` because resolving them requires DNS.
+    #[cfg(not(feature = "uri"))]
+    #[inline]
+    pub fn socket_addr(&self) -> Option<SocketAddr> {
+        self.0.as_str().parse().ok()
+    }
+}
+
+impl From<SocketAddr> for Node {
+    #[cfg(feature = "uri")]
+    fn from(addr: SocketAddr) -> Self {
+        let uri = addr
+            .to_string()
+            .parse()
+            .expect("Socket addresses are valid URI authorities.");
+        Node(UriWrapper::from_uri_unchecked(uri))
+    }
+
+    #[cfg(not(feature = "uri"))]
+    #[inline]
+    fn from(addr: SocketAddr) -> Self {
+        Node(UriWrapper::from_string_unchecked(addr.to_string()))
+    }
 }
 
+#[cfg(feature = "uri")]
 impl<'de> Deserialize<'de> for Node {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -35,10 +83,9 @@ impl<'de> Deserialize<'de> for Node {
     {
         trace!(target: NODE_DE_TARGET, "Deserializing Node");
 
-        // Deserialize NodeTemp first which ensures a valid URI and port.
-        let node_temp = NodeTemp::deserialize(deserializer)?;
-        debug!(target: NODE_DE_TARGET, "Deserialized URI: {node_temp:?}");
-        let NodeTemp((uri, port)) = node_temp;
+        // Deserialize the (host, port) pair first which ensures a valid URI and port.
+        let (uri, port) = deserializer.deserialize_any(NodeVisitor)?;
+        debug!(target: NODE_DE_TARGET, "Deserialized URI: {uri:?}, port: {port}");
 
         // Append port number to authority.
         let mut parts = uri.into_inner().into_parts();
@@ -65,6 +112,7 @@ impl<'de> Deserialize<'de> for Node {
     }
 }
 
+#[cfg(feature = "uri")]
 impl Serialize for Node {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -88,27 +136,18 @@ impl Serialize for Node {
             .map(|query| query.as_str())
             .unwrap_or_default();
 
-        // Extract port from Uri and port position in the authority string
+        // Extract port from Uri and strip it from the end of the authority.
         let port = uri.port().ok_or_else(|| SerError::custom(PORT_INVARIANT))?;
-        // This is so I don't need to allocate a temporary String.
         let port_str = port.as_str();
-        let mut port_find_iter = std::iter::once(':').chain(port_str.chars());
-        let port_pos = authority
-            .rfind(|ch| {
-                if let Some(ch_port) = port_find_iter.next() {
-                    ch == ch_port
-                } else {
-                    false
-                }
-            })
+        let host = authority
+            .strip_suffix(port_str)
+            .and_then(|authority| authority.strip_suffix(':'))
             .ok_or_else(|| SerError::custom(PORT_INVARIANT))?;
 
-        trace!(target: NODE_SER_TARGET, "Port: {port}\nPort position: {port_pos}");
+        trace!(target: NODE_SER_TARGET, "Port: {port}\nHost: {host}");
 
         // Recombine without port.
-        let uri_temp = format!("{scheme}{scheme_sep}{authority_before}{authority_after}/{query}",
-                            authority_before = &authority[0..port_pos],
-                            authority_after = &authority[port_pos + port_str.len()..])
+        let uri_temp = format!("{scheme}{scheme_sep}{host}{query}")
             .parse()
             .map_err(|e| SerError::custom(format!("Invalid URI after splitting and recombining `Node`; this shouldn't happen.\nUri error: {e}")))?;
         let uri_temp = UriWrapper::from_uri_unchecked(uri_temp);
@@ -118,22 +157,199 @@ impl Serialize for Node {
     }
 }
 
+// Without the `uri` feature, nodes are stored as plain `host:port` strings.
+#[cfg(not(feature = "uri"))]
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        trace!(target: NODE_DE_TARGET, "Deserializing Node");
+
+        let (host, port) = deserializer.deserialize_any(NodeVisitor)?;
+        Ok(Node(UriWrapper::from_string_unchecked(format!(
+            "{host}:{port}"
+        ))))
+    }
+}
+
+#[cfg(not(feature = "uri"))]
+impl Serialize for Node {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        trace!(target: NODE_SER_TARGET, "Serializing Node: {self:?}");
+
+        let (host, port) = self
+            .as_uri()
+            .as_str()
+            .rsplit_once(':')
+            .ok_or_else(|| SerError::custom(PORT_INVARIANT))?;
+        let port = port.parse().map_err(SerError::custom)?;
+        let node_temp = NodeTemp((UriWrapper::from_string_unchecked(host.to_owned()), port));
+
+        node_temp.serialize(serializer)
+    }
+}
+
+// Visits the (host, port) pair of a node.
+//
+// Lenient parsing also accepts `host:port` strings and pairs followed by extra elements.
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = (UriWrapper, u16);
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a (host, port) pair")
+    }
+
+    // serde_test wraps the pair in `NodeTemp`.
+    #[inline]
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let host = seq
+            .next_element()?
+            .ok_or_else(|| DeErrorTrait::invalid_length(0, &self))?;
+        let port = seq
+            .next_element()?
+            .ok_or_else(|| DeErrorTrait::invalid_length(1, &self))?;
+
+        let mut extra = 0;
+        while seq.next_element::<IgnoredAny>()?.is_some() {
+            extra += 1;
+        }
+        if extra > 0 {
+            if !parseoptions::current().lenient_nodes {
+                return Err(DeErrorTrait::invalid_length(2 + extra, &self));
+            }
+            warn!(
+                target: NODE_DE_TARGET,
+                "Ignoring {extra} extra elements of node {host}:{port}"
+            );
+            parseobserver::report(|| ParseEvent::LenientFix {
+                field: "nodes",
+                detail: format!("ignored {extra} extra elements of {host}:{port}"),
+            });
+        }
+
+        Ok((host, port))
+    }
+
+    fn visit_str<E>(self, node: &str) -> Result<Self::Value, E>
+    where
+        E: DeErrorTrait,
+    {
+        if !parseoptions::current().lenient_nodes {
+            return Err(DeErrorTrait::invalid_type(Unexpected::Str(node), &self));
+        }
+
+        let (host, port) = node
+            .rsplit_once(':')
+            .ok_or_else(|| DeErrorTrait::invalid_value(Unexpected::Str(node), &"host:port"))?;
+        let port = port
+            .parse()
+            .map_err(|_| DeErrorTrait::invalid_value(Unexpected::Str(port), &"a port number"))?;
+        warn!(target: NODE_DE_TARGET, "Accepting node as a string: {node}");
+        parseobserver::report(|| ParseEvent::LenientFix {
+            field: "nodes",
+            detail: format!("split `{node}` into host and port"),
+        });
+
+        Ok((host.parse().map_err(DeErrorTrait::custom)?, port))
+    }
+
+    fn visit_bytes<E>(self, node: &[u8]) -> Result<Self::Value, E>
+    where
+        E: DeErrorTrait,
+    {
+        std::str::from_utf8(node)
+            .map_err(|_| DeErrorTrait::invalid_type(Unexpected::Bytes(node), &self))
+            .and_then(|node| self.visit_str(node))
+    }
+}
+
 // Nodes are represented as (host, port) pairs as per [Node].
 // NodeTemp is the actual type that will be deserialized and serialized while [Node] is a [UriWrapper]...wrapper.
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Serialize, Debug)]
 struct NodeTemp((UriWrapper, u16));
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Node {
+    // An IPv4 host and a port.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let ip = std::net::Ipv4Addr::from(u.arbitrary::<[u8; 4]>()?);
+        let port: u16 = u.int_in_range(1..=u16::MAX)?;
+
+        #[cfg(feature = "uri")]
+        let node = Node(
+            format!("udp://{ip}:{port}")
+                .parse()
+                .expect("Generated socket addresses are always valid."),
+        );
+        #[cfg(not(feature = "uri"))]
+        let node = Node(UriWrapper::from_string_unchecked(format!("{ip}:{port}")));
+
+        Ok(node)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::uri::node::Node;
+    use crate::{parseoptions::ParseOptions, uri::node::Node, Torrent};
     use serde_test::{assert_tokens, Token};
+    use std::net::SocketAddr;
 
     const LOCALHOST_IP: &str = "127.0.0.1:6881";
 
+    // Nodes are (host, port) pairs as per BEP-0005.
     #[test]
     fn localhost_ip() {
         let wrapper = LOCALHOST_IP.parse().expect("URI is valid.");
         let node = Node(wrapper);
-        assert_tokens(&node, &[Token::String(LOCALHOST_IP)])
+        assert_tokens(
+            &node,
+            &[
+                Token::NewtypeStruct { name: "NodeTemp" },
+                Token::Tuple { len: 2 },
+                Token::Str("127.0.0.1"),
+                Token::U16(6881),
+                Token::TupleEnd,
+            ],
+        )
+    }
+
+    #[test]
+    fn node_socket_addr() {
+        let addr: SocketAddr = "[::1]:6881".parse().unwrap();
+        assert_eq!(Node::from(addr).socket_addr(), Some(addr));
+
+        let addr: SocketAddr = LOCALHOST_IP.parse().unwrap();
+        assert_eq!(Node::from(addr).socket_addr(), Some(addr));
+    }
+
+    #[test]
+    fn lenient_nodes() {
+        let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaae5:nodesl14:127.0.0.1:6881l9:127.0.0.1i6882e4:meoweee";
+        assert!(Torrent::parse_with(cats, ParseOptions::default()).is_err());
+
+        let torrent = Torrent::parse_with(cats, ParseOptions::lenient()).unwrap();
+        let addrs: Vec<_> = torrent
+            .nodes
+            .unwrap()
+            .iter()
+            .map(|node| node.socket_addr().unwrap().port())
+            .collect();
+        assert_eq!(addrs, [6881, 6882]);
     }
 }
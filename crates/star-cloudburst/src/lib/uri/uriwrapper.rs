@@ -1,17 +1,21 @@
+#[cfg(feature = "uri")]
 use http::uri::Uri;
 use log::trace;
 use serde::{
     de::{value::Error as DeError, Error as DeErrorTrait},
     Deserialize, Deserializer, Serialize, Serializer,
 };
+#[cfg(feature = "uri")]
+use std::borrow::Borrow;
 use std::{
-    borrow::{Borrow, Cow},
+    borrow::Cow,
     fmt::{self, Display, Formatter},
     str::FromStr,
 };
 
 // Preclude schemes such as `file`
 // This should be fairly liberal in what it accepts.
+#[cfg(feature = "uri")]
 const SANCTIONED_SCHEMES: &[&str] = &[
     "ed2k", "ftp", "http", "https", "gopher", "magnet", "sftp", "tcp", "tftp", "udp", "ws", "wss",
 ];
@@ -21,18 +25,83 @@ const URIWRAPPER_PARSE_TARGET: &str = "star_cloudburst::uri::UriWrapper::from_st
 const URIWRAPPER_DE_TARGET: &str = "star_cloudburst::uri::UriWrapper::deserialize";
 const URIWRAPPER_SER_TARGET: &str = "star_cloudburst::uri::UriWrapper::serialize";
 
+/// A URI as used by torrents for trackers, web seeds, and nodes.
+///
+/// With the `uri` feature, [UriWrapper] wraps an [http::Uri] that is validated while parsing.
+/// Without the feature, [UriWrapper] stores the URI as a plain, unvalidated [String].
+///
+/// Parsed URIs keep their original string which is serialized verbatim so that torrents roundtrip
+/// byte for byte. [http::Uri] may otherwise change the string, such as by lowercasing the scheme.
+/// Equality and hashing only consider the parsed URI. Hosts are compared case insensitively and
+/// internationalized domain names are converted to punycode so that both spellings are equal.
+#[cfg(feature = "uri")]
+#[derive(Debug, Clone)]
+pub struct UriWrapper {
+    uri: Uri,
+    // None if the URI was built rather than parsed.
+    original: Option<Box<str>>,
+}
+
+/// A URI as used by torrents for trackers, web seeds, and nodes.
+///
+/// With the `uri` feature, [UriWrapper] wraps an `http::Uri` that is validated while parsing.
+/// Without the feature, [UriWrapper] stores the URI as a plain, unvalidated [String].
+#[cfg(not(feature = "uri"))]
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct UriWrapper(Uri);
+pub struct UriWrapper(String);
 
+#[cfg(feature = "uri")]
 impl UriWrapper {
     #[inline]
     pub(crate) fn from_uri_unchecked(uri: Uri) -> Self {
-        Self(uri)
+        Self {
+            uri,
+            original: None,
+        }
     }
 
     #[inline]
     pub(crate) fn into_inner(self) -> Uri {
-        self.0
+        self.uri
+    }
+
+    /// The URI as it was written in the torrent, if it was parsed from one.
+    #[inline]
+    pub fn original(&self) -> Option<&str> {
+        self.original.as_deref()
+    }
+}
+
+#[cfg(feature = "uri")]
+impl PartialEq for UriWrapper {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.uri == other.uri
+    }
+}
+
+#[cfg(feature = "uri")]
+impl Eq for UriWrapper {}
+
+#[cfg(feature = "uri")]
+impl std::hash::Hash for UriWrapper {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.uri.hash(state)
+    }
+}
+
+#[cfg(not(feature = "uri"))]
+impl UriWrapper {
+    #[inline]
+    pub(crate) fn from_string_unchecked(uri: String) -> Self {
+        Self(uri)
+    }
+
+    /// The URI as a string slice.
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 }
 
@@ -58,24 +127,52 @@ impl Serialize for UriWrapper {
             target: URIWRAPPER_SER_TARGET,
             "Serializing UriWrapper: {self:?}"
         );
-        serializer.serialize_str(&self.0.to_string())
+        serializer.serialize_str(&self.to_string())
     }
 }
 
+#[cfg(feature = "uri")]
 impl Display for UriWrapper {
     #[inline]
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        <Uri as Display>::fmt(&self.0, f)
+        match &self.original {
+            Some(original) => f.write_str(original),
+            None => Display::fmt(&self.uri, f),
+        }
     }
 }
 
+#[cfg(not(feature = "uri"))]
+impl Display for UriWrapper {
+    #[inline]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(feature = "uri")]
 impl Borrow<Uri> for UriWrapper {
     #[inline]
     fn borrow(&self) -> &Uri {
-        &self.0
+        &self.uri
     }
 }
 
+#[cfg(not(feature = "uri"))]
+impl FromStr for UriWrapper {
+    type Err = DeError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        trace!(
+            target: URIWRAPPER_PARSE_TARGET,
+            "Storing string `{s}` as an unvalidated `UriWrapper`"
+        );
+        Ok(UriWrapper(s.to_owned()))
+    }
+}
+
+#[cfg(feature = "uri")]
 impl FromStr for UriWrapper {
     type Err = DeError;
 
@@ -86,64 +183,156 @@ impl FromStr for UriWrapper {
         );
 
         // Parse into a Uri and then ensure the Uri is correct for torrents.
-        s.parse().map_err(DeError::custom).and_then(|uri: Uri| {
-            // Disallow relative URIs which can indicate path traversal
-            // Absolute URIs work with I.P. addresses too; this is why I can't just use URLs
-            if uri.host().is_some() {
-                let mut parts = uri.into_parts();
-
-                // TODO: Replace with Option::is_some_and when it's stable
-                // I'm not unwrapping because then I'd have to check all of the sanctioned schemes against "".
-                if let Some(scheme_str) = parts.scheme.as_ref().map(|scheme| {
-                    // URI schemes should be lowercased even though they're case insensitive. The http crate doesn't enforce this
-                    // but deterministic casing makes everything easier.
-                    let scheme = scheme.as_str().to_lowercase();
-
-                    // Only allow a liberal subset of schemes.
-                    if SANCTIONED_SCHEMES.contains(&scheme.as_str()) {
-                        Ok(scheme)
+        ascii_host(s)?
+            .parse()
+            .map_err(DeError::custom)
+            .and_then(|uri: Uri| {
+                // Disallow relative URIs which can indicate path traversal
+                // Absolute URIs work with I.P. addresses too; this is why I can't just use URLs
+                if uri.host().is_some() {
+                    let mut parts = uri.into_parts();
+
+                    // TODO: Replace with Option::is_some_and when it's stable
+                    // I'm not unwrapping because then I'd have to check all of the sanctioned schemes against "".
+                    if let Some(scheme_str) = parts.scheme.as_ref().map(|scheme| {
+                        // URI schemes should be lowercased even though they're case insensitive. The http crate doesn't enforce this
+                        // but deterministic casing makes everything easier.
+                        let scheme = scheme.as_str().to_lowercase();
+
+                        // Only allow a liberal subset of schemes.
+                        if SANCTIONED_SCHEMES.contains(&scheme.as_str()) {
+                            Ok(scheme)
+                        } else {
+                            Err(DeErrorTrait::custom(format!("invalid scheme: `{scheme}`",)))
+                        }
+                    }) {
+                        let scheme_str = scheme_str?;
+                        parts.scheme = Some(scheme_str.as_str().try_into().unwrap());
+
+                        // Cleaned and sanctioned scheme, absolute URI
+                        Ok(UriWrapper {
+                            uri: parts.try_into().unwrap(),
+                            original: Some(s.into()),
+                        })
                     } else {
-                        Err(DeErrorTrait::custom(format!("invalid scheme: `{scheme}`",)))
+                        // No scheme, absolute URI
+                        Ok(UriWrapper {
+                            uri: parts.try_into().unwrap(),
+                            original: Some(s.into()),
+                        })
                     }
-                }) {
-                    let scheme_str = scheme_str?;
-                    parts.scheme = Some(scheme_str.as_str().try_into().unwrap());
-
-                    // Cleaned and sanctioned scheme, absolute URI
-                    Ok(UriWrapper(parts.try_into().unwrap()))
                 } else {
-                    // No scheme, absolute URI
-                    Ok(UriWrapper(parts.try_into().unwrap()))
+                    Err(DeErrorTrait::custom("relative URL without a base"))
                 }
-            } else {
-                Err(DeErrorTrait::custom("relative URL without a base"))
-            }
-        })
+            })
     }
 }
 
-#[cfg(test)]
+/// Convert an internationalized host to punycode.
+///
+/// [Uri] only accepts ASCII and compares hosts case insensitively, so
+/// converting internationalized domain names lets `bücher.example` and
+/// `xn--bcher-kva.example` compare equal.
+#[cfg(feature = "uri")]
+fn ascii_host(s: &str) -> Result<Cow<'_, str>, DeError> {
+    if s.is_ascii() {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    let authority_start = s.find("://").map(|i| i + 3).unwrap_or_default();
+    let authority_end = s[authority_start..]
+        .find(['/', '?', '#'])
+        .map_or(s.len(), |i| authority_start + i);
+    let authority = &s[authority_start..authority_end];
+
+    let host_start = authority.rfind('@').map(|i| i + 1).unwrap_or_default();
+    let host_end = match authority.rfind(':') {
+        Some(i) if i >= host_start && !authority[i..].contains(']') => i,
+        _ => authority.len(),
+    };
+    let host = &authority[host_start..host_end];
+
+    let ascii = idna::domain_to_ascii(host).map_err(|e| {
+        DeErrorTrait::custom(format!("invalid internationalized host `{host}`: {e}"))
+    })?;
+    trace!(
+        target: URIWRAPPER_PARSE_TARGET,
+        "Converted host `{host}` to `{ascii}`"
+    );
+
+    let host_start = authority_start + host_start;
+    let host_end = authority_start + host_end;
+    Ok(Cow::Owned(format!(
+        "{}{ascii}{}",
+        &s[..host_start],
+        &s[host_end..]
+    )))
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for UriWrapper {
+    // Tracker-like URIs such as `udp://tracker0.example:6969/announce`.
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let scheme = u.choose(&["http", "https", "udp"])?;
+        let host = u.choose(&["tracker", "seed", "node", "cats"])?;
+        let tld = u.choose(&["com", "net", "org", "example"])?;
+        let id: u8 = u.arbitrary()?;
+        let port: u16 = u.int_in_range(1..=u16::MAX)?;
+
+        Ok(format!("{scheme}://{host}{id}.{tld}:{port}/announce")
+            .parse()
+            .expect("Generated URIs are always valid."))
+    }
+}
+
+#[cfg(all(test, feature = "uri"))]
 mod tests {
     use super::UriWrapper;
     use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+    use std::collections::HashSet;
 
     const EXAMPLE_OK: &str = "https://example.com/";
     const EXAMPLE_BAD: &str = "/etc/shadow";
     const EXAMPLE_TRACKER: &str = "udp://somefakesitemeow.faketld:666/announce";
+    const EXAMPLE_SHOUTY: &str = "UDP://somefakesitemeow.faketld:666/announce";
     const EXAMPLE_FILE: &str = "file://home/joshua/Documents/Essays/why_i_like_mudkips.tex";
 
     #[test]
     fn uriwrapper_okay() {
-        let uri = UriWrapper(EXAMPLE_OK.parse().unwrap());
+        let uri = UriWrapper::from_uri_unchecked(EXAMPLE_OK.parse().unwrap());
         assert_tokens(&uri, &[Token::String(EXAMPLE_OK)])
     }
 
     #[test]
     fn uriwrapper_tracker_okay() {
-        let uri = UriWrapper(EXAMPLE_TRACKER.parse().unwrap());
+        let uri = UriWrapper::from_uri_unchecked(EXAMPLE_TRACKER.parse().unwrap());
         assert_tokens(&uri, &[Token::String(EXAMPLE_TRACKER)])
     }
 
+    #[test]
+    fn uriwrapper_verbatim() {
+        let uri: UriWrapper = EXAMPLE_SHOUTY.parse().unwrap();
+        assert_eq!(uri, EXAMPLE_TRACKER.parse().unwrap());
+        assert_tokens(&uri, &[Token::String(EXAMPLE_SHOUTY)])
+    }
+
+    #[test]
+    fn uriwrapper_idn() {
+        let unicode: UriWrapper = "udp://Bücher.example:6969/announce".parse().unwrap();
+        let punycode: UriWrapper = "udp://xn--bcher-kva.EXAMPLE:6969/announce".parse().unwrap();
+
+        assert_eq!(unicode, punycode);
+        assert_eq!(
+            HashSet::from([unicode.clone(), punycode]).len(),
+            1,
+            "IDN trackers should deduplicate"
+        );
+        assert_eq!(
+            unicode.original(),
+            Some("udp://Bücher.example:6969/announce")
+        );
+    }
+
     #[test]
     fn uriwrapper_oops() {
         assert_de_tokens_error::<UriWrapper>(
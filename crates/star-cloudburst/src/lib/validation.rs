@@ -0,0 +1,408 @@
+//! Problems found while parsing a torrent that didn't stop it from parsing.
+//!
+//! Corpus maintainers use a [ValidationReport] to see what torrents in the
+//! wild carry that this crate doesn't model yet. [Torrent::validate] checks
+//! invariants between fields that parsing can't check one field at a time.
+
+use crate::{
+    crypto::sha2::Sha2,
+    files::{FileTree, FlatFile, MetaV1FileRepr},
+    hexadecimal::HexBytes,
+    metainfo::{MetaInfo, MetaVersion},
+    Torrent,
+};
+use log::debug;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+const VALIDATE_TARGET: &str = "star_cloudburst::Torrent::validate";
+
+/// Findings of [crate::Torrent::parse_report].
+#[non_exhaustive]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Paths of fields that aren't declared by this crate's types, such as
+    /// `info.file tree.dir1.cats.mkv.color`.
+    ///
+    /// Only collected with [crate::parseoptions::UnknownFieldPolicy::Report].
+    /// Paths are sorted.
+    pub unknown_fields: Vec<String>,
+}
+
+impl ValidationReport {
+    /// Whether nothing was found.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.unknown_fields.is_empty()
+    }
+}
+
+/// An invariant between fields that [Torrent::validate] found broken.
+///
+/// File paths are relative to the torrent's directory and joined with `/`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The number of version 1 `pieces` doesn't cover the files' total length.
+    PieceCount { expected: u64, actual: usize },
+    /// A file is described with different lengths by the version 1 files and
+    /// the `file tree` of a hybrid torrent. A missing length means that one
+    /// side doesn't have the file at all.
+    HybridFileMismatch {
+        path: String,
+        v1: Option<u64>,
+        v2: Option<u64>,
+    },
+    /// A hybrid torrent lists the same files in a different order in its
+    /// version 1 files and its `file tree`.
+    HybridFileOrder,
+    /// A hybrid torrent with a `file tree` doesn't have version 1 `pieces`.
+    MissingPieces,
+    /// A hybrid torrent with a `meta version` doesn't have a `file tree`.
+    MissingFileTree,
+    /// A version 2 dict doesn't have a `meta version`.
+    MissingMetaVersion,
+    /// `meta version` is less than two.
+    InvalidMetaVersion(u8),
+    /// A file with data doesn't have a `pieces root`.
+    MissingPiecesRoot { path: String },
+    /// A file larger than a piece doesn't have an entry in `piece layers`.
+    MissingPieceLayer { pieces_root: Sha2 },
+    /// A piece layer doesn't have one hash per piece of its file.
+    PieceLayerLength {
+        pieces_root: Sha2,
+        expected: usize,
+        actual: usize,
+    },
+    /// A piece layer doesn't hash to its `pieces root`.
+    ///
+    /// Only checked with the `crypto` feature.
+    PieceLayerRoot { pieces_root: Sha2 },
+    /// A `piece layers` key isn't the `pieces root` of any file larger than a
+    /// piece.
+    UnusedPieceLayer(HexBytes),
+}
+
+impl Torrent {
+    /// Check invariants between fields and return every one that's broken.
+    ///
+    /// Hybrid torrents must describe the same files in their version 1 files
+    /// and `file tree`, `pieces` must cover every file, and `piece layers` must
+    /// match the `pieces root` of each file that's larger than a piece. An
+    /// empty list means the torrent is consistent.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{validation::Violation, Torrent};
+    /// use serde_bencode::Error;
+    ///
+    /// // Two pieces for a 42 byte file with 16 KiB pieces.
+    /// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces40:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// assert_eq!(
+    ///     torrent.validate(),
+    ///     [Violation::PieceCount { expected: 1, actual: 2 }]
+    /// );
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let piece_length = self.info.piece_length().get();
+
+        // Version 1 files including padding and their pieces.
+        let v1 = match &self.info {
+            MetaInfo::MetaV1(info) => Some(match &info.files {
+                MetaV1FileRepr::Single(length) => {
+                    (vec![(vec![self.name()], length.get(), false)], &info.pieces)
+                }
+                MetaV1FileRepr::Multiple(files) => (flat_files(files), &info.pieces),
+            }),
+            MetaInfo::Hybrid(info) => info.pieces.as_ref().map(|pieces| {
+                let files = match (&info.files, info.length) {
+                    (Some(files), _) => flat_files(files),
+                    (None, length) => {
+                        vec![(
+                            vec![self.name()],
+                            length.map_or(0, |length| length.get()),
+                            false,
+                        )]
+                    }
+                };
+                (files, pieces)
+            }),
+            MetaInfo::MetaV2(_) | MetaInfo::MerkleV1(_) => None,
+        };
+        if let Some((files, pieces)) = &v1 {
+            let total: u64 = files.iter().map(|(_, length, _)| length).sum();
+            let expected = total.div_ceil(piece_length);
+            if pieces.len() as u64 != expected {
+                violations.push(Violation::PieceCount {
+                    expected,
+                    actual: pieces.len(),
+                });
+            }
+        }
+
+        let (tree, meta_version) = match &self.info {
+            MetaInfo::MetaV2(info) => (Some(&info.file_tree), Some(info.meta_version)),
+            MetaInfo::Hybrid(info) => (info.file_tree.as_ref(), info.meta_version),
+            MetaInfo::MetaV1(_) | MetaInfo::MerkleV1(_) => (None, None),
+        };
+        if let MetaInfo::Hybrid(info) = &self.info {
+            match (&info.file_tree, &info.pieces, info.meta_version) {
+                (Some(_), None, _) => violations.push(Violation::MissingPieces),
+                (None, _, Some(_)) => violations.push(Violation::MissingFileTree),
+                _ => {}
+            }
+        }
+        match (tree, meta_version) {
+            (Some(_), None) => violations.push(Violation::MissingMetaVersion),
+            (_, Some(MetaVersion::Future(version))) if version <= 2 => {
+                violations.push(Violation::InvalidMetaVersion(version))
+            }
+            _ => {}
+        }
+
+        if let Some(tree) = tree {
+            if let Some((files, _)) = &v1 {
+                hybrid_files(files, tree, &mut violations);
+            }
+            self.piece_layers(tree, piece_length, &mut violations);
+        }
+
+        debug!(
+            target: VALIDATE_TARGET,
+            "{} has {} violations",
+            self.name(),
+            violations.len()
+        );
+        violations
+    }
+
+    // Check `piece layers` against the `pieces root` of every file in `tree`.
+    fn piece_layers(&self, tree: &FileTree, piece_length: u64, violations: &mut Vec<Violation>) {
+        let mut used = HashSet::new();
+
+        for view in tree.iter_dfs() {
+            let file = view.file_info;
+            if file.length == 0 || file.is_padding() || file.is_symlink() {
+                continue;
+            }
+            let Some(root) = &file.pieces_root else {
+                violations.push(Violation::MissingPiecesRoot {
+                    path: tree_path(&view.directory, view.name).join("/"),
+                });
+                continue;
+            };
+            if file.length <= piece_length {
+                continue;
+            }
+
+            used.insert(&root.as_bytes()[..]);
+            let layer = self
+                .piece_layers
+                .as_ref()
+                .and_then(|layers| layers.get(&root.as_bytes()[..]));
+            let expected = file.length.div_ceil(piece_length) as usize;
+            match layer {
+                None => violations.push(Violation::MissingPieceLayer {
+                    pieces_root: root.clone(),
+                }),
+                Some(layer) if layer.len() != expected * 32 => {
+                    violations.push(Violation::PieceLayerLength {
+                        pieces_root: root.clone(),
+                        expected,
+                        actual: layer.len() / 32,
+                    })
+                }
+                #[cfg(feature = "crypto")]
+                Some(layer)
+                    if !crate::crypto::merkle::verify_piece_layer(
+                        layer.as_slice(),
+                        root,
+                        file.length,
+                        self.info.piece_length(),
+                    ) =>
+                {
+                    violations.push(Violation::PieceLayerRoot {
+                        pieces_root: root.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        let mut unused: Vec<_> = self
+            .piece_layers
+            .iter()
+            .flatten()
+            .map(|(key, _)| key)
+            .filter(|key| !used.contains(key.as_slice()))
+            .cloned()
+            .collect();
+        unused.sort_by(|left, right| left.as_slice().cmp(right.as_slice()));
+        violations.extend(unused.into_iter().map(Violation::UnusedPieceLayer));
+    }
+}
+
+// Path, length, and whether it's padding of each version 1 file.
+fn flat_files(files: &[FlatFile]) -> Vec<(Vec<&str>, u64, bool)> {
+    files
+        .iter()
+        .map(|file| {
+            let path = file.path.iter().map(|part| &**part).collect();
+            (path, file.length.get(), file.is_padding())
+        })
+        .collect()
+}
+
+// Path components of a file tree view without the root and the empty names of
+// version 2 files.
+fn tree_path<'a>(directory: &[&'a str], name: &'a str) -> Vec<&'a str> {
+    directory[1..]
+        .iter()
+        .copied()
+        .chain([name])
+        .filter(|part| !part.is_empty())
+        .collect()
+}
+
+// Compare the version 1 files of a hybrid torrent with its file tree.
+fn hybrid_files(v1: &[(Vec<&str>, u64, bool)], tree: &FileTree, violations: &mut Vec<Violation>) {
+    let v1: Vec<_> = v1
+        .iter()
+        .filter(|(_, _, padding)| !padding)
+        .map(|(path, length, _)| (path.join("/"), *length))
+        .collect();
+    let v2: Vec<_> = tree
+        .iter_dfs()
+        .filter(|view| !view.file_info.is_padding())
+        .map(|view| {
+            (
+                tree_path(&view.directory, view.name).join("/"),
+                view.file_info.length,
+            )
+        })
+        .collect();
+
+    let v1_lengths: BTreeMap<_, _> = v1.iter().cloned().collect();
+    let v2_lengths: BTreeMap<_, _> = v2.iter().cloned().collect();
+    let before = violations.len();
+    for path in v1_lengths
+        .keys()
+        .chain(v2_lengths.keys())
+        .collect::<BTreeSet<_>>()
+    {
+        let (v1, v2) = (v1_lengths.get(path).copied(), v2_lengths.get(path).copied());
+        if v1 != v2 {
+            violations.push(Violation::HybridFileMismatch {
+                path: path.clone(),
+                v1,
+                v2,
+            });
+        }
+    }
+
+    if violations.len() == before && v1 != v2 {
+        violations.push(Violation::HybridFileOrder);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Violation;
+    use crate::{crypto::sha2::Sha2, hexadecimal::HexBytes, metainfo::MetaInfo, Torrent};
+    use std::{env, fs, num::NonZeroU64};
+
+    fn example(name: &str) -> Torrent {
+        let path = format!("{}/resources/tests/{name}", env!("CARGO_MANIFEST_DIR"));
+        let contents = fs::read(path).expect("Example torrents exist.");
+        serde_bencode::from_bytes(&contents).expect("Example torrents are valid.")
+    }
+
+    #[test]
+    fn consistent_examples() {
+        for name in [
+            "bittorrent-v2-hybrid-test.torrent",
+            "bittorrent-v2-test.torrent",
+            "big-buck-bunny.torrent",
+        ] {
+            assert_eq!(example(name).validate(), [], "{name}");
+        }
+    }
+
+    #[test]
+    fn hybrid_violations() {
+        let mut torrent = example("bittorrent-v2-hybrid-test.torrent");
+
+        // Move a piece layer to a key that isn't a pieces root.
+        let layers = torrent
+            .piece_layers
+            .as_mut()
+            .expect("Large files have layers.");
+        let key = layers
+            .keys()
+            .min_by_key(|key| key.as_slice())
+            .cloned()
+            .unwrap();
+        let layer = layers.remove(&key).unwrap();
+        layers.insert(HexBytes::from([0; 32]), layer);
+
+        let MetaInfo::Hybrid(info) = torrent.info_mut() else {
+            panic!("Example torrent is a hybrid.")
+        };
+        info.meta_version = None;
+        info.files
+            .as_mut()
+            .expect("Example torrent has files.")
+            .swap(0, 2);
+
+        assert_eq!(
+            torrent.validate(),
+            [
+                Violation::MissingMetaVersion,
+                Violation::HybridFileOrder,
+                Violation::MissingPieceLayer {
+                    pieces_root: Sha2::try_from(key.as_slice()).unwrap()
+                },
+                Violation::UnusedPieceLayer(HexBytes::from([0; 32])),
+            ]
+        );
+
+        let MetaInfo::Hybrid(info) = torrent.info_mut() else {
+            unreachable!()
+        };
+        let file = &mut info.files.as_mut().unwrap()[0];
+        let length = file.length.get();
+        file.length = NonZeroU64::new(length + 1).unwrap();
+        let path = file.path.join("/");
+
+        let violations = torrent.validate();
+        assert!(violations.contains(&Violation::HybridFileMismatch {
+            path,
+            v1: Some(length + 1),
+            v2: Some(length)
+        }));
+        assert!(!violations.contains(&Violation::HybridFileOrder));
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn corrupt_piece_layer() {
+        let mut torrent = example("bittorrent-v2-test.torrent");
+        let layers = torrent
+            .piece_layers
+            .as_mut()
+            .expect("Large files have layers.");
+        let (key, layer) = layers.iter_mut().next().unwrap();
+        let key = Sha2::try_from(key.as_slice()).unwrap();
+
+        let mut corrupt = layer.as_slice().to_vec();
+        corrupt[0] ^= 1;
+        *layer = corrupt.into();
+
+        assert_eq!(
+            torrent.validate(),
+            [Violation::PieceLayerRoot { pieces_root: key }]
+        );
+    }
+}
@@ -0,0 +1,15 @@
+//! Check downloaded data against a torrent's piece hashes.
+//!
+//! [Torrent::verify](crate::Torrent::verify) hashes the files of a torrent on
+//! disk and reports each piece as valid, missing, or corrupted through a
+//! [verifier::Verifier].
+//!
+//! Rechecking a multi-terabyte torrent takes hours and is often interrupted.
+//! [state::VerifyState] records which pieces were checked so that a recheck
+//! may resume where it stopped.
+
+pub mod state;
+pub mod verifier;
+
+pub use state::{Bitfield, VerifyState};
+pub use verifier::{PieceCheck, Verifier, VerifyFile, VerifyStatus};
@@ -0,0 +1,273 @@
+//! Checkpoints of an interrupted verification.
+//!
+//! [VerifyState] is a bitfield of the pieces that were checked and another of
+//! the pieces that were valid. It's stored as bencode along with the torrent's
+//! info hash so that a state file is never resumed for a different torrent.
+
+use crate::{crypto::sha::Sha2, Torrent};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    path::Path,
+};
+
+const VERIFYSTATE_TARGET: &str = "star_cloudburst::verify::state::VerifyState";
+
+/// One bit per piece where the first piece is the high bit of the first byte.
+///
+/// This is the layout of the BitTorrent `bitfield` message. Spare bits at the
+/// end are always zero.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Bitfield {
+    #[serde(with = "serde_bytes")]
+    bytes: Vec<u8>,
+    #[serde(rename = "length")]
+    len: usize,
+}
+
+impl Bitfield {
+    /// A bitfield of `len` cleared bits.
+    #[inline]
+    pub fn new(len: usize) -> Self {
+        Self {
+            bytes: vec![0; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// Whether bit `index` is set. Out of range bits are clear.
+    #[inline]
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Set or clear bit `index`.
+    ///
+    /// # Panics
+    /// Panics if `index` is out of range.
+    #[inline]
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "bit {index} is out of range");
+        if value {
+            self.bytes[index / 8] |= 0x80 >> (index % 8);
+        } else {
+            self.bytes[index / 8] &= !(0x80 >> (index % 8));
+        }
+    }
+
+    /// Number of bits.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of set bits.
+    #[inline]
+    pub fn count_ones(&self) -> usize {
+        self.bytes
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum()
+    }
+
+    /// The bits as bytes.
+    #[inline]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    // Deserialized bitfields may be truncated or have spare bits set.
+    fn is_consistent(&self) -> bool {
+        self.bytes.len() == self.len.div_ceil(8)
+            && (self.len.is_multiple_of(8)
+                || self.bytes.last().unwrap() & (0xff >> (self.len % 8)) == 0)
+    }
+}
+
+/// Progress of a verification that may be saved and resumed.
+///
+/// ```rust
+/// use star_cloudburst::{verify::VerifyState, Torrent};
+///
+/// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+/// let torrent = Torrent::from_bytes(cats)?;
+/// let state_path = std::env::temp_dir().join("star_cloudburst_verify_state_doc");
+/// # let _ = std::fs::remove_file(&state_path);
+///
+/// let mut state = VerifyState::resume(&state_path, &torrent, 3)?;
+/// state.record(0, true);
+/// state.save(&state_path)?;
+///
+/// // Interrupted here. Piece 0 isn't hashed again.
+/// let state = VerifyState::resume(&state_path, &torrent, 3)?;
+/// assert_eq!(state.unchecked().collect::<Vec<_>>(), [1, 2]);
+/// assert_eq!(state.valid().count_ones(), 1);
+/// # std::fs::remove_file(state_path)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct VerifyState {
+    /// SHA-256 of the info dict of the verified torrent.
+    #[serde(rename = "info hash")]
+    info_hash: Sha2,
+    /// Pieces that were hashed.
+    checked: Bitfield,
+    /// Pieces that matched their hash.
+    valid: Bitfield,
+}
+
+impl VerifyState {
+    /// A state where no piece of `torrent` was checked.
+    pub fn new(torrent: &Torrent, piece_count: usize) -> Result<Self, serde_bencode::Error> {
+        let info_hash = torrent
+            .info_hash_internal
+            .get_or_calculate(&torrent.info, &torrent.info_raw)?
+            .sha2
+            .clone();
+
+        Ok(Self {
+            info_hash,
+            checked: Bitfield::new(piece_count),
+            valid: Bitfield::new(piece_count),
+        })
+    }
+
+    /// Load the state file at `path` to continue verifying `torrent`.
+    ///
+    /// A new state is returned if the file is missing or is for another
+    /// torrent or piece count. Files that aren't a state are an error rather
+    /// than overwritten.
+    pub fn resume(path: &Path, torrent: &Torrent, piece_count: usize) -> io::Result<Self> {
+        let new = Self::new(torrent, piece_count)
+            .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+
+        let saved: Self = match fs::read(path) {
+            Ok(bytes) => serde_bencode::from_bytes(&bytes)
+                .map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(new),
+            Err(e) => return Err(e),
+        };
+
+        if !saved.checked.is_consistent() || !saved.valid.is_consistent() {
+            return Err(io::Error::new(
+                ErrorKind::InvalidData,
+                "verification state has malformed bitfields",
+            ));
+        }
+
+        if saved.info_hash != new.info_hash
+            || saved.checked.len() != piece_count
+            || saved.valid.len() != piece_count
+        {
+            warn!(
+                target: VERIFYSTATE_TARGET,
+                "{} is for another torrent; starting over",
+                path.display()
+            );
+            return Ok(new);
+        }
+
+        debug!(
+            target: VERIFYSTATE_TARGET,
+            "Resuming with {} of {piece_count} pieces checked",
+            saved.checked.count_ones()
+        );
+        Ok(saved)
+    }
+
+    /// Write the state to `path`.
+    ///
+    /// The state is written to a temporary file first so that an interruption
+    /// while saving doesn't lose the previous checkpoint.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let bytes =
+            serde_bencode::to_bytes(self).map_err(|e| io::Error::new(ErrorKind::InvalidData, e))?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, bytes)?;
+        fs::rename(temp, path)
+    }
+
+    /// Record the result of checking `piece`.
+    ///
+    /// # Panics
+    /// Panics if `piece` is out of range.
+    #[inline]
+    pub fn record(&mut self, piece: usize, valid: bool) {
+        self.checked.set(piece, true);
+        self.valid.set(piece, valid);
+    }
+
+    /// Pieces that haven't been checked in ascending order.
+    #[inline]
+    pub fn unchecked(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.checked.len()).filter(|&piece| !self.checked.get(piece))
+    }
+
+    /// Whether every piece was checked.
+    #[inline]
+    pub fn is_complete(&self) -> bool {
+        self.checked.count_ones() == self.checked.len()
+    }
+
+    #[inline]
+    pub fn checked(&self) -> &Bitfield {
+        &self.checked
+    }
+
+    #[inline]
+    pub fn valid(&self) -> &Bitfield {
+        &self.valid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Bitfield, VerifyState};
+    use crate::Torrent;
+    use std::fs;
+
+    #[test]
+    fn bitfield_layout() {
+        let mut bits = Bitfield::new(10);
+        bits.set(0, true);
+        bits.set(9, true);
+        assert_eq!(bits.as_bytes(), [0x80, 0x40]);
+        assert!(bits.get(9) && !bits.get(8) && !bits.get(10));
+        assert_eq!(bits.count_ones(), 2);
+
+        bits.set(0, false);
+        assert_eq!(bits.as_bytes(), [0, 0x40]);
+        assert!(bits.is_consistent());
+    }
+
+    #[test]
+    fn resume_other_torrent() -> Result<(), Box<dyn std::error::Error>> {
+        let cats: Torrent = serde_bencode::from_bytes(b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee")?;
+        let mews: Torrent = serde_bencode::from_bytes(b"d4:infod6:lengthi42e4:name8:mews.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee")?;
+        let path = std::env::temp_dir().join(format!(
+            "star-cloudburst-verify-state-{}",
+            std::process::id()
+        ));
+
+        let mut state = VerifyState::new(&cats, 1)?;
+        state.record(0, false);
+        assert!(state.is_complete());
+        state.save(&path)?;
+
+        assert_eq!(VerifyState::resume(&path, &cats, 1)?, state);
+        assert_eq!(VerifyState::resume(&path, &cats, 2)?.unchecked().count(), 2);
+        assert!(!VerifyState::resume(&path, &mews, 1)?.is_complete());
+
+        fs::write(&path, b"i42e")?;
+        assert!(VerifyState::resume(&path, &cats, 1).is_err());
+        fs::remove_file(path)?;
+        Ok(())
+    }
+}
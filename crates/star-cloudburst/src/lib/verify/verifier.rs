@@ -0,0 +1,625 @@
+//! Hash the files of a torrent on disk and compare them to its pieces.
+//!
+//! [Torrent::verify] yields one [PieceCheck] per piece so that progress may be
+//! shown while a large torrent is checked. Version 1 pieces are SHA-1 hashes of
+//! the concatenated files and version 2 pieces are nodes of each file's Merkle
+//! tree. Hybrid torrents are checked with their version 1 pieces, which cover
+//! the same data.
+
+use crate::{
+    crypto::merkle::{hash_file_v2, hash_piece_v2},
+    files::MetaV1FileRepr,
+    layout::nested_path,
+    metainfo::{MetaInfo, TorrentVersion},
+    pieces::PieceLength,
+    verify::{Bitfield, VerifyState},
+    Torrent,
+};
+use digest::Digest;
+use log::debug;
+use std::{
+    fs::File,
+    io::{self, ErrorKind, Read, Seek, SeekFrom},
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+const VERIFIER_TARGET: &str = "star_cloudburst::verify::verifier::Verifier";
+
+// Each piece is read into memory whole, so torrents with longer pieces are
+// rejected before anything is allocated.
+const MAX_PIECE_LENGTH: u64 = 512 * 1024 * 1024;
+
+/// Result of checking a piece or a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VerifyStatus {
+    /// The data matches the torrent's hashes.
+    Valid,
+    /// A file doesn't exist or is shorter than the torrent says.
+    Missing,
+    /// The data doesn't match the torrent's hashes.
+    Corrupted,
+}
+
+/// A file as it's read by [Verifier].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifyFile {
+    /// Path relative to the root directory.
+    pub path: PathBuf,
+    pub length: u64,
+    /// Padding files are zeroes that aren't read from disk.
+    pub padding: bool,
+}
+
+/// Result of checking one piece.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PieceCheck {
+    pub piece: usize,
+    pub status: VerifyStatus,
+    /// Indices into [Verifier::files] of the files that the piece overlaps.
+    pub files: Range<usize>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Expected {
+    Sha1([u8; 20]),
+    // Files that fit in one piece are checked against their root.
+    PiecesRoot([u8; 32]),
+    PieceLayer([u8; 32]),
+}
+
+// Span of a piece in the concatenated files.
+#[derive(Debug, Clone, Copy)]
+struct Piece {
+    start: u64,
+    length: u64,
+    expected: Expected,
+}
+
+/// Iterator over the pieces of a torrent as they're checked.
+///
+/// Created by [Torrent::verify]. Files are read from disk lazily so
+/// [Verifier::next] hashes one piece at a time. Missing files aren't errors;
+/// their pieces are [VerifyStatus::Missing].
+#[derive(Debug)]
+pub struct Verifier {
+    root: PathBuf,
+    files: Vec<VerifyFile>,
+    // Offset of each file in the concatenated files.
+    offsets: Vec<u64>,
+    pieces: Vec<Piece>,
+    piece_length: PieceLength,
+    next: usize,
+    skip: Option<Bitfield>,
+    open: Option<(usize, File)>,
+    // Files that were found missing or too short while reading.
+    missing: Vec<bool>,
+    buffer: Vec<u8>,
+}
+
+impl Torrent {
+    /// Check the files of the torrent saved under `root`.
+    ///
+    /// `root` is the directory the torrent was downloaded to, so multiple
+    /// file torrents are read from the directory named after [Torrent::name]
+    /// like [Torrent::layout]. Merkle torrents only have a root hash and can't
+    /// be checked by piece.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{verify::VerifyStatus, Torrent};
+    ///
+    /// let cats = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    /// let root = std::env::temp_dir().join("star_cloudburst_verify_doc");
+    /// std::fs::create_dir_all(&root)?;
+    /// # let _ = std::fs::remove_file(root.join("cats.mkv"));
+    ///
+    /// let check = torrent.verify(&root)?.next().unwrap()?;
+    /// assert_eq!(check.status, VerifyStatus::Missing);
+    ///
+    /// std::fs::write(root.join("cats.mkv"), [b'c'; 42])?;
+    /// let check = torrent.verify(&root)?.next().unwrap()?;
+    /// assert_eq!(check.status, VerifyStatus::Corrupted);
+    /// # std::fs::remove_dir_all(root)?;
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn verify(&self, root: &Path) -> io::Result<Verifier> {
+        let piece_length = self.info.piece_length();
+        if piece_length.get() > MAX_PIECE_LENGTH {
+            return Err(invalid_data(format!(
+                "{} has {} byte pieces but at most {MAX_PIECE_LENGTH} bytes are verified at once",
+                self.name(),
+                piece_length.get()
+            )));
+        }
+
+        let (files, pieces) = match self.info.version() {
+            TorrentVersion::V1 | TorrentVersion::Hybrid => v1_pieces(self, piece_length)?,
+            TorrentVersion::V2 => v2_pieces(self, piece_length)?,
+            _ => {
+                return Err(io::Error::new(
+                    ErrorKind::Unsupported,
+                    "Merkle torrents don't have piece hashes",
+                ))
+            }
+        };
+
+        let offsets = files
+            .iter()
+            .scan(0, |offset, file| {
+                let start = *offset;
+                *offset += file.length;
+                Some(start)
+            })
+            .collect();
+
+        debug!(
+            target: VERIFIER_TARGET,
+            "Verifying {} pieces of {} under {}",
+            pieces.len(),
+            self.name(),
+            root.display()
+        );
+        Ok(Verifier {
+            root: root.to_owned(),
+            missing: vec![false; files.len()],
+            files,
+            offsets,
+            pieces,
+            piece_length,
+            next: 0,
+            skip: None,
+            open: None,
+            buffer: Vec::new(),
+        })
+    }
+}
+
+impl Verifier {
+    /// Skip the pieces that `state` already checked.
+    ///
+    /// Record each [PieceCheck] in `state` to resume an interrupted
+    /// verification later.
+    #[inline]
+    pub fn skip_checked(mut self, state: &VerifyState) -> Self {
+        self.skip = Some(state.checked().clone());
+        self
+    }
+
+    /// Files in the order they're hashed, including padding files.
+    #[inline]
+    pub fn files(&self) -> &[VerifyFile] {
+        &self.files
+    }
+
+    #[inline]
+    pub fn piece_count(&self) -> usize {
+        self.pieces.len()
+    }
+
+    /// Check the remaining pieces and summarize each file that isn't padding.
+    ///
+    /// Files are missing if they don't exist or are shorter than the torrent
+    /// says. Otherwise they're corrupted if any piece they overlap isn't
+    /// valid, which includes pieces shared with a missing file. Pieces that
+    /// were skipped aren't taken into account.
+    pub fn report(mut self) -> io::Result<Vec<(VerifyFile, VerifyStatus)>> {
+        let mut corrupted = vec![false; self.files.len()];
+        for check in self.by_ref() {
+            let check = check?;
+            if check.status != VerifyStatus::Valid {
+                corrupted[check.files].fill(true);
+            }
+        }
+
+        // Empty files aren't part of any piece.
+        for (file, missing) in self.files.iter().zip(&mut self.missing) {
+            if file.length == 0 && !file.padding && !self.root.join(&file.path).exists() {
+                *missing = true;
+            }
+        }
+
+        Ok(self
+            .files
+            .into_iter()
+            .zip(self.missing)
+            .zip(corrupted)
+            .filter(|((file, _), _)| !file.padding)
+            .map(|((file, missing), corrupted)| {
+                let status = if missing {
+                    VerifyStatus::Missing
+                } else if corrupted {
+                    VerifyStatus::Corrupted
+                } else {
+                    VerifyStatus::Valid
+                };
+                (file, status)
+            })
+            .collect())
+    }
+
+    fn check(&mut self, index: usize) -> io::Result<PieceCheck> {
+        let Piece {
+            start,
+            length,
+            expected,
+        } = self.pieces[index];
+        let end = start + length;
+
+        // Files that end after the piece starts and start before it ends.
+        let first = self
+            .offsets
+            .iter()
+            .zip(&self.files)
+            .position(|(offset, file)| offset + file.length > start)
+            .unwrap_or(self.files.len());
+        let last = self.offsets.partition_point(|&offset| offset < end);
+        let files = first..last.max(first);
+
+        self.buffer.resize(length as usize, 0);
+        let status = if !self.read(files.clone(), start)? {
+            VerifyStatus::Missing
+        } else if self.matches(expected)? {
+            VerifyStatus::Valid
+        } else {
+            VerifyStatus::Corrupted
+        };
+
+        Ok(PieceCheck {
+            piece: index,
+            status,
+            files,
+        })
+    }
+
+    // Fill the buffer with the piece starting at `start`. Returns false if a
+    // file is missing or too short.
+    fn read(&mut self, files: Range<usize>, start: u64) -> io::Result<bool> {
+        let end = start + self.buffer.len() as u64;
+
+        for index in files {
+            let file = &self.files[index];
+            let file_start = self.offsets[index];
+            let from = start.max(file_start);
+            let to = end.min(file_start + file.length);
+            if from >= to {
+                continue;
+            }
+
+            let chunk = &mut self.buffer[(from - start) as usize..(to - start) as usize];
+            if file.padding {
+                chunk.fill(0);
+                continue;
+            }
+
+            if self.open.as_ref().map(|(open, _)| *open) != Some(index) {
+                self.open = match File::open(self.root.join(&file.path)) {
+                    Ok(opened) => Some((index, opened)),
+                    Err(e) if e.kind() == ErrorKind::NotFound => {
+                        self.missing[index] = true;
+                        return Ok(false);
+                    }
+                    Err(e) => return Err(e),
+                };
+            }
+
+            let opened = &mut self.open.as_mut().expect("File was just opened.").1;
+            opened.seek(SeekFrom::Start(from - file_start))?;
+            match opened.read_exact(chunk) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => {
+                    self.missing[index] = true;
+                    return Ok(false);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn matches(&self, expected: Expected) -> io::Result<bool> {
+        Ok(match expected {
+            Expected::Sha1(hash) => sha1::Sha1::digest(&self.buffer)[..] == hash,
+            Expected::PiecesRoot(root) => hash_file_v2(self.buffer.as_slice(), self.piece_length)?
+                .is_some_and(|hashes| *hashes.pieces_root.as_bytes() == root),
+            Expected::PieceLayer(hash) => hash_piece_v2(&self.buffer, self.piece_length) == hash,
+        })
+    }
+}
+
+impl Iterator for Verifier {
+    type Item = io::Result<PieceCheck>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.skip.as_ref().is_some_and(|skip| skip.get(self.next)) {
+            self.next += 1;
+        }
+
+        let index = self.next;
+        if index >= self.pieces.len() {
+            return None;
+        }
+        self.next += 1;
+        Some(self.check(index))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+// Files with padding and SHA-1 pieces over their concatenation.
+fn v1_pieces(
+    torrent: &Torrent,
+    piece_length: PieceLength,
+) -> io::Result<(Vec<VerifyFile>, Vec<Piece>)> {
+    let name = torrent.name();
+    let (flat_files, length, pieces) = match &torrent.info {
+        MetaInfo::MetaV1(info) => match &info.files {
+            MetaV1FileRepr::Single(length) => (None, length.get(), Some(&info.pieces)),
+            MetaV1FileRepr::Multiple(files) => (Some(files), 0, Some(&info.pieces)),
+        },
+        MetaInfo::Hybrid(info) => (
+            info.files.as_ref(),
+            info.length.map(|length| length.get()).unwrap_or_default(),
+            info.pieces.as_ref(),
+        ),
+        _ => (None, 0, None),
+    };
+    let pieces =
+        pieces.ok_or_else(|| invalid_data(format!("{name} doesn't have version 1 pieces")))?;
+
+    let files = match flat_files {
+        None => vec![VerifyFile {
+            path: nested_path("", &[name])?,
+            length,
+            padding: false,
+        }],
+        // Symlinks don't have data.
+        Some(flat_files) => flat_files
+            .iter()
+            .filter(|file| file.symlink_path.is_none())
+            .map(|file| {
+                Ok(VerifyFile {
+                    path: nested_path(name, &file.path)?,
+                    length: file.length.get(),
+                    padding: file.is_padding(),
+                })
+            })
+            .collect::<io::Result<_>>()?,
+    };
+
+    let total = files
+        .iter()
+        .try_fold(0u64, |total, file| total.checked_add(file.length))
+        .ok_or_else(|| invalid_data(format!("{name} is larger than {} bytes", u64::MAX)))?;
+    let piece_length_u64 = piece_length.get();
+    if pieces.len() as u64 != total.div_ceil(piece_length_u64) {
+        return Err(invalid_data(format!(
+            "{name} has {} pieces but its files need {}",
+            pieces.len(),
+            total.div_ceil(piece_length_u64)
+        )));
+    }
+
+    let pieces = (0..pieces.len())
+        .filter_map(|index| pieces.get(index))
+        .enumerate()
+        .map(|(index, hash)| {
+            let start = index as u64 * piece_length_u64;
+            Piece {
+                start,
+                length: piece_length_u64.min(total - start),
+                expected: Expected::Sha1(*hash),
+            }
+        })
+        .collect();
+    Ok((files, pieces))
+}
+
+// Files of the file tree and the pieces of each file.
+fn v2_pieces(
+    torrent: &Torrent,
+    piece_length: PieceLength,
+) -> io::Result<(Vec<VerifyFile>, Vec<Piece>)> {
+    let tree = match &torrent.info {
+        MetaInfo::MetaV2(info) => Some(&info.file_tree),
+        MetaInfo::Hybrid(info) => info.file_tree.as_ref(),
+        _ => None,
+    }
+    .ok_or_else(|| invalid_data(format!("{} doesn't have a file tree", torrent.name())))?;
+    let piece_length_u64 = piece_length.get();
+
+    // The layout lists the same files in the same order.
+    let views = tree
+        .iter_dfs()
+        .filter(|view| !view.file_info.is_padding() && !view.file_info.is_symlink());
+    let mut files = Vec::new();
+    let mut pieces = Vec::new();
+    let mut offset = 0;
+
+    for (view, file) in views.zip(torrent.layout()?) {
+        let length = file.length;
+        if length > 0 {
+            let root = view.file_info.pieces_root.as_ref().ok_or_else(|| {
+                invalid_data(format!(
+                    "{} doesn't have a pieces root",
+                    file.path.display()
+                ))
+            })?;
+
+            if length <= piece_length_u64 {
+                pieces.push(Piece {
+                    start: offset,
+                    length,
+                    expected: Expected::PiecesRoot(*root.as_bytes()),
+                });
+            } else {
+                let layer = torrent
+                    .piece_layers
+                    .as_ref()
+                    .and_then(|layers| layers.get(&root.as_bytes()[..]))
+                    .filter(|layer| layer.len() as u64 == length.div_ceil(piece_length_u64) * 32)
+                    .ok_or_else(|| {
+                        invalid_data(format!(
+                            "{} doesn't have a valid piece layer",
+                            file.path.display()
+                        ))
+                    })?;
+
+                for (index, hash) in layer.as_slice().chunks_exact(32).enumerate() {
+                    let start = index as u64 * piece_length_u64;
+                    pieces.push(Piece {
+                        start: offset + start,
+                        length: piece_length_u64.min(length - start),
+                        expected: Expected::PieceLayer(
+                            hash.try_into().expect("Chunks are 32 bytes."),
+                        ),
+                    });
+                }
+            }
+        }
+
+        offset = offset.checked_add(length).ok_or_else(|| {
+            invalid_data(format!(
+                "{} is larger than {} bytes",
+                torrent.name(),
+                u64::MAX
+            ))
+        })?;
+        files.push(VerifyFile {
+            path: file.path,
+            length,
+            padding: false,
+        });
+    }
+
+    Ok((files, pieces))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VerifyStatus;
+    use crate::{verify::VerifyState, Torrent};
+    use std::{error::Error, fs, io::ErrorKind};
+
+    #[test]
+    fn verify_v1_files() -> Result<(), Box<dyn Error>> {
+        // Two pieces: cats.mkv is the first and shares the second with mews.mkv.
+        let cats = [b'c'; 16384];
+        let mews = [b'm'; 10];
+        let mut torrent = b"d4:infod5:filesld6:lengthi16384e4:pathl8:cats.mkveed6:lengthi10e4:pathl8:mews.mkveee4:name4:cats12:piece lengthi16384e6:pieces40:".to_vec();
+        torrent.extend(sha1(&cats));
+        torrent.extend(sha1(&mews[..1]));
+        torrent.extend(b"ee");
+        let torrent: Torrent = serde_bencode::from_bytes(&torrent)?;
+
+        let root =
+            std::env::temp_dir().join(format!("star-cloudburst-verify-{}", std::process::id()));
+        fs::create_dir_all(root.join("cats"))?;
+        fs::write(root.join("cats/cats.mkv"), cats)?;
+
+        let checks = torrent
+            .verify(&root)?
+            .map(|check| check.map(|check| (check.status, check.files)))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            checks,
+            [(VerifyStatus::Valid, 0..1), (VerifyStatus::Missing, 1..2)]
+        );
+
+        // The second piece's hash only covers the first byte of mews.mkv.
+        fs::write(root.join("cats/mews.mkv"), mews)?;
+        let report = torrent.verify(&root)?.report()?;
+        assert_eq!(report[0].1, VerifyStatus::Valid);
+        assert_eq!(report[1].1, VerifyStatus::Corrupted);
+
+        // Checked pieces are skipped while resuming.
+        let mut state = VerifyState::new(&torrent, 2)?;
+        state.record(0, true);
+        let mut verifier = torrent.verify(&root)?.skip_checked(&state);
+        assert_eq!(
+            verifier.next().transpose()?.map(|check| check.piece),
+            Some(1)
+        );
+        assert!(verifier.next().is_none());
+
+        fs::remove_dir_all(root)?;
+        Ok(())
+    }
+
+    #[test]
+    fn verify_rejects_oversized_torrents() -> Result<(), Box<dyn Error>> {
+        let root = std::env::temp_dir();
+
+        // Three files of i64::MAX bytes overflow the total length.
+        let huge = b"d4:infod5:filesld6:lengthi9223372036854775807e4:pathl1:aeed6:lengthi9223372036854775807e4:pathl1:beed6:lengthi9223372036854775807e4:pathl1:ceee4:name4:cats12:piece lengthi16384e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+        let torrent = Torrent::from_bytes(huge)?;
+        let error = torrent.verify(&root).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().starts_with("cats is larger than"));
+
+        // 1 GiB pieces aren't allocated.
+        let long = b"d4:infod6:lengthi42e4:name8:cats.mkv12:piece lengthi1073741824e6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00ee";
+        let torrent = Torrent::from_bytes(long)?;
+        let error = torrent.verify(&root).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(error.to_string().contains("1073741824 byte pieces"));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "create")]
+    #[test]
+    fn verify_every_version() -> Result<(), Box<dyn Error>> {
+        use crate::{create::TorrentBuilder, metainfo::TorrentVersion, PieceLength};
+
+        let parent =
+            std::env::temp_dir().join(format!("star-cloudburst-verify-all-{}", std::process::id()));
+        let root = parent.join("cats");
+        fs::create_dir_all(root.join("season 1"))?;
+        fs::write(root.join("season 1/cats.mkv"), vec![b'c'; 40_000])?;
+        fs::write(root.join("mews.mkv"), b"mew")?;
+
+        for version in [
+            TorrentVersion::V1,
+            TorrentVersion::V2,
+            TorrentVersion::Hybrid,
+        ] {
+            let torrent = TorrentBuilder::new(&root)
+                .version(version)
+                .piece_length(PieceLength::new(16384).unwrap())
+                .build()?;
+
+            let report = torrent.verify(&parent)?.report()?;
+            assert_eq!(report.len(), 2);
+            assert!(report
+                .iter()
+                .all(|(_, status)| *status == VerifyStatus::Valid));
+
+            fs::write(root.join("season 1/cats.mkv"), vec![b'm'; 40_000])?;
+            fs::rename(root.join("mews.mkv"), root.join("mews.bak"))?;
+            let report = torrent.verify(&parent)?.report()?;
+            for (file, status) in report {
+                let expected = if file.path.ends_with("mews.mkv") {
+                    VerifyStatus::Missing
+                } else {
+                    VerifyStatus::Corrupted
+                };
+                assert_eq!(status, expected, "{version}: {}", file.path.display());
+            }
+
+            fs::write(root.join("season 1/cats.mkv"), vec![b'c'; 40_000])?;
+            fs::rename(root.join("mews.bak"), root.join("mews.mkv"))?;
+        }
+
+        fs::remove_dir_all(parent)?;
+        Ok(())
+    }
+
+    fn sha1(data: &[u8]) -> [u8; 20] {
+        use digest::Digest;
+        sha1::Sha1::digest(data).into()
+    }
+}
@@ -0,0 +1,56 @@
+//! HTTP sources for torrent data.
+//!
+//! Torrents may list web servers that host the shared files in two ways:
+//! `httpseeds` ([BEP-0017](https://www.bittorrent.org/beps/bep_0017.html))
+//! and `url-list` ([BEP-0019](https://www.bittorrent.org/beps/bep_0019.html)).
+//! The two are requested differently so [WebSeed] records which one a URL came
+//! from.
+
+use crate::{uri::uriwrapper::UriWrapper, Torrent};
+
+/// Where a web seed was listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WebSeedKind {
+    /// BEP-0017 seed which serves pieces through a script.
+    HttpSeed,
+    /// BEP-0019 seed which serves the files themselves.
+    UrlList,
+}
+
+/// A web server that seeds the torrent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WebSeed<'torrent> {
+    pub kind: WebSeedKind,
+    pub uri: &'torrent UriWrapper,
+}
+
+impl Torrent {
+    /// Iterator over the web seeds of both `httpseeds` and `url-list`.
+    ///
+    /// `httpseeds` are yielded first in order. `url-list` is a set so its
+    /// order is unspecified.
+    ///
+    /// ```rust
+    /// use star_cloudburst::{webseed::WebSeedKind, Torrent};
+    /// use serde_bencode::Error;
+    ///
+    /// let cats = b"d9:httpseedsl22:http://cats.test/seedee4:infod4:name8:cats.mkv6:pieces20:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x0012:piece lengthi16384ee8:url-listl17:http://mews.test/ee";
+    /// let torrent = Torrent::from_bytes(cats)?;
+    ///
+    /// let kinds: Vec<_> = torrent.web_seeds().map(|seed| seed.kind).collect();
+    /// assert_eq!(kinds, [WebSeedKind::HttpSeed, WebSeedKind::UrlList]);
+    /// # Ok::<(), Error>(())
+    /// ```
+    pub fn web_seeds(&self) -> impl Iterator<Item = WebSeed<'_>> + '_ {
+        let http_seeds = self.httpseeds.iter().flatten().map(|uri| WebSeed {
+            kind: WebSeedKind::HttpSeed,
+            uri,
+        });
+        let url_list = self.url_list.iter().flatten().map(|uri| WebSeed {
+            kind: WebSeedKind::UrlList,
+            uri,
+        });
+
+        http_seeds.chain(url_list)
+    }
+}
@@ -1,5 +1,9 @@
-use star_cloudburst::Torrent;
-use std::{env, error::Error, fs};
+use star_cloudburst::{
+    borrowed::{BorrowedStr, TorrentRef},
+    parseoptions::ParseOptions,
+    Torrent,
+};
+use std::{borrow::Cow, env, error::Error, fs};
 
 #[test]
 fn test_files() {
@@ -18,8 +22,8 @@ fn test_files() {
                 entry.path()
             )
         });
-        let _torrent: Torrent =
-            serde_bencode::from_bytes(&contents).unwrap_or_else(|error| {
+        let _torrent =
+            Torrent::parse_with(&contents, ParseOptions::strict()).unwrap_or_else(|error| {
                 panic!(
                 "Torrent file failed to deserialize\nPath: {:?}\nError: {error}\nSource: {source}",
                 entry.path(),
@@ -28,3 +32,43 @@ fn test_files() {
             });
     }
 }
+
+#[test]
+fn test_files_borrowed() {
+    let path = format!(
+        "{}/{}",
+        env::var("CARGO_MANIFEST_DIR").expect("This test uses CARGO_MANIFEST_DIR to find the torrents directory. Run with Cargo or provide the path manually."),
+        "resources/tests/"
+    );
+
+    for entry in fs::read_dir(&path).expect("Test torrent files not found.") {
+        let entry =
+            entry.unwrap_or_else(|error| panic!("Unable to open files at {path}\nWhy: {error}"));
+        let contents = fs::read(entry.path()).unwrap_or_else(|error| {
+            panic!(
+                "Unable to read contents of file: {:?}\nWhy: {error}",
+                entry.path()
+            )
+        });
+        let torrent = TorrentRef::from_bytes(&contents).unwrap_or_else(|error| {
+            panic!(
+                "Torrent file failed to deserialize as a borrowed torrent\nPath: {:?}\nError: {error}",
+                entry.path(),
+            )
+        });
+        assert!(!torrent.name().is_empty());
+        assert!(matches!(torrent.info.name, Cow::Borrowed(_)));
+        assert!(torrent
+            .info
+            .pieces
+            .as_ref()
+            .is_none_or(|pieces| matches!(pieces, Cow::Borrowed(_))));
+        assert!(torrent
+            .info
+            .files
+            .iter()
+            .flatten()
+            .flat_map(|file| &file.path)
+            .all(BorrowedStr::is_borrowed));
+    }
+}